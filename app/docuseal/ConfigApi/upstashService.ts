@@ -123,6 +123,14 @@ const upstashService = {
         const url = `/public/submissions/${token}/fields`;
         return await axiosClient.get(url)
     },
+    getSubmitterDraft: async (token: string): Promise<any> => {
+        const url = `/public/submissions/${token}/draft`;
+        return await axiosClient.get(url)
+    },
+    saveSubmitterDraft: async (token: string, values: Record<number, string>): Promise<any> => {
+        const url = `/public/submissions/${token}/draft`;
+        return await axiosClient.put(url, { values })
+    },
     bulkSign: async (token: string, data: any): Promise<any> => {
         const url = `/public/signatures/bulk/${token}`;
         return await axiosClient.post(url, data)
@@ -171,8 +179,8 @@ const upstashService = {
         const url = `/api/templates/${template_id}/move/${parent_folder_id}`;
         return await axiosClient.put(url)
     },
-    deleteFolder: async (folderId: number): Promise<any> => {
-        const url = `/api/folders/${folderId}`;
+    deleteFolder: async (folderId: number, moveContentsToParent = false): Promise<any> => {
+        const url = `/api/folders/${folderId}${moveContentsToParent ? '?contents=move_to_parent' : ''}`;
         return await axiosClient.delete(url)
     },
     // Team APIs can be added here
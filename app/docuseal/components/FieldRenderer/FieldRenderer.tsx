@@ -30,7 +30,7 @@ interface FieldRendererProps {
   children?: React.ReactNode;
   defaultSignature?: string; // Signature từ user profile
   defaultInitials?: string;  // Initials từ user profile
-  submitterId?: number;
+  signatureId?: string;
   submitterEmail?: string;
   reason?: string;
   globalSettings ?: any;
@@ -45,7 +45,7 @@ const FieldRenderer: React.FC<FieldRendererProps> = ({
   children,
   defaultSignature,
   defaultInitials,
-  submitterId,
+  signatureId,
   submitterEmail,
   reason,globalSettings
 }) => {
@@ -76,7 +76,7 @@ const FieldRenderer: React.FC<FieldRendererProps> = ({
               data={displayValue}
               width={field.position.width * 600}
               height={field.position.height * 800}
-              submitterId={submitterId}
+              signatureId={signatureId}
               submitterEmail={submitterEmail}
               reason={reason}
               globalSettings ={globalSettings}
@@ -13,7 +13,7 @@ interface DocumentViewerProps {
   onPageChange?: (page: number) => void;
   scale?: number;
   showDebug?: boolean;
-  submitterId?: number;
+  signatureId?: string;
   submitterEmail?: string;
   globalSettings?: any;
 }
@@ -28,7 +28,7 @@ const DocumentViewer: React.FC<DocumentViewerProps> = ({
   page,
   onPageChange,
   scale: initialScale = 1.5,
-  submitterId,
+  signatureId,
   submitterEmail,
   globalSettings
 }) => {
@@ -159,7 +159,7 @@ const DocumentViewer: React.FC<DocumentViewerProps> = ({
                     width={normalizedPos.width * 600} 
                     height={normalizedPos.height * 800}
                     fieldType={fieldType}
-                    submitterId={submitterId}
+                    signatureId={signatureId}
                     submitterEmail={submitterEmail}
                     reason={(f as any).reason}
                     globalSettings={globalSettings}
@@ -1,6 +1,5 @@
 import { useBasicSettings } from '@/hooks/useBasicSettings';
 import React, { useMemo } from 'react';
-import { hashId } from '../constants/reminderDurations';
 
 interface SignatureRendererProps {
   data: string; // JSON string of point groups or typed text
@@ -9,7 +8,7 @@ interface SignatureRendererProps {
   fieldType?: string;
   color?: string; // Color for signature/text
   additionalText?: string; // Additional text to display below the signature
-  submitterId?: number;
+  signatureId?: string; // Signature ID issued by the server
   submitterEmail?: string;
   reason?: string; // Signing reason to display
   globalSettings?: any;
@@ -22,7 +21,7 @@ const SignatureRenderer: React.FC<SignatureRendererProps> = ({
   fieldType,
   color = '#000000',
   additionalText,
-  submitterId,
+  signatureId,
   submitterEmail,
   reason, globalSettings
 }) => {
@@ -125,7 +124,7 @@ const SignatureRenderer: React.FC<SignatureRendererProps> = ({
       if (globalSettings?.require_signing_reason && reason) {
         lines.push(`Reason: ${reason}`);
       }
-      if (submitterId) lines.push(`ID: ${hashId(submitterId + 1)}`);
+      if (signatureId) lines.push(`ID: ${signatureId}`);
       if (submitterEmail) lines.push(submitterEmail);
       lines.push(new Date().toLocaleString(locale, dateOptions));
     } else {
@@ -137,7 +136,7 @@ const SignatureRenderer: React.FC<SignatureRendererProps> = ({
     }
 
     return lines;
-  }, [globalSettings, additionalText, submitterId, submitterEmail, reason]);
+  }, [globalSettings, additionalText, signatureId, submitterEmail, reason]);
 
   // Render Helpers
   const renderContent = () => {
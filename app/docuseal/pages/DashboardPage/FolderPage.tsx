@@ -82,14 +82,18 @@ const FolderPage: React.FC = () => {
   const handleDeleteFolder = async () => {
     if (!currentFolder) return;
 
+    const hasContents = folders.length > 0 || templates.length > 0;
+    const destination = parentFolder ? `"${parentFolder.name}"` : 'the top level';
     const confirmDelete = window.confirm(
-      `Are you sure you want to delete the folder "${currentFolder.name}"? This action cannot be undone and will also delete all templates and subfolders inside it.`
+      hasContents
+        ? `The folder "${currentFolder.name}" contains ${folders.length} subfolder(s) and ${templates.length} template(s). Delete it and move them to ${destination}?`
+        : `Are you sure you want to delete the folder "${currentFolder.name}"?`
     );
 
     if (!confirmDelete) return;
 
     try {
-      const response = await upstashService.deleteFolder(currentFolder.id);
+      const response = await upstashService.deleteFolder(currentFolder.id, hasContents);
       if (response.success) {
         showToast('Folder deleted successfully', 'success');
         // Navigate back to parent folder or home
@@ -101,8 +105,8 @@ const FolderPage: React.FC = () => {
       } else {
         showToast('Error deleting folder', 'error');
       }
-    } catch (error) {
-      showToast('Error deleting folder', 'error');
+    } catch (error: any) {
+      showToast(error?.message || 'Error deleting folder', 'error');
     }
   };
   const fetchData = async () => {
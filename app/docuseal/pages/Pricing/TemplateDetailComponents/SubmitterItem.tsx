@@ -45,7 +45,8 @@ const SubmitterItem: React.FC<SubmitterItemProps> = ({
       // 2. Chuẩn bị submitter info
       const submitterInfo = {
         id: party.id,
-        email: party.email
+        email: party.email,
+        signature_id: signaturesResult.data.signature_id
       };
 
       // 3. Fetch real audit log, fallback to mock
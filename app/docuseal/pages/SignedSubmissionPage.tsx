@@ -10,7 +10,7 @@ const SignedSubmissionPage = () => {
   const navigate = useNavigate();
   const [data, setData] = useState<any>(null);
   const [error, setError] = useState('');
-  const [submitterInfo, setSubmitterInfo] = useState<{ id: number; email: string } | null>(null);
+  const [submitterInfo, setSubmitterInfo] = useState<{ id: number; email: string; signature_id?: string } | null>(null);
   useEffect(() => {
     const fetchData = async () => {
       try {
@@ -34,7 +34,8 @@ const SignedSubmissionPage = () => {
         if (fieldsResult.success && fieldsResult.data.information) {
           setSubmitterInfo({
             id: fieldsResult.data.information.id,
-            email: fieldsResult.data.information.email
+            email: fieldsResult.data.information.email,
+            signature_id: fieldsResult.data.information.signature_id
           });
         }
       } catch (err) {
@@ -88,7 +89,7 @@ const SignedSubmissionPage = () => {
           <PdfViewer
             filePath={data.template_info.document.url}
             fields={data?.bulk_signatures?.map(sig => ({ ...sig.field_info, signature_value: sig.signature_value, reason: sig.reason }))}
-            submitterId={submitterInfo?.id}
+            signatureId={submitterInfo?.signature_id}
             submitterEmail={submitterInfo?.email}
             globalSettings={data?.submitter?.global_settings}
             // scale={1.5}
@@ -142,7 +143,7 @@ const SignedSubmissionPage = () => {
                           data={sig.signature_value} 
                           width={200} 
                           height={100}
-                          submitterId={submitterInfo?.id}
+                          signatureId={submitterInfo?.signature_id}
                           submitterEmail={submitterInfo?.email}
                           reason={sig.reason}
                           globalSettings={data?.submitter?.global_settings}
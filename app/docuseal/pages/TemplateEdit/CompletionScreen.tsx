@@ -70,7 +70,8 @@ const CompletionScreen: React.FC<CompletionScreenProps> = ({
       if (fieldsResult.data.information) {
         submitterInfo = {
           id: fieldsResult.data.information.id,
-          email: fieldsResult.data.information.email
+          email: fieldsResult.data.information.email,
+          signature_id: fieldsResult.data.information.signature_id
         };
       }
 
@@ -47,7 +47,7 @@ interface PdfFullViewProps {
   onFieldClick: (field: TemplateField) => void;
   texts: Record<number, string>;
   token: string;
-  submitterId?: number;
+  signatureId?: string;
   submitterEmail?: string;
   reasons?: Record<number, string>;
   clearedFields?: Set<number>;
@@ -62,7 +62,7 @@ const PdfFullView: React.FC<PdfFullViewProps> = ({
   onFieldClick,
   texts,
   token,
-  submitterId,
+  signatureId,
   submitterEmail,
   reasons,
   clearedFields,
@@ -109,7 +109,7 @@ const PdfFullView: React.FC<PdfFullViewProps> = ({
                   value={texts[field.id]}
                   defaultSignature={clearedFields?.has(field.id) || !globalSettings?.remember_and_pre_fill_signatures ? undefined : user?.signature}
                   defaultInitials={clearedFields?.has(field.id) || !globalSettings?.remember_and_pre_fill_signatures ? undefined : user?.initials}
-                  submitterId={submitterId}
+                  signatureId={signatureId}
                   submitterEmail={submitterEmail}
                   reason={reasons?.[field.id]}
                   globalSettings={globalSettings}
@@ -61,6 +61,7 @@ const TemplateEditPage = () => {
   const [submitterInfo, setSubmitterInfo] = useState<{
     id: number;
     email: string;
+    signature_id?: string;
     template_name?: string;
     status: string;
     signed_at?: string;
@@ -95,6 +96,7 @@ const TemplateEditPage = () => {
             setSubmitterInfo({
               id: data.data.information.id,
               email: data.data.information.email,
+              signature_id: data.data.information.signature_id,
               template_name: submitterData.data.template_name,
               status: submitterData.data.status,
               signed_at: submitterData.data.signed_at, 
@@ -104,6 +106,7 @@ const TemplateEditPage = () => {
             setSubmitterInfo({
               id: data.data.information.id,
               email: data.data.information.email,
+              signature_id: data.data.information.signature_id,
               status: 'pending',
               global_settings: submitterData.data?.global_settings
             });
@@ -138,6 +141,12 @@ const TemplateEditPage = () => {
         });
 
         setFields(processedFields);
+
+        // Restore what the signer entered in an earlier visit
+        const draft = await upstashService.getSubmitterDraft(token).catch(() => null);
+        if (draft?.success && draft.data?.values) {
+          setTexts(prev => ({ ...draft.data.values, ...prev }));
+        }
       }
     } catch (err) {
       console.error('Fetch error:', err);
@@ -166,6 +175,18 @@ const TemplateEditPage = () => {
     }
   }, [fields, user, submitterInfo?.global_settings?.remember_and_pre_fill_signatures]);
 
+  // Save a draft shortly after the signer stops typing, so they can come back to it
+  useEffect(() => {
+    if (!token || !submitterInfo || ['signed', 'completed', 'declined'].includes(submitterInfo.status) || Object.keys(texts).length === 0) return;
+    const timer = setTimeout(() => {
+      const values = Object.fromEntries(
+        Object.entries(texts).filter(([fieldId]) => !pendingUploads[Number(fieldId)])
+      );
+      upstashService.saveSubmitterDraft(token, values).catch(err => console.error('Draft save error:', err));
+    }, 1500);
+    return () => clearTimeout(timer);
+  }, [texts, token, submitterInfo, pendingUploads]);
+
   // Update reasons state when selected reason changes
   useEffect(() => {
     if (submitterInfo?.global_settings?.require_signing_reason) {
@@ -477,7 +498,7 @@ const TemplateEditPage = () => {
         onFieldClick={onFieldClick}
         texts={texts}
         token={token}
-        submitterId={submitterInfo?.id}
+        signatureId={submitterInfo?.signature_id}
         submitterEmail={submitterInfo?.email}
         reasons={reasons}
         clearedFields={clearedFields}
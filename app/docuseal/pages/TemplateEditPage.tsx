@@ -55,7 +55,7 @@ const TemplateEditPage = () => {
   const [isModalOpen, setIsModalOpen] = useState(false);
   const [page, setPage] = useState(1);
   const [fileUploading, setFileUploading] = useState(false);
-  const [submitterInfo, setSubmitterInfo] = useState<{ id: number; email: string } | null>(null);
+  const [submitterInfo, setSubmitterInfo] = useState<{ id: number; email: string; signature_id?: string } | null>(null);
   console.log('fields' , fields)
   const uploadFile = async (file: File): Promise<string | null> => {
     try {
@@ -88,7 +88,8 @@ const TemplateEditPage = () => {
         if (data.data.information) {
           setSubmitterInfo({
             id: data.data.information.id,
-            email: data.data.information.email
+            email: data.data.information.email,
+            signature_id: data.data.information.signature_id
           });
         }
         
@@ -251,7 +252,7 @@ const TemplateEditPage = () => {
         onFieldClick={onFieldClick}
         texts={texts}
         token={token}
-        submitterId={submitterInfo?.id}
+        signatureId={submitterInfo?.signature_id}
         submitterEmail={submitterInfo?.email}
       />
 
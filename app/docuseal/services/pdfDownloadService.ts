@@ -1,5 +1,4 @@
 import { PDFDocument, rgb, StandardFonts } from 'pdf-lib';
-import { hashId } from '../constants/reminderDurations';
 
 // Interface for audit log entry
 interface AuditLogEntry {
@@ -70,7 +69,7 @@ export const generateMockAuditLog = (
 
 // Helper function to render vector signature to canvas and convert to image
 export const renderSignatureToImage = (signatureData: string, width: number, height: number, options?: {
-  submitterId?: number;
+  signatureId?: string;
   submitterEmail?: string;
   reason?: string;
   additionalText?: string;
@@ -147,7 +146,7 @@ export const renderSignatureToImage = (signatureData: string, width: number, hei
         // Estimate text height: 12px per line + 6px padding
         let lineCount = 0;
         if (options?.globalSettings?.add_signature_id_to_the_documents) {
-          lineCount += (options?.submitterId ? 1 : 0) + (options?.submitterEmail ? 1 : 0) + 1; // date
+          lineCount += (options?.signatureId ? 1 : 0) + (options?.submitterEmail ? 1 : 0) + 1; // date
         }
         if (options?.globalSettings?.require_signing_reason && options?.reason) {
           lineCount += 1;
@@ -201,11 +200,11 @@ export const renderSignatureToImage = (signatureData: string, width: number, hei
       ctx.imageSmoothingEnabled = true;
 
       // Render additional text below the signature if enabled (giống SignatureRenderer)
-      const { submitterId, submitterEmail, reason, additionalText, globalSettings } = options || {};
+      const { signatureId, submitterEmail, reason, additionalText, globalSettings } = options || {};
 
       let textToShow: string[] = [];
       if (globalSettings?.add_signature_id_to_the_documents) {
-        if (submitterId) textToShow.push(`ID: ${hashId(submitterId + 1)}`);
+        if (signatureId) textToShow.push(`ID: ${signatureId}`);
         if (submitterEmail) textToShow.push(submitterEmail);
         textToShow.push(new Date().toLocaleString('vi-VN', {
           year: 'numeric', month: '2-digit', day: '2-digit',
@@ -220,7 +219,7 @@ export const renderSignatureToImage = (signatureData: string, width: number, hei
       if (globalSettings?.require_signing_reason && reason) {
         if (globalSettings?.add_signature_id_to_the_documents) {
           // Show both reason and ID/email/date
-          textToShow = [`Reason: ${reason}`, `ID: ${hashId(submitterId + 1)}`, submitterEmail, new Date().toLocaleString('vi-VN', {
+          textToShow = [`Reason: ${reason}`, signatureId && `ID: ${signatureId}`, submitterEmail, new Date().toLocaleString('vi-VN', {
             year: 'numeric', month: '2-digit', day: '2-digit',
             hour: '2-digit', minute: '2-digit', second: '2-digit',
             timeZone: 'Asia/Ho_Chi_Minh'
@@ -273,7 +272,7 @@ export const downloadSignedPDF = async (
   pdfUrl: string,
   signatures: any[],
   templateName: string,
-  submitterInfo?: { id: number; email: string } | null,
+  submitterInfo?: { id: number; email: string; signature_id?: string } | null,
   globalSettings?: any,
   auditLog?: AuditLogEntry[]
 ) => {
@@ -434,7 +433,7 @@ export const downloadSignedPDF = async (
             canvasWidth,
             canvasHeight,
             {
-              submitterId: submitterInfo?.id,
+              signatureId: submitterInfo?.signature_id,
               submitterEmail: submitterInfo?.email,
               reason: signature.reason,
               globalSettings
@@ -789,7 +788,7 @@ export const downloadSignedPDFWithAuditLog = async (
   pdfUrl: string,
   signatures: any[],
   templateName: string,
-  submitterInfo?: { id: number; email: string } | null,
+  submitterInfo?: { id: number; email: string; signature_id?: string } | null,
   globalSettings?: any,
   auditLog?: AuditLogEntry[]
 ) => {
@@ -903,7 +902,7 @@ export const downloadSignedPDFWithAuditLog = async (
             canvasWidth,
             canvasHeight,
             {
-              submitterId: submitterInfo?.id,
+              signatureId: submitterInfo?.signature_id,
               submitterEmail: submitterInfo?.email,
               reason: signature.reason,
               globalSettings
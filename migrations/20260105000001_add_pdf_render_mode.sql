@@ -0,0 +1,14 @@
+-- Migration: Add PDF render mode settings
+-- Completed documents can either be stamped (values drawn on top of the page)
+-- or have values written into the source PDF's AcroForm fields.
+
+-- Per-template overrides (render mode, flattening, ...)
+ALTER TABLE templates ADD COLUMN IF NOT EXISTS settings JSONB;
+
+-- Account-wide defaults
+ALTER TABLE global_settings ADD COLUMN IF NOT EXISTS pdf_render_mode VARCHAR(20) NOT NULL DEFAULT 'stamp';
+ALTER TABLE global_settings ADD COLUMN IF NOT EXISTS flatten_form_fields BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN templates.settings IS 'Per-template overrides for completed documents (pdf_render_mode, flatten_form_fields)';
+COMMENT ON COLUMN global_settings.pdf_render_mode IS 'stamp = draw values on the page, acroform = fill native PDF form fields';
+COMMENT ON COLUMN global_settings.flatten_form_fields IS 'Flatten filled AcroForm fields into the page content';
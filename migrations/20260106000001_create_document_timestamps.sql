@@ -0,0 +1,20 @@
+-- Migration: Create document_timestamps table
+-- RFC 3161 timestamp tokens issued for completed documents and audit logs
+
+CREATE TABLE IF NOT EXISTS document_timestamps (
+    id BIGSERIAL PRIMARY KEY,
+    template_id BIGINT NOT NULL REFERENCES templates(id) ON DELETE CASCADE,
+    submitter_id BIGINT REFERENCES submitters(id) ON DELETE CASCADE, -- NULL for template-wide documents
+    document_type VARCHAR(50) NOT NULL, -- signed_document, audit_log
+    tsa_name VARCHAR(500) NOT NULL,
+    serial_number VARCHAR(100) NOT NULL,
+    message_imprint VARCHAR(64) NOT NULL, -- hex SHA-256 of the timestamped data
+    gen_time TIMESTAMP WITH TIME ZONE NOT NULL,
+    token BYTEA NOT NULL, -- DER encoded TimeStampToken
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_timestamps_template_id ON document_timestamps(template_id);
+CREATE INDEX IF NOT EXISTS idx_document_timestamps_submitter_id ON document_timestamps(submitter_id);
+
+COMMENT ON TABLE document_timestamps IS 'RFC 3161 timestamp tokens embedded in completed documents and audit logs';
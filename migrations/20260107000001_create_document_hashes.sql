@@ -0,0 +1,22 @@
+-- Migration: Document integrity records
+-- SHA-256 of every completed document and audit log we generate, plus the
+-- signature ID printed next to each signature, so both can be verified publicly.
+
+CREATE TABLE IF NOT EXISTS document_hashes (
+    id BIGSERIAL PRIMARY KEY,
+    template_id BIGINT NOT NULL REFERENCES templates(id) ON DELETE CASCADE,
+    submitter_id BIGINT REFERENCES submitters(id) ON DELETE CASCADE, -- NULL for template-wide documents
+    document_type VARCHAR(50) NOT NULL, -- signed_document, audit_log
+    sha256 VARCHAR(64) NOT NULL, -- hex SHA-256 of the issued file
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_document_hashes_sha256 ON document_hashes(sha256);
+CREATE INDEX IF NOT EXISTS idx_document_hashes_template_id ON document_hashes(template_id);
+
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS signature_id VARCHAR(36);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_submitters_signature_id ON submitters(signature_id);
+
+COMMENT ON TABLE document_hashes IS 'SHA-256 hashes of issued completed documents and audit logs';
+COMMENT ON COLUMN submitters.signature_id IS 'Signature ID printed next to the signature (HMAC of the submitter ID)';
@@ -0,0 +1,6 @@
+-- Migration: PDF/A-2b archival output
+-- When enabled, completed documents and audit logs are converted to PDF/A-2b before signing.
+
+ALTER TABLE global_settings ADD COLUMN IF NOT EXISTS pdfa_output BOOLEAN NOT NULL DEFAULT FALSE;
+
+COMMENT ON COLUMN global_settings.pdfa_output IS 'Convert completed documents and audit logs to PDF/A-2b (templates.settings.pdfa_output overrides)';
@@ -0,0 +1,7 @@
+-- Migration: Personalized submission documents
+-- DOCX templates with {{placeholders}} are filled with each submission's values and converted
+-- to PDF; the submitters of that submission share the resulting documents.
+
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS documents JSONB;
+
+COMMENT ON COLUMN submitters.documents IS 'Documents personalized for this submission (same format as templates.documents); NULL means the template documents';
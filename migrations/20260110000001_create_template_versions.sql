@@ -0,0 +1,21 @@
+-- Migration: Template versions
+-- Immutable snapshots of a template's documents and fields, recorded when the template is
+-- edited or sent. Submitters keep the version they were sent, so later edits don't change
+-- what pending signers see or how their completed documents render.
+
+CREATE TABLE IF NOT EXISTS template_versions (
+    id BIGSERIAL PRIMARY KEY,
+    template_id BIGINT NOT NULL REFERENCES templates(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL, -- 1, 2, ... per template
+    documents JSONB NOT NULL, -- same format as templates.documents, with ids
+    fields JSONB NOT NULL, -- the template's fields at the time
+    checksum VARCHAR(64) NOT NULL, -- hex SHA-256 of documents and fields, to skip unchanged snapshots
+    created_by BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (template_id, version)
+);
+
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS template_version_id BIGINT REFERENCES template_versions(id) ON DELETE SET NULL;
+
+COMMENT ON TABLE template_versions IS 'Immutable snapshots of template documents and fields';
+COMMENT ON COLUMN submitters.template_version_id IS 'Template version the submission was sent with; NULL for submissions sent before versioning';
@@ -0,0 +1,9 @@
+-- Migration: Template trash
+-- Deleting a template moves it to the trash; it is restorable until it is purged, on request or
+-- after the retention period (TEMPLATE_TRASH_RETENTION_DAYS).
+
+ALTER TABLE templates ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_templates_deleted_at ON templates(deleted_at);
+
+COMMENT ON COLUMN templates.deleted_at IS 'Timestamp when the template was moved to the trash (NULL means not deleted)';
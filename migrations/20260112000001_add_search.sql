@@ -0,0 +1,58 @@
+-- Migration: Template tags and full-text search
+-- Templates are searched by name, tags and the text of their documents (extracted when they are
+-- uploaded); submitters by name, email and the values their submission was prefilled with.
+-- The 'simple' configuration is used as documents and names are not all in one language.
+
+ALTER TABLE templates ADD COLUMN IF NOT EXISTS tags TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE templates ADD COLUMN IF NOT EXISTS document_text TEXT;
+ALTER TABLE templates ADD COLUMN IF NOT EXISTS search_vector TSVECTOR;
+
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS prefilled_values JSONB;
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS search_vector TSVECTOR;
+
+CREATE OR REPLACE FUNCTION update_templates_search_vector()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector =
+        setweight(to_tsvector('simple', coalesce(NEW.name, '')), 'A') ||
+        setweight(to_tsvector('simple', array_to_string(NEW.tags, ' ')), 'B') ||
+        setweight(to_tsvector('simple', coalesce(NEW.document_text, '')), 'C');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_templates_search_vector ON templates;
+CREATE TRIGGER trigger_templates_search_vector
+    BEFORE INSERT OR UPDATE OF name, tags, document_text ON templates
+    FOR EACH ROW
+    EXECUTE FUNCTION update_templates_search_vector();
+
+-- Emails are indexed whole and split at '@' and '.', so "jane" finds jane.doe@example.com
+CREATE OR REPLACE FUNCTION update_submitters_search_vector()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.search_vector =
+        setweight(to_tsvector('simple', coalesce(NEW.name, '')), 'A') ||
+        setweight(to_tsvector('simple', coalesce(NEW.email, '') || ' ' || translate(coalesce(NEW.email, ''), '@.', '  ')), 'A') ||
+        setweight(jsonb_to_tsvector('simple', coalesce(NEW.prefilled_values, '{}'::jsonb), '["string", "numeric"]'), 'B');
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS trigger_submitters_search_vector ON submitters;
+CREATE TRIGGER trigger_submitters_search_vector
+    BEFORE INSERT OR UPDATE OF name, email, prefilled_values ON submitters
+    FOR EACH ROW
+    EXECUTE FUNCTION update_submitters_search_vector();
+
+-- Fill the vectors of existing rows; document text of existing templates is extracted on startup
+UPDATE templates SET name = name;
+UPDATE submitters SET name = name;
+
+CREATE INDEX IF NOT EXISTS idx_templates_search_vector ON templates USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS idx_templates_tags ON templates USING GIN (tags);
+CREATE INDEX IF NOT EXISTS idx_submitters_search_vector ON submitters USING GIN (search_vector);
+
+COMMENT ON COLUMN templates.tags IS 'Lowercase tags for organizing and searching templates';
+COMMENT ON COLUMN templates.document_text IS 'Text extracted from the template documents, for search';
+COMMENT ON COLUMN submitters.prefilled_values IS 'Values the submission was created with (DOCX placeholders), for search';
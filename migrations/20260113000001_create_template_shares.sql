@@ -0,0 +1,102 @@
+-- Migration: Template and folder sharing
+-- Templates and folders of an account are open to its team by role: Admins and Editors can edit
+-- them, Members and Agents can use them to send, Viewers can view them. Shares grant a specific
+-- user or role more than that, and a restricted template or folder (and everything in it) is only
+-- open to its owner, account Admins and whoever it is shared with.
+-- Levels are ranked 1 = view, 2 = use (view and send), 3 = edit; 0 is no access.
+
+ALTER TABLE templates ADD COLUMN IF NOT EXISTS access_restricted BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE template_folders ADD COLUMN IF NOT EXISTS access_restricted BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE IF NOT EXISTS template_shares (
+    id BIGSERIAL PRIMARY KEY,
+    template_id BIGINT REFERENCES templates(id) ON DELETE CASCADE,
+    folder_id BIGINT REFERENCES template_folders(id) ON DELETE CASCADE,
+    user_id BIGINT REFERENCES users(id) ON DELETE CASCADE, -- shared with this user...
+    role user_role, -- ...or with everyone of the account with this role
+    access_level VARCHAR(10) NOT NULL CHECK (access_level IN ('view', 'use', 'edit')),
+    created_by BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((template_id IS NULL) <> (folder_id IS NULL)),
+    CHECK ((user_id IS NULL) <> (role IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS idx_template_shares_template_id ON template_shares(template_id);
+CREATE INDEX IF NOT EXISTS idx_template_shares_folder_id ON template_shares(folder_id);
+
+-- Access of a user to a template (p_template_id) or folder (p_folder_id alone), inheriting the
+-- restrictions and shares of the folders it is in
+CREATE OR REPLACE FUNCTION shared_access_level(
+    p_user_id BIGINT,
+    p_owner_id BIGINT,
+    p_account_id BIGINT,
+    p_restricted BOOLEAN,
+    p_template_id BIGINT,
+    p_folder_id BIGINT
+)
+RETURNS INTEGER AS $$
+DECLARE
+    v_role user_role;
+    v_account_id BIGINT;
+    v_restricted BOOLEAN;
+    v_granted INTEGER;
+    v_default INTEGER;
+BEGIN
+    IF p_owner_id = p_user_id THEN
+        RETURN 3;
+    END IF;
+    SELECT role, account_id INTO v_role, v_account_id FROM users WHERE id = p_user_id;
+    IF v_account_id IS NULL OR v_account_id IS DISTINCT FROM p_account_id THEN
+        RETURN 0;
+    END IF;
+    IF v_role = 'admin' THEN
+        RETURN 3;
+    END IF;
+
+    WITH RECURSIVE folders AS (
+        SELECT id, parent_folder_id, access_restricted, 0 AS depth FROM template_folders WHERE id = p_folder_id
+        UNION ALL
+        SELECT f.id, f.parent_folder_id, f.access_restricted, a.depth + 1
+        FROM template_folders f INNER JOIN folders a ON f.id = a.parent_folder_id
+        WHERE a.depth < 100
+    )
+    SELECT p_restricted OR coalesce(bool_or(access_restricted), FALSE),
+           coalesce((
+               SELECT max(CASE s.access_level WHEN 'edit' THEN 3 WHEN 'use' THEN 2 ELSE 1 END)
+               FROM template_shares s
+               WHERE (s.template_id = p_template_id OR s.folder_id IN (SELECT id FROM folders))
+                 AND (s.user_id = p_user_id OR s.role = v_role)
+           ), 0)
+    INTO v_restricted, v_granted
+    FROM folders;
+
+    v_default := CASE
+        WHEN v_restricted THEN 0
+        WHEN v_role = 'editor' THEN 3
+        WHEN v_role IN ('member', 'agent') THEN 2
+        WHEN v_role = 'viewer' THEN 1
+        ELSE 0
+    END;
+    RETURN greatest(v_default, v_granted);
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+CREATE OR REPLACE FUNCTION template_access_level(p_template_id BIGINT, p_user_id BIGINT)
+RETURNS INTEGER AS $$
+    SELECT coalesce((
+        SELECT shared_access_level(p_user_id, user_id, account_id, access_restricted, id, folder_id)
+        FROM templates WHERE id = p_template_id
+    ), 0);
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION folder_access_level(p_folder_id BIGINT, p_user_id BIGINT)
+RETURNS INTEGER AS $$
+    SELECT coalesce((
+        SELECT shared_access_level(p_user_id, user_id, account_id, FALSE, NULL, id)
+        FROM template_folders WHERE id = p_folder_id
+    ), 0);
+$$ LANGUAGE sql STABLE;
+
+COMMENT ON TABLE template_shares IS 'Access to a template or folder granted to a user or to a role of the account';
+COMMENT ON COLUMN templates.access_restricted IS 'Only open to the owner, account Admins and the users and roles it is shared with';
+COMMENT ON COLUMN template_folders.access_restricted IS 'Restricts the folder and everything in it like templates.access_restricted';
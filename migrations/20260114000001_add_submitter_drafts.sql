@@ -0,0 +1,11 @@
+-- Migration: Signer drafts
+-- The signing page saves the values entered so far, so signers can pick up where they left off
+-- and template analytics can tell at which field unfinished submissions were abandoned.
+
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS draft_values JSONB;
+ALTER TABLE submitters ADD COLUMN IF NOT EXISTS draft_saved_at TIMESTAMP WITH TIME ZONE;
+
+CREATE INDEX IF NOT EXISTS idx_submitters_template_created ON submitters(template_id, created_at);
+
+COMMENT ON COLUMN submitters.draft_values IS 'Values entered on the signing page before completing, by submission field id';
+COMMENT ON COLUMN submitters.draft_saved_at IS 'When the draft was last saved';
@@ -0,0 +1,22 @@
+-- Migration: Field snippets
+-- Named groups of template fields (e.g. signature, printed name, date and title) saved with their
+-- positions relative to each other, to place them on other templates in one go. A snippet belongs
+-- to the user who saved it and can be shared with everyone of their account.
+
+CREATE TABLE IF NOT EXISTS field_snippets (
+    id BIGSERIAL PRIMARY KEY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    account_id BIGINT REFERENCES accounts(id) ON DELETE CASCADE, -- the owner's account when saved
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    fields JSONB NOT NULL, -- positions relative to the top-left corner of the group
+    shared BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_field_snippets_user_id ON field_snippets(user_id);
+CREATE INDEX IF NOT EXISTS idx_field_snippets_account_id ON field_snippets(account_id) WHERE shared;
+
+COMMENT ON TABLE field_snippets IS 'Saved groups of template fields to apply to other templates';
+COMMENT ON COLUMN field_snippets.shared IS 'Usable by everyone of the account, not only the owner';
@@ -0,0 +1,7 @@
+-- Migration: File names of issued documents
+-- Documents issued separately share a template, submitter and type; the file name tells their
+-- hashes apart. Every issued copy keeps its own row, as each copy is signed and timestamped anew.
+
+ALTER TABLE document_hashes ADD COLUMN IF NOT EXISTS file_name VARCHAR(255) NOT NULL DEFAULT '';
+
+COMMENT ON COLUMN document_hashes.file_name IS 'File name of the document, to tell documents issued separately apart';
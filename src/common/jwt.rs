@@ -126,6 +126,16 @@ pub async fn auth_middleware(mut request: Request, next: Next) -> Result<Respons
     Ok(next.run(request).await)
 }
 
+// The user of a valid bearer token, for public routes that serve more to signed-in users
+pub fn bearer_user_id(headers: &axum::http::HeaderMap) -> Option<i64> {
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))?;
+    let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string());
+    verify_jwt(token, &secret).ok().map(|claims| claims.sub)
+}
+
 pub fn decode_jwt(token: &str, secret: &str) -> Result<Claims, StatusCode> {
     let key = DecodingKey::from_secret(secret.as_ref());
     let validation = Validation::new(Algorithm::HS256);
@@ -1,5 +1,6 @@
 pub mod authorization;
 pub mod jwt;
+pub mod pagination;
 pub mod requests;
 pub mod responses;
 pub mod token;
@@ -0,0 +1,127 @@
+use crate::common::responses::Pagination;
+
+pub const DEFAULT_PER_PAGE: i64 = 50;
+pub const MAX_PER_PAGE: i64 = 200;
+
+/// Page of a list to return
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageRequest {
+    pub page: i64,
+    pub per_page: i64,
+}
+
+impl PageRequest {
+    /// None when neither page nor per_page is given: lists are then returned whole, as they were
+    /// before they could be paginated
+    pub fn from_query(page: Option<i64>, per_page: Option<i64>) -> Option<PageRequest> {
+        if page.is_none() && per_page.is_none() {
+            return None;
+        }
+        Some(PageRequest {
+            page: page.unwrap_or(1).max(1),
+            per_page: per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE),
+        })
+    }
+
+    pub fn limit(&self) -> i64 {
+        self.per_page
+    }
+
+    pub fn offset(&self) -> i64 {
+        // Saturates for absurd page numbers, which then return an empty page
+        (self.page.max(1) - 1).saturating_mul(self.per_page)
+    }
+}
+
+/// Pagination of a list with `total` items, of which `page` was returned (all if None)
+pub fn pagination(total: i64, page: Option<PageRequest>) -> Pagination {
+    let page = page.unwrap_or(PageRequest { page: 1, per_page: total.max(1) });
+    Pagination {
+        total,
+        page: page.page,
+        per_page: page.per_page,
+        total_pages: (total + page.per_page - 1) / page.per_page,
+    }
+}
+
+/// Column and direction to sort a list by
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SortOrder {
+    pub column: &'static str,
+    pub descending: bool,
+}
+
+impl SortOrder {
+    /// The `sort` column must be one of `columns`; `order` is "asc" or "desc". Without them the
+    /// list is sorted by `default`.
+    pub fn from_query(
+        sort: Option<&str>,
+        order: Option<&str>,
+        columns: &[&'static str],
+        default: SortOrder,
+    ) -> Result<SortOrder, String> {
+        let column = match sort {
+            None => default.column,
+            Some(sort) => *columns.iter()
+                .find(|column| **column == sort)
+                .ok_or_else(|| format!("Cannot sort by '{}'; use one of: {}", sort, columns.join(", ")))?,
+        };
+        let descending = match order {
+            None if sort.is_none() => default.descending,
+            None => false,
+            Some("asc") => false,
+            Some("desc") => true,
+            Some(order) => return Err(format!("Unknown sort order '{}'; use asc or desc", order)),
+        };
+        Ok(SortOrder { column, descending })
+    }
+
+    /// ORDER BY clause, with `tie_breaker` keeping pages stable when sort values repeat
+    pub fn order_by(&self, table: &str, tie_breaker: &str) -> String {
+        let direction = if self.descending { "DESC" } else { "ASC" };
+        format!(
+            "ORDER BY {table}.{column} {direction} NULLS LAST, {table}.{tie_breaker} {direction}",
+            table = table,
+            column = self.column,
+            direction = direction,
+            tie_breaker = tie_breaker,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_are_optional_and_bounded() {
+        assert_eq!(PageRequest::from_query(None, None), None);
+        let page = PageRequest::from_query(Some(3), Some(1000)).unwrap();
+        assert_eq!((page.limit(), page.offset()), (MAX_PER_PAGE, 2 * MAX_PER_PAGE));
+        assert_eq!(PageRequest::from_query(Some(0), None).unwrap().offset(), 0);
+        assert_eq!(PageRequest::from_query(Some(i64::MAX), Some(MAX_PER_PAGE)).unwrap().offset(), i64::MAX);
+        assert_eq!(PageRequest { page: i64::MIN, per_page: 50 }.offset(), 0);
+
+        let all = pagination(7, None);
+        assert_eq!((all.page, all.per_page, all.total_pages), (1, 7, 1));
+        assert_eq!(pagination(0, None).total_pages, 0);
+        assert_eq!(pagination(101, Some(PageRequest { page: 2, per_page: 50 })).total_pages, 3);
+    }
+
+    #[test]
+    fn sort_columns_are_whitelisted() {
+        let default = SortOrder { column: "created_at", descending: true };
+        let columns = ["created_at", "name"];
+        assert_eq!(SortOrder::from_query(None, None, &columns, default), Ok(default));
+        assert_eq!(
+            SortOrder::from_query(Some("name"), None, &columns, default),
+            Ok(SortOrder { column: "name", descending: false })
+        );
+        assert!(SortOrder::from_query(Some("name; DROP TABLE templates"), None, &columns, default).is_err());
+        assert!(SortOrder::from_query(None, Some("up"), &columns, default).is_err());
+        assert_eq!(
+            SortOrder::from_query(Some("name"), Some("desc"), &columns, default).unwrap().order_by("t", "id"),
+            "ORDER BY t.name DESC NULLS LAST, t.id DESC"
+        );
+    }
+}
@@ -11,6 +11,19 @@ pub struct ApiResponse<T> {
     pub message: String,
     pub data: Option<T>,
     pub error: Option<String>,
+    /// Set by list endpoints
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+}
+
+/// Position of a page in a list
+#[derive(Serialize, ToSchema)]
+pub struct Pagination {
+    /// Number of items matching the filters, on all pages
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+    pub total_pages: i64,
 }
 
 impl<T> ApiResponse<T> {
@@ -24,6 +37,7 @@ impl<T> ApiResponse<T> {
                 message,
                 data: Some(data),
                 error: None,
+                pagination: None,
             }),
         )
     }
@@ -38,6 +52,7 @@ impl<T> ApiResponse<T> {
                 message,
                 data: Some(data),
                 error: None,
+                pagination: None,
             }),
         )
     }
@@ -52,6 +67,7 @@ impl<T> ApiResponse<T> {
                 message: "Bad Request".to_string(),
                 data: None,
                 error: Some(error),
+                pagination: None,
             }),
         )
     }
@@ -66,6 +82,7 @@ impl<T> ApiResponse<T> {
                 message: "Unauthorized".to_string(),
                 data: None,
                 error: Some(error),
+                pagination: None,
             }),
         )
     }
@@ -80,6 +97,7 @@ impl<T> ApiResponse<T> {
                 message: "Forbidden".to_string(),
                 data: None,
                 error: Some(error),
+                pagination: None,
             }),
         )
     }
@@ -94,6 +112,22 @@ impl<T> ApiResponse<T> {
                 message: "Not Found".to_string(),
                 data: None,
                 error: Some(error),
+                pagination: None,
+            }),
+        )
+    }
+
+    /// 409 Conflict - Request conflicts with the resource's current state
+    pub fn conflict(error: String) -> (StatusCode, Json<ApiResponse<T>>) {
+        (
+            StatusCode::CONFLICT,
+            Json(ApiResponse {
+                success: false,
+                status_code: 409,
+                message: "Conflict".to_string(),
+                data: None,
+                error: Some(error),
+                pagination: None,
             }),
         )
     }
@@ -108,6 +142,7 @@ impl<T> ApiResponse<T> {
                 message: "Internal Server Error".to_string(),
                 data: None,
                 error: Some(error),
+                pagination: None,
             }),
         )
     }
@@ -116,6 +151,13 @@ impl<T> ApiResponse<T> {
     pub fn success(data: T, message: String) -> (StatusCode, Json<Self>) {
         Self::ok(data, message)
     }
+
+    /// 200 OK - One page of a list
+    pub fn paginated(data: T, pagination: Pagination, message: String) -> (StatusCode, Json<Self>) {
+        let (status, Json(mut response)) = Self::ok(data, message);
+        response.pagination = Some(pagination);
+        (status, Json(response))
+    }
 }
 
 /// Login response containing JWT token and user info
@@ -144,6 +186,7 @@ impl ApiResponse<LoginResponse> {
                 message,
                 data: Some(login_data),
                 error: None,
+                pagination: None,
             }),
         )
     }
@@ -160,6 +203,7 @@ impl ApiResponse<TwoFactorRequiredResponse> {
                 message,
                 data: Some(tfa_data),
                 error: None,
+                pagination: None,
             }),
         )
     }
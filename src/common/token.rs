@@ -19,4 +19,14 @@ pub fn generate_token() -> String {
     let mut rng = rand::thread_rng();
     let bytes: [u8; 32] = rng.gen();
     base64::encode(&bytes).replace('/', "_").trim_end_matches('=').to_string()
-}
\ No newline at end of file
+}
+/// Signature ID printed next to signatures on completed documents.
+/// 128-bit HMAC of the submitter ID formatted as a UUID, so IDs can't be guessed or enumerated.
+/// Keyed with SIGNATURE_ID_SECRET (falls back to JWT_SECRET); rotating it changes future IDs.
+pub fn signature_id(submitter_id: i64) -> String {
+    let secret = std::env::var("SIGNATURE_ID_SECRET")
+        .or_else(|_| std::env::var("JWT_SECRET"))
+        .expect("SIGNATURE_ID_SECRET or JWT_SECRET must be set");
+    let hex = hash_token(&secret, &format!("signature-id:{}", submitter_id)).to_uppercase();
+    format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])
+}
@@ -69,6 +69,7 @@ pub struct CreateOAuthToken {
     pub expires_at: Option<DateTime<Utc>>,
 }
 use sqlx::FromRow;
+use crate::common::pagination::{PageRequest, SortOrder};
 use crate::models::role::Role;
 
 // Database-specific account model
@@ -216,6 +217,7 @@ pub struct DbTemplate {
     pub account_id: Option<i64>,
     pub folder_id: Option<i64>,
     pub documents: Option<serde_json::Value>, // JSONB field
+    pub settings: Option<serde_json::Value>, // JSONB field - per-template overrides (TemplateSettings)
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -337,6 +339,184 @@ pub struct CreateSubmissionField {
     pub partner: Option<String>, // Which partner/signer this field belongs to
 }
 
+// RFC 3161 timestamp token issued for a completed document or audit log
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbDocumentTimestamp {
+    pub id: i64,
+    pub template_id: i64,
+    pub submitter_id: Option<i64>,
+    pub document_type: String, // signed_document, audit_log
+    pub tsa_name: String,
+    pub serial_number: String,
+    pub message_imprint: String,
+    pub gen_time: DateTime<Utc>,
+    pub token: Vec<u8>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Create document timestamp request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDocumentTimestamp {
+    pub template_id: i64,
+    pub submitter_id: Option<i64>,
+    pub document_type: String,
+    pub tsa_name: String,
+    pub serial_number: String,
+    pub message_imprint: String,
+    pub gen_time: DateTime<Utc>,
+    pub token: Vec<u8>,
+}
+
+// SHA-256 of an issued completed document or audit log
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbDocumentHash {
+    pub id: i64,
+    pub template_id: i64,
+    pub submitter_id: Option<i64>,
+    pub document_type: String, // signed_document, audit_log
+    pub sha256: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+// Create document hash request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDocumentHash {
+    pub template_id: i64,
+    pub submitter_id: Option<i64>,
+    pub document_type: String,
+    pub file_name: String,
+    pub sha256: String,
+    pub size_bytes: i64,
+}
+
+// Immutable snapshot of a template's documents and fields
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbTemplateVersion {
+    pub id: i64,
+    pub template_id: i64,
+    pub version: i32,
+    pub documents: serde_json::Value, // Vec<Document>, with ids
+    pub fields: serde_json::Value, // Vec<TemplateVersionField>
+    pub checksum: String,
+    pub created_by: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Access to a template or folder granted to a user or to a role of the account
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbTemplateShare {
+    pub id: i64,
+    pub template_id: Option<i64>,
+    pub folder_id: Option<i64>,
+    pub user_id: Option<i64>,
+    pub role: Option<crate::models::role::Role>,
+    pub access_level: String, // view, use or edit
+    pub created_by: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Saved group of template fields; fields is a JSON array of SnippetField
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbFieldSnippet {
+    pub id: i64,
+    pub user_id: i64,
+    pub account_id: Option<i64>,
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: serde_json::Value,
+    pub shared: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// What template analytics need of a submitter
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbSubmitterActivity {
+    pub id: i64,
+    pub name: String,
+    pub email: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub viewed_at: Option<DateTime<Utc>>,
+    pub signed_at: Option<DateTime<Utc>>,
+    pub reminder_count: i32,
+    pub decline_reason: Option<String>,
+    pub draft_values: Option<serde_json::Value>, // By submission field id
+    pub draft_saved_at: Option<DateTime<Utc>>,
+}
+
+// Folders the template list is limited to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TemplateFolderFilter {
+    Root, // templates outside any folder
+    Any,
+    Folder(i64),
+}
+
+// Filters, sort order and page of the team's template list
+#[derive(Debug, Clone)]
+pub struct TemplateListFilter {
+    pub folder: TemplateFolderFilter,
+    pub user_id: Option<i64>, // owner
+    pub tag: Option<String>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: SortOrder,
+    pub page: Option<PageRequest>, // None: all of them
+}
+
+// Filters, sort order and page of the team's submitter list
+#[derive(Debug, Clone)]
+pub struct SubmitterListFilter {
+    pub status: Option<String>,
+    pub template_id: Option<i64>,
+    pub user_id: Option<i64>, // sender
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: SortOrder,
+    pub page: Option<PageRequest>, // None: all of them
+}
+
+// Filters, sort order and page of one level of the team's folder tree
+#[derive(Debug, Clone)]
+pub struct FolderListFilter {
+    pub parent_folder_id: Option<i64>, // None: top-level folders
+    pub user_id: Option<i64>, // creator
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    pub sort: SortOrder,
+    pub page: Option<PageRequest>, // None: all of them
+}
+
+// Template matching a full-text search, most relevant first
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbTemplateSearchHit {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub user_id: i64,
+    pub folder_id: Option<i64>,
+    pub tags: Vec<String>,
+    pub rank: f32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Submitter matching a full-text search, most relevant first
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DbSubmitterSearchHit {
+    pub id: i64,
+    pub template_id: i64,
+    pub template_name: String,
+    pub name: String,
+    pub email: String,
+    pub status: String,
+    pub rank: f32,
+    pub signed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
 // Create payment record request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreatePaymentRecord {
@@ -373,6 +553,9 @@ pub struct DbGlobalSettings {
     pub completion_body: Option<String>,
     pub redirect_title: Option<String>,
     pub redirect_url: Option<String>,
+    pub pdf_render_mode: String, // "stamp" | "acroform"
+    pub flatten_form_fields: bool,
+    pub pdfa_output: bool, // Convert completed documents to PDF/A-2b
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -399,6 +582,9 @@ pub struct UpdateGlobalSettings {
     pub completion_body: Option<String>,
     pub redirect_title: Option<String>,
     pub redirect_url: Option<String>,
+    pub pdf_render_mode: Option<String>,
+    pub flatten_form_fields: Option<bool>,
+    pub pdfa_output: Option<bool>,
 }
 
 // Email template database model
@@ -417,6 +603,19 @@ pub struct DbEmailTemplate {
     pub updated_at: DateTime<Utc>,
 }
 
+// Create email template request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateEmailTemplate {
+    pub user_id: i64,
+    pub template_type: String, // 'invitation', 'reminder', 'completion', 'copy'
+    pub subject: String,
+    pub body: String,
+    pub body_format: String, // 'text' or 'html'
+    pub is_default: bool,
+    pub attach_documents: bool,
+    pub attach_audit_log: bool,
+}
+
 // Update email template request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateEmailTemplate {
@@ -1,8 +1,9 @@
 use sqlx::{PgPool, Row};
 use chrono::{Utc, DateTime};
 
-use super::models::{DbUser, CreateUser, DbTemplate, CreateTemplate, DbTemplateField, CreateTemplateField, CreateSubmitter, DbSubmitter, DbPaymentRecord, CreatePaymentRecord, DbSignatureData, DbSubscriptionPlan, DbTemplateFolder, CreateTemplateFolder, DbSubmissionField, CreateSubmissionField, DbGlobalSettings, UpdateGlobalSettings, DbEmailTemplate, UpdateEmailTemplate, DbAccount, CreateAccount, UpdateAccount, DbAccountLinkedAccount};
+use super::models::{DbUser, CreateUser, DbTemplate, CreateTemplate, DbTemplateField, CreateTemplateField, CreateSubmitter, DbSubmitter, DbPaymentRecord, CreatePaymentRecord, DbSignatureData, DbSubscriptionPlan, DbTemplateFolder, CreateTemplateFolder, DbSubmissionField, CreateSubmissionField, DbGlobalSettings, UpdateGlobalSettings, DbEmailTemplate, CreateEmailTemplate, UpdateEmailTemplate, DbAccount, CreateAccount, UpdateAccount, DbAccountLinkedAccount, DbDocumentTimestamp, CreateDocumentTimestamp, DbDocumentHash, CreateDocumentHash, DbTemplateVersion, DbTemplateShare, DbFieldSnippet, DbSubmitterActivity, DbTemplateSearchHit, DbSubmitterSearchHit, TemplateListFilter, TemplateFolderFilter, SubmitterListFilter, FolderListFilter};
 use crate::models::signature::SignatureInfo;
+use crate::models::template_share::{AccessLevel, ShareRequest};
 
 // Structured query implementations for better organization
 pub struct AccountQueries;
@@ -517,7 +518,7 @@ impl TemplateQueries {
             r#"
             INSERT INTO templates (name, slug, user_id, account_id, folder_id, documents, created_at, updated_at)
             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at
+            RETURNING id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at
             "#
         )
         .bind(&template_data.name)
@@ -540,14 +541,15 @@ impl TemplateQueries {
             folder_id: row.get(5),
             // fields: None, // Removed - now stored in template_fields table
             documents: row.get(6),
-            created_at: row.get(7),
-            updated_at: row.get(8),
+            settings: row.get(7),
+            created_at: row.get(8),
+            updated_at: row.get(9),
         })
     }
 
     pub async fn get_template_by_id(pool: &PgPool, id: i64) -> Result<Option<DbTemplate>, sqlx::Error> {
         let row = sqlx::query(
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE id = $1"
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE id = $1 AND deleted_at IS NULL"
         )
         .bind(id)
         .fetch_optional(pool)
@@ -563,6 +565,7 @@ impl TemplateQueries {
                 folder_id: row.try_get("folder_id")?,
                 // fields: None, // Removed - now stored in template_fields table
                 documents: row.try_get("documents")?,
+                settings: row.try_get("settings")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             })),
@@ -572,7 +575,7 @@ impl TemplateQueries {
 
     pub async fn get_template_by_slug(pool: &PgPool, slug: &str) -> Result<Option<DbTemplate>, sqlx::Error> {
         let row = sqlx::query(
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE slug = $1"
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE slug = $1 AND deleted_at IS NULL"
         )
         .bind(slug)
         .fetch_optional(pool)
@@ -588,6 +591,7 @@ impl TemplateQueries {
                 folder_id: row.try_get("folder_id")?,
                 // fields: None, // Removed - now stored in template_fields table
                 documents: row.try_get("documents")?,
+                settings: row.try_get("settings")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             })),
@@ -606,10 +610,10 @@ impl TemplateQueries {
         
         let query_str = if let Some(acc_id) = account_id {
             // User has account - show all templates in the account
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE account_id = $1 AND folder_id IS NULL ORDER BY created_at DESC"
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE account_id = $1 AND folder_id IS NULL AND deleted_at IS NULL ORDER BY created_at DESC"
         } else {
             // User doesn't have account - show only their templates
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE user_id = $1 AND folder_id IS NULL ORDER BY created_at DESC"
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE user_id = $1 AND folder_id IS NULL AND deleted_at IS NULL ORDER BY created_at DESC"
         };
         
         let rows = sqlx::query(query_str)
@@ -628,6 +632,7 @@ impl TemplateQueries {
                 folder_id: row.try_get("folder_id")?,
                 // fields: None, // Removed - now stored in template_fields table
                 documents: row.try_get("documents")?,
+                settings: row.try_get("settings")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             });
@@ -635,8 +640,9 @@ impl TemplateQueries {
         Ok(templates)
     }
 
-    // Get templates accessible by team (invited users can see inviter's templates)
-    pub async fn get_team_templates(pool: &PgPool, user_id: i64) -> Result<Vec<DbTemplate>, sqlx::Error> {
+    // Get templates accessible by team (invited users can see inviter's templates), one page of
+    // them with the number matching the filter
+    pub async fn get_team_templates(pool: &PgPool, user_id: i64, filter: &TemplateListFilter) -> Result<(Vec<DbTemplate>, i64), sqlx::Error> {
         // Get user's account_id
         let account_id_result = sqlx::query("SELECT account_id FROM users WHERE id = $1")
             .bind(user_id)
@@ -646,46 +652,58 @@ impl TemplateQueries {
 
         // If user has account_id, get all templates in that account
         // Otherwise, only get user's own templates
-        let query_str = if account_id.is_some() {
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at 
-             FROM templates 
-             WHERE account_id = $1 AND folder_id IS NULL 
-             ORDER BY created_at DESC"
-        } else {
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at 
-             FROM templates 
-             WHERE user_id = $1 AND folder_id IS NULL 
-             ORDER BY created_at DESC"
+        let scope = if account_id.is_some() { "account_id" } else { "user_id" };
+        let (any_folder, folder_id) = match filter.folder {
+            TemplateFolderFilter::Root => (false, None),
+            TemplateFolderFilter::Any => (true, None),
+            TemplateFolderFilter::Folder(folder_id) => (false, Some(folder_id)),
         };
+        let conditions = format!(
+            "templates.{} = $1 AND deleted_at IS NULL
+               AND ($2 OR folder_id IS NOT DISTINCT FROM $3)
+               AND ($4::bigint IS NULL OR user_id = $4)
+               AND ($5::text IS NULL OR tags @> ARRAY[$5::text])
+               AND ($6::timestamptz IS NULL OR created_at >= $6)
+               AND ($7::timestamptz IS NULL OR created_at < $7)
+               AND template_access_level(templates.id, $8) > 0",
+            scope
+        );
 
-        let rows = if let Some(acc_id) = account_id {
-            sqlx::query(query_str)
-                .bind(acc_id)
-                .fetch_all(pool)
-                .await?
-        } else {
-            sqlx::query(query_str)
-                .bind(user_id)
-                .fetch_all(pool)
-                .await?
-        };
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM templates WHERE {}", conditions))
+            .bind(account_id.unwrap_or(user_id))
+            .bind(any_folder)
+            .bind(folder_id)
+            .bind(filter.user_id)
+            .bind(filter.tag.as_deref())
+            .bind(filter.created_from)
+            .bind(filter.created_to)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+        let rows = sqlx::query(&format!(
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at
+             FROM templates
+             WHERE {}
+             {}
+             LIMIT $9 OFFSET $10",
+            conditions,
+            filter.sort.order_by("templates", "id")
+        ))
+        .bind(account_id.unwrap_or(user_id))
+        .bind(any_folder)
+        .bind(folder_id)
+        .bind(filter.user_id)
+        .bind(filter.tag.as_deref())
+        .bind(filter.created_from)
+        .bind(filter.created_to)
+        .bind(user_id)
+        .bind(filter.page.map(|page| page.limit()))
+        .bind(filter.page.map_or(0, |page| page.offset()))
+        .fetch_all(pool)
+        .await?;
 
-        let mut templates = Vec::new();
-        for row in rows {
-            templates.push(DbTemplate {
-                id: row.try_get("id")?,
-                name: row.try_get("name")?,
-                slug: row.try_get("slug")?,
-                user_id: row.try_get("user_id")?,
-                account_id: row.try_get("account_id")?,
-                folder_id: row.try_get("folder_id")?,
-                // fields: None, // Removed - now stored in template_fields table
-                documents: row.try_get("documents")?,
-                created_at: row.try_get("created_at")?,
-                updated_at: row.try_get("updated_at")?,
-            });
-        }
-        Ok(templates)
+        let templates = rows.iter().map(Self::template_from_row).collect::<Result<Vec<_>, _>>()?;
+        Ok((templates, total))
     }
 
     pub async fn update_template(pool: &PgPool, id: i64, name: Option<&str>) -> Result<Option<DbTemplate>, sqlx::Error> {
@@ -696,7 +714,7 @@ impl TemplateQueries {
             UPDATE templates
             SET name = COALESCE($2, name), updated_at = $3
             WHERE id = $1
-            RETURNING id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at
+            RETURNING id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at
             "#
         )
         .bind(id)
@@ -715,6 +733,7 @@ impl TemplateQueries {
                 folder_id: row.try_get("folder_id")?,
                 // fields: None, // Removed - now stored in template_fields table
                 documents: row.try_get("documents")?,
+                settings: row.try_get("settings")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             })),
@@ -722,6 +741,277 @@ impl TemplateQueries {
         }
     }
 
+    pub async fn update_template_settings(pool: &PgPool, id: i64, settings: &serde_json::Value) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET settings = $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(settings)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Sets the given keys of the template's settings, keeping the others
+    pub async fn merge_template_settings(pool: &PgPool, id: i64, settings: &serde_json::Value) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET settings = COALESCE(settings, '{}'::jsonb) || $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(settings)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_template_documents(pool: &PgPool, id: i64, documents: &serde_json::Value) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET documents = $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(documents)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Store a template's new document list together with the positions of the fields that change
+    // with it (None removes the field), so fields never point into a document the template no
+    // longer has
+    pub async fn update_template_documents_and_fields(
+        pool: &PgPool,
+        id: i64,
+        documents: &serde_json::Value,
+        fields: &[(i64, Option<serde_json::Value>)],
+    ) -> Result<bool, sqlx::Error> {
+        let now = Utc::now();
+        let mut tx = pool.begin().await?;
+        for (field_id, position) in fields {
+            match position {
+                Some(position) => {
+                    sqlx::query("UPDATE template_fields SET position = $3, updated_at = $4 WHERE id = $1 AND template_id = $2 AND deleted_at IS NULL")
+                        .bind(field_id)
+                        .bind(id)
+                        .bind(position)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+                None => {
+                    sqlx::query("UPDATE template_fields SET deleted_at = $3 WHERE id = $1 AND template_id = $2 AND deleted_at IS NULL")
+                        .bind(field_id)
+                        .bind(id)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+        }
+        let result = sqlx::query("UPDATE templates SET documents = $2, updated_at = $3 WHERE id = $1")
+            .bind(id)
+            .bind(documents)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Move a template to the trash; it is purged with delete_template later
+    pub async fn trash_template(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET deleted_at = $2 WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn restore_template(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET deleted_at = NULL, updated_at = $2 WHERE id = $1 AND deleted_at IS NOT NULL")
+            .bind(id)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // A template in the trash, with the time it was deleted
+    pub async fn get_trashed_template(pool: &PgPool, id: i64) -> Result<Option<(DbTemplate, DateTime<Utc>)>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at, deleted_at FROM templates WHERE id = $1 AND deleted_at IS NOT NULL"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Some((Self::template_from_row(&row)?, row.try_get("deleted_at")?))),
+            None => Ok(None),
+        }
+    }
+
+    // Templates in the trash the user can see (their account's, or their own), latest deleted first
+    pub async fn get_trashed_templates(pool: &PgPool, user_id: i64) -> Result<Vec<(DbTemplate, DateTime<Utc>)>, sqlx::Error> {
+        let account_id: Option<i64> = sqlx::query_scalar("SELECT account_id FROM users WHERE id = $1")
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+
+        let query_str = if account_id.is_some() {
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at, deleted_at
+             FROM templates
+             WHERE account_id = $1 AND deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC"
+        } else {
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at, deleted_at
+             FROM templates
+             WHERE user_id = $1 AND deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC"
+        };
+        let rows = sqlx::query(query_str)
+            .bind(account_id.unwrap_or(user_id))
+            .fetch_all(pool)
+            .await?;
+
+        let mut templates = Vec::new();
+        for row in rows {
+            templates.push((Self::template_from_row(&row)?, row.try_get("deleted_at")?));
+        }
+        Ok(templates)
+    }
+
+    // Templates deleted before the given time, due to be purged
+    pub async fn get_templates_trashed_before(pool: &PgPool, before: DateTime<Utc>) -> Result<Vec<DbTemplate>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE deleted_at < $1"
+        )
+        .bind(before)
+        .fetch_all(pool)
+        .await?;
+
+        rows.iter().map(Self::template_from_row).collect()
+    }
+
+    pub async fn get_template_tags(pool: &PgPool, id: i64) -> Result<Vec<String>, sqlx::Error> {
+        let tags: Option<Vec<String>> = sqlx::query_scalar("SELECT tags FROM templates WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(tags.unwrap_or_default())
+    }
+
+    pub async fn set_template_tags(pool: &PgPool, id: i64, tags: &[String]) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET tags = $2, updated_at = $3 WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id)
+            .bind(tags)
+            .bind(Utc::now())
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Tags used by the templates the user can see, most used first
+    pub async fn get_team_template_tags(pool: &PgPool, user_id: i64) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT tag FROM templates, unnest(tags) AS tag
+            WHERE (account_id = (SELECT account_id FROM users WHERE id = $1) OR user_id = $1) AND deleted_at IS NULL
+              AND template_access_level(id, $1) > 0
+            GROUP BY tag
+            ORDER BY COUNT(*) DESC, tag
+            "#
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    // Text extracted from the template's documents, indexed for search
+    pub async fn set_template_document_text(pool: &PgPool, id: i64, text: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("UPDATE templates SET document_text = $2 WHERE id = $1")
+            .bind(id)
+            .bind(text)
+            .execute(pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Templates whose document text has not been extracted yet, oldest first
+    pub async fn get_templates_without_document_text(pool: &PgPool, limit: i64) -> Result<Vec<DbTemplate>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE document_text IS NULL AND deleted_at IS NULL ORDER BY id LIMIT $1"
+        )
+        .bind(limit)
+        .fetch_all(pool)
+        .await?;
+
+        rows.iter().map(Self::template_from_row).collect()
+    }
+
+    // Templates the user can see (their account's, or their own) matching a tsquery, ranked by
+    // relevance, with the total number of matches
+    pub async fn search_templates(
+        pool: &PgPool,
+        user_id: i64,
+        tsquery: &str,
+        tag: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<DbTemplateSearchHit>, i64), sqlx::Error> {
+        let filter = r#"
+            FROM templates t
+            WHERE (t.account_id = (SELECT account_id FROM users WHERE id = $1) OR t.user_id = $1)
+              AND t.deleted_at IS NULL
+              AND t.search_vector @@ to_tsquery('simple', $2)
+              AND ($3::text IS NULL OR t.tags @> ARRAY[$3::text])
+              AND template_access_level(t.id, $1) > 0
+        "#;
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {}", filter))
+            .bind(user_id)
+            .bind(tsquery)
+            .bind(tag)
+            .fetch_one(pool)
+            .await?;
+        let hits = sqlx::query_as::<_, DbTemplateSearchHit>(&format!(
+            "SELECT t.id, t.name, t.slug, t.user_id, t.folder_id, t.tags, ts_rank(t.search_vector, to_tsquery('simple', $2)) AS rank, t.created_at, t.updated_at
+             {}
+             ORDER BY rank DESC, t.updated_at DESC
+             LIMIT $4 OFFSET $5",
+            filter
+        ))
+        .bind(user_id)
+        .bind(tsquery)
+        .bind(tag)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(pool)
+        .await?;
+
+        Ok((hits, total))
+    }
+
+    fn template_from_row(row: &sqlx::postgres::PgRow) -> Result<DbTemplate, sqlx::Error> {
+        Ok(DbTemplate {
+            id: row.try_get("id")?,
+            name: row.try_get("name")?,
+            slug: row.try_get("slug")?,
+            user_id: row.try_get("user_id")?,
+            account_id: row.try_get("account_id")?,
+            folder_id: row.try_get("folder_id")?,
+            documents: row.try_get("documents")?,
+            settings: row.try_get("settings")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
     pub async fn delete_template(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query("DELETE FROM templates WHERE id = $1")
             .bind(id)
@@ -731,6 +1021,31 @@ impl TemplateQueries {
         Ok(result.rows_affected() > 0)
     }
 
+    // Which of the given storage keys documents of a template (trashed ones included), a template
+    // version or a submitter still point at, as their file or its source
+    pub async fn get_referenced_storage_keys(pool: &PgPool, keys: &[String]) -> Result<std::collections::HashSet<String>, sqlx::Error> {
+        if keys.is_empty() {
+            return Ok(std::collections::HashSet::new());
+        }
+        let rows = sqlx::query(
+            "WITH documents AS (
+                 SELECT jsonb_array_elements(CASE WHEN jsonb_typeof(documents) = 'array' THEN documents ELSE '[]'::jsonb END) AS document FROM templates
+                 UNION ALL
+                 SELECT jsonb_array_elements(CASE WHEN jsonb_typeof(documents) = 'array' THEN documents ELSE '[]'::jsonb END) FROM template_versions
+                 UNION ALL
+                 SELECT jsonb_array_elements(CASE WHEN jsonb_typeof(documents) = 'array' THEN documents ELSE '[]'::jsonb END) FROM submitters
+             )
+             SELECT DISTINCT key FROM documents,
+                 LATERAL (VALUES (document->>'url'), (document#>>'{source,url}')) AS keys(key)
+             WHERE key = ANY($1)"
+        )
+        .bind(keys)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.iter().map(|row| row.get::<String, _>(0)).collect())
+    }
+
     pub async fn clone_template(pool: &PgPool, original_id: i64, user_id: i64, new_name: &str, new_slug: &str) -> Result<Option<DbTemplate>, sqlx::Error> {
         // First get the original template
         if let Some(original) = Self::get_template_by_id(pool, original_id).await? {
@@ -745,7 +1060,16 @@ impl TemplateQueries {
                 documents: original.documents,
             };
 
-            Self::create_template(pool, create_data).await.map(Some)
+            let clone = Self::create_template(pool, create_data).await?;
+            // The copy has the same documents, so their text needn't be extracted again
+            sqlx::query(
+                "UPDATE templates SET tags = original.tags, document_text = original.document_text FROM templates original WHERE templates.id = $1 AND original.id = $2"
+            )
+            .bind(clone.id)
+            .bind(original_id)
+            .execute(pool)
+            .await?;
+            Ok(Some(clone))
         } else {
             Ok(None)
         }
@@ -888,7 +1212,7 @@ impl TemplateFolderQueries {
         let rows = if let Some(folder_id) = folder_id {
             if let Some(acc_id) = account_id {
                 sqlx::query(
-                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE account_id = $1 AND folder_id = $2 ORDER BY created_at DESC"
+                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE account_id = $1 AND folder_id = $2 AND deleted_at IS NULL ORDER BY created_at DESC"
                 )
                 .bind(acc_id)
                 .bind(folder_id)
@@ -896,7 +1220,7 @@ impl TemplateFolderQueries {
                 .await?
             } else {
                 sqlx::query(
-                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE user_id = $1 AND folder_id = $2 ORDER BY created_at DESC"
+                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE user_id = $1 AND folder_id = $2 AND deleted_at IS NULL ORDER BY created_at DESC"
                 )
                 .bind(user_id)
                 .bind(folder_id)
@@ -906,14 +1230,14 @@ impl TemplateFolderQueries {
         } else {
             if let Some(acc_id) = account_id {
                 sqlx::query(
-                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE account_id = $1 AND folder_id IS NULL ORDER BY created_at DESC"
+                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE account_id = $1 AND folder_id IS NULL AND deleted_at IS NULL ORDER BY created_at DESC"
                 )
                 .bind(acc_id)
                 .fetch_all(pool)
                 .await?
             } else {
                 sqlx::query(
-                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at FROM templates WHERE user_id = $1 AND folder_id IS NULL ORDER BY created_at DESC"
+                    "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at FROM templates WHERE user_id = $1 AND folder_id IS NULL AND deleted_at IS NULL ORDER BY created_at DESC"
                 )
                 .bind(user_id)
                 .fetch_all(pool)
@@ -931,6 +1255,7 @@ impl TemplateFolderQueries {
                 account_id: row.try_get("account_id")?,
                 folder_id: row.try_get("folder_id")?,
                 documents: row.try_get("documents")?,
+                settings: row.try_get("settings")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             });
@@ -972,41 +1297,129 @@ impl TemplateFolderQueries {
         }
     }
 
-    pub async fn delete_folder(pool: &PgPool, id: i64, folder_user_id: i64) -> Result<bool, sqlx::Error> {
-        // First, move all templates in this folder to root (no folder)
-        sqlx::query("UPDATE templates SET folder_id = NULL WHERE folder_id = $1 AND user_id = $2")
-            .bind(id)
-            .bind(folder_user_id)
-            .execute(pool)
-            .await?;
+    // Delete a folder that holds no folders and no templates (those in the trash aside, which
+    // are restored outside any folder). False when it is not empty or does not exist.
+    pub async fn delete_empty_folder(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM template_folders
+            WHERE id = $1
+              AND NOT EXISTS (SELECT 1 FROM template_folders WHERE parent_folder_id = $1)
+              AND NOT EXISTS (SELECT 1 FROM templates WHERE folder_id = $1 AND deleted_at IS NULL)
+            "#
+        )
+        .bind(id)
+        .execute(pool)
+        .await?;
 
-        // Move child folders to parent folder (or root if no parent)
-        let parent_folder_id: Option<i64> = sqlx::query_scalar(
-            "SELECT parent_folder_id FROM template_folders WHERE id = $1 AND user_id = $2"
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Delete a folder, moving its templates and subfolders, whoever created them, to its parent
+    // folder (or to the top level)
+    pub async fn delete_folder(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+        let mut tx = pool.begin().await?;
+
+        let parent_folder_id: Option<Option<i64>> = sqlx::query_scalar(
+            "SELECT parent_folder_id FROM template_folders WHERE id = $1 FOR UPDATE"
         )
         .bind(id)
-        .bind(folder_user_id)
-        .fetch_optional(pool)
-        .await?
-        .flatten();
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some(parent_folder_id) = parent_folder_id else { return Ok(false) };
 
-        sqlx::query("UPDATE template_folders SET parent_folder_id = $1 WHERE parent_folder_id = $2 AND user_id = $3")
+        sqlx::query("UPDATE templates SET folder_id = $1 WHERE folder_id = $2")
             .bind(parent_folder_id)
             .bind(id)
-            .bind(folder_user_id)
-            .execute(pool)
+            .execute(&mut *tx)
             .await?;
-
-        // Delete the folder
-        let result = sqlx::query("DELETE FROM template_folders WHERE id = $1 AND user_id = $2")
+        sqlx::query("UPDATE template_folders SET parent_folder_id = $1, updated_at = $3 WHERE parent_folder_id = $2")
+            .bind(parent_folder_id)
             .bind(id)
-            .bind(folder_user_id)
-            .execute(pool)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        let result = sqlx::query("DELETE FROM template_folders WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
             .await?;
 
+        tx.commit().await?;
         Ok(result.rows_affected() > 0)
     }
 
+    // Number of subfolders and of templates (not in the trash) directly in the folder
+    pub async fn count_folder_contents(pool: &PgPool, id: i64) -> Result<(i64, i64), sqlx::Error> {
+        let row = sqlx::query(
+            r#"
+            SELECT (SELECT COUNT(*) FROM template_folders WHERE parent_folder_id = $1) AS folders,
+                   (SELECT COUNT(*) FROM templates WHERE folder_id = $1 AND deleted_at IS NULL) AS templates
+            "#
+        )
+        .bind(id)
+        .fetch_one(pool)
+        .await?;
+
+        Ok((row.try_get("folders")?, row.try_get("templates")?))
+    }
+
+    // The folder and the folders above it, from the top level down
+    pub async fn get_folder_ancestors(pool: &PgPool, id: i64) -> Result<Vec<DbTemplateFolder>, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateFolder>(
+            r#"
+            WITH RECURSIVE ancestors AS (
+                SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at, 0 AS depth
+                FROM template_folders WHERE id = $1
+                UNION
+                SELECT f.id, f.name, f.user_id, f.account_id, f.parent_folder_id, f.created_at, f.updated_at, a.depth + 1
+                FROM template_folders f INNER JOIN ancestors a ON f.id = a.parent_folder_id
+                WHERE a.depth < 100
+            )
+            SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at FROM ancestors ORDER BY depth DESC
+            "#
+        )
+        .bind(id)
+        .fetch_all(pool)
+        .await
+    }
+
+    // Move a folder, with everything in it, under another folder (None: to the top level).
+    // Returns None when the folder does not exist or the new parent is inside it.
+    pub async fn move_folder(pool: &PgPool, id: i64, parent_folder_id: Option<i64>) -> Result<Option<DbTemplateFolder>, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateFolder>(
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM template_folders WHERE id = $1
+                UNION
+                SELECT f.id FROM template_folders f INNER JOIN subtree s ON f.parent_folder_id = s.id
+            )
+            UPDATE template_folders
+            SET parent_folder_id = $2, updated_at = $3
+            WHERE id = $1 AND ($2::bigint IS NULL OR $2 NOT IN (SELECT id FROM subtree))
+            RETURNING id, name, user_id, account_id, parent_folder_id, created_at, updated_at
+            "#
+        )
+        .bind(id)
+        .bind(parent_folder_id)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await
+    }
+
+    // Move several templates into a folder (None: out of any folder)
+    pub async fn move_templates_to_folder(pool: &PgPool, template_ids: &[i64], folder_id: Option<i64>) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE templates SET folder_id = $1, updated_at = $3 WHERE id = ANY($2) AND deleted_at IS NULL"
+        )
+        .bind(folder_id)
+        .bind(template_ids)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     pub async fn move_template_to_folder(pool: &PgPool, template_id: i64, folder_id: Option<i64>, user_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query(
             "UPDATE templates SET folder_id = $1 WHERE id = $2 AND user_id = $3"
@@ -1020,7 +1433,8 @@ impl TemplateFolderQueries {
         Ok(result.rows_affected() > 0)
     }
 
-    pub async fn get_team_folders(pool: &PgPool, user_id: i64) -> Result<Vec<DbTemplateFolder>, sqlx::Error> {
+    // One level of the team's folder tree, with the number of folders matching the filter
+    pub async fn get_team_folders(pool: &PgPool, user_id: i64, filter: &FolderListFilter) -> Result<(Vec<DbTemplateFolder>, i64), sqlx::Error> {
         // Get user's account_id
         let account_id_result = sqlx::query("SELECT account_id FROM users WHERE id = $1")
             .bind(user_id)
@@ -1030,43 +1444,69 @@ impl TemplateFolderQueries {
 
         // If user has account_id, get all folders in that account
         // Otherwise, only get user's own folders
-        let query_str = if account_id.is_some() {
-            "SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at 
-             FROM template_folders 
-             WHERE account_id = $1 
-             ORDER BY name ASC"
-        } else {
-            "SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at 
-             FROM template_folders 
-             WHERE user_id = $1 
-             ORDER BY name ASC"
-        };
+        let scope = if account_id.is_some() { "account_id" } else { "user_id" };
+        let conditions = format!(
+            "template_folders.{} = $1
+               AND parent_folder_id IS NOT DISTINCT FROM $2
+               AND ($3::bigint IS NULL OR user_id = $3)
+               AND ($4::timestamptz IS NULL OR created_at >= $4)
+               AND ($5::timestamptz IS NULL OR created_at < $5)
+               AND folder_access_level(template_folders.id, $6) > 0",
+            scope
+        );
 
-        let rows = if let Some(acc_id) = account_id {
-            sqlx::query(query_str)
-                .bind(acc_id)
-                .fetch_all(pool)
-                .await?
-        } else {
-            sqlx::query(query_str)
-                .bind(user_id)
-                .fetch_all(pool)
-                .await?
-        };
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM template_folders WHERE {}", conditions))
+            .bind(account_id.unwrap_or(user_id))
+            .bind(filter.parent_folder_id)
+            .bind(filter.user_id)
+            .bind(filter.created_from)
+            .bind(filter.created_to)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+        let folders = sqlx::query_as::<_, DbTemplateFolder>(&format!(
+            "SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at
+             FROM template_folders
+             WHERE {}
+             {}
+             LIMIT $7 OFFSET $8",
+            conditions,
+            filter.sort.order_by("template_folders", "id")
+        ))
+        .bind(account_id.unwrap_or(user_id))
+        .bind(filter.parent_folder_id)
+        .bind(filter.user_id)
+        .bind(filter.created_from)
+        .bind(filter.created_to)
+        .bind(user_id)
+        .bind(filter.page.map(|page| page.limit()))
+        .bind(filter.page.map_or(0, |page| page.offset()))
+        .fetch_all(pool)
+        .await?;
 
-        let mut folders = Vec::new();
-        for row in rows {
-            folders.push(DbTemplateFolder {
-                id: row.try_get("id")?,
-                name: row.try_get("name")?,
-                user_id: row.try_get("user_id")?,
-                account_id: row.try_get("account_id")?,
-                parent_folder_id: row.try_get("parent_folder_id")?,
-                created_at: row.try_get("created_at")?,
-                updated_at: row.try_get("updated_at")?,
-            });
-        }
-        Ok(folders)
+        Ok((folders, total))
+    }
+
+    // Every folder below the given ones, by name
+    pub async fn get_descendant_folders(pool: &PgPool, folder_ids: &[i64], user_id: i64) -> Result<Vec<DbTemplateFolder>, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateFolder>(
+            r#"
+            WITH RECURSIVE descendants AS (
+                SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at
+                FROM template_folders WHERE parent_folder_id = ANY($1)
+                UNION
+                SELECT f.id, f.name, f.user_id, f.account_id, f.parent_folder_id, f.created_at, f.updated_at
+                FROM template_folders f INNER JOIN descendants d ON f.parent_folder_id = d.id
+            )
+            SELECT id, name, user_id, account_id, parent_folder_id, created_at, updated_at FROM descendants
+            WHERE folder_access_level(id, $2) > 0
+            ORDER BY name ASC
+            "#
+        )
+        .bind(folder_ids)
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
     }
 
     // Get templates in a specific folder that are accessible by team members
@@ -1081,14 +1521,14 @@ impl TemplateFolderQueries {
         // If user has account_id, get all templates in that account and folder
         // Otherwise, only get user's own templates in that folder
         let query_str = if account_id.is_some() {
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at 
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at 
              FROM templates 
-             WHERE account_id = $1 AND folder_id = $2
+             WHERE account_id = $1 AND folder_id = $2 AND deleted_at IS NULL AND template_access_level(id, $3) > 0
              ORDER BY created_at DESC"
         } else {
-            "SELECT id, name, slug, user_id, account_id, folder_id, documents, created_at, updated_at 
+            "SELECT id, name, slug, user_id, account_id, folder_id, documents, settings, created_at, updated_at 
              FROM templates 
-             WHERE user_id = $1 AND folder_id = $2
+             WHERE user_id = $1 AND folder_id = $2 AND deleted_at IS NULL AND template_access_level(id, $3) > 0
              ORDER BY created_at DESC"
         };
 
@@ -1096,12 +1536,14 @@ impl TemplateFolderQueries {
             sqlx::query(query_str)
                 .bind(acc_id)
                 .bind(folder_id)
+                .bind(user_id)
                 .fetch_all(pool)
                 .await?
         } else {
             sqlx::query(query_str)
                 .bind(user_id)
                 .bind(folder_id)
+                .bind(user_id)
                 .fetch_all(pool)
                 .await?
         };
@@ -1116,6 +1558,7 @@ impl TemplateFolderQueries {
                 account_id: row.try_get("account_id")?,
                 folder_id: row.try_get("folder_id")?,
                 documents: row.try_get("documents")?,
+                settings: row.try_get("settings")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             });
@@ -1244,6 +1687,19 @@ impl TemplateFieldQueries {
         }
     }
 
+    pub async fn update_template_field_position(pool: &PgPool, field_id: i64, position: &serde_json::Value) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE template_fields SET position = $2, updated_at = $3 WHERE id = $1 AND deleted_at IS NULL"
+        )
+        .bind(field_id)
+        .bind(position)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
     pub async fn delete_template_field(pool: &PgPool, field_id: i64) -> Result<bool, sqlx::Error> {
         let result = sqlx::query(
             "UPDATE template_fields SET deleted_at = CURRENT_TIMESTAMP WHERE id = $1 AND deleted_at IS NULL"
@@ -1422,6 +1878,28 @@ impl SubmitterQueries {
         Ok(submitters)
     }
 
+    // Whether the storage key is one of the documents the submitter with the token signs: their
+    // own, those of the template version they were sent or the template's current ones
+    pub async fn submitter_has_storage_key(pool: &PgPool, token: &str, key: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "WITH documents AS (
+                 SELECT jsonb_array_elements(CASE WHEN jsonb_typeof(s.documents) = 'array' THEN s.documents ELSE '[]'::jsonb END) AS document
+                 FROM submitters s WHERE s.token = $1
+                 UNION ALL
+                 SELECT jsonb_array_elements(CASE WHEN jsonb_typeof(v.documents) = 'array' THEN v.documents ELSE '[]'::jsonb END)
+                 FROM submitters s JOIN template_versions v ON v.id = s.template_version_id WHERE s.token = $1
+                 UNION ALL
+                 SELECT jsonb_array_elements(CASE WHEN jsonb_typeof(t.documents) = 'array' THEN t.documents ELSE '[]'::jsonb END)
+                 FROM submitters s JOIN templates t ON t.id = s.template_id WHERE s.token = $1
+             )
+             SELECT EXISTS (SELECT 1 FROM documents WHERE document->>'url' = $2 OR document#>>'{source,url}' = $2)"
+        )
+        .bind(token)
+        .bind(key)
+        .fetch_one(pool)
+        .await
+    }
+
     pub async fn get_submitter_by_token(pool: &PgPool, token: &str) -> Result<Option<DbSubmitter>, sqlx::Error> {
         let row = sqlx::query(
             "SELECT id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone
@@ -1465,7 +1943,9 @@ impl SubmitterQueries {
         let signed_at = if status == Some("signed") { Some(now) } else { None };
         
         let row = sqlx::query(
-            "UPDATE submitters SET status = COALESCE($1, status), signed_at = COALESCE($2, signed_at), updated_at = $3 
+            "UPDATE submitters SET status = COALESCE($1, status), signed_at = COALESCE($2, signed_at), updated_at = $3,
+                 draft_values = CASE WHEN $1 IN ('signed', 'completed', 'declined') THEN NULL ELSE draft_values END,
+                 draft_saved_at = CASE WHEN $1 IN ('signed', 'completed', 'declined') THEN NULL ELSE draft_saved_at END
              WHERE id = $4 
              RETURNING id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone"
         )
@@ -1505,6 +1985,7 @@ impl SubmitterQueries {
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_submitter_with_signatures(
         pool: &PgPool,
         id: i64,
@@ -1513,11 +1994,13 @@ impl SubmitterQueries {
         user_agent: Option<&str>,
         session_id: Option<&str>,
         timezone: Option<&str>,
+        signature_id: &str,
     ) -> Result<Option<DbSubmitter>, sqlx::Error> {
         let now = Utc::now();
 
         let row = sqlx::query(
-            "UPDATE submitters SET bulk_signatures = $1, ip_address = $2, user_agent = $3, session_id = $4, timezone = $5, status = 'signed', signed_at = $6, updated_at = $6 
+            "UPDATE submitters SET bulk_signatures = $1, ip_address = $2, user_agent = $3, session_id = $4, timezone = $5, status = 'signed', signed_at = $6, updated_at = $6,
+                 draft_values = NULL, draft_saved_at = NULL, signature_id = $8
              WHERE id = $7 
              RETURNING id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone"
         )
@@ -1528,6 +2011,7 @@ impl SubmitterQueries {
         .bind(timezone)
         .bind(now)
         .bind(id)
+        .bind(signature_id)
         .fetch_optional(pool)
         .await?;
 
@@ -1573,7 +2057,8 @@ impl SubmitterQueries {
         let now = Utc::now();
 
         let row = sqlx::query(
-            "UPDATE submitters SET status = 'declined', decline_reason = $1, bulk_signatures = $2, ip_address = $3, user_agent = $4, session_id = $5, timezone = $6, updated_at = $7 
+            "UPDATE submitters SET status = 'declined', decline_reason = $1, bulk_signatures = $2, ip_address = $3, user_agent = $4, session_id = $5, timezone = $6, updated_at = $7,
+                 draft_values = NULL, draft_saved_at = NULL
              WHERE id = $8 
              RETURNING id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone"
         )
@@ -1663,6 +2148,7 @@ impl SubmitterQueries {
             FROM submitters s
             LEFT JOIN templates t ON s.template_id = t.id
             WHERE s.status IN ('pending', 'sent', 'viewed')
+              AND t.deleted_at IS NULL
               AND s.reminder_config IS NOT NULL
               AND s.reminder_count < 3
             ORDER BY s.created_at
@@ -1726,8 +2212,9 @@ impl SubmitterQueries {
         Ok(result.rows_affected() > 0)
     }
 
-    // Get submitters accessible by team (invited users can see inviter's submitters)
-    pub async fn get_team_submitters(pool: &PgPool, user_id: i64) -> Result<Vec<DbSubmitter>, sqlx::Error> {
+    // Users whose submitters the user can see: themselves, and the team they invited or were
+    // invited into
+    async fn team_member_ids(pool: &PgPool, user_id: i64) -> Result<Vec<i64>, sqlx::Error> {
         // Get the user's invitation info to find their team
         let team_query = sqlx::query(
             r#"
@@ -1786,28 +2273,105 @@ impl SubmitterQueries {
             ids
         };
 
+        Ok(team_member_ids)
+    }
+
+    // Get submitters accessible by team (invited users can see inviter's submitters), one page of
+    // them with the number matching the filter
+    pub async fn get_team_submitters(pool: &PgPool, user_id: i64, filter: &SubmitterListFilter) -> Result<(Vec<DbSubmitter>, i64), sqlx::Error> {
+        let team_member_ids = Self::team_member_ids(pool, user_id).await?;
+
         // Get submitters for all team members
         if team_member_ids.is_empty() {
-            return Ok(vec![]);
+            return Ok((vec![], 0));
         }
 
-        let placeholders: Vec<String> = (1..=team_member_ids.len())
-            .map(|i| format!("${}", i))
-            .collect();
+        let conditions = "user_id = ANY($1)
+               AND ($2::text IS NULL OR status = $2)
+               AND ($3::bigint IS NULL OR template_id = $3)
+               AND ($4::bigint IS NULL OR user_id = $4)
+               AND ($5::timestamptz IS NULL OR created_at >= $5)
+               AND ($6::timestamptz IS NULL OR created_at < $6)
+               AND (user_id = $7 OR template_access_level(template_id, $7) > 0)";
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM submitters WHERE {}", conditions))
+            .bind(&team_member_ids)
+            .bind(filter.status.as_deref())
+            .bind(filter.template_id)
+            .bind(filter.user_id)
+            .bind(filter.created_from)
+            .bind(filter.created_to)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
         let query_str = format!(
             "SELECT id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone
              FROM submitters 
-             WHERE user_id IN ({}) 
-             ORDER BY created_at DESC",
-            placeholders.join(", ")
+             WHERE {}
+             {}
+             LIMIT $8 OFFSET $9",
+            conditions,
+            filter.sort.order_by("submitters", "id")
         );
 
-        let mut query = sqlx::query(&query_str);
-        for id in team_member_ids {
-            query = query.bind(id);
+        let rows = sqlx::query(&query_str)
+            .bind(&team_member_ids)
+            .bind(filter.status.as_deref())
+            .bind(filter.template_id)
+            .bind(filter.user_id)
+            .bind(filter.created_from)
+            .bind(filter.created_to)
+            .bind(user_id)
+            .bind(filter.page.map(|page| page.limit()))
+            .bind(filter.page.map_or(0, |page| page.offset()))
+            .fetch_all(pool)
+            .await?;
+
+        let mut submitters = Vec::new();
+        for row in rows {
+            submitters.push(DbSubmitter {
+                id: row.get(0),
+                template_id: row.get(1),
+                user_id: row.get(2),
+                name: row.get(3),
+                email: row.get(4),
+                status: row.get(5),
+                signed_at: row.get(6),
+                token: row.get(7),
+                bulk_signatures: row.get(8),
+                ip_address: row.get(9),
+                user_agent: row.get(10),
+                reminder_config: row.get(11),
+                last_reminder_sent_at: row.get(12),
+                reminder_count: row.get(13),
+                created_at: row.get(14),
+                updated_at: row.get(15),
+                decline_reason: row.get(16),
+                session_id: row.get(17),
+                viewed_at: row.get(18),
+                timezone: row.get(19),
+            template_name: None,
+            });
         }
+        Ok((submitters, total))
+    }
 
-        let rows = query.fetch_all(pool).await?;
+    pub async fn resubmit_submitter(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE submitters SET status = 'pending' WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_submitters_by_template_id(pool: &PgPool, template_id: i64) -> Result<Vec<DbSubmitter>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone
+             FROM submitters WHERE template_id = $1"
+        )
+        .bind(template_id)
+        .fetch_all(pool)
+        .await?;
 
         let mut submitters = Vec::new();
         for row in rows {
@@ -1838,50 +2402,151 @@ impl SubmitterQueries {
         Ok(submitters)
     }
 
-    pub async fn resubmit_submitter(pool: &PgPool, id: i64) -> Result<(), sqlx::Error> {
-        sqlx::query("UPDATE submitters SET status = 'pending' WHERE id = $1")
+    pub async fn set_signature_id(pool: &PgPool, id: i64, signature_id: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE submitters SET signature_id = $2 WHERE id = $1 AND signature_id IS DISTINCT FROM $2")
+            .bind(id)
+            .bind(signature_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    // Submitters of the template who haven't signed or declined yet
+    pub async fn count_active_submitters(pool: &PgPool, template_id: i64) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM submitters WHERE template_id = $1 AND status IN ('pending', 'sent', 'viewed')")
+            .bind(template_id)
+            .fetch_one(pool)
+            .await
+    }
+
+    // Documents personalized for the submitter's submission; None means the template's
+    pub async fn get_submitter_documents(pool: &PgPool, id: i64) -> Result<Option<serde_json::Value>, sqlx::Error> {
+        let documents: Option<Option<serde_json::Value>> = sqlx::query_scalar("SELECT documents FROM submitters WHERE id = $1")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+        Ok(documents.flatten())
+    }
+
+    pub async fn set_submitter_documents(pool: &PgPool, id: i64, documents: &serde_json::Value) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE submitters SET documents = $2, updated_at = NOW() WHERE id = $1")
+            .bind(id)
+            .bind(documents)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    // Values the submission was created with, indexed for search
+    pub async fn set_submitter_prefilled_values(pool: &PgPool, id: i64, values: &serde_json::Value) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE submitters SET prefilled_values = $2 WHERE id = $1")
             .bind(id)
+            .bind(values)
             .execute(pool)
             .await?;
         Ok(())
     }
 
-    pub async fn get_submitters_by_template_id(pool: &PgPool, template_id: i64) -> Result<Vec<DbSubmitter>, sqlx::Error> {
-        let rows = sqlx::query(
-            "SELECT id, template_id, user_id, name, email, status, signed_at, token, bulk_signatures, ip_address, user_agent, reminder_config, last_reminder_sent_at, reminder_count, created_at, updated_at, decline_reason, session_id, viewed_at, timezone
-             FROM submitters WHERE template_id = $1"
+    // Save what the signer entered so far; false once the submitter has signed or declined
+    pub async fn save_submitter_draft(pool: &PgPool, id: i64, values: &serde_json::Value) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "UPDATE submitters SET draft_values = $2, draft_saved_at = $3
+             WHERE id = $1 AND status NOT IN ('signed', 'completed', 'declined')"
+        )
+        .bind(id)
+        .bind(values)
+        .bind(Utc::now())
+        .execute(pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn get_submitter_draft(pool: &PgPool, id: i64) -> Result<Option<(serde_json::Value, DateTime<Utc>)>, sqlx::Error> {
+        let row = sqlx::query("SELECT draft_values, draft_saved_at FROM submitters WHERE id = $1 AND draft_values IS NOT NULL")
+            .bind(id)
+            .fetch_optional(pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some((row.try_get("draft_values")?, row.try_get("draft_saved_at")?))),
+            None => Ok(None),
+        }
+    }
+
+    // Submitters of a template sent in [from, to), for analytics
+    pub async fn get_template_submitter_activity(
+        pool: &PgPool,
+        template_id: i64,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<DbSubmitterActivity>, sqlx::Error> {
+        sqlx::query_as::<_, DbSubmitterActivity>(
+            "SELECT id, name, email, status, created_at, viewed_at, signed_at, reminder_count, decline_reason, draft_values, draft_saved_at
+             FROM submitters
+             WHERE template_id = $1
+               AND ($2::timestamptz IS NULL OR created_at >= $2)
+               AND ($3::timestamptz IS NULL OR created_at < $3)
+             ORDER BY created_at"
         )
         .bind(template_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(pool)
+        .await
+    }
+
+    // Submitters of the user's team matching a tsquery, ranked by relevance, with the total
+    // number of matches. Submitters of templates in the trash are left out.
+    pub async fn search_submitters(
+        pool: &PgPool,
+        user_id: i64,
+        tsquery: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<DbSubmitterSearchHit>, i64), sqlx::Error> {
+        let team_member_ids = Self::team_member_ids(pool, user_id).await?;
+        let filter = r#"
+            FROM submitters s
+            INNER JOIN templates t ON t.id = s.template_id AND t.deleted_at IS NULL
+            WHERE s.user_id = ANY($1)
+              AND s.search_vector @@ to_tsquery('simple', $2)
+              AND (s.user_id = $3 OR template_access_level(t.id, $3) > 0)
+        "#;
+
+        let total: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) {}", filter))
+            .bind(&team_member_ids)
+            .bind(tsquery)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+        let hits = sqlx::query_as::<_, DbSubmitterSearchHit>(&format!(
+            "SELECT s.id, s.template_id, t.name AS template_name, s.name, s.email, s.status, ts_rank(s.search_vector, to_tsquery('simple', $2)) AS rank, s.signed_at, s.created_at
+             {}
+             ORDER BY rank DESC, s.created_at DESC
+             LIMIT $4 OFFSET $5",
+            filter
+        ))
+        .bind(&team_member_ids)
+        .bind(tsquery)
+        .bind(user_id)
+        .bind(limit)
+        .bind(offset)
         .fetch_all(pool)
         .await?;
 
-        let mut submitters = Vec::new();
-        for row in rows {
-            submitters.push(DbSubmitter {
-                id: row.get(0),
-                template_id: row.get(1),
-                user_id: row.get(2),
-                name: row.get(3),
-                email: row.get(4),
-                status: row.get(5),
-                signed_at: row.get(6),
-                token: row.get(7),
-                bulk_signatures: row.get(8),
-                ip_address: row.get(9),
-                user_agent: row.get(10),
-                reminder_config: row.get(11),
-                last_reminder_sent_at: row.get(12),
-                reminder_count: row.get(13),
-                created_at: row.get(14),
-                updated_at: row.get(15),
-                decline_reason: row.get(16),
-                session_id: row.get(17),
-                viewed_at: row.get(18),
-                timezone: row.get(19),
-            template_name: None,
-            });
+        Ok((hits, total))
+    }
+
+    pub async fn get_submitter_by_signature_id(pool: &PgPool, signature_id: &str) -> Result<Option<DbSubmitter>, sqlx::Error> {
+        let id: Option<i64> = sqlx::query_scalar("SELECT id FROM submitters WHERE signature_id = $1")
+            .bind(signature_id)
+            .fetch_optional(pool)
+            .await?;
+        match id {
+            Some(id) => Self::get_submitter_by_id(pool, id).await,
+            None => Ok(None),
         }
-        Ok(submitters)
     }
 }
 
@@ -1954,6 +2619,424 @@ impl SubmissionFieldQueries {
         }
         Ok(fields)
     }
+
+    // Fields of several submitters, each submitter's in display order
+    pub async fn get_submission_fields_by_submitter_ids(pool: &PgPool, submitter_ids: &[i64]) -> Result<Vec<DbSubmissionField>, sqlx::Error> {
+        sqlx::query_as::<_, DbSubmissionField>(
+            "SELECT id, submitter_id, template_field_id, name, field_type, required, display_order, position, options, metadata, partner, created_at, updated_at
+             FROM submission_fields WHERE submitter_id = ANY($1) ORDER BY submitter_id, display_order, id"
+        )
+        .bind(submitter_ids)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+pub struct DocumentTimestampQueries;
+
+impl DocumentTimestampQueries {
+    pub async fn create_document_timestamp(pool: &PgPool, data: CreateDocumentTimestamp) -> Result<DbDocumentTimestamp, sqlx::Error> {
+        let row = sqlx::query(
+            "INSERT INTO document_timestamps (template_id, submitter_id, document_type, tsa_name, serial_number, message_imprint, gen_time, token, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+             RETURNING id, template_id, submitter_id, document_type, tsa_name, serial_number, message_imprint, gen_time, token, created_at"
+        )
+        .bind(data.template_id)
+        .bind(data.submitter_id)
+        .bind(data.document_type)
+        .bind(data.tsa_name)
+        .bind(data.serial_number)
+        .bind(data.message_imprint)
+        .bind(data.gen_time)
+        .bind(data.token)
+        .bind(Utc::now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(Self::row_to_timestamp(&row))
+    }
+
+    pub async fn get_document_timestamps_by_template(pool: &PgPool, template_id: i64) -> Result<Vec<DbDocumentTimestamp>, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT id, template_id, submitter_id, document_type, tsa_name, serial_number, message_imprint, gen_time, token, created_at
+             FROM document_timestamps WHERE template_id = $1 ORDER BY gen_time"
+        )
+        .bind(template_id)
+        .fetch_all(pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::row_to_timestamp).collect())
+    }
+
+    fn row_to_timestamp(row: &sqlx::postgres::PgRow) -> DbDocumentTimestamp {
+        DbDocumentTimestamp {
+            id: row.get(0),
+            template_id: row.get(1),
+            submitter_id: row.get(2),
+            document_type: row.get(3),
+            tsa_name: row.get(4),
+            serial_number: row.get(5),
+            message_imprint: row.get(6),
+            gen_time: row.get(7),
+            token: row.get(8),
+            created_at: row.get(9),
+        }
+    }
+}
+
+pub struct DocumentHashQueries;
+
+impl DocumentHashQueries {
+    pub async fn create_document_hash(pool: &PgPool, data: CreateDocumentHash) -> Result<DbDocumentHash, sqlx::Error> {
+        let row = sqlx::query(
+            "INSERT INTO document_hashes (template_id, submitter_id, document_type, file_name, sha256, size_bytes, created_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7)
+             RETURNING id, template_id, submitter_id, document_type, sha256, size_bytes, created_at"
+        )
+        .bind(data.template_id)
+        .bind(data.submitter_id)
+        .bind(data.document_type)
+        .bind(data.file_name)
+        .bind(data.sha256)
+        .bind(data.size_bytes)
+        .bind(Utc::now())
+        .fetch_one(pool)
+        .await?;
+
+        Ok(DbDocumentHash {
+            id: row.get(0),
+            template_id: row.get(1),
+            submitter_id: row.get(2),
+            document_type: row.get(3),
+            sha256: row.get(4),
+            size_bytes: row.get(5),
+            created_at: row.get(6),
+        })
+    }
+
+    // Earliest record for a hash (when the document was first issued)
+    pub async fn get_document_hash_by_sha256(pool: &PgPool, sha256: &str) -> Result<Option<DbDocumentHash>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT id, template_id, submitter_id, document_type, sha256, size_bytes, created_at
+             FROM document_hashes WHERE sha256 = $1 ORDER BY created_at ASC LIMIT 1"
+        )
+        .bind(sha256)
+        .fetch_optional(pool)
+        .await?;
+
+        Ok(row.map(|row| DbDocumentHash {
+            id: row.get(0),
+            template_id: row.get(1),
+            submitter_id: row.get(2),
+            document_type: row.get(3),
+            sha256: row.get(4),
+            size_bytes: row.get(5),
+            created_at: row.get(6),
+        }))
+    }
+}
+
+pub struct TemplateVersionQueries;
+
+impl TemplateVersionQueries {
+    // Stored as the template's next version number
+    pub async fn create_template_version(
+        pool: &PgPool,
+        template_id: i64,
+        documents: &serde_json::Value,
+        fields: &serde_json::Value,
+        checksum: &str,
+        created_by: Option<i64>,
+    ) -> Result<DbTemplateVersion, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateVersion>(
+            "INSERT INTO template_versions (template_id, version, documents, fields, checksum, created_by, created_at)
+             SELECT $1, COALESCE(MAX(version), 0) + 1, $2, $3, $4, $5, $6 FROM template_versions WHERE template_id = $1
+             RETURNING id, template_id, version, documents, fields, checksum, created_by, created_at"
+        )
+        .bind(template_id)
+        .bind(documents)
+        .bind(fields)
+        .bind(checksum)
+        .bind(created_by)
+        .bind(Utc::now())
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn get_latest_template_version(pool: &PgPool, template_id: i64) -> Result<Option<DbTemplateVersion>, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateVersion>(
+            "SELECT id, template_id, version, documents, fields, checksum, created_by, created_at
+             FROM template_versions WHERE template_id = $1 ORDER BY version DESC LIMIT 1"
+        )
+        .bind(template_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    // Newest first
+    pub async fn get_template_versions(pool: &PgPool, template_id: i64) -> Result<Vec<DbTemplateVersion>, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateVersion>(
+            "SELECT id, template_id, version, documents, fields, checksum, created_by, created_at
+             FROM template_versions WHERE template_id = $1 ORDER BY version DESC"
+        )
+        .bind(template_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    // Version a submitter was sent with; None for submissions sent before versioning
+    pub async fn get_submitter_template_version(pool: &PgPool, submitter_id: i64) -> Result<Option<DbTemplateVersion>, sqlx::Error> {
+        sqlx::query_as::<_, DbTemplateVersion>(
+            "SELECT v.id, v.template_id, v.version, v.documents, v.fields, v.checksum, v.created_by, v.created_at
+             FROM template_versions v JOIN submitters s ON s.template_version_id = v.id WHERE s.id = $1"
+        )
+        .bind(submitter_id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn set_submitter_template_version(pool: &PgPool, submitter_id: i64, version_id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE submitters SET template_version_id = $2, updated_at = NOW() WHERE id = $1")
+            .bind(submitter_id)
+            .bind(version_id)
+            .execute(pool)
+            .await?;
+        Ok(())
+    }
+
+    // Number of submitters sent with each of the template's versions, by version id
+    pub async fn count_submitters_by_version(pool: &PgPool, template_id: i64) -> Result<Vec<(i64, i64)>, sqlx::Error> {
+        sqlx::query_as::<_, (i64, i64)>(
+            "SELECT template_version_id, COUNT(*) FROM submitters
+             WHERE template_id = $1 AND template_version_id IS NOT NULL GROUP BY template_version_id"
+        )
+        .bind(template_id)
+        .fetch_all(pool)
+        .await
+    }
+}
+
+pub struct TemplateShareQueries;
+
+impl TemplateShareQueries {
+    // What the user can do with the template, following the rules of the template_access_level
+    // SQL function; None when they have no access
+    pub async fn get_template_access_level(pool: &PgPool, template_id: i64, user_id: i64) -> Result<Option<AccessLevel>, sqlx::Error> {
+        let rank: i32 = sqlx::query_scalar("SELECT template_access_level($1, $2)")
+            .bind(template_id)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+        Ok(AccessLevel::from_rank(rank))
+    }
+
+    pub async fn get_folder_access_level(pool: &PgPool, folder_id: i64, user_id: i64) -> Result<Option<AccessLevel>, sqlx::Error> {
+        let rank: i32 = sqlx::query_scalar("SELECT folder_access_level($1, $2)")
+            .bind(folder_id)
+            .bind(user_id)
+            .fetch_one(pool)
+            .await?;
+        Ok(AccessLevel::from_rank(rank))
+    }
+
+    // Whether the user can view a template whose documents (current, of a version or sent to a
+    // submitter) include the storage key
+    pub async fn can_view_storage_key(pool: &PgPool, key: &str, user_id: i64) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "WITH documents AS (
+                 SELECT id AS template_id, jsonb_array_elements(CASE WHEN jsonb_typeof(documents) = 'array' THEN documents ELSE '[]'::jsonb END) AS document FROM templates
+                 UNION ALL
+                 SELECT template_id, jsonb_array_elements(CASE WHEN jsonb_typeof(documents) = 'array' THEN documents ELSE '[]'::jsonb END) FROM template_versions
+                 UNION ALL
+                 SELECT template_id, jsonb_array_elements(CASE WHEN jsonb_typeof(documents) = 'array' THEN documents ELSE '[]'::jsonb END) FROM submitters
+             )
+             SELECT EXISTS (
+                 SELECT 1 FROM documents
+                 WHERE (document->>'url' = $1 OR document#>>'{source,url}' = $1)
+                   AND template_access_level(template_id, $2) > 0
+             )"
+        )
+        .bind(key)
+        .bind(user_id)
+        .fetch_one(pool)
+        .await
+    }
+
+    // Whether the template is restricted, and its shares
+    pub async fn get_template_sharing(pool: &PgPool, template_id: i64) -> Result<(bool, Vec<DbTemplateShare>), sqlx::Error> {
+        let restricted: bool = sqlx::query_scalar("SELECT access_restricted FROM templates WHERE id = $1")
+            .bind(template_id)
+            .fetch_one(pool)
+            .await?;
+        let shares = sqlx::query_as::<_, DbTemplateShare>(
+            "SELECT id, template_id, folder_id, user_id, role, access_level, created_by, created_at
+             FROM template_shares WHERE template_id = $1 ORDER BY id"
+        )
+        .bind(template_id)
+        .fetch_all(pool)
+        .await?;
+        Ok((restricted, shares))
+    }
+
+    pub async fn get_folder_sharing(pool: &PgPool, folder_id: i64) -> Result<(bool, Vec<DbTemplateShare>), sqlx::Error> {
+        let restricted: bool = sqlx::query_scalar("SELECT access_restricted FROM template_folders WHERE id = $1")
+            .bind(folder_id)
+            .fetch_one(pool)
+            .await?;
+        let shares = sqlx::query_as::<_, DbTemplateShare>(
+            "SELECT id, template_id, folder_id, user_id, role, access_level, created_by, created_at
+             FROM template_shares WHERE folder_id = $1 ORDER BY id"
+        )
+        .bind(folder_id)
+        .fetch_all(pool)
+        .await?;
+        Ok((restricted, shares))
+    }
+
+    // Replace the template's restriction and shares
+    pub async fn set_template_sharing(pool: &PgPool, template_id: i64, restricted: bool, shares: &[ShareRequest], created_by: i64) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("UPDATE templates SET access_restricted = $2 WHERE id = $1")
+            .bind(template_id)
+            .bind(restricted)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM template_shares WHERE template_id = $1")
+            .bind(template_id)
+            .execute(&mut *tx)
+            .await?;
+        for share in shares {
+            sqlx::query(
+                "INSERT INTO template_shares (template_id, user_id, role, access_level, created_by, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(template_id)
+            .bind(share.user_id)
+            .bind(&share.role)
+            .bind(share.access_level.as_str())
+            .bind(created_by)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    // Replace the folder's restriction and shares, which apply to everything in it
+    pub async fn set_folder_sharing(pool: &PgPool, folder_id: i64, restricted: bool, shares: &[ShareRequest], created_by: i64) -> Result<(), sqlx::Error> {
+        let mut tx = pool.begin().await?;
+        sqlx::query("UPDATE template_folders SET access_restricted = $2 WHERE id = $1")
+            .bind(folder_id)
+            .bind(restricted)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM template_shares WHERE folder_id = $1")
+            .bind(folder_id)
+            .execute(&mut *tx)
+            .await?;
+        for share in shares {
+            sqlx::query(
+                "INSERT INTO template_shares (folder_id, user_id, role, access_level, created_by, created_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(folder_id)
+            .bind(share.user_id)
+            .bind(&share.role)
+            .bind(share.access_level.as_str())
+            .bind(created_by)
+            .bind(Utc::now())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+}
+
+pub struct FieldSnippetQueries;
+
+impl FieldSnippetQueries {
+    pub async fn create_field_snippet(
+        pool: &PgPool,
+        user_id: i64,
+        account_id: Option<i64>,
+        name: &str,
+        description: Option<&str>,
+        fields: &serde_json::Value,
+        shared: bool,
+    ) -> Result<DbFieldSnippet, sqlx::Error> {
+        let now = Utc::now();
+        sqlx::query_as::<_, DbFieldSnippet>(
+            "INSERT INTO field_snippets (user_id, account_id, name, description, fields, shared, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $7)
+             RETURNING id, user_id, account_id, name, description, fields, shared, created_at, updated_at"
+        )
+        .bind(user_id)
+        .bind(account_id)
+        .bind(name)
+        .bind(description)
+        .bind(fields)
+        .bind(shared)
+        .bind(now)
+        .fetch_one(pool)
+        .await
+    }
+
+    pub async fn get_field_snippet_by_id(pool: &PgPool, id: i64) -> Result<Option<DbFieldSnippet>, sqlx::Error> {
+        sqlx::query_as::<_, DbFieldSnippet>(
+            "SELECT id, user_id, account_id, name, description, fields, shared, created_at, updated_at
+             FROM field_snippets WHERE id = $1"
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+    }
+
+    // The user's own snippets and those shared in their account, by name
+    pub async fn get_available_field_snippets(pool: &PgPool, user_id: i64) -> Result<Vec<DbFieldSnippet>, sqlx::Error> {
+        sqlx::query_as::<_, DbFieldSnippet>(
+            "SELECT id, user_id, account_id, name, description, fields, shared, created_at, updated_at
+             FROM field_snippets
+             WHERE user_id = $1
+                OR (shared AND account_id = (SELECT account_id FROM users WHERE id = $1))
+             ORDER BY lower(name), id"
+        )
+        .bind(user_id)
+        .fetch_all(pool)
+        .await
+    }
+
+    pub async fn update_field_snippet(
+        pool: &PgPool,
+        id: i64,
+        name: Option<&str>,
+        description: Option<&str>,
+        shared: Option<bool>,
+    ) -> Result<Option<DbFieldSnippet>, sqlx::Error> {
+        sqlx::query_as::<_, DbFieldSnippet>(
+            "UPDATE field_snippets
+             SET name = COALESCE($2, name),
+                 description = COALESCE($3, description),
+                 shared = COALESCE($4, shared),
+                 updated_at = $5
+             WHERE id = $1
+             RETURNING id, user_id, account_id, name, description, fields, shared, created_at, updated_at"
+        )
+        .bind(id)
+        .bind(name)
+        .bind(description)
+        .bind(shared)
+        .bind(Utc::now())
+        .fetch_optional(pool)
+        .await
+    }
+
+    pub async fn delete_field_snippet(pool: &PgPool, id: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM field_snippets WHERE id = $1")
+            .bind(id)
+            .execute(pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
 }
 
 pub struct SignatureQueries;
@@ -2311,7 +3394,7 @@ impl OAuthTokenQueries {
 impl GlobalSettingsQueries {
     pub async fn get_global_settings(pool: &PgPool) -> Result<Option<DbGlobalSettings>, sqlx::Error> {
         let row = sqlx::query(
-            "SELECT id, user_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, created_at, updated_at FROM global_settings WHERE user_id IS NULL"
+            "SELECT id, user_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at FROM global_settings WHERE user_id IS NULL"
         )
         .fetch_optional(pool)
         .await?;
@@ -2340,6 +3423,9 @@ impl GlobalSettingsQueries {
                 completion_body: row.try_get("completion_body")?,
                 redirect_title: row.try_get("redirect_title")?,
                 redirect_url: row.try_get("redirect_url")?,
+                pdf_render_mode: row.try_get("pdf_render_mode")?,
+                flatten_form_fields: row.try_get("flatten_form_fields")?,
+                pdfa_output: row.try_get("pdfa_output")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             })),
@@ -2362,7 +3448,7 @@ impl GlobalSettingsQueries {
 
         // Query settings by account_id (team-wide settings)
         let row = sqlx::query(
-            "SELECT id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, created_at, updated_at FROM global_settings WHERE account_id = $1"
+            "SELECT id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at FROM global_settings WHERE account_id = $1"
         )
         .bind(account_id)
         .fetch_optional(pool)
@@ -2392,6 +3478,9 @@ impl GlobalSettingsQueries {
                 completion_body: row.try_get("completion_body")?,
                 redirect_title: row.try_get("redirect_title")?,
                 redirect_url: row.try_get("redirect_url")?,
+                pdf_render_mode: row.try_get("pdf_render_mode")?,
+                flatten_form_fields: row.try_get("flatten_form_fields")?,
+                pdfa_output: row.try_get("pdfa_output")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             })),
@@ -2412,9 +3501,9 @@ impl GlobalSettingsQueries {
         // Check if settings already exist for this account
         // If account_id is NULL, use the global settings (id=1)
         let query_str = if account_id.is_some() {
-            "SELECT id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, created_at, updated_at FROM global_settings WHERE account_id = $1".to_string()
+            "SELECT id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at FROM global_settings WHERE account_id = $1".to_string()
         } else {
-            "SELECT id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, created_at, updated_at FROM global_settings WHERE id = 1".to_string()
+            "SELECT id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at FROM global_settings WHERE id = 1".to_string()
         };
 
         if let Some(existing) = sqlx::query(&query_str)
@@ -2444,6 +3533,9 @@ impl GlobalSettingsQueries {
                 completion_body: existing.try_get("completion_body")?,
                 redirect_title: existing.try_get("redirect_title")?,
                 redirect_url: existing.try_get("redirect_url")?,
+                pdf_render_mode: existing.try_get("pdf_render_mode")?,
+                flatten_form_fields: existing.try_get("flatten_form_fields")?,
+                pdfa_output: existing.try_get("pdfa_output")?,
                 created_at: existing.try_get("created_at")?,
                 updated_at: existing.try_get("updated_at")?,
             });
@@ -2453,7 +3545,7 @@ impl GlobalSettingsQueries {
             r#"
             INSERT INTO global_settings (user_id, account_id, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, created_at, updated_at)
             VALUES ($1, $2, false, false, false, true, false, false, false, false, false, false, false, $3, $3)
-            RETURNING id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, NULL as completion_title, NULL as completion_body, NULL as redirect_title, NULL as redirect_url, created_at, updated_at
+            RETURNING id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, NULL as completion_title, NULL as completion_body, NULL as redirect_title, NULL as redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at
             "#
         )
         .bind(user_id)
@@ -2485,6 +3577,9 @@ impl GlobalSettingsQueries {
             completion_body: row.try_get("completion_body")?,
             redirect_title: row.try_get("redirect_title")?,
             redirect_url: row.try_get("redirect_url")?,
+            pdf_render_mode: row.try_get("pdf_render_mode")?,
+            flatten_form_fields: row.try_get("flatten_form_fields")?,
+            pdfa_output: row.try_get("pdfa_output")?,
             created_at: row.try_get("created_at")?,
             updated_at: row.try_get("updated_at")?,
         })
@@ -2503,7 +3598,10 @@ impl GlobalSettingsQueries {
                 remember_and_pre_fill_signatures = $11, require_authentication_for_file_download_links = $12, 
                 combine_completed_documents_and_audit_log = $13, expirable_file_download_links = $14,
                 enable_confetti = $15,
-                updated_at = $16
+                pdf_render_mode = COALESCE($16, pdf_render_mode),
+                flatten_form_fields = COALESCE($17, flatten_form_fields),
+                pdfa_output = COALESCE($18, pdfa_output),
+                updated_at = $19
             WHERE user_id IS NULL
             "#
         )
@@ -2522,6 +3620,9 @@ impl GlobalSettingsQueries {
         .bind(settings.combine_completed_documents_and_audit_log)
         .bind(settings.expirable_file_download_links)
         .bind(settings.enable_confetti)
+        .bind(settings.pdf_render_mode)
+        .bind(settings.flatten_form_fields)
+        .bind(settings.pdfa_output)
         .bind(now)
         .execute(pool)
         .await?;
@@ -2547,9 +3648,9 @@ impl GlobalSettingsQueries {
             // Create settings for this account
             let row = sqlx::query(
                 r#"
-                INSERT INTO global_settings (user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, created_at, updated_at)
-                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $22)
-                RETURNING id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, created_at, updated_at
+                INSERT INTO global_settings (user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18, $19, $20, $21, $22, $23, $24, $25, $25)
+                RETURNING id, user_id, account_id, company_name, timezone, locale, logo_url, force_2fa_with_authenticator_app, add_signature_id_to_the_documents, require_signing_reason, allow_typed_text_signatures, allow_to_resubmit_completed_forms, allow_to_decline_documents, remember_and_pre_fill_signatures, require_authentication_for_file_download_links, combine_completed_documents_and_audit_log, expirable_file_download_links, enable_confetti, completion_title, completion_body, redirect_title, redirect_url, pdf_render_mode, flatten_form_fields, pdfa_output, created_at, updated_at
                 "#
             )
             .bind(user_id)
@@ -2573,6 +3674,9 @@ impl GlobalSettingsQueries {
             .bind(settings.completion_body.as_deref())
             .bind(settings.redirect_title.as_deref())
             .bind(settings.redirect_url.as_deref())
+            .bind(settings.pdf_render_mode.as_deref().unwrap_or("stamp"))
+            .bind(settings.flatten_form_fields.unwrap_or(false))
+            .bind(settings.pdfa_output.unwrap_or(false))
             .bind(now)
             .fetch_one(pool)
             .await?;
@@ -2600,6 +3704,9 @@ impl GlobalSettingsQueries {
                 completion_body: row.try_get("completion_body")?,
                 redirect_title: row.try_get("redirect_title")?,
                 redirect_url: row.try_get("redirect_url")?,
+                pdf_render_mode: row.try_get("pdf_render_mode")?,
+                flatten_form_fields: row.try_get("flatten_form_fields")?,
+                pdfa_output: row.try_get("pdfa_output")?,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
             });
@@ -2628,8 +3735,11 @@ impl GlobalSettingsQueries {
                 completion_body = COALESCE($17, completion_body),
                 redirect_title = COALESCE($18, redirect_title),
                 redirect_url = COALESCE($19, redirect_url),
-                updated_at = $20
-            WHERE account_id = $21
+                pdf_render_mode = COALESCE($20, pdf_render_mode),
+                flatten_form_fields = COALESCE($21, flatten_form_fields),
+                pdfa_output = COALESCE($22, pdfa_output),
+                updated_at = $23
+            WHERE account_id = $24
             "#
         );
         
@@ -2653,6 +3763,9 @@ impl GlobalSettingsQueries {
         query = query.bind(settings.completion_body.as_deref());
         query = query.bind(settings.redirect_title.as_deref());
         query = query.bind(settings.redirect_url.as_deref());
+        query = query.bind(settings.pdf_render_mode.as_deref());
+        query = query.bind(settings.flatten_form_fields);
+        query = query.bind(settings.pdfa_output);
         query = query.bind(now);
         query = query.bind(account_id);
         
@@ -2771,6 +3884,40 @@ impl EmailTemplateQueries {
         }
     }
 
+    pub async fn create_template(pool: &PgPool, data: CreateEmailTemplate) -> Result<DbEmailTemplate, sqlx::Error> {
+        let now = Utc::now();
+        let row = sqlx::query(
+            "INSERT INTO email_templates (user_id, template_type, subject, body, body_format, is_default, attach_documents, attach_audit_log, created_at, updated_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9)
+             RETURNING id, user_id, template_type, subject, body, body_format, is_default, attach_documents, attach_audit_log, created_at, updated_at"
+        )
+        .bind(data.user_id)
+        .bind(&data.template_type)
+        .bind(&data.subject)
+        .bind(&data.body)
+        .bind(&data.body_format)
+        .bind(data.is_default)
+        .bind(data.attach_documents)
+        .bind(data.attach_audit_log)
+        .bind(now)
+        .fetch_one(pool)
+        .await?;
+
+        Ok(DbEmailTemplate {
+            id: row.try_get("id")?,
+            user_id: row.try_get("user_id")?,
+            template_type: row.try_get("template_type")?,
+            subject: row.try_get("subject")?,
+            body: row.try_get("body")?,
+            body_format: row.try_get("body_format")?,
+            is_default: row.try_get("is_default")?,
+            attach_documents: row.try_get("attach_documents")?,
+            attach_audit_log: row.try_get("attach_audit_log")?,
+            created_at: row.try_get("created_at")?,
+            updated_at: row.try_get("updated_at")?,
+        })
+    }
+
     pub async fn get_template_by_id(pool: &PgPool, id: i64, user_id: i64) -> Result<Option<DbEmailTemplate>, sqlx::Error> {
         let row = sqlx::query(
             "SELECT id, user_id, template_type, subject, body, body_format, is_default, attach_documents, attach_audit_log, created_at, updated_at FROM email_templates WHERE id = $1 AND user_id = $2"
@@ -44,16 +44,42 @@ use models::template::Template;
         routes::templates::delete_folder,
         routes::templates::get_folder_templates,
         routes::templates::move_template_to_folder,
+        routes::templates::move_templates,
+        routes::templates::move_folder,
+        routes::templates::get_folder_breadcrumbs,
         routes::templates::get_templates,
         routes::templates::get_template,
         routes::templates::get_template_full_info,
         routes::templates::update_template,
         routes::templates::delete_template,
+        routes::templates::get_trashed_templates,
+        routes::templates::restore_template,
+        routes::templates::purge_template,
+        routes::templates::get_template_tags,
+        routes::templates::update_template_tags,
+        routes::templates::get_template_sharing,
+        routes::templates::update_template_sharing,
+        routes::templates::get_folder_sharing,
+        routes::templates::update_folder_sharing,
+        routes::templates::get_template_analytics,
+        routes::templates::export_template,
+        routes::templates::export_folder,
+        routes::templates::import_templates,
         routes::templates::clone_template,
         routes::templates::create_template_from_html,
         routes::templates::create_template_from_pdf,
         routes::templates::create_template_from_docx,
         routes::templates::merge_templates,
+        routes::templates::add_template_document,
+        routes::templates::replace_template_document,
+        routes::templates::delete_template_document,
+        routes::templates::arrange_template_document_pages,
+        routes::templates::insert_template_document_pages,
+        routes::templates::preview_template_document,
+        routes::templates::get_template_placeholders,
+        routes::templates::get_template_versions,
+        routes::templates::get_template_version,
+        routes::templates::diff_template_versions,
         routes::templates::download_file,
         routes::templates::preview_file,
         routes::templates::get_template_fields,
@@ -63,6 +89,8 @@ use models::template::Template;
         routes::templates::delete_template_field,
         routes::submissions::create_submission,
         routes::submitters::get_public_submitter_fields,
+        routes::submitters::get_public_submitter_draft,
+        routes::submitters::save_public_submitter_draft,
         routes::submitters::get_public_submitter_signatures,
         routes::submitters::get_public_submitter,
         routes::submitters::update_public_submitter,
@@ -75,7 +103,17 @@ use models::template::Template;
         routes::submitters::get_submitter_audit_log,
         routes::reminder_settings::get_reminder_settings,
         routes::reminder_settings::update_reminder_settings,
+        routes::search::search,
+        routes::field_snippets::get_field_snippets,
+        routes::field_snippets::create_field_snippet,
+        routes::field_snippets::get_field_snippet,
+        routes::field_snippets::update_field_snippet,
+        routes::field_snippets::delete_field_snippet,
+        routes::field_snippets::apply_field_snippet,
         routes::global_settings::get_user_settings,
+        routes::verification::verify_document,
+        routes::verification::verify_signature_id,
+        routes::verification::validate_pdfa,
         // routes::subscription::get_subscription_status,
         // routes::subscription::get_payment_link
     ),
@@ -90,6 +128,7 @@ use models::template::Template;
             routes::web::ForgotPasswordRequest,
             routes::web::ResetPasswordRequest,
             routes::web::UpdateUserRequest,
+            common::responses::Pagination,
             common::responses::ApiResponse<User>,
             common::responses::ApiResponse<common::responses::LoginResponse>,
             common::responses::ApiResponse<Vec<Template>>,
@@ -100,6 +139,7 @@ use models::template::Template;
             models::submitter::Submitter,
             common::responses::ApiResponse<Vec<models::submitter::Submitter>>,
             common::responses::ApiResponse<String>,
+            common::responses::ApiResponse<Vec<String>>,
             common::responses::ApiResponse<Vec<models::template::TemplateField>>,
             common::responses::ApiResponse<models::template::TemplateField>,
             common::responses::ApiResponse<Vec<models::user::TeamMember>>,
@@ -107,17 +147,59 @@ use models::template::Template;
             models::template::CreateTemplateFieldRequest,
             models::template::UpdateTemplateFieldRequest,
             models::template::FieldPosition,
+            models::template::ArrangeDocumentPagesRequest,
+            models::template::DocumentPage,
+            models::template::TrashedTemplate,
+            models::template::UpdateTemplateTagsRequest,
+            models::template::TemplateImportResult,
+            models::template::ImportedTemplate,
+            models::template::ImportConflict,
+            models::template::TemplateVersion,
+            models::template::TemplateVersionField,
+            models::template::TemplateVersionDiff,
+            models::template::TemplateVersionFieldChange,
+            models::template::TemplateVersionDocumentChange,
             models::template::TemplateField,
             models::template::TemplateFolder,
             models::template::CreateFolderRequest,
             models::template::UpdateFolderRequest,
+            models::template::FolderBreadcrumb,
+            models::template::MoveFolderRequest,
+            models::template::MoveTemplatesRequest,
+            models::template_share::AccessLevel,
+            models::template_share::TemplateShare,
+            models::template_share::SharingSettings,
+            models::template_share::ShareRequest,
+            models::template_share::UpdateSharingRequest,
+            models::analytics::TemplateAnalytics,
+            models::analytics::ReminderEffectiveness,
+            models::analytics::DeclineReasonCount,
+            models::analytics::AbandonmentField,
             models::submitter::PublicSubmitterFieldsResponse,
             models::submitter::PublicSubmitterSignaturesResponse,
+            models::submitter::SubmitterDraft,
+            models::submitter::SaveSubmitterDraftRequest,
             models::submitter::ReminderConfig,
             routes::reminder_settings::UserReminderSettingsResponse,
             routes::reminder_settings::UpdateReminderSettingsRequest,
             common::responses::ApiResponse<routes::reminder_settings::UserReminderSettingsResponse>,
-            database::models::DbGlobalSettings
+            models::search::SearchResults,
+            models::search::TemplateSearchResults,
+            models::search::SubmitterSearchResults,
+            models::search::TemplateSearchHit,
+            models::search::SubmitterSearchHit,
+            models::field_snippet::FieldSnippet,
+            models::field_snippet::SnippetField,
+            models::field_snippet::SnippetPosition,
+            models::field_snippet::CreateFieldSnippetRequest,
+            models::field_snippet::UpdateFieldSnippetRequest,
+            models::field_snippet::ApplyFieldSnippetRequest,
+            database::models::DbGlobalSettings,
+            routes::verification::DocumentVerificationResponse,
+            routes::verification::VerifiedSigner,
+            common::responses::ApiResponse<routes::verification::DocumentVerificationResponse>,
+            routes::verification::PdfaValidationResponse,
+            common::responses::ApiResponse<routes::verification::PdfaValidationResponse>
             // models::user::UserSubscriptionStatus,
             // models::user::CreatePaymentRequest,
             // routes::subscription::SubscriptionStatusResponse,
@@ -129,8 +211,11 @@ use models::template::Template;
         (name = "folders", description = "Template folder management endpoints"),
         (name = "templates", description = "Template management endpoints"),
         (name = "template_fields", description = "Template field management endpoints"),
+        (name = "field_snippets", description = "Saved field groups to reuse across templates"),
         (name = "submissions", description = "Document submission endpoints"),
-        (name = "submitters", description = "Submitter management endpoints")
+        (name = "submitters", description = "Submitter management endpoints"),
+        (name = "search", description = "Full-text search across templates and submitters"),
+        (name = "verification", description = "Public document verification endpoints")
         // (name = "subscription", description = "Subscription and billing endpoints")
     ),
     security(("bearer_auth" = [])),
@@ -154,6 +239,12 @@ async fn main() {
         }
     }
 
+    // Signature IDs on signed documents are keyed with this secret
+    if std::env::var("SIGNATURE_ID_SECRET").or_else(|_| std::env::var("JWT_SECRET")).is_err() {
+        println!("Neither SIGNATURE_ID_SECRET nor JWT_SECRET is set");
+        std::process::exit(1);
+    }
+
     // Initialize database connection
     let pool = establish_connection().await.expect("Failed to connect to database");
 
@@ -164,7 +255,16 @@ async fn main() {
         .await
     {
         Ok(count) => {
-            if count >= 8 {
+            // Compare against the migration files on disk so newly added migrations still run
+            let expected_migrations = std::fs::read_dir("./migrations")
+                .map(|entries| {
+                    entries
+                        .filter_map(|e| e.ok())
+                        .filter(|e| e.path().extension().map_or(false, |ext| ext == "sql"))
+                        .count() as i64
+                })
+                .unwrap_or(i64::MAX);
+            if count >= expected_migrations {
                 println!("✅ Database schema is up to date ({} migrations applied), skipping migrations", count);
             } else {
                 println!("Running database migrations...");
@@ -230,7 +330,19 @@ async fn main() {
         reminder_queue_clone.start_processing().await;
     });
     
-    println!("✅ Background services started (Payment Queue, Reminder Queue)");
+    // Purge templates whose time in the trash is over
+    let trash_pool = db_pool_arc.clone();
+    tokio::spawn(async move {
+        crate::services::template_trash::start_purging(trash_pool).await;
+    });
+
+    // Extract the text of documents uploaded before search existed
+    let search_pool = db_pool_arc.clone();
+    tokio::spawn(async move {
+        crate::services::template_search::index_existing(search_pool).await;
+    });
+    
+    println!("✅ Background services started (Payment Queue, Reminder Queue, Template Trash, Search Indexing)");
 
     // Create API routes
     let api_routes = create_router();
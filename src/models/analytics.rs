@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateAnalytics {
+    pub template_id: i64,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub sends: i64, // Submitters sent in the range
+    pub viewed: i64,
+    pub completed: i64,
+    pub declined: i64,
+    pub pending: i64,
+    pub completion_rate: f64, // Of sends, 0 to 1
+    pub decline_rate: f64,
+    pub median_time_to_first_view_seconds: Option<i64>,
+    pub median_time_to_sign_seconds: Option<i64>,
+    pub reminders: Vec<ReminderEffectiveness>,
+    pub decline_reasons: Vec<DeclineReasonCount>,
+    pub abandoned_drafts: i64, // Drafts not saved for a day and still not signed or declined
+    pub abandonment_fields: Vec<AbandonmentField>,
+}
+
+// How many submitters signed after exactly `reminder` reminders (0: without one)
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct ReminderEffectiveness {
+    pub reminder: i32,
+    pub reminded: i64, // Submitters sent at least this many reminders
+    pub signed_after: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct DeclineReasonCount {
+    pub reason: String,
+    pub count: i64,
+}
+
+// The first required field left empty in abandoned drafts, most common first
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct AbandonmentField {
+    pub template_field_id: i64,
+    pub name: String,
+    pub count: i64,
+}
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+use crate::database::models::DbFieldSnippet;
+
+// Where a snippet field sits relative to the top-left corner of the group, in the units of
+// the template fields it was saved from
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct SnippetPosition {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub page_offset: i32, // 0: the page the snippet is applied to
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+pub struct SnippetField {
+    pub name: String,
+    pub field_type: String,
+    pub required: bool,
+    pub position: SnippetPosition,
+    pub options: Option<serde_json::Value>,
+    pub partner: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FieldSnippet {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub fields: Vec<SnippetField>,
+    pub shared: bool, // With everyone of the owner's account
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Saves fields of a template as a snippet
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct CreateFieldSnippetRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub template_id: i64,
+    pub field_ids: Vec<i64>,
+    pub shared: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateFieldSnippetRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub shared: Option<bool>,
+}
+
+// Places a snippet's top-left corner at (x, y) on a page of the template
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ApplyFieldSnippetRequest {
+    pub page: i32, // 1-based, within the document
+    pub x: f64,
+    pub y: f64,
+    pub document_id: Option<String>, // Template document; unset means the first document
+    pub partner: Option<String>, // Assign all fields to this partner instead of the saved ones
+}
+
+impl From<DbFieldSnippet> for FieldSnippet {
+    fn from(snippet: DbFieldSnippet) -> Self {
+        FieldSnippet {
+            id: snippet.id,
+            user_id: snippet.user_id,
+            name: snippet.name,
+            description: snippet.description,
+            fields: serde_json::from_value(snippet.fields).unwrap_or_default(),
+            shared: snippet.shared,
+            created_at: snippet.created_at,
+            updated_at: snippet.updated_at,
+        }
+    }
+}
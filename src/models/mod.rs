@@ -5,4 +5,8 @@ pub mod submitter;
 pub mod signature;
 pub mod role;
 pub mod email_template;
-pub mod account;
\ No newline at end of file
+pub mod account;
+pub mod search;
+pub mod template_share;
+pub mod analytics;
+pub mod field_snippet;
@@ -0,0 +1,86 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+use crate::database::models::{DbSubmitterSearchHit, DbTemplateSearchHit};
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SearchResults {
+    pub query: String,
+    pub page: i64,
+    pub per_page: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub templates: Option<TemplateSearchResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submitters: Option<SubmitterSearchResults>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateSearchResults {
+    pub total: i64,
+    pub items: Vec<TemplateSearchHit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubmitterSearchResults {
+    pub total: i64,
+    pub items: Vec<SubmitterSearchHit>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateSearchHit {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub user_id: i64,
+    pub folder_id: Option<i64>,
+    pub tags: Vec<String>,
+    pub rank: f32, // Relevance; name matches weigh most, then tags, then document text
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubmitterSearchHit {
+    pub id: i64,
+    pub template_id: i64,
+    pub template_name: String,
+    pub name: String,
+    pub email: String,
+    pub status: String,
+    pub rank: f32, // Relevance; name and email matches weigh more than prefilled values
+    pub signed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<DbTemplateSearchHit> for TemplateSearchHit {
+    fn from(hit: DbTemplateSearchHit) -> Self {
+        TemplateSearchHit {
+            id: hit.id,
+            name: hit.name,
+            slug: hit.slug,
+            user_id: hit.user_id,
+            folder_id: hit.folder_id,
+            tags: hit.tags,
+            rank: hit.rank,
+            created_at: hit.created_at,
+            updated_at: hit.updated_at,
+        }
+    }
+}
+
+impl From<DbSubmitterSearchHit> for SubmitterSearchHit {
+    fn from(hit: DbSubmitterSearchHit) -> Self {
+        SubmitterSearchHit {
+            id: hit.id,
+            template_id: hit.template_id,
+            template_name: hit.template_name,
+            name: hit.name,
+            email: hit.email,
+            status: hit.status,
+            rank: hit.rank,
+            signed_at: hit.signed_at,
+            created_at: hit.created_at,
+        }
+    }
+}
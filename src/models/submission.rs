@@ -38,6 +38,9 @@ pub struct CreateSubmissionRequest {
     pub name: Option<String>,
     pub submitters: Vec<CreateSubmitterRequest>,
     pub expires_at: Option<DateTime<Utc>>,
+    // Values for the {{placeholders}} of DOCX templates, as a JSON object; arrays fill repeating table rows
+    #[serde(default)]
+    pub values: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -89,7 +89,9 @@ pub struct PublicTemplateInfo {
     pub name: String,
     pub slug: String,
     pub user_id: i64,
+    // First document, kept for single-document clients
     pub document: Option<crate::models::template::Document>,
+    pub documents: Vec<crate::models::template::Document>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -103,10 +105,25 @@ pub struct PublicSubmitterFieldsResponse {
 pub struct SubmitterInformation {
     pub email: String,
     pub id: i64,
+    /// ID printed next to the submitter's signatures
+    pub signature_id: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct PublicSubmitterSignaturesResponse {
     pub template_info: PublicTemplateInfo,
     pub bulk_signatures: Option<serde_json::Value>,
-}
\ No newline at end of file
+    /// ID printed next to the submitter's signatures
+    pub signature_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SubmitterDraft {
+    pub values: serde_json::Value, // By field id, as on the signing page
+    pub saved_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SaveSubmitterDraftRequest {
+    pub values: serde_json::Value,
+}
@@ -15,6 +15,14 @@ pub struct TemplateFolder {
     pub children: Option<Vec<TemplateFolder>>, // Nested folders
     #[serde(skip_serializing_if = "Option::is_none")]
     pub templates: Option<Vec<Template>>, // Templates in this folder
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub breadcrumbs: Option<Vec<FolderBreadcrumb>>, // Path from the top level down to this folder
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct FolderBreadcrumb {
+    pub id: i64,
+    pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -24,6 +32,22 @@ pub struct CreateFolderRequest {
     pub template_id: Option<i64>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MoveFolderRequest {
+    pub parent_folder_id: Option<i64>, // None moves the folder to the top level
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct MoveTemplatesRequest {
+    pub template_ids: Vec<i64>,
+    pub folder_id: Option<i64>, // None moves the templates out of any folder
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateTemplateTagsRequest {
+    pub tags: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct UpdateFolderRequest {
     pub name: Option<String>,
@@ -38,6 +62,8 @@ pub struct Template {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user_name: Option<String>,
     pub folder_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tags: Option<Vec<String>>,
     // pub fields: Option<Vec<Field>>, // Removed - now stored in separate table
     #[serde(skip_serializing_if = "Option::is_none")]
     pub template_fields: Option<Vec<TemplateField>>, // New: fields from separate table
@@ -45,19 +71,54 @@ pub struct Template {
     pub submitters: Option<Vec<Submitter>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub documents: Option<Vec<Document>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<TemplateSettings>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+// A deleted template, restorable until it is purged
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TrashedTemplate {
+    #[serde(flatten)]
+    pub template: Template,
+    pub deleted_at: DateTime<Utc>,
+    pub purge_at: DateTime<Utc>,
+}
+
+// Per-template overrides for completed documents.
+// Unset options fall back to the account's global settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, ToSchema)]
+pub struct TemplateSettings {
+    // "stamp" draws values on top of the page content,
+    // "acroform" writes them into the PDF's own form fields (unmatched fields are still stamped)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pdf_render_mode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flatten_form_fields: Option<bool>,
+    // Convert the completed document and audit log to PDF/A-2b
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pdfa_output: Option<bool>,
+    // Multi-document templates: one completed PDF per document instead of a single merged PDF
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separate_documents: Option<bool>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FieldPosition {
     pub x: f64,
     pub y: f64,
     pub width: f64,
     pub height: f64,
-    pub page: i32,
+    pub page: i32, // 1-based, within the document
+    // Template document the field is on; unset means the first document
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub document_id: Option<String>,
     pub suggested: Option<SuggestedPosition>,
     pub allow_custom: Option<bool>,
+    // Set when the document was replaced and the field's page or area isn't in the new one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_of_bounds: Option<bool>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -94,12 +155,34 @@ pub struct Submitter {
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Document {
+    // Referenced by FieldPosition::document_id. Documents stored before templates could hold
+    // several documents have no id and are referenced by their index.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    pub filename: String,
+    pub content_type: String,
+    pub size: i64,
+    pub url: String,
+    // File the PDF was converted from (e.g. a DOCX), kept to re-generate the PDF
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<SourceDocument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SourceDocument {
     pub filename: String,
     pub content_type: String,
     pub size: i64,
     pub url: String,
 }
 
+impl Document {
+    /// Id fields use to reference this document
+    pub fn key(&self, index: usize) -> String {
+        self.id.clone().unwrap_or_else(|| index.to_string())
+    }
+}
+
 // Request/Response structs for API
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreateTemplateRequest {
@@ -113,6 +196,8 @@ pub struct CreateTemplateRequest {
 pub struct UpdateTemplateRequest {
     pub name: Option<String>,
     pub folder_id: Option<i64>,
+    #[serde(default)]
+    pub settings: Option<TemplateSettings>,
     // pub fields: Option<Vec<Field>>, // Removed - now use separate endpoints
 }
 
@@ -152,6 +237,12 @@ pub struct CreateTemplateFromHtmlRequest {
     pub folder_id: Option<i64>,
     // pub fields: Option<Vec<Field>>, // Removed - now use separate endpoints
     pub submitters: Option<Vec<Submitter>>,
+    // CSS @page size ("A4", "Letter landscape", "210mm 297mm"); defaults to the HTML's own @page rules
+    #[serde(default)]
+    pub page_size: Option<String>,
+    // CSS @page margin ("20mm", "1in 0.75in")
+    #[serde(default)]
+    pub page_margin: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -176,6 +267,100 @@ pub struct MergeTemplatesRequest {
     pub folder_id: Option<i64>,
 }
 
+// New page order of a template document: pages left out are deleted
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ArrangeDocumentPagesRequest {
+    pub pages: Vec<DocumentPage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocumentPage {
+    pub page: i32, // 1-based, in the current document
+    // Degrees to turn the page clockwise, a multiple of 90
+    #[serde(default)]
+    pub rotate: i32,
+}
+
+// Immutable snapshot of a template's documents and fields. Submissions keep the version they
+// were sent with.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVersion {
+    pub id: i64,
+    pub template_id: i64,
+    pub version: i32,
+    pub documents: Vec<Document>,
+    pub fields: Vec<TemplateVersionField>,
+    pub created_by: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub submitter_count: i64, // submitters sent with this version
+}
+
+// A template field as it was when the version was recorded
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVersionField {
+    pub id: i64, // template field id
+    pub name: String,
+    pub field_type: String,
+    pub required: bool,
+    pub display_order: i32,
+    pub position: Option<Value>,
+    pub options: Option<Value>,
+    pub partner: Option<String>,
+}
+
+// Changes from one template version to another
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVersionDiff {
+    pub from: i32,
+    pub to: i32,
+    pub fields_added: Vec<TemplateVersionField>,
+    pub fields_removed: Vec<TemplateVersionField>,
+    pub fields_changed: Vec<TemplateVersionFieldChange>,
+    pub documents_added: Vec<Document>,
+    pub documents_removed: Vec<Document>,
+    pub documents_changed: Vec<TemplateVersionDocumentChange>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVersionFieldChange {
+    pub id: i64,
+    pub changes: Vec<String>, // names of the attributes that differ
+    pub before: TemplateVersionField,
+    pub after: TemplateVersionField,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateVersionDocumentChange {
+    pub id: String,
+    pub before: Document,
+    pub after: Document,
+}
+
+// Outcome of importing a template bundle
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateImportResult {
+    pub templates: Vec<ImportedTemplate>,
+    pub email_templates: Vec<String>, // types of the email templates created or replaced
+    pub conflicts: Vec<ImportConflict>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportedTemplate {
+    pub id: i64,
+    pub name: String,
+    pub slug: String,
+    pub original_slug: String, // slug on the instance it was exported from
+    pub folder_id: Option<i64>,
+}
+
+// Something in the bundle that clashed with what the importing account already has
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ImportConflict {
+    pub kind: String, // slug, name, email_template
+    pub name: String,
+    pub message: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct CreateTemplateFieldsRequest {
     pub fields: Vec<CreateTemplateFieldRequest>,
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use utoipa::ToSchema;
+
+use crate::database::models::DbTemplateShare;
+use crate::models::role::Role;
+
+// What a user can do with a template or folder; each level includes the ones before it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLevel {
+    View,
+    Use, // Send it for signing
+    Edit,
+}
+
+impl AccessLevel {
+    // From the rank returned by the template_access_level and folder_access_level SQL functions
+    pub fn from_rank(rank: i32) -> Option<AccessLevel> {
+        match rank {
+            1 => Some(AccessLevel::View),
+            2 => Some(AccessLevel::Use),
+            r if r >= 3 => Some(AccessLevel::Edit),
+            _ => None,
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<AccessLevel> {
+        match s {
+            "view" => Some(AccessLevel::View),
+            "use" => Some(AccessLevel::Use),
+            "edit" => Some(AccessLevel::Edit),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccessLevel::View => "view",
+            AccessLevel::Use => "use",
+            AccessLevel::Edit => "edit",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TemplateShare {
+    pub id: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    pub access_level: AccessLevel,
+    pub created_by: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Sharing settings of a template or folder
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct SharingSettings {
+    pub restricted: bool, // Only the owner, account Admins and the shares below have access
+    pub shares: Vec<TemplateShare>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub access_level: Option<AccessLevel>, // What the requesting user can do
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ShareRequest {
+    pub user_id: Option<i64>, // Either a user of the account...
+    pub role: Option<Role>, // ...or everyone of the account with this role
+    pub access_level: AccessLevel,
+}
+
+// Replaces all shares of the template or folder
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct UpdateSharingRequest {
+    pub restricted: bool,
+    pub shares: Vec<ShareRequest>,
+}
+
+impl From<DbTemplateShare> for TemplateShare {
+    fn from(share: DbTemplateShare) -> Self {
+        TemplateShare {
+            id: share.id,
+            user_id: share.user_id,
+            role: share.role,
+            access_level: AccessLevel::parse(&share.access_level).unwrap_or(AccessLevel::View),
+            created_by: share.created_by,
+            created_at: share.created_at,
+        }
+    }
+}
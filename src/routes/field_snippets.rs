@@ -0,0 +1,319 @@
+use axum::{
+    extract::{Path, State, Extension},
+    http::StatusCode,
+    response::Json,
+    routing::{get, post, put, delete},
+    Router,
+};
+use std::collections::HashSet;
+use sqlx::PgPool;
+use crate::common::responses::ApiResponse;
+use crate::database::models::{DbFieldSnippet, DbUser};
+use crate::database::queries::{FieldSnippetQueries, TemplateFieldQueries, TemplateQueries, UserQueries};
+use crate::models::field_snippet::{ApplyFieldSnippetRequest, CreateFieldSnippetRequest, FieldSnippet, UpdateFieldSnippetRequest};
+use crate::models::role::Role;
+use crate::models::template::TemplateField;
+use crate::models::template_share::AccessLevel;
+use crate::routes::templates::has_template_access;
+use crate::routes::web::AppState;
+use crate::services::{field_snippets, template_versions};
+
+// Snippets can be used by their owner and, when shared, by everyone of the owner's account
+fn can_use_snippet(snippet: &DbFieldSnippet, user: &DbUser) -> bool {
+    snippet.user_id == user.id || (snippet.shared && snippet.account_id.is_some() && snippet.account_id == user.account_id)
+}
+
+// ...and changed by their owner and the account's Admins
+fn can_manage_snippet(snippet: &DbFieldSnippet, user: &DbUser) -> bool {
+    snippet.user_id == user.id || (user.role == Role::Admin && snippet.account_id.is_some() && snippet.account_id == user.account_id)
+}
+
+// The snippet and the requesting user, if the snippet exists and the user can use it
+async fn available_snippet(pool: &PgPool, id: i64, user_id: i64) -> Result<Option<(DbFieldSnippet, DbUser)>, sqlx::Error> {
+    let Some(user) = UserQueries::get_user_by_id(pool, user_id).await? else {
+        return Ok(None);
+    };
+    match FieldSnippetQueries::get_field_snippet_by_id(pool, id).await? {
+        Some(snippet) if can_use_snippet(&snippet, &user) => Ok(Some((snippet, user))),
+        _ => Ok(None),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/field-snippets",
+    responses(
+        (status = 200, description = "Your snippets and the ones shared in your account, by name", body = ApiResponse<Vec<FieldSnippet>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<FieldSnippet>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "field_snippets"
+)]
+pub async fn get_field_snippets(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Vec<FieldSnippet>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match FieldSnippetQueries::get_available_field_snippets(pool, user_id).await {
+        Ok(snippets) => ApiResponse::success(snippets.into_iter().map(FieldSnippet::from).collect(), "Field snippets retrieved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve field snippets: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/field-snippets",
+    request_body = CreateFieldSnippetRequest,
+    responses(
+        (status = 201, description = "Fields of the template saved as a snippet", body = ApiResponse<FieldSnippet>),
+        (status = 400, description = "No name, or fields missing, not placed or on different documents", body = ApiResponse<FieldSnippet>),
+        (status = 404, description = "Template not found", body = ApiResponse<FieldSnippet>),
+        (status = 500, description = "Internal server error", body = ApiResponse<FieldSnippet>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "field_snippets"
+)]
+pub async fn create_field_snippet(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<CreateFieldSnippetRequest>,
+) -> (StatusCode, Json<ApiResponse<FieldSnippet>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return ApiResponse::bad_request("Snippet name is required".to_string());
+    }
+    let user = match UserQueries::get_user_by_id(pool, user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return ApiResponse::not_found("User not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve user: {}", e)),
+    };
+    match TemplateQueries::get_template_by_id(pool, payload.template_id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => {}
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+
+    let template_fields = match TemplateFieldQueries::get_template_fields(pool, payload.template_id).await {
+        Ok(fields) => fields,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template fields: {}", e)),
+    };
+    let field_ids: HashSet<i64> = payload.field_ids.iter().copied().collect();
+    let selected: Vec<_> = template_fields.into_iter().filter(|f| field_ids.contains(&f.id)).collect();
+    if selected.len() != field_ids.len() {
+        return ApiResponse::bad_request("Some fields are not fields of this template".to_string());
+    }
+    let fields = match field_snippets::snippet_fields(&selected) {
+        Ok(fields) => fields,
+        Err(e) => return ApiResponse::bad_request(e.to_string()),
+    };
+
+    let fields = serde_json::to_value(&fields).unwrap_or_else(|_| serde_json::json!([]));
+    let shared = payload.shared.unwrap_or(false);
+    match FieldSnippetQueries::create_field_snippet(pool, user_id, user.account_id, name, payload.description.as_deref(), &fields, shared).await {
+        Ok(snippet) => ApiResponse::created(snippet.into(), "Field snippet saved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to save field snippet: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/field-snippets/{id}",
+    params(
+        ("id" = i64, Path, description = "Field snippet ID")
+    ),
+    responses(
+        (status = 200, description = "Field snippet retrieved successfully", body = ApiResponse<FieldSnippet>),
+        (status = 404, description = "Field snippet not found", body = ApiResponse<FieldSnippet>),
+        (status = 500, description = "Internal server error", body = ApiResponse<FieldSnippet>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "field_snippets"
+)]
+pub async fn get_field_snippet(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<FieldSnippet>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match available_snippet(pool, id, user_id).await {
+        Ok(Some((snippet, _))) => ApiResponse::success(snippet.into(), "Field snippet retrieved successfully".to_string()),
+        Ok(None) => ApiResponse::not_found("Field snippet not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve field snippet: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/field-snippets/{id}",
+    params(
+        ("id" = i64, Path, description = "Field snippet ID")
+    ),
+    request_body = UpdateFieldSnippetRequest,
+    responses(
+        (status = 200, description = "Field snippet updated successfully", body = ApiResponse<FieldSnippet>),
+        (status = 400, description = "Empty name", body = ApiResponse<FieldSnippet>),
+        (status = 403, description = "Only the owner and account Admins can change a snippet", body = ApiResponse<FieldSnippet>),
+        (status = 404, description = "Field snippet not found", body = ApiResponse<FieldSnippet>),
+        (status = 500, description = "Internal server error", body = ApiResponse<FieldSnippet>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "field_snippets"
+)]
+pub async fn update_field_snippet(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<UpdateFieldSnippetRequest>,
+) -> (StatusCode, Json<ApiResponse<FieldSnippet>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let name = payload.name.as_deref().map(str::trim);
+    if name == Some("") {
+        return ApiResponse::bad_request("Snippet name cannot be empty".to_string());
+    }
+    match available_snippet(pool, id, user_id).await {
+        Ok(Some((snippet, user))) if can_manage_snippet(&snippet, &user) => {}
+        Ok(Some(_)) => return ApiResponse::forbidden("Only the owner and account Admins can change this snippet".to_string()),
+        Ok(None) => return ApiResponse::not_found("Field snippet not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve field snippet: {}", e)),
+    }
+
+    match FieldSnippetQueries::update_field_snippet(pool, id, name, payload.description.as_deref(), payload.shared).await {
+        Ok(Some(snippet)) => ApiResponse::success(snippet.into(), "Field snippet updated successfully".to_string()),
+        Ok(None) => ApiResponse::not_found("Field snippet not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to update field snippet: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/field-snippets/{id}",
+    params(
+        ("id" = i64, Path, description = "Field snippet ID")
+    ),
+    responses(
+        (status = 200, description = "Field snippet deleted successfully; fields placed with it stay", body = ApiResponse<()>),
+        (status = 403, description = "Only the owner and account Admins can delete a snippet", body = ApiResponse<()>),
+        (status = 404, description = "Field snippet not found", body = ApiResponse<()>),
+        (status = 500, description = "Internal server error", body = ApiResponse<()>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "field_snippets"
+)]
+pub async fn delete_field_snippet(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<()>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match available_snippet(pool, id, user_id).await {
+        Ok(Some((snippet, user))) if can_manage_snippet(&snippet, &user) => {}
+        Ok(Some(_)) => return ApiResponse::forbidden("Only the owner and account Admins can delete this snippet".to_string()),
+        Ok(None) => return ApiResponse::not_found("Field snippet not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve field snippet: {}", e)),
+    }
+
+    match FieldSnippetQueries::delete_field_snippet(pool, id).await {
+        Ok(true) => ApiResponse::success((), "Field snippet deleted successfully".to_string()),
+        Ok(false) => ApiResponse::not_found("Field snippet not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to delete field snippet: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates/{template_id}/field-snippets/{id}/apply",
+    params(
+        ("template_id" = i64, Path, description = "Template to add the fields to"),
+        ("id" = i64, Path, description = "Field snippet ID")
+    ),
+    request_body = ApplyFieldSnippetRequest,
+    responses(
+        (status = 201, description = "Fields added to the template; names already in use get a number", body = ApiResponse<Vec<TemplateField>>),
+        (status = 400, description = "Invalid page or offset", body = ApiResponse<Vec<TemplateField>>),
+        (status = 403, description = "No permission to edit the template", body = ApiResponse<Vec<TemplateField>>),
+        (status = 404, description = "Template or field snippet not found", body = ApiResponse<Vec<TemplateField>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<TemplateField>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "field_snippets"
+)]
+pub async fn apply_field_snippet(
+    State(state): State<AppState>,
+    Path((template_id, id)): Path<(i64, i64)>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<ApplyFieldSnippetRequest>,
+) -> (StatusCode, Json<ApiResponse<Vec<TemplateField>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    if payload.page < 1 || payload.x < 0.0 || payload.y < 0.0 {
+        return ApiResponse::bad_request("Page must be 1 or more and the offset not negative".to_string());
+    }
+    match TemplateQueries::get_template_by_id(pool, template_id).await {
+        Ok(Some(db_template)) => {
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::View).await {
+                return ApiResponse::not_found("Template not found".to_string());
+            }
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
+            }
+        }
+        Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to verify template: {}", e)),
+    }
+    let snippet = match available_snippet(pool, id, user_id).await {
+        Ok(Some((snippet, _))) => FieldSnippet::from(snippet),
+        Ok(None) => return ApiResponse::not_found("Field snippet not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve field snippet: {}", e)),
+    };
+
+    let existing = match TemplateFieldQueries::get_template_fields(pool, template_id).await {
+        Ok(fields) => fields,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template fields: {}", e)),
+    };
+    let taken_names: HashSet<String> = existing.iter().map(|f| f.name.clone()).collect();
+    let first_display_order = existing.iter().map(|f| f.display_order + 1).max().unwrap_or(0);
+    let partner = payload.partner.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    let new_fields = field_snippets::place_fields(
+        template_id, &snippet.fields, payload.page, payload.x, payload.y,
+        payload.document_id.clone(), partner, &taken_names, first_display_order,
+    );
+
+    let mut created_fields = Vec::new();
+    for create_field in new_fields {
+        match TemplateFieldQueries::create_template_field(pool, create_field).await {
+            Ok(db_field) => created_fields.push(TemplateField {
+                id: db_field.id,
+                template_id: db_field.template_id,
+                name: db_field.name,
+                field_type: db_field.field_type,
+                required: db_field.required,
+                display_order: db_field.display_order,
+                position: db_field.position.and_then(|v| serde_json::from_value(v).ok()),
+                options: db_field.options,
+                partner: db_field.partner,
+                created_at: db_field.created_at,
+                updated_at: db_field.updated_at,
+            }),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to create template field: {}", e)),
+        }
+    }
+    template_versions::record_edit(pool, template_id, user_id).await;
+
+    ApiResponse::created(created_fields, "Field snippet applied successfully".to_string())
+}
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/field-snippets", get(get_field_snippets))
+        .route("/field-snippets", post(create_field_snippet))
+        .route("/field-snippets/:id", get(get_field_snippet))
+        .route("/field-snippets/:id", put(update_field_snippet))
+        .route("/field-snippets/:id", delete(delete_field_snippet))
+        .route("/templates/:template_id/field-snippets/:id/apply", post(apply_field_snippet))
+}
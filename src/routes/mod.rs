@@ -8,4 +8,6 @@ pub mod subscription;
 pub mod reminder_settings;
 pub mod global_settings;
 pub mod email_templates;
-pub mod team;
\ No newline at end of file
+pub mod team;
+pub mod verification;
+pub mod search;pub mod field_snippets;
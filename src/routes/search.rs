@@ -0,0 +1,95 @@
+use axum::{
+    extract::{State, Extension, Query},
+    http::StatusCode,
+    response::Json,
+    routing::get,
+    Router,
+};
+use crate::common::responses::ApiResponse;
+use crate::database::queries::{SubmitterQueries, TemplateQueries};
+use crate::models::search::{SearchResults, SubmitterSearchResults, TemplateSearchResults};
+use crate::routes::web::AppState;
+use crate::services::template_search;
+
+const DEFAULT_PER_PAGE: i64 = 20;
+const MAX_PER_PAGE: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    scope: Option<String>, // "all" (default), "templates" or "submitters"
+    tag: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    params(
+        ("q" = String, Query, description = "Words to look for; the last one may be incomplete"),
+        ("scope" = Option<String>, Query, description = "What to search: all (default), templates or submitters"),
+        ("tag" = Option<String>, Query, description = "Only templates with this tag"),
+        ("page" = Option<i64>, Query, description = "Page number, from 1 (default 1)"),
+        ("per_page" = Option<i64>, Query, description = "Results per page of each kind, up to 100 (default 20)")
+    ),
+    responses(
+        (status = 200, description = "Templates matching by name, tag or document text, and submitters matching by name, email or prefilled values, most relevant first", body = ApiResponse<SearchResults>),
+        (status = 400, description = "Empty query or unknown scope", body = ApiResponse<SearchResults>),
+        (status = 500, description = "Internal server error", body = ApiResponse<SearchResults>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "search"
+)]
+pub async fn search(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+    Query(query): Query<SearchQuery>,
+) -> (StatusCode, Json<ApiResponse<SearchResults>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let Some(tsquery) = template_search::prefix_query(&query.q) else {
+        return ApiResponse::bad_request("Search query must contain at least one word".to_string());
+    };
+    let (search_templates, search_submitters) = match query.scope.as_deref().unwrap_or("all") {
+        "all" => (true, true),
+        "templates" => (true, false),
+        "submitters" => (false, true),
+        scope => return ApiResponse::bad_request(format!("Unknown search scope '{}'", scope)),
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+    let offset = (page - 1).saturating_mul(per_page);
+    let tag = query.tag.as_deref().map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty());
+
+    let mut results = SearchResults {
+        query: query.q.clone(),
+        page,
+        per_page,
+        templates: None,
+        submitters: None,
+    };
+    if search_templates {
+        match TemplateQueries::search_templates(pool, user_id, &tsquery, tag.as_deref(), per_page, offset).await {
+            Ok((hits, total)) => {
+                results.templates = Some(TemplateSearchResults { total, items: hits.into_iter().map(Into::into).collect() });
+            }
+            Err(e) => return ApiResponse::internal_error(format!("Failed to search templates: {}", e)),
+        }
+    }
+    if search_submitters {
+        match SubmitterQueries::search_submitters(pool, user_id, &tsquery, per_page, offset).await {
+            Ok((hits, total)) => {
+                results.submitters = Some(SubmitterSearchResults { total, items: hits.into_iter().map(Into::into).collect() });
+            }
+            Err(e) => return ApiResponse::internal_error(format!("Failed to search submitters: {}", e)),
+        }
+    }
+
+    ApiResponse::success(results, "Search completed successfully".to_string())
+}
+
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/search", get(search))
+}
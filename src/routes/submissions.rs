@@ -16,13 +16,19 @@ use crate::models::submission::{Submission, CreateSubmissionRequest};
 use crate::models::submitter::Submitter;
 use crate::database::connection::DbPool;
 use crate::database::models::CreateSubmitter;
-use crate::database::queries::{SubmitterQueries, TemplateQueries, SubmissionFieldQueries, EmailTemplateQueries};
+use crate::database::queries::{SubmitterQueries, TemplateQueries, SubmissionFieldQueries, EmailTemplateQueries, TemplateVersionQueries};
 use crate::database::models::CreateSubmissionField;
 use crate::routes::subscription::{can_user_submit, increment_usage_count_by};
-use crate::routes::templates::convert_db_template_to_template;
+use crate::routes::templates::{convert_db_template_to_template, has_template_access};
+use crate::models::template_share::AccessLevel;
 use crate::common::jwt::auth_middleware;
 use crate::common::authorization::require_admin_or_team_member;
 use crate::services::email::EmailService;
+use crate::services::docx_merge::UnresolvedPlaceholders;
+use crate::services::page_previews;
+use crate::services::storage::StorageService;
+use crate::services::template_documents;
+use crate::services::template_versions;
 
 use crate::routes::web::AppState;
 
@@ -41,7 +47,7 @@ fn replace_template_variables(content: &str, variables: &std::collections::HashM
     request_body = CreateSubmissionRequest,
     responses(
         (status = 201, description = "Submission created successfully", body = ApiResponse<Submission>),
-        (status = 400, description = "Bad request", body = ApiResponse<Submission>),
+        (status = 400, description = "Bad request, or values missing for DOCX placeholders", body = ApiResponse<Submission>),
         (status = 404, description = "Template not found", body = ApiResponse<Submission>)
     ),
     security(("bearer_auth" = []))
@@ -70,26 +76,38 @@ pub async fn create_submission(
     // Check if template exists
     match TemplateQueries::get_template_by_id(pool, payload.template_id).await {
         Ok(Some(db_template)) => {
-            // Check if user has permission to access this template
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin/Member role (Members can send signature requests from all team templates)
-                    let has_access = db_template.user_id == user_id || 
-                            matches!(
-                                user.role, 
-                                crate::models::role::Role::Editor |
-                                crate::models::role::Role::Admin |
-                                crate::models::role::Role::Member |
-                                crate::models::role::Role::Agent
-                            );
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("You do not have access to this form".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            // Sending needs use access: Members and Agents have it on the team's templates unless
+            // they are restricted
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Use).await {
+                return ApiResponse::forbidden("You do not have access to this form".to_string());
             }
 
+            // Fill the {{placeholders}} of DOCX documents with this submission's values
+            let values = match &payload.values {
+                None => serde_json::Map::new(),
+                Some(serde_json::Value::Object(values)) => values.clone(),
+                Some(_) => return ApiResponse::bad_request("values must be a JSON object".to_string()),
+            };
+            let personalized_documents = match StorageService::new().await {
+                Ok(storage) => match template_documents::personalize_documents(&storage, &db_template, &values).await {
+                    Ok(documents) => documents.map(|documents| {
+                        page_previews::pregenerate_files(documents.iter().map(|document| document.url.clone()).collect());
+                        serde_json::to_value(documents).unwrap_or(serde_json::Value::Null)
+                    }),
+                    Err(e) => match e.downcast_ref::<UnresolvedPlaceholders>() {
+                        Some(unresolved) => return ApiResponse::bad_request(unresolved.to_string()),
+                        None => return ApiResponse::internal_error(format!("Failed to personalize documents: {}", e)),
+                    },
+                },
+                Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+            };
+
+            // The submission keeps the template as it is now, whatever edits follow
+            let template_version = match template_versions::record_version(pool, payload.template_id, Some(user_id)).await {
+                Ok(version) => version,
+                Err(e) => return ApiResponse::internal_error(format!("Failed to record template version: {}", e)),
+            };
+
             // In merged schema, we create submitters directly without a separate submission record
             let mut created_submitters = Vec::new();
             let mut emails_sent_count = 0;
@@ -139,6 +157,23 @@ pub async fn create_submission(
 
                 match SubmitterQueries::create_submitter(pool, create_submitter).await {
                     Ok(db_submitter) => {
+                        if let Err(e) = TemplateVersionQueries::set_submitter_template_version(pool, db_submitter.id, template_version.id).await {
+                            return ApiResponse::internal_error(format!("Failed to store template version: {}", e));
+                        }
+
+                        if !values.is_empty() {
+                            if let Err(e) = SubmitterQueries::set_submitter_prefilled_values(pool, db_submitter.id, &serde_json::Value::Object(values.clone())).await {
+                                return ApiResponse::internal_error(format!("Failed to store submission values: {}", e));
+                            }
+                        }
+
+                        // The submitters of this submission share its personalized documents
+                        if let Some(documents) = &personalized_documents {
+                            if let Err(e) = SubmitterQueries::set_submitter_documents(pool, db_submitter.id, documents).await {
+                                return ApiResponse::internal_error(format!("Failed to store submission documents: {}", e));
+                            }
+                        }
+
                         let reminder_config = db_submitter.reminder_config.as_ref()
                             .and_then(|v| serde_json::from_value(v.clone()).ok());
                             
@@ -212,26 +247,12 @@ pub async fn create_submission(
                                     let subject = replace_template_variables(&email_template.subject, &variables);
                                     let body = replace_template_variables(&email_template.body, &variables);
 
-                                    // Generate attachments if needed
-                                    let mut document_path = None;
-
-                                    if email_template.attach_documents {
-                                        // Generate original PDF for invitation
-                                        if let Ok(storage_service) = crate::services::storage::StorageService::new().await {
-                                            if let Some(documents) = &db_template.documents {
-                                                if let Ok(docs) = serde_json::from_value::<Vec<crate::models::template::Document>>(documents.clone()) {
-                                                    if let Some(first_doc) = docs.first() {
-                                                        if let Ok(pdf_bytes) = storage_service.download_file(&first_doc.url).await {
-                                                            let temp_file = std::env::temp_dir().join(format!("original_document_{}.pdf", db_template.id));
-                                                            if let Ok(_) = tokio::fs::write(&temp_file, pdf_bytes).await {
-                                                                document_path = Some(temp_file.to_string_lossy().to_string());
-                                                            }
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
+                                    // Attach the original documents if needed
+                                    let documents = if email_template.attach_documents {
+                                        template_documents::original_document_attachments(pool, &db_template, &[db_submitter.id]).await
+                                    } else {
+                                        Vec::new()
+                                    };
 
                                     if let Err(e) = email_service.send_template_email(
                                         &submitter.email,
@@ -241,7 +262,7 @@ pub async fn create_submission(
                                         &email_template.body_format,
                                         email_template.attach_documents,
                                         email_template.attach_audit_log,
-                                        document_path.as_deref(),
+                                        &documents,
                                         None, // No audit log for invitation
                                     ).await {
                                         eprintln!("Failed to send template email to {}: {}", submitter.email, e);
@@ -249,10 +270,8 @@ pub async fn create_submission(
                                         emails_sent_count += 1;
                                     }
 
-                                    // Clean up temporary file
-                                    if let Some(path) = document_path {
-                                        let _ = tokio::fs::remove_file(path).await;
-                                    }
+                                    // Clean up temporary files
+                                    template_documents::remove_attachments(&documents).await;
                                 },
                                 _ => {
                                     // Fall back to default hardcoded email
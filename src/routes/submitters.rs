@@ -1,5 +1,5 @@
 use axum::{
-    extract::{Path, State, Extension, ConnectInfo},
+    extract::{Path, State, Extension, ConnectInfo, Query},
     http::{StatusCode, header},
     response::{Json, Response, IntoResponse},
     routing::{get, put, delete},
@@ -8,15 +8,30 @@ use axum::{
     body::Body,
 };
 use std::net::SocketAddr;
+use crate::common::pagination::{self, PageRequest, SortOrder};
 use crate::common::responses::ApiResponse;
-use crate::database::queries::{SubmitterQueries, UserQueries, SubmissionFieldQueries, GlobalSettingsQueries, TemplateQueries, EmailTemplateQueries, TemplateFieldQueries};
+use crate::database::queries::{SubmitterQueries, UserQueries, SubmissionFieldQueries, GlobalSettingsQueries, TemplateQueries, EmailTemplateQueries, TemplateFieldQueries, DocumentTimestampQueries, DocumentHashQueries, TemplateVersionQueries};
+use crate::database::models::{CreateDocumentTimestamp, CreateDocumentHash, SubmitterListFilter};
 use crate::common::jwt::{auth_middleware, verify_jwt};
 use crate::common::authorization::require_admin_or_team_member;
+use crate::common::token;
 use crate::services::storage::StorageService;
+use crate::services::acroform;
+use crate::services::pdf_signing;
+use crate::services::pdfa;
+use crate::services::pdf_merge;
+use crate::services::template_documents::{self, DocumentFile};
+use crate::services::template_versions;
+use crate::models::template::TemplateVersionField;
+use crate::services::fonts::UnicodeFonts;
+use crate::services::page_geometry::{begin_overlay, end_overlay, normalize_position, PageGeometry};
+use crate::services::timestamp::{self, TimestampAuthority, TimestampToken};
 use chrono::Utc;
 use serde_json;
 use md5;
 use crate::models::signature::SignatureInfo;
+use crate::models::template_share::AccessLevel;
+use crate::routes::templates::has_template_access;
 use sqlx::PgPool;
 
 use crate::routes::web::AppState;
@@ -29,11 +44,36 @@ fn replace_template_variables(content: &str, variables: &std::collections::HashM
     result
 }
 
+#[derive(serde::Deserialize)]
+pub struct SubmitterListQuery {
+    status: Option<String>,
+    template_id: Option<i64>,
+    user_id: Option<i64>,
+    created_from: Option<chrono::DateTime<Utc>>,
+    created_to: Option<chrono::DateTime<Utc>>,
+    sort: Option<String>,
+    order: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
 #[utoipa::path(
     get,
     path = "/api/submitters",
+    params(
+        ("status" = Option<String>, Query, description = "Only submitters with this status, e.g. pending, viewed, signed, declined"),
+        ("template_id" = Option<i64>, Query, description = "Only submitters of this template"),
+        ("user_id" = Option<i64>, Query, description = "Only submitters sent by this user"),
+        ("created_from" = Option<String>, Query, description = "Only submitters sent at or after this time (RFC 3339)"),
+        ("created_to" = Option<String>, Query, description = "Only submitters sent before this time (RFC 3339)"),
+        ("sort" = Option<String>, Query, description = "created_at (default), updated_at, signed_at, name, email or status"),
+        ("order" = Option<String>, Query, description = "asc or desc (default: desc for the default sort, asc otherwise)"),
+        ("page" = Option<i64>, Query, description = "Page number, from 1; without page and per_page all submitters are returned"),
+        ("per_page" = Option<i64>, Query, description = "Submitters per page, up to 200 (default 50)")
+    ),
     responses(
-        (status = 200, description = "Submitters retrieved successfully", body = ApiResponse<Vec<crate::models::submitter::Submitter>>),
+        (status = 200, description = "Submitters retrieved successfully, with the total count in pagination", body = ApiResponse<Vec<crate::models::submitter::Submitter>>),
+        (status = 400, description = "Invalid sort", body = ApiResponse<Vec<crate::models::submitter::Submitter>>),
         (status = 500, description = "Internal server error", body = ApiResponse<Vec<crate::models::submitter::Submitter>>)
     ),
     security(("bearer_auth" = []))
@@ -41,12 +81,33 @@ fn replace_template_variables(content: &str, variables: &std::collections::HashM
 pub async fn get_submitters(
     State(state): State<AppState>,
     Extension(user_id): Extension<i64>,
+    Query(query): Query<SubmitterListQuery>,
 ) -> (StatusCode, Json<ApiResponse<Vec<crate::models::submitter::Submitter>>>) {
     let pool = &state.lock().await.db_pool;
 
+    let page = PageRequest::from_query(query.page, query.per_page);
+    let sort = match SortOrder::from_query(
+        query.sort.as_deref(),
+        query.order.as_deref(),
+        &["created_at", "updated_at", "signed_at", "name", "email", "status"],
+        SortOrder { column: "created_at", descending: true },
+    ) {
+        Ok(sort) => sort,
+        Err(e) => return ApiResponse::bad_request(e),
+    };
+    let filter = SubmitterListFilter {
+        status: query.status,
+        template_id: query.template_id,
+        user_id: query.user_id,
+        created_from: query.created_from,
+        created_to: query.created_to,
+        sort,
+        page,
+    };
+
     // Get submitters for this user and their team members
-    match SubmitterQueries::get_team_submitters(pool, user_id).await {
-        Ok(db_submitters) => {
+    match SubmitterQueries::get_team_submitters(pool, user_id, &filter).await {
+        Ok((db_submitters, total)) => {
             let mut all_submitters = Vec::new();
             
             for db_submitter in db_submitters {
@@ -76,12 +137,24 @@ pub async fn get_submitters(
                 all_submitters.push(submitter);
             }
             
-            ApiResponse::success(all_submitters, "Submitters retrieved successfully".to_string())
+            ApiResponse::paginated(all_submitters, pagination::pagination(total, page), "Submitters retrieved successfully".to_string())
         }
         Err(e) => ApiResponse::internal_error(format!("Failed to get submitters: {}", e)),
     }
 }
 
+// Whoever sent a submitter can always manage it; others need access to its template: view to see
+// it, use to change or delete it
+async fn has_submitter_access(pool: &PgPool, db_submitter: &crate::database::models::DbSubmitter, user_id: i64, level: AccessLevel) -> bool {
+    if db_submitter.user_id == user_id {
+        return true;
+    }
+    match TemplateQueries::get_template_by_id(pool, db_submitter.template_id).await {
+        Ok(Some(db_template)) => has_template_access(pool, &db_template, user_id, level).await,
+        _ => false,
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/api/submitters/{id}",
@@ -103,17 +176,8 @@ pub async fn get_submitter(
 
     match SubmitterQueries::get_submitter_by_id(pool, submitter_id).await {
         Ok(Some(db_submitter)) => {
-            // Check permissions - allow access if user is the owner OR has Editor/Admin/Member role
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    let has_access = db_submitter.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_submitter_access(pool, &db_submitter, user_id, AccessLevel::View).await {
+                return ApiResponse::forbidden("Access denied".to_string());
             }
 
             let reminder_config = db_submitter.reminder_config.as_ref()
@@ -243,17 +307,8 @@ pub async fn update_submitter(
     // First, verify the submitter exists and check permissions
     match SubmitterQueries::get_submitter_by_id(pool, submitter_id).await {
         Ok(Some(db_submitter)) => {
-            // Check permissions - allow access if user is the owner OR has Editor/Admin/Member role
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    let has_access = db_submitter.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_submitter_access(pool, &db_submitter, user_id, AccessLevel::Use).await {
+                return ApiResponse::forbidden("Access denied".to_string());
             }
 
             match SubmitterQueries::update_submitter(pool, submitter_id, payload.status.as_deref()).await {
@@ -315,17 +370,8 @@ pub async fn delete_submitter(
     // First, verify the submitter exists and belongs to this user or team
     match SubmitterQueries::get_submitter_by_id(pool, submitter_id).await {
         Ok(Some(db_submitter)) => {
-            // Check permissions - allow access if user is the owner OR has Editor/Admin/Member role
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    let has_access = db_submitter.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::unauthorized("You don't have permission to delete this submitter".to_string());
-                    }
-                }
-                _ => return ApiResponse::unauthorized("User not found".to_string()),
+            if !has_submitter_access(pool, &db_submitter, user_id, AccessLevel::Use).await {
+                return ApiResponse::unauthorized("You don't have permission to delete this submitter".to_string());
             }
 
             // Delete the submitter
@@ -592,6 +638,7 @@ pub async fn submit_bulk_signatures(
         payload.user_agent.as_deref(),
         payload.session_id.as_deref(),
         payload.timezone.as_deref(),
+        &token::signature_id(db_submitter.id),
     ).await {
         Ok(Some(submitter)) => submitter,
         Ok(None) => return ApiResponse::not_found("Submitter not found".to_string()),
@@ -794,16 +841,14 @@ async fn send_completion_notifications(
     use std::collections::HashSet;
     let mut notified_emails: HashSet<String> = HashSet::new();
     
-    // Generate combined PDF once if needed
-    let combined_document_path = if email_template.as_ref().map(|t| t.attach_documents).unwrap_or(false) {
+    // Generate combined documents once if needed
+    let combined_documents = if email_template.as_ref().map(|t| t.attach_documents).unwrap_or(false) {
         if let Ok(storage_service) = StorageService::new().await {
-            if let Ok(signed_pdf_bytes) = generate_signed_pdf_for_template_with_filter(pool, template_id, &storage_service, None).await {
-                let temp_file = std::env::temp_dir().join(format!("signed_document_all_{}.pdf", template_id));
-                tokio::fs::write(&temp_file, signed_pdf_bytes).await.ok();
-                Some(temp_file.to_string_lossy().to_string())
-            } else { None }
-        } else { None }
-    } else { None };
+            if let Ok(signed_documents) = generate_signed_documents_for_template_with_filter(pool, template_id, &storage_service, None).await {
+                template_documents::write_attachments(&format!("signed_document_all_{}", template_id), &signed_documents).await
+            } else { Vec::new() }
+        } else { Vec::new() }
+    } else { Vec::new() };
 
     // Send to completion_email first
     if let Some(ref email_tmpl) = email_template {
@@ -819,7 +864,7 @@ async fn send_completion_notifications(
             &all_submitters,
             completed_count,
             total_count,
-            combined_document_path.as_deref(),
+            &combined_documents,
             template_id,
             Some(submitter_id),
         ).await?;
@@ -843,7 +888,7 @@ async fn send_completion_notifications(
                         &all_submitters,
                         completed_count,
                         total_count,
-                        combined_document_path.as_deref(),
+                        &combined_documents,
                         template_id,
                         Some(submitter_info.id),
                     ).await;
@@ -854,9 +899,7 @@ async fn send_completion_notifications(
     }
 
     // Cleanup
-    if let Some(path) = combined_document_path {
-        let _ = tokio::fs::remove_file(path).await;
-    }
+    template_documents::remove_attachments(&combined_documents).await;
 
     Ok(())
 }
@@ -873,7 +916,7 @@ async fn send_single_completion_email(
     all_submitters: &[crate::database::models::DbSubmitter],
     completed_count: usize,
     total_count: usize,
-    combined_document_path: Option<&str>,
+    combined_documents: &[(String, String)],
     template_id: i64,
     submitter_id: Option<i64>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
@@ -914,22 +957,20 @@ async fn send_single_completion_email(
     let subject = replace_template_variables(&email_template.subject, &subject_variables);
     let body = replace_template_variables(&email_template.body, &body_variables);
 
-    let mut document_path = combined_document_path.map(|s| s.to_string());
+    let mut submitter_documents = Vec::new();
     let mut audit_log_path = None;
 
-    // Generate per-submitter PDF if no combined and attach_documents is true
-    if email_template.attach_documents && document_path.is_none() {
+    // Generate per-submitter documents if no combined ones and attach_documents is true
+    if email_template.attach_documents && combined_documents.is_empty() {
         if let Some(sid) = submitter_id {
             if let Ok(storage_service) = StorageService::new().await {
-                if let Ok(signed_pdf_bytes) = generate_signed_pdf_for_template_with_filter(pool, template_id, &storage_service, Some(sid)).await {
-                    let temp_file = std::env::temp_dir().join(format!("signed_document_{}.pdf", sid));
-                    if tokio::fs::write(&temp_file, signed_pdf_bytes).await.is_ok() {
-                        document_path = Some(temp_file.to_string_lossy().to_string());
-                    }
+                if let Ok(signed_documents) = generate_signed_documents_for_template_with_filter(pool, template_id, &storage_service, Some(sid)).await {
+                    submitter_documents = template_documents::write_attachments(&format!("signed_document_{}", sid), &signed_documents).await;
                 }
             }
         }
     }
+    let documents = if combined_documents.is_empty() { &submitter_documents[..] } else { combined_documents };
 
     if email_template.attach_audit_log {
         if let Ok(audit_pdf_bytes) = generate_template_audit_log_pdf(pool, template_id).await {
@@ -948,16 +989,12 @@ async fn send_single_completion_email(
         &email_template.body_format,
         email_template.attach_documents,
         email_template.attach_audit_log,
-        document_path.as_deref(),
+        documents,
         audit_log_path.as_deref(),
     ).await;
 
     // Cleanup temp files
-    if let Some(path) = document_path {
-        if !combined_document_path.map(|p| p == path).unwrap_or(false) {
-            let _ = tokio::fs::remove_file(path).await;
-        }
-    }
+    template_documents::remove_attachments(&submitter_documents).await;
     if let Some(path) = audit_log_path {
         let _ = tokio::fs::remove_file(path).await;
     }
@@ -1003,7 +1040,7 @@ pub async fn get_public_submitter_fields(
                                     position: sf.position.map(|pos| {
                                         // Parse position JSON to FieldPosition
                                         serde_json::from_value(pos).unwrap_or_else(|_| crate::models::template::FieldPosition {
-                                            x: 0.0, y: 0.0, width: 100.0, height: 20.0, page: 1, suggested: None, allow_custom: None
+                                            x: 0.0, y: 0.0, width: 100.0, height: 20.0, page: 1, document_id: None, suggested: None, allow_custom: None, out_of_bounds: None
                                         })
                                     }),
                                     options: sf.options,
@@ -1014,21 +1051,14 @@ pub async fn get_public_submitter_fields(
                             }).collect();
 
                             // Extract template info
-                            let document = db_template.documents.as_ref()
-                                .and_then(|docs| {
-                                    if let serde_json::Value::Array(arr) = docs {
-                                        arr.get(0)
-                                    } else {
-                                        None
-                                    }
-                                })
-                                .and_then(|doc| serde_json::from_value(doc.clone()).ok());
+                            let documents = template_documents::submitter_documents(pool, &db_template, &[db_submitter.id]).await.unwrap_or_default();
                             let template_info = crate::models::submitter::PublicTemplateInfo {
                                 id: db_template.id,
                                 name: db_template.name.clone(),
                                 slug: db_template.slug.clone(),
                                 user_id: db_template.user_id,
-                                document,
+                                document: documents.first().cloned(),
+                                documents,
                             };
 
                             // Filter fields based on partner matching submitter's name or email
@@ -1053,6 +1083,7 @@ pub async fn get_public_submitter_fields(
                                 information: crate::models::submitter::SubmitterInformation {
                                     email: db_submitter.email.clone(),
                                     id: db_submitter.id,
+                                    signature_id: token::signature_id(db_submitter.id),
                                 },
                             };
                             ApiResponse::success(response, "Submission fields retrieved successfully".to_string())
@@ -1069,6 +1100,80 @@ pub async fn get_public_submitter_fields(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/public/submissions/{token}/draft",
+    params(
+        ("token" = String, Path, description = "Submitter token")
+    ),
+    responses(
+        (status = 200, description = "Values saved so far; empty if no draft was saved", body = ApiResponse<crate::models::submitter::SubmitterDraft>),
+        (status = 404, description = "Submitter not found", body = ApiResponse<crate::models::submitter::SubmitterDraft>)
+    )
+)]
+pub async fn get_public_submitter_draft(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> (StatusCode, Json<ApiResponse<crate::models::submitter::SubmitterDraft>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_submitter = match SubmitterQueries::get_submitter_by_token(pool, &token).await {
+        Ok(Some(db_submitter)) => db_submitter,
+        Ok(None) => return ApiResponse::not_found("Submitter not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve submitter: {}", e)),
+    };
+    match SubmitterQueries::get_submitter_draft(pool, db_submitter.id).await {
+        Ok(Some((values, saved_at))) => ApiResponse::success(
+            crate::models::submitter::SubmitterDraft { values, saved_at: Some(saved_at) },
+            "Draft retrieved successfully".to_string(),
+        ),
+        Ok(None) => ApiResponse::success(
+            crate::models::submitter::SubmitterDraft { values: serde_json::json!({}), saved_at: None },
+            "No draft saved".to_string(),
+        ),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve draft: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/public/submissions/{token}/draft",
+    params(
+        ("token" = String, Path, description = "Submitter token")
+    ),
+    request_body = crate::models::submitter::SaveSubmitterDraftRequest,
+    responses(
+        (status = 200, description = "Draft saved", body = ApiResponse<crate::models::submitter::SubmitterDraft>),
+        (status = 400, description = "Values are not an object", body = ApiResponse<crate::models::submitter::SubmitterDraft>),
+        (status = 404, description = "Submitter not found", body = ApiResponse<crate::models::submitter::SubmitterDraft>),
+        (status = 409, description = "Already signed or declined", body = ApiResponse<crate::models::submitter::SubmitterDraft>)
+    )
+)]
+pub async fn save_public_submitter_draft(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    Json(payload): Json<crate::models::submitter::SaveSubmitterDraftRequest>,
+) -> (StatusCode, Json<ApiResponse<crate::models::submitter::SubmitterDraft>>) {
+    let pool = &state.lock().await.db_pool;
+
+    if !payload.values.is_object() {
+        return ApiResponse::bad_request("Draft values must be an object keyed by field id".to_string());
+    }
+    let db_submitter = match SubmitterQueries::get_submitter_by_token(pool, &token).await {
+        Ok(Some(db_submitter)) => db_submitter,
+        Ok(None) => return ApiResponse::not_found("Submitter not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve submitter: {}", e)),
+    };
+    match SubmitterQueries::save_submitter_draft(pool, db_submitter.id, &payload.values).await {
+        Ok(true) => ApiResponse::success(
+            crate::models::submitter::SubmitterDraft { values: payload.values, saved_at: Some(Utc::now()) },
+            "Draft saved successfully".to_string(),
+        ),
+        Ok(false) => ApiResponse::conflict("This document has already been signed or declined".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to save draft: {}", e)),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/public/submissions/{token}/signatures",
@@ -1092,17 +1197,17 @@ pub async fn get_public_submitter_signatures(
             let template_id = db_submitter.template_id;
             match crate::database::queries::TemplateQueries::get_template_by_id(pool, template_id).await {
                 Ok(Some(db_template)) => {
+                    let documents = template_documents::submitter_documents(pool, &db_template, &[db_submitter.id]).await.unwrap_or_default();
                     match crate::routes::templates::convert_db_template_to_template_with_fields(db_template, pool).await {
                         Ok(template) => {
                             // Extract template info
-                            let document = template.documents.as_ref()
-                                .and_then(|docs| docs.get(0).cloned());
                             let template_info = crate::models::submitter::PublicTemplateInfo {
                                 id: template.id,
                                 name: template.name.clone(),
                                 slug: template.slug.clone(),
                                 user_id: template.user_id,
-                                document,
+                                document: documents.first().cloned(),
+                                documents,
                             };
                             
                             // Get all submitters for this template
@@ -1181,6 +1286,7 @@ pub async fn get_public_submitter_signatures(
                                     let response = crate::models::submitter::PublicSubmitterSignaturesResponse {
                                         template_info,
                                         bulk_signatures,
+                                        signature_id: token::signature_id(db_submitter.id),
                                     };
                                     ApiResponse::success(response, "All signatures retrieved successfully".to_string())
                                 }
@@ -1199,27 +1305,6 @@ pub async fn get_public_submitter_signatures(
     }
 }
 
-/// Helper function to normalize position coordinates (matching frontend logic)
-/// Converts pixel coordinates to decimal (0-1) format using 600x800 reference dimensions
-fn normalize_position(x: f64, y: f64, width: f64, height: f64) -> (f64, f64, f64, f64) {
-    const PAGE_WIDTH: f64 = 600.0;  // Default A4 width in pixels (matching frontend)
-    const PAGE_HEIGHT: f64 = 800.0; // Default A4 height in pixels (matching frontend)
-    
-    // Check if position is in pixels (values > 1) or already in decimal (0-1)
-    if x > 1.0 || y > 1.0 || width > 1.0 || height > 1.0 {
-        // Position is in pixels, convert to decimal (0-1)
-        (
-            x / PAGE_WIDTH,
-            y / PAGE_HEIGHT,
-            width / PAGE_WIDTH,
-            height / PAGE_HEIGHT,
-        )
-    } else {
-        // Already in decimal format
-        (x, y, width, height)
-    }
-}
-
 /// Helper function to render signatures on PDF using the position formula
 fn render_signatures_on_pdf(
     pdf_bytes: &[u8],
@@ -1233,6 +1318,7 @@ fn render_signatures_on_pdf(
     
     // Load the PDF document
     let mut doc = Document::load_mem(pdf_bytes)?;
+    let mut fonts = UnicodeFonts::new();
     
     // Get all page IDs first
     let page_ids: Vec<_> = doc.get_pages()
@@ -1242,6 +1328,9 @@ fn render_signatures_on_pdf(
     
     println!("Rendering {} signatures on PDF", signatures.len());
     
+    // Pages drawn on so far, with their display geometry
+    let mut overlay_pages: std::collections::BTreeMap<lopdf::ObjectId, PageGeometry> = std::collections::BTreeMap::new();
+    
     // Process each signature
     for (field_name, field_type, signature_value, area_x, area_y, area_w, area_h, page_num, signature_json) in signatures {
         // Skip empty signatures
@@ -1262,40 +1351,17 @@ fn render_signatures_on_pdf(
         
         let page_id = page_ids[page_index];
         
-        // Get page dimensions (MediaBox)
-        let (page_width, page_height) = {
-            let page_obj = doc.get_object(page_id)?;
-            let page_dict = page_obj.as_dict()?;
-            
-            if let Ok(mediabox) = page_dict.get(b"MediaBox") {
-                if let Ok(mediabox_array) = mediabox.as_array() {
-                    if mediabox_array.len() >= 4 {
-                        let width = if let Ok(w) = mediabox_array[2].as_f32() {
-                            w as f64
-                        } else if let Ok(w) = mediabox_array[2].as_i64() {
-                            w as f64
-                        } else {
-                            612.0
-                        };
-                        
-                        let height = if let Ok(h) = mediabox_array[3].as_f32() {
-                            h as f64
-                        } else if let Ok(h) = mediabox_array[3].as_i64() {
-                            h as f64
-                        } else {
-                            792.0
-                        };
-                        (width, height)
-                    } else {
-                        (612.0, 792.0)
-                    }
-                } else {
-                    (612.0, 792.0)
-                }
-            } else {
-                (612.0, 792.0)
+        // Fields are placed on the page as displayed: CropBox, rotated, possibly inherited from
+        // the page tree. Renderers below draw in that display space.
+        let geometry = match overlay_pages.get(&page_id) {
+            Some(geometry) => *geometry,
+            None => {
+                let geometry = begin_overlay(&mut doc, page_id)?;
+                overlay_pages.insert(page_id, geometry);
+                geometry
             }
         };
+        let (page_width, page_height) = geometry.display_size();
         
         // Try to get absolute coordinates from signature_json first, fallback to calculation
         let (x_pos, y_pos, field_width, field_height) = if let (Some(abs_x), Some(abs_y), Some(abs_w), Some(abs_h)) = (
@@ -1355,11 +1421,11 @@ fn render_signatures_on_pdf(
             "multiple" => {
                 // Chia giá trị theo dấu phẩy và nối chúng bằng dấu cách
                 let display_value = signature_value.split(',').collect::<Vec<&str>>().join(" ");
-                render_text_field(&mut doc, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
+                render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
             },
             "cells" => {
                 // Hiển thị trong bố cục lưới với mỗi ký tự trong một ô riêng biệt
-                render_cells_field(&mut doc, page_id, &signature_value, x_pos, pdf_y, field_width, field_height)?;
+                render_cells_field(&mut doc, &mut fonts, page_id, &signature_value, x_pos, pdf_y, field_width, field_height)?;
             },
             "radio" => {
                 // Hiển thị giá trị đã chọn hoặc chỗ giữ chỗ
@@ -1368,7 +1434,7 @@ fn render_signatures_on_pdf(
                 } else {
                     signature_value.to_string()
                 };
-                render_text_field(&mut doc, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
+                render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
             },
             "initials" => {
                 // Calculate text height dynamically (matching SignatureRenderer.tsx)
@@ -1439,34 +1505,34 @@ fn render_signatures_on_pdf(
                     // JSON object - có thể có text hoặc vector
                     if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(signature_value) {
                         if let Some(text) = json_value.get("text").and_then(|t| t.as_str()) {
-                            render_initials_field(&mut doc, page_id, text, x_pos, sig_y, field_width, sig_height)?;
+                            render_initials_field(&mut doc, &mut fonts, page_id, text, x_pos, sig_y, field_width, sig_height)?;
                         } else if let Some(initials) = json_value.get("initials").and_then(|i| i.as_str()) {
-                            render_initials_field(&mut doc, page_id, initials, x_pos, sig_y, field_width, sig_height)?;
+                            render_initials_field(&mut doc, &mut fonts, page_id, initials, x_pos, sig_y, field_width, sig_height)?;
                         } else {
-                            render_initials_field(&mut doc, page_id, "[SIGNATURE]", x_pos, sig_y, field_width, sig_height)?;
+                            render_initials_field(&mut doc, &mut fonts, page_id, "[SIGNATURE]", x_pos, sig_y, field_width, sig_height)?;
                         }
                     } else {
-                        render_initials_field(&mut doc, page_id, &signature_value, x_pos, sig_y, field_width, sig_height)?;
+                        render_initials_field(&mut doc, &mut fonts, page_id, &signature_value, x_pos, sig_y, field_width, sig_height)?;
                     }
                 } else {
                     // Plain text
-                    render_initials_field(&mut doc, page_id, &signature_value, x_pos, sig_y, field_width, sig_height)?;
+                    render_initials_field(&mut doc, &mut fonts, page_id, &signature_value, x_pos, sig_y, field_width, sig_height)?;
                 }
                 
                 // Add signature ID information below the signature (always show for downloaded PDFs)
-                render_signature_id_info(&mut doc, page_id, submitter, &signature_json, x_pos, pdf_y, field_width, field_height, user_settings)?;
+                render_signature_id_info(&mut doc, &mut fonts, page_id, submitter, &signature_json, x_pos, pdf_y, field_width, field_height, user_settings)?;
             },
             "image" => {
                 // Hiển thị <img> với giá trị làm nguồn, được co giãn để vừa với khu vực trường
                 // Note: lopdf không hỗ trợ embed images trực tiếp, sẽ render như text placeholder
                 let display_value = format!("[IMAGE: {}]", signature_value);
-                render_text_field(&mut doc, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
+                render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
             },
             "file" => {
                 // Hiển thị liên kết tải xuống có thể nhấp với tên tệp được trích xuất từ URL
                 let filename = extract_filename_from_url(&signature_value);
                 let display_value = format!("[DOWNLOAD: {}]", filename);
-                render_text_field(&mut doc, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
+                render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
             },
             "text" => {
                 // Pure text field - use full field dimensions without subtracting text height
@@ -1475,7 +1541,7 @@ fn render_signatures_on_pdf(
                 } else {
                     signature_value.to_string()
                 };
-                render_text_field(&mut doc, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
+                render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, pdf_y, field_width, field_height)?;
             },
             _ => {
                 // Calculate text height dynamically (matching SignatureRenderer.tsx)
@@ -1551,16 +1617,16 @@ fn render_signatures_on_pdf(
                     // JSON object - có thể có text hoặc vector
                     if let Ok(json_value) = serde_json::from_str::<serde_json::Value>(signature_value) {
                         if let Some(text) = json_value.get("text").and_then(|t| t.as_str()) {
-                            render_text_field(&mut doc, page_id, text, x_pos, sig_y, field_width, sig_height)?;
+                            render_text_field(&mut doc, &mut fonts, page_id, text, x_pos, sig_y, field_width, sig_height)?;
                         } else if let Some(sig_text) = json_value.get("signature").and_then(|s| s.as_str()) {
-                            render_text_field(&mut doc, page_id, sig_text, x_pos, sig_y, field_width, sig_height)?;
+                            render_text_field(&mut doc, &mut fonts, page_id, sig_text, x_pos, sig_y, field_width, sig_height)?;
                         } else {
                             let display_value = if signature_value.is_empty() {
                                 field_name.clone()
                             } else {
                                 signature_value.to_string()
                             };
-                            render_text_field(&mut doc, page_id, &display_value, x_pos, sig_y, field_width, sig_height)?;
+                            render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, sig_y, field_width, sig_height)?;
                         }
                     } else {
                         let display_value = if signature_value.is_empty() {
@@ -1568,7 +1634,7 @@ fn render_signatures_on_pdf(
                         } else {
                             signature_value.to_string()
                         };
-                        render_text_field(&mut doc, page_id, &display_value, x_pos, sig_y, field_width, sig_height)?;
+                        render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, sig_y, field_width, sig_height)?;
                     }
                 } else {
                     // Plain text
@@ -1577,17 +1643,24 @@ fn render_signatures_on_pdf(
                     } else {
                         signature_value.to_string()
                     };
-                    render_text_field(&mut doc, page_id, &display_value, x_pos, sig_y, field_width, sig_height)?;
+                    render_text_field(&mut doc, &mut fonts, page_id, &display_value, x_pos, sig_y, field_width, sig_height)?;
                 }
                 
                 // Add signature ID information below signatures (always show for downloaded PDFs)
                 if field_type == "signature" {
-                    render_signature_id_info(&mut doc, page_id, submitter, &signature_json, x_pos, pdf_y, field_width, field_height, user_settings)?;
+                    render_signature_id_info(&mut doc, &mut fonts, page_id, submitter, &signature_json, x_pos, pdf_y, field_width, field_height, user_settings)?;
                 }
             }
         }
     }
     
+    for page_id in overlay_pages.keys() {
+        end_overlay(&mut doc, *page_id)?;
+    }
+    
+    // Embed the font subsets used by the rendered text
+    fonts.finish(&mut doc)?;
+    
     // Save modified PDF to bytes
     let mut output = Vec::new();
     doc.save_to(&mut output)?;
@@ -1599,37 +1672,6 @@ fn extract_filename_from_url(url: &str) -> String {
     url.split('/').last().unwrap_or("file").to_string()
 }
 
-// Helper function to generate hash ID similar to frontend hashId function
-fn hash_id(value: i64) -> String {
-    let str_value = value.to_string();
-    
-    // Create 32-bit hash from value
-    let mut hash: i32 = 0;
-    for ch in str_value.chars() {
-        hash = ((hash << 5).wrapping_sub(hash).wrapping_add(ch as i32)) | 0;
-    }
-    
-    // Generate hex string (8 characters from 32-bit hash)
-    let mut hex = String::new();
-    for i in 0..8 {
-        let h = ((hash >> (i * 4)) & 0xF) as u8;
-        hex.push_str(&format!("{:X}", h));
-    }
-    
-    // Repeat to get 32 characters: hex.len() = 8, we need 32, so repeat 4 times
-    let hex32 = format!("{}{}{}{}", hex, hex, hex, hex);
-    
-    // Format as UUID (8-4-4-4-12 = 32 characters)
-    format!(
-        "{}-{}-{}-{}-{}",
-        &hex32[0..8],
-        &hex32[8..12],
-        &hex32[12..16],
-        &hex32[16..20],
-        &hex32[20..32]
-    )
-}
-
 // Calculate text height for signature info (matching SignatureRenderer.tsx logic)
 fn calculate_signature_text_height(
     user_settings: &crate::database::models::DbGlobalSettings,
@@ -1661,6 +1703,7 @@ fn calculate_signature_text_height(
 // Render signature ID information below the signature
 fn render_signature_id_info(
     doc: &mut lopdf::Document,
+    fonts: &mut UnicodeFonts,
     page_id: lopdf::ObjectId,
     submitter: &crate::database::models::DbSubmitter,
     signature_data: &serde_json::Value,
@@ -1673,8 +1716,8 @@ fn render_signature_id_info(
     use lopdf::{Object, Stream, Dictionary};
     use lopdf::content::{Content, Operation};
 
-    // Generate signature ID using hashId function (matching frontend)
-    let signature_id = hash_id(submitter.id + 1);
+    // Verifiable signature ID (see /public/verify)
+    let signature_id = token::signature_id(submitter.id);
 
     // Get reason from signature data
     let reason = signature_data.get("reason")
@@ -1838,10 +1881,6 @@ fn render_signature_id_info(
     // Create text content stream for signature info with multiple lines
     let mut text_operations = vec![
         Operation::new("BT", vec![]), // Begin text
-        Operation::new("Tf", vec![
-            Object::Name(b"F1".to_vec()), // Use Arial (F1) instead of Helvetica
-            Object::Real(font_size as f32),
-        ]), // Set font
         Operation::new("rg", vec![
             Object::Real(0.0),
             Object::Real(0.0),
@@ -1867,12 +1906,11 @@ fn render_signature_id_info(
             Object::Real(line_y as f32),  // f: vertical position
         ]));
         
-        text_operations.push(Operation::new("Tj", vec![
-            Object::string_literal(line.clone()),
-        ])); // Show text
+        text_operations.extend(fonts.layout(doc, line).operations(font_size)); // Show text
     }
     
     text_operations.push(Operation::new("ET", vec![])); // End text
+    fonts.add_to_page(doc, page_id)?;
     
     let content = Content { operations: text_operations };
     let content_data = content.encode()?;
@@ -1887,21 +1925,25 @@ fn render_signature_id_info(
             // Add to page's content array
             if let Ok(contents_obj) = page_dict.get_mut(b"Contents") {
                 match contents_obj {
-                    Object::Reference(_ref_id) => {
-                        // For simplicity, replace the content reference with our new stream
-                        *contents_obj = Object::Reference(stream_id);
+                    Object::Reference(ref_id) => {
+                        // Keep the existing page content and draw on top of it
+                        let old_ref = *ref_id;
+                        *contents_obj = Object::Array(vec![
+                            Object::Reference(old_ref),
+                            Object::Reference(stream_id),
+                        ]);
                     },
                     Object::Array(ref mut contents_array) => {
                         contents_array.push(Object::Reference(stream_id));
                     },
                     _ => {
                         // Replace with new content stream
-                        *contents_obj = Object::Reference(stream_id);
+                        *contents_obj = Object::Array(vec![Object::Reference(stream_id)]);
                     }
                 }
             } else {
                 // Add new Contents array
-                page_dict.set(b"Contents", Object::Reference(stream_id));
+                page_dict.set(b"Contents", Object::Array(vec![Object::Reference(stream_id)]));
             }
         }
     }
@@ -1912,6 +1954,7 @@ fn render_signature_id_info(
 // Render text field (default)
 fn render_text_field(
     doc: &mut lopdf::Document,
+    fonts: &mut UnicodeFonts,
     page_id: lopdf::ObjectId,
     text: &str,
     x_pos: f64,
@@ -1926,50 +1969,13 @@ fn render_text_field(
     let font_size = 12.0; // Fixed size to match frontend
 
     // Truncate text if too long (matching frontend behavior)
-    let display_text = if text.len() > 10 { format!("{}...", &text[..10]) } else { text.to_string() };
-
-    // Create Arial font if not exists
-    let font_name = b"F1".to_vec();
-    let font_dict_id = {
-        let mut arial_dict = Dictionary::new();
-        arial_dict.set("Type", Object::Name(b"Font".to_vec()));
-        arial_dict.set("Subtype", Object::Name(b"Type1".to_vec()));
-        arial_dict.set("BaseFont", Object::Name(b"Arial".to_vec()));
-        arial_dict.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
-        doc.add_object(Object::Dictionary(arial_dict))
+    let display_text = if text.chars().count() > 10 {
+        format!("{}...", text.chars().take(10).collect::<String>())
+    } else {
+        text.to_string()
     };
-
-    // Add font to page resources
-    {
-        let page_obj = doc.get_object_mut(page_id)?;
-        let page_dict = page_obj.as_dict_mut()?;
-
-        // Get or create Resources
-        if !page_dict.has(b"Resources") {
-            page_dict.set("Resources", Object::Dictionary(Dictionary::new()));
-        }
-    }
-
-    // Update the resources (separate borrow)
-    {
-        let page_obj = doc.get_object_mut(page_id)?;
-        let page_dict = page_obj.as_dict_mut()?;
-
-        if let Ok(resources_obj) = page_dict.get_mut(b"Resources") {
-            if let Ok(resources) = resources_obj.as_dict_mut() {
-                // Get or create Font dictionary
-                if !resources.has(b"Font") {
-                    let mut font_dict = Dictionary::new();
-                    font_dict.set(font_name.clone(), Object::Reference(font_dict_id));
-                    resources.set("Font", Object::Dictionary(font_dict));
-                } else if let Ok(font_obj) = resources.get_mut(b"Font") {
-                    if let Ok(fonts) = font_obj.as_dict_mut() {
-                        fonts.set(font_name.clone(), Object::Reference(font_dict_id));
-                    }
-                }
-            }
-        }
-    }
+    let line = fonts.layout(doc, &display_text);
+    fonts.add_to_page(doc, page_id)?;
 
     // Center text vertically and horizontally (matching frontend behavior)
     // Frontend: ctx.fillText(data || '', width / 2, (height - textHeight) / 2 + 5);
@@ -1977,19 +1983,13 @@ fn render_text_field(
     let text_y = pdf_y + field_height / 2.0 + 5.0;
 
     // Center horizontally
-    let text_x = x_pos + field_width / 2.0;
+    let text_x = x_pos + (field_width - line.width(font_size)) / 2.0;
 
     // Create text content stream
-    let operations = vec![
+    let mut operations = vec![
         // Begin text object
         Operation::new("BT", vec![]),
 
-        // Set font and size
-        Operation::new("Tf", vec![
-            Object::Name(font_name),
-            Object::Real(font_size as f32),
-        ]),
-
         // Set text color to black
         Operation::new("rg", vec![
             Object::Real(0.0),
@@ -2002,15 +2002,13 @@ fn render_text_field(
             Object::Real(text_x as f32),
             Object::Real(text_y as f32),
         ]),
+    ];
 
-        // Show text
-        Operation::new("Tj", vec![
-            Object::string_literal(display_text),
-        ]),
+    // Show text
+    operations.extend(line.operations(font_size));
 
-        // End text object
-        Operation::new("ET", vec![]),
-    ];
+    // End text object
+    operations.push(Operation::new("ET", vec![]));
 
     let content = Content { operations };
     let content_data = content.encode()?;
@@ -2173,6 +2171,7 @@ fn render_empty_checkbox(
 // Render cells field (grid layout)
 fn render_cells_field(
     doc: &mut lopdf::Document,
+    fonts: &mut UnicodeFonts,
     page_id: lopdf::ObjectId,
     text: &str,
     x_pos: f64,
@@ -2187,49 +2186,6 @@ fn render_cells_field(
     let cell_width = field_width / chars.len() as f64;
     let font_size = (field_height * 0.8).min(cell_width * 0.8);
 
-    // Create Arial font if not exists
-    let font_name = b"F1".to_vec();
-    let font_dict_id = {
-        let mut arial_dict = Dictionary::new();
-        arial_dict.set("Type", Object::Name(b"Font".to_vec()));
-        arial_dict.set("Subtype", Object::Name(b"Type1".to_vec()));
-        arial_dict.set("BaseFont", Object::Name(b"Arial".to_vec()));
-        arial_dict.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
-        doc.add_object(Object::Dictionary(arial_dict))
-    };
-
-    // Add font to page resources
-    {
-        let page_obj = doc.get_object_mut(page_id)?;
-        let page_dict = page_obj.as_dict_mut()?;
-
-        // Get or create Resources
-        if !page_dict.has(b"Resources") {
-            page_dict.set("Resources", Object::Dictionary(Dictionary::new()));
-        }
-    }
-
-    // Update the resources (separate borrow)
-    {
-        let page_obj = doc.get_object_mut(page_id)?;
-        let page_dict = page_obj.as_dict_mut()?;
-
-        if let Ok(resources_obj) = page_dict.get_mut(b"Resources") {
-            if let Ok(resources) = resources_obj.as_dict_mut() {
-                // Get or create Font dictionary
-                if !resources.has(b"Font") {
-                    let mut font_dict = Dictionary::new();
-                    font_dict.set(font_name.clone(), Object::Reference(font_dict_id));
-                    resources.set("Font", Object::Dictionary(font_dict));
-                } else if let Ok(font_obj) = resources.get_mut(b"Font") {
-                    if let Ok(fonts) = font_obj.as_dict_mut() {
-                        fonts.set(font_name.clone(), Object::Reference(font_dict_id));
-                    }
-                }
-            }
-        }
-    }
-
     let mut operations = Vec::new();
 
     // Draw grid lines
@@ -2255,24 +2211,26 @@ fn render_cells_field(
 
     // Draw characters
     operations.push(Operation::new("BT", vec![]));
-    operations.push(Operation::new("Tf", vec![
-        Object::Name(font_name),
-        Object::Real(font_size as f32),
-    ]));
     operations.push(Operation::new("rg", vec![Object::Real(0.0), Object::Real(0.0), Object::Real(0.0)]));
 
     for (i, ch) in chars.iter().enumerate() {
         let cell_x = x_pos + i as f64 * cell_width + cell_width * 0.1;
         let baseline_y = pdf_y + field_height * 0.8;
 
-        operations.push(Operation::new("Td", vec![
+        // Absolute position per cell (Td would be relative to the previous cell)
+        operations.push(Operation::new("Tm", vec![
+            Object::Real(1.0),
+            Object::Real(0.0),
+            Object::Real(0.0),
+            Object::Real(1.0),
             Object::Real(cell_x as f32),
             Object::Real(baseline_y as f32),
         ]));
-        operations.push(Operation::new("Tj", vec![Object::string_literal(ch.to_string())]));
+        operations.extend(fonts.layout(doc, &ch.to_string()).operations(font_size));
     }
 
     operations.push(Operation::new("ET", vec![]));
+    fonts.add_to_page(doc, page_id)?;
 
     let content = Content { operations };
     let content_data = content.encode()?;
@@ -2317,11 +2275,12 @@ fn render_cells_field(
 // Render initials field with special positioning
 fn render_initials_field(
     doc: &mut lopdf::Document,
+    fonts: &mut UnicodeFonts,
     page_id: lopdf::ObjectId,
     text: &str,
     x_pos: f64,
     pdf_y: f64,
-    _field_width: f64,
+    field_width: f64,
     field_height: f64,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
     use lopdf::{Object, Stream, Dictionary};
@@ -2330,67 +2289,8 @@ fn render_initials_field(
     // Special font size for initials (smaller, more condensed)
     let font_size = (field_height * 0.6).max(10.0).min(18.0);
 
-    // Helper: approximate text width (average width per character groups)
-    let approx_text_width = |text: &str, font_size_pt: f64| -> f64 {
-        // Rough per-character width factors for Latin alphabet (approximation)
-        // narrow: i, l; mid: a-z; wide: m, w, W, M; space smaller
-        let mut width = 0.0_f64;
-        for ch in text.chars() {
-            let factor = match ch {
-                'i' | 'I' | 'l' | 'j' | 't' | '\'' | '|' => 0.28,
-                ' ' => 0.28,
-                'f' | 'r' | 's' | ',' | ':' | ';' | '.' => 0.35,
-                'a'..='z' | 'A'..='Z' | '0'..='9' => 0.55,
-                'm' | 'w' | 'M' | 'W' => 0.85,
-                _ => 0.6,
-            };
-            width += factor * font_size_pt;
-        }
-        width
-    };
-
-    // Create Arial font if not exists
-    let font_name = b"F1".to_vec();
-    let font_dict_id = {
-        let mut arial_dict = Dictionary::new();
-        arial_dict.set("Type", Object::Name(b"Font".to_vec()));
-        arial_dict.set("Subtype", Object::Name(b"Type1".to_vec()));
-        arial_dict.set("BaseFont", Object::Name(b"Arial".to_vec()));
-        arial_dict.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
-        doc.add_object(Object::Dictionary(arial_dict))
-    };
-
-    // Add font to page resources
-    {
-        let page_obj = doc.get_object_mut(page_id)?;
-        let page_dict = page_obj.as_dict_mut()?;
-
-        // Get or create Resources
-        if !page_dict.has(b"Resources") {
-            page_dict.set("Resources", Object::Dictionary(Dictionary::new()));
-        }
-    }
-
-    // Update the resources (separate borrow)
-    {
-        let page_obj = doc.get_object_mut(page_id)?;
-        let page_dict = page_obj.as_dict_mut()?;
-
-        if let Ok(resources_obj) = page_dict.get_mut(b"Resources") {
-            if let Ok(resources) = resources_obj.as_dict_mut() {
-                // Get or create Font dictionary
-                if !resources.has(b"Font") {
-                    let mut font_dict = Dictionary::new();
-                    font_dict.set(font_name.clone(), Object::Reference(font_dict_id));
-                    resources.set("Font", Object::Dictionary(font_dict));
-                } else if let Ok(font_obj) = resources.get_mut(b"Font") {
-                    if let Ok(fonts) = font_obj.as_dict_mut() {
-                        fonts.set(font_name.clone(), Object::Reference(font_dict_id));
-                    }
-                }
-            }
-        }
-    }
+    let line = fonts.layout(doc, text);
+    fonts.add_to_page(doc, page_id)?;
 
     // Calculate positioning for initials (matching frontend centering logic)
     // Center in available space, similar to frontend
@@ -2403,12 +2303,6 @@ fn render_initials_field(
         // Begin text object
         Operation::new("BT", vec![]),
 
-        // Set font and size (bold for initials)
-        Operation::new("Tf", vec![
-            Object::Name(font_name),
-            Object::Real(font_size as f32),
-        ]),
-
         // Set text color to black
         Operation::new("rg", vec![
             Object::Real(0.0),
@@ -2418,18 +2312,16 @@ fn render_initials_field(
 
         // Position text at baseline (centered horizontally)
         Operation::new("Td", vec![
-            Object::Real((x_pos + _field_width / 2.0 - approx_text_width(text, font_size) / 2.0) as f32),
+            Object::Real((x_pos + field_width / 2.0 - line.width(font_size) / 2.0) as f32),
             Object::Real(baseline_y as f32),
         ]),
+    ];
 
-        // Show text
-        Operation::new("Tj", vec![
-            Object::string_literal(text.to_string()),
-        ]),
+    // Show text
+    text_operations.extend(line.operations(font_size));
 
-        // End text object
-        Operation::new("ET", vec![]),
-    ];
+    // End text object
+    text_operations.push(Operation::new("ET", vec![]));
 
     let content = Content { operations: text_operations };
     let content_data = content.encode()?;
@@ -2614,64 +2506,6 @@ fn render_vector_signature(
     Ok(())
 }
 
-/// Merge multiple PDFs into one
-fn merge_pdfs(pdf_bytes_list: Vec<Vec<u8>>) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    use lopdf::{Document, Object, Dictionary};
-    
-    if pdf_bytes_list.is_empty() {
-        return Err("No PDFs to merge".into());
-    }
-    
-    if pdf_bytes_list.len() == 1 {
-        return Ok(pdf_bytes_list[0].clone());
-    }
-    
-    // Load all documents
-    let mut documents: Vec<Document> = Vec::new();
-    for pdf_bytes in &pdf_bytes_list {
-        documents.push(Document::load_mem(pdf_bytes)?);
-    }
-    
-    // Create a new merged document by combining pages manually
-    let mut merged_doc = Document::with_version("1.5");
-    let mut max_id = 1;
-    
-    for doc in &documents {
-        // Get all pages from this document
-        let pages: Vec<_> = doc.get_pages().into_iter().collect();
-        
-        for (_page_num, page_id) in pages {
-            // Get the page object
-            if let Ok(page_obj) = doc.get_object(page_id) {
-                if let Ok(_page_dict) = page_obj.as_dict() {
-                    // Clone the page and all its resources
-                    // Simple approach: add all objects from source doc
-                    for (obj_id, obj) in doc.objects.iter() {
-                        if obj_id.0 > max_id {
-                            max_id = obj_id.0;
-                        }
-                        merged_doc.objects.insert((obj_id.0 + max_id, obj_id.1), obj.clone());
-                    }
-                    max_id += max_id;
-                }
-            }
-        }
-    }
-    
-    // Rebuild page tree
-    merged_doc.renumber_objects();
-    merged_doc.compress();
-    
-    // Alternative simpler approach: concatenate pages
-    // Since lopdf doesn't have add_page_from, we'll use a simpler concatenation
-    // For now, return the first document with signatures as fallback
-    // In production, you might want to use a different library or implement proper merging
-    
-    // Simple fallback: return all PDFs concatenated (not truly merged)
-    // For a production solution, consider using pdfium-render or pdf_writer
-    Ok(pdf_bytes_list.concat())
-}
-
 
 #[utoipa::path(
     put,
@@ -2819,17 +2653,14 @@ pub async fn send_copy_email(
                             let body = replace_template_variables(&email_template.body, &body_variables);
 
                             // Generate attachments if needed
-                            let mut document_path = None;
+                            let mut documents = Vec::new();
                             let mut audit_log_path = None;
 
                             if email_template.attach_documents {
-                                // Generate signed PDF (only include signatures from this submitter for their email)
+                                // Generate signed PDFs (only include signatures from this submitter for their email)
                                 if let Ok(storage_service) = StorageService::new().await {
-                                    if let Ok(signed_pdf_bytes) = generate_signed_pdf_for_template_with_filter(pool, db_submitter.template_id, &storage_service, Some(db_submitter.id)).await {
-                                        let temp_file = std::env::temp_dir().join(format!("signed_document_{}.pdf", db_submitter.id));
-                                        if let Ok(_) = tokio::fs::write(&temp_file, signed_pdf_bytes).await {
-                                            document_path = Some(temp_file.to_string_lossy().to_string());
-                                        }
+                                    if let Ok(signed_documents) = generate_signed_documents_for_template_with_filter(pool, db_submitter.template_id, &storage_service, Some(db_submitter.id)).await {
+                                        documents = template_documents::write_attachments(&format!("signed_document_{}", db_submitter.id), &signed_documents).await;
                                     }
                                 }
                             }
@@ -2852,14 +2683,12 @@ pub async fn send_copy_email(
                                 &email_template.body_format,
                                 email_template.attach_documents,
                                 email_template.attach_audit_log,
-                                document_path.as_deref(),
+                                &documents,
                                 audit_log_path.as_deref(),
                             ).await {
                                 Ok(_) => {
                                     // Clean up temporary files
-                                    if let Some(path) = document_path {
-                                        let _ = tokio::fs::remove_file(path).await;
-                                    }
+                                    template_documents::remove_attachments(&documents).await;
                                     if let Some(path) = audit_log_path {
                                         let _ = tokio::fs::remove_file(path).await;
                                     }
@@ -3020,51 +2849,50 @@ pub async fn get_submitter_audit_log(
     }
 }
 
-async fn generate_signed_pdf_for_template(
-    pool: &PgPool,
-    template_id: i64,
-    storage_service: &StorageService,
-) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
-    generate_signed_pdf_for_template_with_filter(pool, template_id, storage_service, None).await
-}
-
-// Generate signed PDF with optional submitter filter
+// Generate the signed PDFs with optional submitter filter
 // If submitter_id is Some, only include signatures from that submitter
 // If submitter_id is None, include all signatures from all submitters
-async fn generate_signed_pdf_for_template_with_filter(
+// Multi-document templates produce one merged PDF, or one PDF per document when the template's
+// separate_documents setting is on
+async fn generate_signed_documents_for_template_with_filter(
     pool: &PgPool,
     template_id: i64,
     storage_service: &StorageService,
     submitter_id: Option<i64>,
-) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+) -> Result<Vec<DocumentFile>, Box<dyn std::error::Error + Send + Sync + 'static>> {
     // Get template
     let template = TemplateQueries::get_template_by_id(pool, template_id).await?
         .ok_or("Template not found")?;
 
-    // Get template PDF bytes
-    let pdf_bytes = if let Some(documents) = &template.documents {
-        if let Ok(docs) = serde_json::from_value::<Vec<crate::models::template::Document>>(documents.clone()) {
-            if let Some(first_doc) = docs.first() {
-                // Use the URL as key for storage download
-                storage_service.download_file(&first_doc.url).await?
-            } else {
-                return Err("No documents found in template".into());
-            }
-        } else {
-            return Err("Invalid documents format".into());
-        }
-    } else {
-        return Err("Template has no documents".into());
-    };
-
     // Get all submitters for this template
     let submitters = SubmitterQueries::get_submitters_by_template_id(pool, template_id).await?;
 
-    // Get template fields for position information
-    let template_fields = TemplateFieldQueries::get_template_fields(pool, template_id).await?;
+    // Get template PDF bytes, per document. Submissions with personalized documents sign those.
+    let included: Vec<i64> = submitters.iter()
+        .map(|s| s.id)
+        .filter(|id| submitter_id.map_or(true, |filter_id| *id == filter_id))
+        .collect();
+    let document_list = template_documents::submitter_documents(pool, &template, &included).await?;
+    let documents = template_documents::download_document_list(storage_service, document_list).await?;
+    let document_list: Vec<_> = documents.iter().map(|(document, _)| document.clone()).collect();
+
+    // Signature IDs are stored when a submitter signs; this registers those of submitters who
+    // signed before that
+    for submitter in submitters.iter().filter(|s| s.bulk_signatures.is_some()) {
+        if let Err(e) = SubmitterQueries::set_signature_id(pool, submitter.id, &token::signature_id(submitter.id)).await {
+            eprintln!("Failed to store signature ID for submitter {}: {}", submitter.id, e);
+        }
+    }
+
+    // Get template fields for position information: as they were in the version each submitter
+    // was sent with, otherwise the template's current ones
+    let current_fields: Vec<TemplateVersionField> = TemplateFieldQueries::get_template_fields(pool, template_id).await?
+        .iter()
+        .map(TemplateVersionField::from)
+        .collect();
 
-    // Collect all signatures with position information
-    let mut all_signatures = Vec::new();
+    // Collect all signatures with position information, per document
+    let mut signatures_by_document = vec![Vec::new(); documents.len()];
     for submitter in &submitters {
         // Filter by submitter_id if provided
         if let Some(filter_id) = submitter_id {
@@ -3073,6 +2901,12 @@ async fn generate_signed_pdf_for_template_with_filter(
             }
         }
         
+        let version_fields = match TemplateVersionQueries::get_submitter_template_version(pool, submitter.id).await? {
+            Some(version) => Some(template_versions::version_fields(&version)),
+            None => None,
+        };
+        let template_fields = version_fields.as_ref().unwrap_or(&current_fields);
+
         if let Some(bulk_signatures) = &submitter.bulk_signatures {
             if let Ok(signatures) = serde_json::from_value::<Vec<serde_json::Value>>(bulk_signatures.clone()) {
                 for sig in signatures {
@@ -3085,6 +2919,14 @@ async fn generate_signed_pdf_for_template_with_filter(
                             // Parse position from JSON
                             if let Some(position_json) = &template_field.position {
                                 if let Ok(position) = serde_json::from_value::<crate::models::template::FieldPosition>(position_json.clone()) {
+                                    let document_index = match template_documents::document_index(&document_list, position.document_id.as_deref()) {
+                                        Some(index) => index,
+                                        None => {
+                                            eprintln!("Warning: field {} is on unknown document {:?}", field_name, position.document_id);
+                                            continue;
+                                        }
+                                    };
+
                                     // Use absolute coordinates from bulk_signatures if available, otherwise use template position
                                     let (final_x, final_y, final_w, final_h) = if let (Some(abs_x), Some(abs_y), Some(abs_w), Some(abs_h)) = (
                                         sig.get("abs_x").and_then(|v| v.as_f64()),
@@ -3115,7 +2957,7 @@ async fn generate_signed_pdf_for_template_with_filter(
                                         (norm_x, norm_y, norm_w, norm_h)
                                     };
                                     
-                                    all_signatures.push((
+                                    signatures_by_document[document_index].push((
                                         field_name.to_string(),
                                         template_field.field_type.clone(),
                                         signature_value.to_string(),
@@ -3160,22 +3002,238 @@ async fn generate_signed_pdf_for_template_with_filter(
             completion_body: None,
             redirect_title: None,
             redirect_url: None,
+            pdf_render_mode: "stamp".to_string(),
+            flatten_form_fields: false,
+            pdfa_output: false,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         });
 
+    // Template settings override the account-wide render mode
+    let template_settings: crate::models::template::TemplateSettings = template.settings.clone()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let render_mode = template_settings.pdf_render_mode.clone()
+        .unwrap_or_else(|| user_settings.pdf_render_mode.clone());
+    let flatten_form_fields = template_settings.flatten_form_fields
+        .unwrap_or(user_settings.flatten_form_fields);
+    let pdfa_output = template_settings.pdfa_output
+        .unwrap_or(user_settings.pdfa_output);
+
     // Create a dummy submitter for rendering (we need this for the function signature)
     let dummy_submitter = submitters.first().ok_or("No submitters found")?;
 
-    // Render signatures on PDF
-    let signed_pdf = render_signatures_on_pdf(
-        &pdf_bytes,
-        &all_signatures,
-        &user_settings,
-        dummy_submitter,
-    )?;
+    // Fill and stamp each document
+    let mut rendered = Vec::new();
+    for ((document, pdf_bytes), all_signatures) in documents.into_iter().zip(signatures_by_document) {
+        // In AcroForm mode write values into the PDF's own form fields first, anything that
+        // doesn't match a form field is stamped as before
+        let (pdf_bytes, all_signatures) = if render_mode == acroform::RENDER_MODE_ACROFORM {
+            let values: Vec<acroform::FormFieldValue> = all_signatures.iter()
+                .map(|(name, field_type, value, ..)| acroform::FormFieldValue { name, field_type, value })
+                .collect();
+            match acroform::fill_form_fields(&pdf_bytes, &values, flatten_form_fields) {
+                Ok((filled_pdf, filled)) => {
+                    let remaining = all_signatures.into_iter()
+                        .filter(|(name, ..)| !filled.contains(name))
+                        .collect::<Vec<_>>();
+                    (filled_pdf, remaining)
+                }
+                Err(e) => {
+                    eprintln!("Failed to fill AcroForm fields, falling back to stamping: {}", e);
+                    (pdf_bytes, all_signatures)
+                }
+            }
+        } else {
+            (pdf_bytes, all_signatures)
+        };
+
+        // Render signatures on PDF
+        let bytes = render_signatures_on_pdf(
+            &pdf_bytes,
+            &all_signatures,
+            &user_settings,
+            dummy_submitter,
+        )?;
+        rendered.push(DocumentFile { filename: pdf_filename(&document.filename), bytes });
+    }
+
+    // One merged PDF unless the template asks for a PDF per document
+    let outputs = if rendered.len() > 1 && !template_settings.separate_documents.unwrap_or(false) {
+        let pdfs: Vec<Vec<u8>> = rendered.into_iter().map(|document| document.bytes).collect();
+        vec![DocumentFile { filename: format!("{}.pdf", template.name), bytes: pdf_merge::merge_pdfs(&pdfs)? }]
+    } else {
+        rendered
+    };
+
+    let mut signed_documents = Vec::new();
+    for output in outputs {
+        let bytes = finalize_signed_document(pool, &template, submitter_id, &output.filename, pdfa_output, output.bytes).await;
+        signed_documents.push(DocumentFile { filename: output.filename, bytes });
+    }
+    Ok(signed_documents)
+}
+
+// PDF/A conversion, PAdES signature or document timestamp, and hash registration of a completed PDF
+async fn finalize_signed_document(
+    pool: &PgPool,
+    template: &crate::database::models::DbTemplate,
+    submitter_id: Option<i64>,
+    file_name: &str,
+    pdfa_output: bool,
+    signed_pdf: Vec<u8>,
+) -> Vec<u8> {
+    let template_id = template.id;
+
+    // PDF/A conversion has to happen before signing so the signature covers the final file
+    let signed_pdf = if pdfa_output {
+        convert_to_pdfa_or_keep(signed_pdf, &template.name)
+    } else {
+        signed_pdf
+    };
+
+    // Apply a PAdES digital signature when a signing certificate is configured for the account,
+    // timestamped by the configured TSA. Without a certificate the TSA alone timestamps the document.
+    let tsa = configured_timestamp_authority();
+    let signed_pdf = match pdf_signing::SigningCredentials::load_for_account(template.account_id) {
+        Ok(Some(credentials)) => {
+            let options = pdf_signing::SignatureOptions {
+                appearance: pdf_signing::SignatureAppearance::from_env(),
+                reason: Some(format!("Completed document: {}", template.name)),
+                location: None,
+                contact_info: None,
+                pdfa: pdfa_output,
+            };
+            match pdf_signing::sign_pdf(&signed_pdf, &credentials, &options, tsa.as_deref()).await {
+                Ok(digitally_signed) => {
+                    if let Some(token) = &digitally_signed.timestamp {
+                        record_document_timestamp(pool, template_id, submitter_id, "signed_document", token).await;
+                    }
+                    digitally_signed.bytes
+                }
+                Err(e) => {
+                    eprintln!("Failed to digitally sign PDF for template {}: {}", template_id, e);
+                    signed_pdf
+                }
+            }
+        }
+        Ok(None) => match &tsa {
+            Some(tsa) => apply_document_timestamp(pool, template_id, submitter_id, "signed_document", signed_pdf, tsa.as_ref()).await,
+            None => signed_pdf,
+        },
+        Err(e) => {
+            eprintln!("Failed to load signing certificate for template {}: {}", template_id, e);
+            signed_pdf
+        }
+    };
+
+    record_document_hash(pool, template_id, submitter_id, "signed_document", file_name, &signed_pdf).await;
+
+    signed_pdf
+}
+
+// Attachment name for a completed document: the source file name with a .pdf extension
+fn pdf_filename(name: &str) -> String {
+    let stem = match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem,
+        _ => name,
+    };
+    format!("{}.pdf", stem)
+}
+
+// Convert to PDF/A-2b, logging what couldn't be made conformant.
+// The original document is kept if the conversion fails.
+fn convert_to_pdfa_or_keep(pdf_bytes: Vec<u8>, title: &str) -> Vec<u8> {
+    match pdfa::convert_to_pdfa(&pdf_bytes, title) {
+        Ok((converted, issues)) => {
+            for issue in &issues {
+                eprintln!("PDF/A conformance issue in '{}': {}", title, issue);
+            }
+            converted
+        }
+        Err(e) => {
+            eprintln!("Failed to convert '{}' to PDF/A: {}", title, e);
+            pdf_bytes
+        }
+    }
+}
+
+fn configured_timestamp_authority() -> Option<Box<dyn TimestampAuthority>> {
+    match timestamp::timestamp_authority_from_env() {
+        Ok(tsa) => tsa,
+        Err(e) => {
+            eprintln!("Failed to configure timestamp authority: {}", e);
+            None
+        }
+    }
+}
+
+// Apply a document timestamp, returning the PDF unchanged if the TSA fails
+async fn apply_document_timestamp(
+    pool: &PgPool,
+    template_id: i64,
+    submitter_id: Option<i64>,
+    document_type: &str,
+    pdf_bytes: Vec<u8>,
+    tsa: &dyn TimestampAuthority,
+) -> Vec<u8> {
+    match pdf_signing::timestamp_pdf(&pdf_bytes, tsa).await {
+        Ok(timestamped) => {
+            if let Some(token) = &timestamped.timestamp {
+                record_document_timestamp(pool, template_id, submitter_id, document_type, token).await;
+            }
+            timestamped.bytes
+        }
+        Err(e) => {
+            eprintln!("Failed to timestamp {} for template {}: {}", document_type, template_id, e);
+            pdf_bytes
+        }
+    }
+}
+
+// Record the SHA-256 of an issued document for public verification. Each copy is signed and
+// timestamped when it's generated, so every copy sent or downloaded gets its own record.
+async fn record_document_hash(
+    pool: &PgPool,
+    template_id: i64,
+    submitter_id: Option<i64>,
+    document_type: &str,
+    file_name: &str,
+    pdf_bytes: &[u8],
+) {
+    let record = CreateDocumentHash {
+        template_id,
+        submitter_id,
+        document_type: document_type.to_string(),
+        file_name: file_name.to_string(),
+        sha256: crate::routes::verification::document_sha256(pdf_bytes),
+        size_bytes: pdf_bytes.len() as i64,
+    };
+    if let Err(e) = DocumentHashQueries::create_document_hash(pool, record).await {
+        eprintln!("Failed to record document hash for template {}: {}", template_id, e);
+    }
+}
 
-    Ok(signed_pdf)
+async fn record_document_timestamp(
+    pool: &PgPool,
+    template_id: i64,
+    submitter_id: Option<i64>,
+    document_type: &str,
+    token: &TimestampToken,
+) {
+    let record = CreateDocumentTimestamp {
+        template_id,
+        submitter_id,
+        document_type: document_type.to_string(),
+        tsa_name: token.tsa_name.clone(),
+        serial_number: token.serial_number.clone(),
+        message_imprint: hex::encode(&token.message_imprint),
+        gen_time: token.gen_time,
+        token: token.token.clone(),
+    };
+    if let Err(e) = DocumentTimestampQueries::create_document_timestamp(pool, record).await {
+        eprintln!("Failed to record timestamp token for template {}: {}", template_id, e);
+    }
 }
 
 async fn generate_template_audit_log_pdf(
@@ -3333,6 +3391,22 @@ async fn generate_template_audit_log_pdf(
         }));
     }
 
+    // 6. Trusted timestamps applied to the completed documents
+    let document_timestamps = DocumentTimestampQueries::get_document_timestamps_by_template(pool, template_id).await
+        .unwrap_or_default();
+    for document_timestamp in document_timestamps.iter().filter(|t| t.document_type == "signed_document") {
+        audit_entries.push(serde_json::json!({
+            "timestamp": document_timestamp.gen_time.format("%d/%m/%Y %H:%M:%S").to_string(),
+            "action": "Document Timestamped",
+            "user": document_timestamp.tsa_name.clone(),
+            "details": format!("RFC 3161 timestamp, serial {}, SHA-256 {}", document_timestamp.serial_number, document_timestamp.message_imprint),
+            "ip": "System",
+            "user_agent": "System",
+            "session_id": "N/A",
+            "timezone": "UTC"
+        }));
+    }
+
     // Sort audit entries by timestamp
     audit_entries.sort_by(|a, b| {
         let a_timestamp = a.get("timestamp").and_then(|v| v.as_str()).unwrap_or("");
@@ -3347,20 +3421,11 @@ async fn generate_template_audit_log_pdf(
     let mut doc = Document::new();
     let pages_id = doc.new_object_id();
 
-    // Create Arial font
-    let font_dict_id = {
-        let mut arial_dict = Dictionary::new();
-        arial_dict.set("Type", Object::Name(b"Font".to_vec()));
-        arial_dict.set("Subtype", Object::Name(b"Type1".to_vec()));
-        arial_dict.set("BaseFont", Object::Name(b"Arial".to_vec()));
-        arial_dict.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
-        doc.add_object(Object::Dictionary(arial_dict))
-    };
+    // Text is shown with embedded Unicode fonts (names, details in any script)
+    let mut fonts = UnicodeFonts::new();
 
     let resources_id = doc.add_object(Object::Dictionary(Dictionary::from_iter(vec![
-        ("Font", Object::Dictionary(Dictionary::from_iter(vec![
-            ("F1", Object::Reference(font_dict_id)),
-        ]))),
+        ("Font", Object::Dictionary(Dictionary::new())),
     ])));
 
     // Create content with audit log text
@@ -3369,7 +3434,7 @@ async fn generate_template_audit_log_pdf(
     // Begin text object
     content.operations.push(Operation::new("BT", vec![]));
 
-    // Set font and size
+    // Set font size
     content.operations.push(Operation::new("Tf", vec![
         Object::Name(b"F1".to_vec()),
         Object::Real(10.0),
@@ -3533,8 +3598,8 @@ async fn generate_template_audit_log_pdf(
             for (i, sig_value) in signature_values.iter().enumerate() {
                 if let Some(sig_str) = sig_value.as_str() {
                     if sig_str != "N/A" {
-                        let truncated_value = if sig_str.len() > 100 {
-                            format!("{}... [TRUNCATED]", &sig_str[..100])
+                        let truncated_value = if sig_str.chars().count() > 100 {
+                            format!("{}... [TRUNCATED]", sig_str.chars().take(100).collect::<String>())
                         } else {
                             sig_str.to_string()
                         };
@@ -3579,6 +3644,7 @@ async fn generate_template_audit_log_pdf(
 
     // End text object
     content.operations.push(Operation::new("ET", vec![]));
+    content.operations = fonts.encode_operations(&mut doc, content.operations);
 
     let content_id = doc.add_object(Object::Stream(Stream::new(Dictionary::new(), content.encode()?)));
 
@@ -3602,6 +3668,8 @@ async fn generate_template_audit_log_pdf(
     ]));
 
     doc.objects.insert(pages_id, pages);
+    fonts.add_to_page(&mut doc, page_id)?;
+    fonts.finish(&mut doc)?;
 
     let catalog_id = doc.add_object(Object::Dictionary(Dictionary::from_iter(vec![
         ("Type", Object::Name("Catalog".into())),
@@ -3614,6 +3682,32 @@ async fn generate_template_audit_log_pdf(
     let mut buffer = Vec::new();
     doc.save_to(&mut buffer)?;
 
+    // Same archival format as the completed document
+    let template_settings: crate::models::template::TemplateSettings = template.settings.clone()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let pdfa_output = match template_settings.pdfa_output {
+        Some(enabled) => enabled,
+        None => GlobalSettingsQueries::get_user_settings(pool, template.user_id as i32).await
+            .ok()
+            .flatten()
+            .map(|settings| settings.pdfa_output)
+            .unwrap_or(false),
+    };
+    let buffer = if pdfa_output {
+        convert_to_pdfa_or_keep(buffer, &format!("{} - Audit Log", template.name))
+    } else {
+        buffer
+    };
+
+    // Timestamp the audit trail itself
+    let buffer = match configured_timestamp_authority() {
+        Some(tsa) => apply_document_timestamp(pool, template_id, None, "audit_log", buffer, tsa.as_ref()).await,
+        None => buffer,
+    };
+
+    record_document_hash(pool, template_id, None, "audit_log", "", &buffer).await;
+
     Ok(buffer)
 }
 
@@ -3730,20 +3824,11 @@ async fn generate_audit_log_pdf(
     let mut doc = Document::new();
     let pages_id = doc.new_object_id();
 
-    // Create Arial font
-    let font_dict_id = {
-        let mut arial_dict = Dictionary::new();
-        arial_dict.set("Type", Object::Name(b"Font".to_vec()));
-        arial_dict.set("Subtype", Object::Name(b"Type1".to_vec()));
-        arial_dict.set("BaseFont", Object::Name(b"Arial".to_vec()));
-        arial_dict.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
-        doc.add_object(Object::Dictionary(arial_dict))
-    };
+    // Text is shown with embedded Unicode fonts (names, details in any script)
+    let mut fonts = UnicodeFonts::new();
 
     let resources_id = doc.add_object(Object::Dictionary(Dictionary::from_iter(vec![
-        ("Font", Object::Dictionary(Dictionary::from_iter(vec![
-            ("F1", Object::Reference(font_dict_id)),
-        ]))),
+        ("Font", Object::Dictionary(Dictionary::new())),
     ])));
 
     // Create content with audit log text
@@ -3752,7 +3837,7 @@ async fn generate_audit_log_pdf(
     // Begin text object
     content.operations.push(Operation::new("BT", vec![]));
 
-    // Set font and size
+    // Set font size
     content.operations.push(Operation::new("Tf", vec![
         Object::Name(b"F1".to_vec()),
         Object::Real(10.0),
@@ -3866,6 +3951,7 @@ async fn generate_audit_log_pdf(
 
     // End text object
     content.operations.push(Operation::new("ET", vec![]));
+    content.operations = fonts.encode_operations(&mut doc, content.operations);
 
     let content_id = doc.add_object(Object::Stream(Stream::new(Dictionary::new(), content.encode()?)));
 
@@ -3889,6 +3975,8 @@ async fn generate_audit_log_pdf(
     ]));
 
     doc.objects.insert(pages_id, pages);
+    fonts.add_to_page(&mut doc, page_id)?;
+    fonts.finish(&mut doc)?;
 
     let catalog_id = doc.add_object(Object::Dictionary(Dictionary::from_iter(vec![
         ("Type", Object::Name("Catalog".into())),
@@ -57,6 +57,7 @@ fn get_content_type_from_filename(filename: &str) -> &'static str {
     }
 }
 
+use crate::common::pagination::{self, PageRequest, SortOrder};
 use crate::common::responses::ApiResponse;
 use crate::models::template::{
     Template, UpdateTemplateRequest, CloneTemplateRequest,
@@ -65,12 +66,28 @@ use crate::models::template::{
     CreateTemplateFieldRequest, UpdateTemplateFieldRequest,
     FileUploadResponse, CreateTemplateFromFileRequest, CreateTemplateRequest,
     TemplateFolder, CreateFolderRequest, UpdateFolderRequest,
-    CreateTemplateFromGoogleDriveRequest
+    FolderBreadcrumb, MoveFolderRequest, MoveTemplatesRequest,
+    CreateTemplateFromGoogleDriveRequest, ArrangeDocumentPagesRequest,
+    TemplateVersion, TemplateVersionDiff, TrashedTemplate, TemplateImportResult, UpdateTemplateTagsRequest
 };
+use crate::models::analytics::TemplateAnalytics;
+use crate::models::template_share::{AccessLevel, SharingSettings, TemplateShare, UpdateSharingRequest};
 use crate::database::connection::DbPool;
-use crate::database::models::{CreateTemplate, CreateTemplateField, CreateTemplateFolder};
-use crate::database::queries::{TemplateQueries, TemplateFolderQueries, TemplateFieldQueries};
+use crate::database::models::{CreateTemplate, CreateTemplateField, CreateTemplateFolder, FolderListFilter, TemplateFolderFilter, TemplateListFilter};
+use crate::database::queries::{SubmissionFieldQueries, SubmitterQueries, TemplateQueries, TemplateFolderQueries, TemplateFieldQueries, TemplateShareQueries};
 use crate::services::storage::StorageService;
+use crate::services::document_conversion;
+use crate::services::html_rendering;
+use crate::services::image_pdf;
+use crate::services::page_previews;
+use crate::services::pdf_merge;
+use crate::services::pdf_pages;
+use crate::services::template_documents;
+use crate::services::template_bundles;
+use crate::services::template_analytics;
+use crate::services::template_search;
+use crate::services::template_trash;
+use crate::services::template_versions;
 use crate::common::jwt::auth_middleware;
 
 use crate::routes::web::AppState;
@@ -99,19 +116,9 @@ pub async fn get_template_full_info(
     // Verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            // match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-            //     Ok(Some(user)) => {
-            //         // Allow access if user is the owner OR if user has Editor/Admin/Member role
-            //         let has_access = db_template.user_id == user_id || 
-            //                        matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-            //         if !has_access {
-            //             return ApiResponse::forbidden("Access denied".to_string());
-            //         }
-            //     }
-            //     _ => return ApiResponse::forbidden("User not found".to_string()),
-            // }
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::View).await {
+                return ApiResponse::forbidden("Access denied".to_string());
+            }
 
             // Convert template to API model with fields loaded
             let template = match convert_db_template_to_template_with_fields(db_template.clone(), pool).await {
@@ -357,6 +364,12 @@ pub fn create_template_router() -> Router<AppState> {
         .route("/folders/:id", put(update_folder))
         .route("/folders/:id", delete(delete_folder))
         .route("/folders/:id/templates", get(get_folder_templates))
+        .route("/folders/:id/export", get(export_folder))
+        .route("/folders/:id/move", put(move_folder))
+        .route("/folders/:id/breadcrumbs", get(get_folder_breadcrumbs))
+        .route("/folders/:id/sharing", get(get_folder_sharing))
+        .route("/folders/:id/sharing", put(update_folder_sharing))
+        .route("/templates/move", post(move_templates))
         .route("/templates/:template_id/move/:folder_id", put(move_template_to_folder))
         // Template routes
         .route("/templates", get(get_templates))
@@ -365,6 +378,16 @@ pub fn create_template_router() -> Router<AppState> {
         .route("/templates/:id/full-info", get(get_template_full_info))
         .route("/templates/:id", put(update_template))
         .route("/templates/:id", delete(delete_template))
+        .route("/templates/trash", get(get_trashed_templates))
+        .route("/templates/tags", get(get_template_tags))
+        .route("/templates/:id/tags", put(update_template_tags))
+        .route("/templates/:id/sharing", get(get_template_sharing))
+        .route("/templates/:id/sharing", put(update_template_sharing))
+        .route("/templates/:id/analytics", get(get_template_analytics))
+        .route("/templates/import", post(import_templates))
+        .route("/templates/:id/export", get(export_template))
+        .route("/templates/:id/restore", post(restore_template))
+        .route("/templates/:id/purge", delete(purge_template))
         .route("/templates/:id/clone", post(clone_template))
         .route("/templates/html", post(create_template_from_html))
         .route("/templates/pdf", post(create_template_from_pdf))
@@ -373,6 +396,16 @@ pub fn create_template_router() -> Router<AppState> {
         .route("/templates/google_drive_documents", post(create_template_from_google_drive))
         .route("/templates/merge", post(merge_templates))
         // Template Fields routes
+        .route("/templates/:id/documents", post(add_template_document))
+        .route("/templates/:id/documents/:document_id", put(replace_template_document))
+        .route("/templates/:id/documents/:document_id", delete(delete_template_document))
+        .route("/templates/:id/documents/:document_id/pages", put(arrange_template_document_pages))
+        .route("/templates/:id/documents/:document_id/pages", post(insert_template_document_pages))
+        .route("/templates/:id/documents/:document_id/preview", get(preview_template_document))
+        .route("/templates/:id/placeholders", get(get_template_placeholders))
+        .route("/templates/:id/versions", get(get_template_versions))
+        .route("/templates/:id/versions/diff", get(diff_template_versions))
+        .route("/templates/:id/versions/:version", get(get_template_version))
         .route("/templates/:template_id/fields", get(get_template_fields))
         .route("/templates/:template_id/fields", post(create_template_field))
         .route("/templates/:template_id/fields/upload", post(upload_template_field_file))
@@ -388,11 +421,51 @@ pub fn create_template_router() -> Router<AppState> {
 
 // ===== TEMPLATE FOLDER ENDPOINTS =====
 
+// Owners can always access their folders; others of the account get the access of their role or
+// of the folder's sharing settings (see TemplateShareQueries::get_folder_access_level)
+async fn has_folder_access(pool: &sqlx::PgPool, db_folder: &crate::database::models::DbTemplateFolder, user_id: i64, level: AccessLevel) -> bool {
+    if db_folder.user_id == user_id {
+        return true;
+    }
+    matches!(
+        TemplateShareQueries::get_folder_access_level(pool, db_folder.id, user_id).await,
+        Ok(Some(granted)) if granted >= level
+    )
+}
+
+async fn folder_breadcrumbs(pool: &sqlx::PgPool, id: i64) -> Result<Vec<FolderBreadcrumb>, sqlx::Error> {
+    let ancestors = TemplateFolderQueries::get_folder_ancestors(pool, id).await?;
+    Ok(ancestors.into_iter().map(|f| FolderBreadcrumb { id: f.id, name: f.name }).collect())
+}
+
+#[derive(serde::Deserialize)]
+pub struct FolderListQuery {
+    parent_id: Option<i64>,
+    user_id: Option<i64>,
+    created_from: Option<chrono::DateTime<chrono::Utc>>,
+    created_to: Option<chrono::DateTime<chrono::Utc>>,
+    sort: Option<String>,
+    order: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
 #[utoipa::path(
     get,
     path = "/api/folders",
+    params(
+        ("parent_id" = Option<i64>, Query, description = "List the subfolders of this folder instead of the top-level folders"),
+        ("user_id" = Option<i64>, Query, description = "Only folders created by this user"),
+        ("created_from" = Option<String>, Query, description = "Only folders created at or after this time (RFC 3339)"),
+        ("created_to" = Option<String>, Query, description = "Only folders created before this time (RFC 3339)"),
+        ("sort" = Option<String>, Query, description = "name (default), created_at or updated_at"),
+        ("order" = Option<String>, Query, description = "asc or desc (default: asc)"),
+        ("page" = Option<i64>, Query, description = "Page number, from 1; without page and per_page all folders are returned"),
+        ("per_page" = Option<i64>, Query, description = "Folders per page, up to 200 (default 50)")
+    ),
     responses(
-        (status = 200, description = "List all template folders with hierarchy", body = ApiResponse<Vec<TemplateFolder>>),
+        (status = 200, description = "One level of template folders, each with all of its subfolders; pagination counts the folders of that level", body = ApiResponse<Vec<TemplateFolder>>),
+        (status = 400, description = "Invalid sort", body = ApiResponse<Vec<TemplateFolder>>),
         (status = 500, description = "Internal server error", body = ApiResponse<Vec<TemplateFolder>>)
     ),
     security(("bearer_auth" = [])),
@@ -401,11 +474,37 @@ pub fn create_template_router() -> Router<AppState> {
 pub async fn get_folders(
     State(state): State<AppState>,
     Extension(user_id): Extension<i64>,
+    Query(query): Query<FolderListQuery>,
 ) -> (StatusCode, Json<ApiResponse<Vec<TemplateFolder>>>) {
     let pool = &state.lock().await.db_pool;
 
-    match TemplateFolderQueries::get_team_folders(pool, user_id).await {
-        Ok(db_folders) => {
+    let page = PageRequest::from_query(query.page, query.per_page);
+    let sort = match SortOrder::from_query(
+        query.sort.as_deref(),
+        query.order.as_deref(),
+        &["name", "created_at", "updated_at"],
+        SortOrder { column: "name", descending: false },
+    ) {
+        Ok(sort) => sort,
+        Err(e) => return ApiResponse::bad_request(e),
+    };
+    let filter = FolderListFilter {
+        parent_folder_id: query.parent_id,
+        user_id: query.user_id,
+        created_from: query.created_from,
+        created_to: query.created_to,
+        sort,
+        page,
+    };
+
+    let (level, total) = match TemplateFolderQueries::get_team_folders(pool, user_id, &filter).await {
+        Ok(result) => result,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve folders: {}", e)),
+    };
+    let level_ids: Vec<i64> = level.iter().map(|folder| folder.id).collect();
+    match TemplateFolderQueries::get_descendant_folders(pool, &level_ids, user_id).await {
+        Ok(descendants) => {
+            let db_folders: Vec<crate::database::models::DbTemplateFolder> = level.into_iter().chain(descendants).collect();
             let mut folders = Vec::new();
             
             // Build hierarchy with proper recursion
@@ -423,6 +522,7 @@ pub async fn get_folders(
                     updated_at: db_folder.updated_at,
                     children: Some(Vec::new()),
                     templates: None,
+                    breadcrumbs: None,
                 };
 
                 // Find and build all children
@@ -438,15 +538,12 @@ pub async fn get_folders(
                 folder
             }
 
-            // Build root folders with their full tree
-            for db_folder in &db_folders {
-                if db_folder.parent_folder_id.is_none() {
-                    let root_folder = build_folder_tree(db_folder.id, &db_folders);
-                    folders.push(root_folder);
-                }
+            // Build the folders of the level, in their order, with their full tree
+            for folder_id in &level_ids {
+                folders.push(build_folder_tree(*folder_id, &db_folders));
             }
 
-            ApiResponse::success(folders, "Folders retrieved successfully".to_string())
+            ApiResponse::paginated(folders, pagination::pagination(total, page), "Folders retrieved successfully".to_string())
         }
         Err(e) => ApiResponse::internal_error(format!("Failed to retrieve folders: {}", e)),
     }
@@ -515,7 +612,7 @@ pub async fn create_folder(
                         } else if let Some(template_id) = payload.template_id {
                             // Get template name when name is not provided
                             match TemplateQueries::get_template_by_id(pool, template_id).await {
-                                Ok(Some(template)) if template.user_id == user_id => {
+                                Ok(Some(template)) if has_template_access(pool, &template, user_id, AccessLevel::View).await => {
                                     template_name_holder = template.name;
                                     Some(template_name_holder.as_str())
                                 }
@@ -543,6 +640,7 @@ pub async fn create_folder(
                                     updated_at: updated_folder.updated_at,
                                     children: None,
                                     templates: None,
+                                    breadcrumbs: None,
                                 };
                                 return ApiResponse::success(folder, "Folder name updated (only 1 child per parent allowed)".to_string());
                             }
@@ -564,17 +662,8 @@ pub async fn create_folder(
         // Get template name when name is not provided
         match TemplateQueries::get_template_by_id(pool, template_id).await {
             Ok(Some(template)) => {
-                // Check user permission to access template
-                match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                    Ok(Some(user)) => {
-                        let has_access = template.user_id == user_id || 
-                                       matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                        
-                        if !has_access {
-                            return ApiResponse::forbidden("Access denied to template".to_string());
-                        }
-                    }
-                    _ => return ApiResponse::forbidden("User not found".to_string()),
+                if !has_template_access(pool, &template, user_id, AccessLevel::View).await {
+                    return ApiResponse::forbidden("Access denied to template".to_string());
                 }
                 template.name
             }
@@ -630,6 +719,7 @@ pub async fn create_folder(
                 updated_at: db_folder.updated_at,
                 children: None,
                 templates: None,
+                breadcrumbs: None,
             };
             ApiResponse::created(folder, "Folder created successfully".to_string())
         }
@@ -660,22 +750,17 @@ pub async fn get_folder(
 
     match TemplateFolderQueries::get_folder_by_id(pool, id).await {
         Ok(Some(db_folder)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin/Member role
-                    let has_access = db_folder.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::not_found("Folder not found".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await {
+                return ApiResponse::not_found("Folder not found".to_string());
             }
 
+            let breadcrumbs = match folder_breadcrumbs(pool, id).await {
+                Ok(breadcrumbs) => breadcrumbs,
+                Err(e) => return ApiResponse::internal_error(format!("Failed to get folder path: {}", e)),
+            };
+
             // Get templates in this folder
-            match TemplateFolderQueries::get_templates_in_folder(pool, db_folder.user_id, Some(id)).await {
+            match TemplateFolderQueries::get_team_templates_in_folder(pool, user_id, id).await {
                 Ok(db_templates) => {
                     let templates = db_templates.into_iter()
                         .map(|db_template| convert_db_template_to_template_without_fields(db_template))
@@ -690,6 +775,7 @@ pub async fn get_folder(
                         updated_at: db_folder.updated_at,
                         children: None,
                         templates: Some(templates),
+                        breadcrumbs: Some(breadcrumbs),
                     };
                     ApiResponse::success(folder, "Folder retrieved successfully".to_string())
                 }
@@ -727,18 +813,8 @@ pub async fn update_folder(
     // First verify user has permission to access this folder
     match TemplateFolderQueries::get_folder_by_id(pool, id).await {
         Ok(Some(db_folder)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' folders)
-                    let has_access = db_folder.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_folder_access(pool, &db_folder, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied".to_string());
             }
 
             match TemplateFolderQueries::update_folder(
@@ -757,6 +833,7 @@ pub async fn update_folder(
                         updated_at: db_folder.updated_at,
                         children: None,
                         templates: None,
+                        breadcrumbs: None,
                     };
                     ApiResponse::success(folder, "Folder updated successfully".to_string())
                 }
@@ -769,15 +846,23 @@ pub async fn update_folder(
     }
 }
 
+#[derive(serde::Deserialize)]
+pub struct DeleteFolderQuery {
+    contents: Option<String>,
+}
+
 #[utoipa::path(
     delete,
     path = "/api/folders/{id}",
     params(
-        ("id" = i64, Path, description = "Folder ID")
+        ("id" = i64, Path, description = "Folder ID"),
+        ("contents" = Option<String>, Query, description = "refuse (default): only delete an empty folder; move_to_parent: move its templates and subfolders to the parent folder first")
     ),
     responses(
         (status = 200, description = "Folder deleted successfully", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "Unknown contents mode", body = ApiResponse<serde_json::Value>),
         (status = 404, description = "Folder not found", body = ApiResponse<serde_json::Value>),
+        (status = 409, description = "Folder is not empty", body = ApiResponse<serde_json::Value>),
         (status = 500, description = "Internal server error", body = ApiResponse<serde_json::Value>)
     ),
     security(("bearer_auth" = [])),
@@ -786,30 +871,44 @@ pub async fn update_folder(
 pub async fn delete_folder(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(query): Query<DeleteFolderQuery>,
     Extension(user_id): Extension<i64>,
 ) -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
     let pool = &state.lock().await.db_pool;
 
+    let move_to_parent = match query.contents.as_deref() {
+        None | Some("refuse") => false,
+        Some("move_to_parent") => true,
+        Some(other) => return ApiResponse::bad_request(format!("Unknown contents mode '{}'; use refuse or move_to_parent", other)),
+    };
+
     // First verify user has permission to access this folder
     match TemplateFolderQueries::get_folder_by_id(pool, id).await {
         Ok(Some(db_folder)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' folders)
-                    let has_access = db_folder.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_folder_access(pool, &db_folder, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied".to_string());
+            }
+
+            if move_to_parent {
+                return match TemplateFolderQueries::delete_folder(pool, id).await {
+                    Ok(true) => ApiResponse::success(serde_json::json!({"deleted": true}), "Folder deleted and its contents moved to the parent folder".to_string()),
+                    Ok(false) => ApiResponse::not_found("Folder not found".to_string()),
+                    Err(e) => ApiResponse::internal_error(format!("Failed to delete folder: {}", e)),
+                };
             }
 
-            match TemplateFolderQueries::delete_folder(pool, id, db_folder.user_id).await {
+            match TemplateFolderQueries::count_folder_contents(pool, id).await {
+                Ok((0, 0)) => {}
+                Ok((folders, templates)) => return ApiResponse::conflict(format!(
+                    "Folder contains {} folder(s) and {} template(s); delete with contents=move_to_parent to move them to the parent folder",
+                    folders, templates
+                )),
+                Err(e) => return ApiResponse::internal_error(format!("Failed to check folder contents: {}", e)),
+            }
+            match TemplateFolderQueries::delete_empty_folder(pool, id).await {
                 Ok(true) => ApiResponse::success(serde_json::json!({"deleted": true}), "Folder deleted successfully".to_string()),
-                Ok(false) => ApiResponse::not_found("Folder not found".to_string()),
+                // Something was added to the folder since it was checked
+                Ok(false) => ApiResponse::conflict("Folder is not empty".to_string()),
                 Err(e) => ApiResponse::internal_error(format!("Failed to delete folder: {}", e)),
             }
         }
@@ -818,6 +917,119 @@ pub async fn delete_folder(
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/api/folders/{id}/move",
+    params(
+        ("id" = i64, Path, description = "Folder ID")
+    ),
+    request_body = MoveFolderRequest,
+    responses(
+        (status = 200, description = "Folder moved with its subfolders and templates", body = ApiResponse<TemplateFolder>),
+        (status = 400, description = "The new parent is the folder itself or one of its subfolders", body = ApiResponse<TemplateFolder>),
+        (status = 403, description = "Access denied", body = ApiResponse<TemplateFolder>),
+        (status = 404, description = "Folder or new parent not found", body = ApiResponse<TemplateFolder>),
+        (status = 500, description = "Internal server error", body = ApiResponse<TemplateFolder>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "folders"
+)]
+pub async fn move_folder(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<MoveFolderRequest>,
+) -> (StatusCode, Json<ApiResponse<TemplateFolder>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_folder = match TemplateFolderQueries::get_folder_by_id(pool, id).await {
+        Ok(Some(db_folder)) => db_folder,
+        Ok(None) => return ApiResponse::not_found("Folder not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve folder: {}", e)),
+    };
+    if !has_folder_access(pool, &db_folder, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied".to_string());
+    }
+
+    if let Some(parent_id) = payload.parent_folder_id {
+        let parent = match TemplateFolderQueries::get_folder_by_id(pool, parent_id).await {
+            Ok(Some(parent)) => parent,
+            Ok(None) => return ApiResponse::not_found("Destination folder not found".to_string()),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve destination folder: {}", e)),
+        };
+        if !has_folder_access(pool, &parent, user_id, AccessLevel::Edit).await {
+            return ApiResponse::forbidden("Access denied: You do not have permission to access the destination folder".to_string());
+        }
+        if parent.account_id != db_folder.account_id {
+            return ApiResponse::bad_request("Folders can only be moved within their account".to_string());
+        }
+        match TemplateFolderQueries::get_folder_ancestors(pool, parent_id).await {
+            Ok(ancestors) if ancestors.iter().any(|f| f.id == id) => {
+                return ApiResponse::bad_request("Cannot move a folder into itself or one of its subfolders".to_string());
+            }
+            Ok(_) => {}
+            Err(e) => return ApiResponse::internal_error(format!("Failed to check folder hierarchy: {}", e)),
+        }
+    }
+
+    let db_folder = match TemplateFolderQueries::move_folder(pool, id, payload.parent_folder_id).await {
+        Ok(Some(db_folder)) => db_folder,
+        // The destination was moved into this folder since it was checked
+        Ok(None) => return ApiResponse::bad_request("Cannot move a folder into itself or one of its subfolders".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to move folder: {}", e)),
+    };
+    let breadcrumbs = match folder_breadcrumbs(pool, id).await {
+        Ok(breadcrumbs) => breadcrumbs,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to get folder path: {}", e)),
+    };
+
+    let folder = TemplateFolder {
+        id: db_folder.id,
+        name: db_folder.name,
+        user_id: db_folder.user_id,
+        parent_folder_id: db_folder.parent_folder_id,
+        created_at: db_folder.created_at,
+        updated_at: db_folder.updated_at,
+        children: None,
+        templates: None,
+        breadcrumbs: Some(breadcrumbs),
+    };
+    ApiResponse::success(folder, "Folder moved successfully".to_string())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/folders/{id}/breadcrumbs",
+    params(
+        ("id" = i64, Path, description = "Folder ID")
+    ),
+    responses(
+        (status = 200, description = "Folders from the top level down to this one", body = ApiResponse<Vec<FolderBreadcrumb>>),
+        (status = 404, description = "Folder not found", body = ApiResponse<Vec<FolderBreadcrumb>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<FolderBreadcrumb>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "folders"
+)]
+pub async fn get_folder_breadcrumbs(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Vec<FolderBreadcrumb>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match TemplateFolderQueries::get_folder_by_id(pool, id).await {
+        Ok(Some(db_folder)) if has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await => {
+            match folder_breadcrumbs(pool, id).await {
+                Ok(breadcrumbs) => ApiResponse::success(breadcrumbs, "Folder path retrieved successfully".to_string()),
+                Err(e) => ApiResponse::internal_error(format!("Failed to get folder path: {}", e)),
+            }
+        }
+        Ok(_) => ApiResponse::not_found("Folder not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve folder: {}", e)),
+    }
+}
+
 #[utoipa::path(
     get,
     path = "/api/folders/{id}/templates",
@@ -842,18 +1054,8 @@ pub async fn get_folder_templates(
     // Verify folder exists and user has permission
     match TemplateFolderQueries::get_folder_by_id(pool, id).await {
         Ok(Some(db_folder)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin/Member role
-                    let has_access = db_folder.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::not_found("Folder not found".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await {
+                return ApiResponse::not_found("Folder not found".to_string());
             }
 
             // Get templates in this folder
@@ -896,20 +1098,10 @@ pub async fn move_template_to_folder(
 
     let target_folder_id = if folder_id == 0 { None } else { Some(folder_id) };
 
-    // Get user role to check permissions
-    let user = match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-        Ok(Some(user)) => user,
-        _ => return ApiResponse::forbidden("User not found".to_string()),
-    };
-
     // Verify template access
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(template)) => {
-            // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' templates)
-            let has_template_access = template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-            
-            if !has_template_access {
+            if !has_template_access(pool, &template, user_id, AccessLevel::Edit).await {
                 return ApiResponse::forbidden("Access denied: You do not have permission to move this template".to_string());
             }
 
@@ -917,11 +1109,7 @@ pub async fn move_template_to_folder(
             if let Some(fid) = target_folder_id {
                 match TemplateFolderQueries::get_folder_by_id(pool, fid).await {
                     Ok(Some(db_folder)) => {
-                        // Check folder access
-                        let has_folder_access = db_folder.user_id == user_id || 
-                                              matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                        
-                        if !has_folder_access {
+                        if !has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await {
                             return ApiResponse::forbidden("Access denied: You do not have permission to access this folder".to_string());
                         }
                     }
@@ -941,13 +1129,90 @@ pub async fn move_template_to_folder(
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/templates/move",
+    request_body = MoveTemplatesRequest,
+    responses(
+        (status = 200, description = "Templates moved; none are moved unless all of them can be", body = ApiResponse<serde_json::Value>),
+        (status = 400, description = "No templates given", body = ApiResponse<serde_json::Value>),
+        (status = 403, description = "Access denied to a template or the folder", body = ApiResponse<serde_json::Value>),
+        (status = 404, description = "Template or folder not found", body = ApiResponse<serde_json::Value>),
+        (status = 500, description = "Internal server error", body = ApiResponse<serde_json::Value>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "folders"
+)]
+pub async fn move_templates(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<MoveTemplatesRequest>,
+) -> (StatusCode, Json<ApiResponse<serde_json::Value>>) {
+    let pool = &state.lock().await.db_pool;
+
+    if payload.template_ids.is_empty() {
+        return ApiResponse::bad_request("No templates to move".to_string());
+    }
+
+    if let Some(folder_id) = payload.folder_id {
+        match TemplateFolderQueries::get_folder_by_id(pool, folder_id).await {
+            Ok(Some(db_folder)) if has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await => {}
+            Ok(Some(_)) => return ApiResponse::forbidden("Access denied: You do not have permission to access this folder".to_string()),
+            Ok(None) => return ApiResponse::not_found("Destination folder not found".to_string()),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to verify folder: {}", e)),
+        }
+    }
+
+    for &template_id in &payload.template_ids {
+        match TemplateQueries::get_template_by_id(pool, template_id).await {
+            Ok(Some(db_template)) => {
+                if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                    return ApiResponse::forbidden(format!("Access denied: You do not have permission to move template {}", template_id));
+                }
+            }
+            Ok(None) => return ApiResponse::not_found(format!("Template {} not found", template_id)),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to verify template: {}", e)),
+        }
+    }
+
+    match TemplateFolderQueries::move_templates_to_folder(pool, &payload.template_ids, payload.folder_id).await {
+        Ok(moved) => ApiResponse::success(serde_json::json!({"moved": moved}), format!("{} template(s) moved successfully", moved)),
+        Err(e) => ApiResponse::internal_error(format!("Failed to move templates: {}", e)),
+    }
+}
+
 // ===== TEMPLATE ENDPOINTS =====
 
+#[derive(serde::Deserialize)]
+pub struct TemplateListQuery {
+    folder_id: Option<String>, // a folder id, "all", or none for templates outside folders
+    user_id: Option<i64>,
+    tag: Option<String>,
+    created_from: Option<chrono::DateTime<chrono::Utc>>,
+    created_to: Option<chrono::DateTime<chrono::Utc>>,
+    sort: Option<String>,
+    order: Option<String>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
 #[utoipa::path(
     get,
     path = "/api/templates",
+    params(
+        ("folder_id" = Option<String>, Query, description = "Templates of this folder, or of every folder with \"all\" (default: templates outside folders)"),
+        ("user_id" = Option<i64>, Query, description = "Only templates owned by this user"),
+        ("tag" = Option<String>, Query, description = "Only templates with this tag"),
+        ("created_from" = Option<String>, Query, description = "Only templates created at or after this time (RFC 3339)"),
+        ("created_to" = Option<String>, Query, description = "Only templates created before this time (RFC 3339)"),
+        ("sort" = Option<String>, Query, description = "created_at (default), updated_at or name"),
+        ("order" = Option<String>, Query, description = "asc or desc (default: desc for the default sort, asc otherwise)"),
+        ("page" = Option<i64>, Query, description = "Page number, from 1; without page and per_page all templates are returned"),
+        ("per_page" = Option<i64>, Query, description = "Templates per page, up to 200 (default 50)")
+    ),
     responses(
-        (status = 200, description = "List all templates", body = ApiResponse<Vec<Template>>),
+        (status = 200, description = "List templates, with the total count in pagination", body = ApiResponse<Vec<Template>>),
+        (status = 400, description = "Invalid filter or sort", body = ApiResponse<Vec<Template>>),
         (status = 500, description = "Internal server error", body = ApiResponse<Vec<Template>>)
     ),
     security(("bearer_auth" = [])),
@@ -956,11 +1221,40 @@ pub async fn move_template_to_folder(
 pub async fn get_templates(
     State(state): State<AppState>,
     Extension(user_id): Extension<i64>,
+    Query(query): Query<TemplateListQuery>,
 ) -> (StatusCode, Json<ApiResponse<Vec<Template>>>) {
     let pool = &state.lock().await.db_pool;
 
-    match TemplateQueries::get_team_templates(pool, user_id).await {
-        Ok(db_templates) => {
+    let folder = match query.folder_id.as_deref() {
+        None => TemplateFolderFilter::Root,
+        Some("all") => TemplateFolderFilter::Any,
+        Some(folder_id) => match folder_id.parse::<i64>() {
+            Ok(folder_id) => TemplateFolderFilter::Folder(folder_id),
+            Err(_) => return ApiResponse::bad_request("folder_id must be a folder id or \"all\"".to_string()),
+        },
+    };
+    let page = PageRequest::from_query(query.page, query.per_page);
+    let sort = match SortOrder::from_query(
+        query.sort.as_deref(),
+        query.order.as_deref(),
+        &["created_at", "updated_at", "name"],
+        SortOrder { column: "created_at", descending: true },
+    ) {
+        Ok(sort) => sort,
+        Err(e) => return ApiResponse::bad_request(e),
+    };
+    let filter = TemplateListFilter {
+        folder,
+        user_id: query.user_id,
+        tag: query.tag.as_deref().map(|tag| tag.trim().to_lowercase()).filter(|tag| !tag.is_empty()),
+        created_from: query.created_from,
+        created_to: query.created_to,
+        sort,
+        page,
+    };
+
+    match TemplateQueries::get_team_templates(pool, user_id, &filter).await {
+        Ok((db_templates, total)) => {
             let mut templates = Vec::new();
             for db_template in db_templates {
                 // Get user name for this template's owner
@@ -969,11 +1263,14 @@ pub async fn get_templates(
                     _ => None,
                 };
                 
+                let tags = TemplateQueries::get_template_tags(pool, db_template.id).await.unwrap_or_default();
+                
                 let mut template = convert_db_template_to_template_without_fields(db_template);
                 template.user_name = user_name;
+                template.tags = Some(tags);
                 templates.push(template);
             }
-            ApiResponse::success(templates, "Templates retrieved successfully".to_string())
+            ApiResponse::paginated(templates, pagination::pagination(total, page), "Templates retrieved successfully".to_string())
         }
         Err(e) => ApiResponse::internal_error(format!("Failed to retrieve templates: {}", e)),
     }
@@ -1002,18 +1299,8 @@ pub async fn get_template(
 
     match TemplateQueries::get_template_by_id(pool, id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin/Member role
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::not_found("Template not found".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::View).await {
+                return ApiResponse::not_found("Template not found".to_string());
             }
             match convert_db_template_to_template_with_fields(db_template, pool).await {
                 Ok(mut template) => {
@@ -1059,18 +1346,24 @@ pub async fn update_template(
     // First verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' templates)
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied".to_string());
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied".to_string());
+            }
+
+            // Per-template render settings; only the supplied ones change
+            if let Some(settings) = &payload.settings {
+                if let Some(mode) = &settings.pdf_render_mode {
+                    if !crate::services::acroform::is_valid_render_mode(mode) {
+                        return ApiResponse::bad_request(format!("Invalid pdf_render_mode '{}', expected 'stamp' or 'acroform'", mode));
                     }
                 }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+                let settings_json = match serde_json::to_value(settings) {
+                    Ok(v) => v,
+                    Err(e) => return ApiResponse::internal_error(format!("Failed to serialize template settings: {}", e)),
+                };
+                if let Err(e) = TemplateQueries::merge_template_settings(pool, id, &settings_json).await {
+                    return ApiResponse::internal_error(format!("Failed to update template settings: {}", e));
+                }
             }
 
             // Update template (fields are managed separately via template_fields endpoints)
@@ -1090,15 +1383,22 @@ pub async fn update_template(
     }
 }
 
+#[derive(serde::Deserialize)]
+pub struct DeleteTemplateQuery {
+    force: Option<bool>,
+}
+
 #[utoipa::path(
     delete,
     path = "/api/templates/{id}",
     params(
-        ("id" = i64, Path, description = "Template ID")
+        ("id" = i64, Path, description = "Template ID"),
+        ("force" = Option<bool>, Query, description = "Delete even if submissions are still waiting for signatures")
     ),
     responses(
-        (status = 200, description = "Template deleted successfully", body = ApiResponse<String>),
+        (status = 200, description = "Template moved to the trash", body = ApiResponse<String>),
         (status = 404, description = "Template not found", body = ApiResponse<String>),
+        (status = 409, description = "Template has submissions waiting for signatures and force is not set", body = ApiResponse<String>),
         (status = 500, description = "Internal server error", body = ApiResponse<String>)
     ),
     security(("bearer_auth" = [])),
@@ -1107,6 +1407,7 @@ pub async fn update_template(
 pub async fn delete_template(
     State(state): State<AppState>,
     Path(id): Path<i64>,
+    Query(query): Query<DeleteTemplateQuery>,
     Extension(user_id): Extension<i64>,
 ) -> (StatusCode, Json<ApiResponse<String>>) {
     let pool = &state.lock().await.db_pool;
@@ -1114,48 +1415,22 @@ pub async fn delete_template(
     // First verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' templates)
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied: You do not have permission to access this folder".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied: You do not have permission to access this folder".to_string());
             }
 
-            // Initialize storage service to delete files
-            let storage = match StorageService::new().await {
-                Ok(storage) => storage,
-                Err(e) => {
-                    eprintln!("Warning: Failed to initialize storage for cleanup: {}", e);
-                    return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e));
-                }
-            };
-
-            // Delete files from S3 if documents exist
-            if let Some(documents) = &db_template.documents {
-                if let Some(docs_array) = documents.as_array() {
-                    for doc in docs_array {
-                        if let Some(url) = doc.get("url").and_then(|u| u.as_str()) {
-                            eprintln!("🗑️ Deleting template document from S3: {}", url);
-                            if let Err(e) = storage.delete_file(url).await {
-                                eprintln!("⚠️ Warning: Failed to delete file '{}' from S3: {}", url, e);
-                            } else {
-                                eprintln!("✅ Successfully deleted file from S3: {}", url);
-                            }
-                        }
-                    }
+            // Signers of pending submissions lose access to a deleted template
+            if !query.force.unwrap_or(false) {
+                match crate::database::queries::SubmitterQueries::count_active_submitters(pool, id).await {
+                    Ok(0) => {}
+                    Ok(count) => return ApiResponse::conflict(format!("Template has {} submission(s) waiting for signatures; delete with force=true to delete it anyway", count)),
+                    Err(e) => return ApiResponse::internal_error(format!("Failed to check submissions: {}", e)),
                 }
             }
 
-            // Delete template from database
-            match TemplateQueries::delete_template(pool, id).await {
-                Ok(true) => ApiResponse::success("Template deleted successfully".to_string(), "Template deleted successfully".to_string()),
+            // Files are kept until the template is purged from the trash
+            match TemplateQueries::trash_template(pool, id).await {
+                Ok(true) => ApiResponse::success("Template moved to trash".to_string(), "Template deleted successfully".to_string()),
                 Ok(false) => ApiResponse::not_found("Template not found".to_string()),
                 Err(e) => ApiResponse::internal_error(format!("Failed to delete template: {}", e)),
             }
@@ -1165,6 +1440,128 @@ pub async fn delete_template(
     }
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/templates/trash",
+    responses(
+        (status = 200, description = "Deleted templates, latest first, with the time they will be purged", body = ApiResponse<Vec<TrashedTemplate>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<TrashedTemplate>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_trashed_templates(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Vec<TrashedTemplate>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match TemplateQueries::get_trashed_templates(pool, user_id).await {
+        Ok(templates) => {
+            let templates = templates.into_iter()
+                .map(|(db_template, deleted_at)| TrashedTemplate {
+                    template: convert_db_template_to_template(db_template),
+                    deleted_at,
+                    purge_at: template_trash::purge_at(deleted_at),
+                })
+                .collect();
+            ApiResponse::success(templates, "Deleted templates retrieved successfully".to_string())
+        }
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve deleted templates: {}", e)),
+    }
+}
+
+// A template in the trash the user may restore or purge
+async fn trashed_template_for_edit<T>(
+    pool: &sqlx::PgPool,
+    id: i64,
+    user_id: i64,
+) -> Result<crate::database::models::DbTemplate, (StatusCode, Json<ApiResponse<T>>)> {
+    match TemplateQueries::get_trashed_template(pool, id).await {
+        Ok(Some((db_template, _))) if has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await => Ok(db_template),
+        Ok(Some(_)) => Err(ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string())),
+        Ok(None) => Err(ApiResponse::not_found("Template not found in trash".to_string())),
+        Err(e) => Err(ApiResponse::internal_error(format!("Failed to retrieve template: {}", e))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates/{id}/restore",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    responses(
+        (status = 200, description = "Template restored from the trash", body = ApiResponse<Template>),
+        (status = 404, description = "Template not found in trash", body = ApiResponse<Template>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Template>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn restore_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Template>>) {
+    let pool = &state.lock().await.db_pool;
+
+    if let Err(response) = trashed_template_for_edit(pool, id, user_id).await {
+        return response;
+    }
+    match TemplateQueries::restore_template(pool, id).await {
+        Ok(true) => {}
+        Ok(false) => return ApiResponse::not_found("Template not found in trash".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to restore template: {}", e)),
+    }
+
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => match convert_db_template_to_template_with_fields(db_template, pool).await {
+            Ok(template) => ApiResponse::success(template, "Template restored successfully".to_string()),
+            Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+        },
+        Ok(None) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/templates/{id}/purge",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    responses(
+        (status = 200, description = "Template, its submissions and its files deleted permanently", body = ApiResponse<String>),
+        (status = 404, description = "Template not found in trash", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn purge_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<String>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_template = match trashed_template_for_edit(pool, id, user_id).await {
+        Ok(db_template) => db_template,
+        Err(response) => return response,
+    };
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+
+    match template_trash::purge_template(pool, &storage, &db_template).await {
+        Ok(true) => ApiResponse::success("Template deleted permanently".to_string(), "Template purged successfully".to_string()),
+        Ok(false) => ApiResponse::not_found("Template not found in trash".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to purge template: {}", e)),
+    }
+}
+
 #[utoipa::path(
     post,
     path = "/api/templates/{id}/clone",
@@ -1191,18 +1588,8 @@ pub async fn clone_template(
     // First get the original template to get its name
     match TemplateQueries::get_template_by_id(pool, id).await {
         Ok(Some(original_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin/Member role
-                    let has_access = original_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::not_found("Template not found".to_string());
-                    }
-                }
-                _ => return ApiResponse::forbidden("User not found".to_string()),
+            if !has_template_access(pool, &original_template, user_id, AccessLevel::View).await {
+                return ApiResponse::not_found("Template not found".to_string());
             }
 
             // Generate new name: original name + " (Clone)"
@@ -1295,6 +1682,8 @@ pub async fn create_template(
 
     match TemplateQueries::create_template(pool, create_template).await {
         Ok(db_template) => {
+            page_previews::pregenerate(&db_template);
+            template_search::index_in_background(pool, db_template.id);
             let template_id = db_template.id;
 
             // Create fields if provided
@@ -1335,8 +1724,7 @@ pub async fn create_template(
     }
 }
 
-// Placeholder handlers for creating templates from different sources
-// These would need actual implementation for PDF/HTML processing
+// Handlers for creating templates from different sources
 
 #[utoipa::path(
     post,
@@ -1344,6 +1732,7 @@ pub async fn create_template(
     request_body = CreateTemplateFromHtmlRequest,
     responses(
         (status = 201, description = "Template created from HTML", body = ApiResponse<Template>),
+        (status = 400, description = "Invalid page size or margin", body = ApiResponse<Template>),
         (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
     security(("bearer_auth" = [])),
@@ -1370,15 +1759,36 @@ pub async fn create_template_from_html(
         }
     };
 
-    // Convert HTML to bytes
+    // Render the HTML; its data-field inputs become fields at their rendered positions
+    let layout = html_rendering::PageLayout {
+        size: payload.page_size.clone(),
+        margin: payload.page_margin.clone(),
+    };
+    if let Err(e) = layout.validate() {
+        return ApiResponse::bad_request(e);
+    }
+    let rendered = match html_rendering::render_html_template(&payload.html, &layout).await {
+        Ok(rendered) => rendered,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to render HTML to PDF: {}", e)),
+    };
+
+    // Keep the HTML to re-render the PDF from
     let html_data = payload.html.as_bytes().to_vec();
+    let html_size = html_data.len() as i64;
     let filename = format!("{}.html", payload.name.to_lowercase().replace(" ", "_"));
-
-    // Upload HTML file to storage
-    let file_key = match storage.upload_file(html_data, &filename, "text/html").await {
+    let html_key = match storage.upload_file(html_data, &filename, "text/html").await {
         Ok(key) => key,
         Err(e) => return ApiResponse::internal_error(format!("Failed to upload HTML file: {}", e)),
     };
+    let pdf_filename = format!("{}.pdf", payload.name.to_lowercase().replace(" ", "_"));
+    let pdf_size = rendered.pdf.len() as i64;
+    let file_key = match storage.upload_file(rendered.pdf, &pdf_filename, "application/pdf").await {
+        Ok(key) => key,
+        Err(e) => {
+            let _ = storage.delete_file(&html_key).await;
+            return ApiResponse::internal_error(format!("Failed to upload file: {}", e));
+        }
+    };
 
     // Generate unique slug
     let slug = format!("html-{}-{}", payload.name.to_lowercase().replace(" ", "-"), chrono::Utc::now().timestamp());
@@ -1397,35 +1807,72 @@ pub async fn create_template_from_html(
         user_id: user_id,
         account_id,
         folder_id: payload.folder_id,
-        // fields: None, // Removed - fields will be added separately
-        documents: None, // Skip documents for now
+        documents: Some(serde_json::json!([{
+            "id": uuid::Uuid::new_v4().to_string(),
+            "filename": pdf_filename,
+            "content_type": "application/pdf",
+            "size": pdf_size,
+            "url": file_key,
+            "source": {
+                "filename": filename,
+                "content_type": "text/html",
+                "size": html_size,
+                "url": html_key
+            }
+        }])),
     };
 
-    match TemplateQueries::create_template(pool, create_template).await {
-        Ok(db_template) => {
-            match convert_db_template_to_template_with_fields(db_template, pool).await {
-                Ok(template) => ApiResponse::created(template, "Template created from HTML successfully".to_string()),
-                Err(e) => {
-                    // Try to delete uploaded file if database operation fails
-                    let _ = storage.delete_file(&file_key).await;
-                    ApiResponse::internal_error(format!("Failed to load template fields: {}", e))
-                }
-            }
-        }
+    let db_template = match TemplateQueries::create_template(pool, create_template).await {
+        Ok(db_template) => db_template,
         Err(e) => {
-            // Try to delete uploaded file if database operation fails
+            // Try to delete uploaded files if database operation fails
             let _ = storage.delete_file(&file_key).await;
-            ApiResponse::internal_error(format!("Failed to create template: {}", e))
+            let _ = storage.delete_file(&html_key).await;
+            return ApiResponse::internal_error(format!("Failed to create template: {}", e));
+        }
+    };
+    page_previews::pregenerate(&db_template);
+    template_search::index_in_background(pool, db_template.id);
+
+    for (display_order, field) in rendered.fields.into_iter().enumerate() {
+        let create_field = CreateTemplateField {
+            template_id: db_template.id,
+            name: field.marker.name,
+            field_type: field.marker.field_type,
+            required: field.marker.required,
+            display_order: display_order as i32,
+            position: Some(serde_json::json!({
+                "x": field.x,
+                "y": field.y,
+                "width": field.width,
+                "height": field.height,
+                "page": field.page
+            })),
+            options: field.marker.options.map(|options| serde_json::json!(options)),
+            metadata: None,
+            partner: field.marker.partner,
+        };
+        if let Err(e) = TemplateFieldQueries::create_template_field(pool, create_field).await {
+            let _ = TemplateQueries::delete_template(pool, db_template.id).await;
+            let _ = storage.delete_file(&file_key).await;
+            let _ = storage.delete_file(&html_key).await;
+            return ApiResponse::internal_error(format!("Failed to create template field: {}", e));
         }
     }
+
+    match convert_db_template_to_template_with_fields(db_template, pool).await {
+        Ok(template) => ApiResponse::created(template, "Template created from HTML successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+    }
 }
 
 #[utoipa::path(
     post,
     path = "/api/templates/pdf",
-    request_body = CreateTemplateFromPdfRequest,
+    request_body(content = CreateTemplateFromPdfRequest, description = "Multipart form: a PDF in `pdf`, or JPEG/PNG/TIFF page images in repeated `images` fields; `name`, and the optional `deskew` and `enhance` flags for images", content_type = "multipart/form-data"),
     responses(
         (status = 201, description = "Template created from PDF", body = ApiResponse<Template>),
+        (status = 400, description = "No PDF or images uploaded, or an image could not be read", body = ApiResponse<Template>),
         (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
     security(("bearer_auth" = [])),
@@ -1447,6 +1894,8 @@ pub async fn create_template_from_pdf(
     let mut pdf_data = Vec::new();
     let mut filename = String::new();
     let mut template_name = String::new();
+    let mut images = Vec::new();
+    let mut cleanup = image_pdf::ImageCleanup::default();
 
     // Parse multipart form data
     while let Some(field) = multipart.next_field().await.unwrap_or(None) {
@@ -1457,6 +1906,22 @@ pub async fn create_template_from_pdf(
                 filename = field.file_name().unwrap_or("template.pdf").to_string();
                 pdf_data = field.bytes().await.unwrap_or_default().to_vec();
             }
+            "images" | "image" => {
+                let data = field.bytes().await.unwrap_or_default().to_vec();
+                if !image_pdf::is_page_image(&data) {
+                    return ApiResponse::bad_request("Images must be JPEG, PNG or TIFF files".to_string());
+                }
+                images.push(data);
+            }
+            "deskew" | "enhance" => {
+                let value = field.text().await.unwrap_or_default();
+                let enabled = matches!(value.trim().to_lowercase().as_str(), "true" | "1" | "on" | "yes");
+                if field_name == "deskew" {
+                    cleanup.deskew = enabled;
+                } else {
+                    cleanup.enhance = enabled;
+                }
+            }
             "name" => {
                 template_name = String::from_utf8(field.bytes().await.unwrap_or_default().to_vec())
                     .unwrap_or_else(|_| "Untitled Template".to_string());
@@ -1465,8 +1930,18 @@ pub async fn create_template_from_pdf(
         }
     }
 
+    // Scanned or photographed pages become one PDF, a page per image
+    if pdf_data.is_empty() && !images.is_empty() {
+        pdf_data = match image_pdf::convert_images_to_pdf(images, cleanup).await {
+            Ok(pdf) => pdf,
+            Err(e) => return ApiResponse::bad_request(format!("Failed to convert images to PDF: {}", e)),
+        };
+        let base_name = if template_name.is_empty() { "scan" } else { template_name.as_str() };
+        filename = format!("{}.pdf", base_name);
+    }
+
     if pdf_data.is_empty() {
-        return ApiResponse::bad_request("PDF file is required".to_string());
+        return ApiResponse::bad_request("PDF file or images are required".to_string());
     }
 
     if template_name.is_empty() {
@@ -1474,6 +1949,7 @@ pub async fn create_template_from_pdf(
     }
 
     // Upload file to storage
+    let size = pdf_data.len() as i64;
     let file_key = match storage.upload_file(pdf_data, &filename, "application/pdf").await {
         Ok(key) => key,
         Err(e) => return ApiResponse::internal_error(format!("Failed to upload file: {}", e)),
@@ -1498,15 +1974,18 @@ pub async fn create_template_from_pdf(
         folder_id: None, // PDF uploads don't specify folder initially
         // fields: None, // TODO: Extract fields from PDF - REMOVED
         documents: Some(serde_json::json!([{
+            "id": uuid::Uuid::new_v4().to_string(),
             "filename": filename,
             "content_type": "application/pdf",
-            "size": 0,
+            "size": size,
             "url": file_key
         }])),
     };
 
     match TemplateQueries::create_template(pool, create_template).await {
         Ok(db_template) => {
+            page_previews::pregenerate(&db_template);
+            template_search::index_in_background(pool, db_template.id);
             match convert_db_template_to_template_with_fields(db_template, pool).await {
                 Ok(template) => ApiResponse::created(template, "Template created from PDF successfully".to_string()),
                 Err(e) => {
@@ -1735,6 +2214,8 @@ pub async fn create_template_from_google_drive(
 
     match TemplateQueries::create_template(pool, create_template).await {
         Ok(db_template) => {
+            page_previews::pregenerate(&db_template);
+            template_search::index_in_background(pool, db_template.id);
             match convert_db_template_to_template_with_fields(db_template, pool).await {
                 Ok(template) => ApiResponse::created(template, "Template created from Google Drive successfully".to_string()),
                 Err(e) => {
@@ -1758,6 +2239,7 @@ pub async fn create_template_from_google_drive(
     request_body = CreateTemplateFromDocxRequest,
     responses(
         (status = 201, description = "Template created from DOCX", body = ApiResponse<Template>),
+        (status = 400, description = "DOCX file is required", body = ApiResponse<Template>),
         (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
     security(("bearer_auth" = [])),
@@ -1805,11 +2287,27 @@ pub async fn create_template_from_docx(
         template_name = "DOCX Template".to_string();
     }
 
-    // Upload file to storage
-    let file_key = match storage.upload_file(docx_data, &filename, "application/vnd.openxmlformats-officedocument.wordprocessingml.document").await {
+    // Fields are placed on (and previews rendered from) the converted PDF
+    let pdf_data = match document_conversion::convert_docx_to_pdf(&docx_data, &filename).await {
+        Ok(pdf_data) => pdf_data,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to convert DOCX to PDF: {}", e)),
+    };
+
+    // Keep the original DOCX to re-generate the PDF from
+    let docx_size = docx_data.len() as i64;
+    let docx_key = match storage.upload_file(docx_data, &filename, document_conversion::DOCX_CONTENT_TYPE).await {
         Ok(key) => key,
         Err(e) => return ApiResponse::internal_error(format!("Failed to upload file: {}", e)),
     };
+    let pdf_filename = format!("{}.pdf", filename.rsplit_once('.').map_or(filename.as_str(), |(stem, _)| stem));
+    let pdf_size = pdf_data.len() as i64;
+    let file_key = match storage.upload_file(pdf_data, &pdf_filename, "application/pdf").await {
+        Ok(key) => key,
+        Err(e) => {
+            let _ = storage.delete_file(&docx_key).await;
+            return ApiResponse::internal_error(format!("Failed to upload file: {}", e));
+        }
+    };
 
     // Generate unique slug
     let slug = format!("docx-{}-{}", template_name.to_lowercase().replace(" ", "-"), chrono::Utc::now().timestamp());
@@ -1828,29 +2326,39 @@ pub async fn create_template_from_docx(
         user_id: user_id,
         account_id,
         folder_id: None, // DOCX uploads don't specify folder initially
-        // fields: None, // TODO: Extract fields from DOCX - REMOVED
         documents: Some(serde_json::json!([{
-            "filename": filename,
-            "content_type": "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
-            "size": 0, // TODO: Get actual file size
-            "url": file_key
+            "id": uuid::Uuid::new_v4().to_string(),
+            "filename": pdf_filename,
+            "content_type": "application/pdf",
+            "size": pdf_size,
+            "url": file_key,
+            "source": {
+                "filename": filename,
+                "content_type": document_conversion::DOCX_CONTENT_TYPE,
+                "size": docx_size,
+                "url": docx_key
+            }
         }])),
     };
 
     match TemplateQueries::create_template(pool, create_template).await {
         Ok(db_template) => {
+            page_previews::pregenerate(&db_template);
+            template_search::index_in_background(pool, db_template.id);
             match convert_db_template_to_template_with_fields(db_template, pool).await {
                 Ok(template) => ApiResponse::created(template, "Template created from DOCX successfully".to_string()),
                 Err(e) => {
-                    // Try to delete uploaded file if database operation fails
+                    // Try to delete uploaded files if database operation fails
                     let _ = storage.delete_file(&file_key).await;
+                    let _ = storage.delete_file(&docx_key).await;
                     ApiResponse::internal_error(format!("Failed to load template fields: {}", e))
                 }
             }
         }
         Err(e) => {
-            // Try to delete uploaded file if database operation fails
+            // Try to delete uploaded files if database operation fails
             let _ = storage.delete_file(&file_key).await;
+            let _ = storage.delete_file(&docx_key).await;
             ApiResponse::internal_error(format!("Failed to create template: {}", e))
         }
     }
@@ -1862,6 +2370,8 @@ pub async fn create_template_from_docx(
     request_body = MergeTemplatesRequest,
     responses(
         (status = 201, description = "Templates merged successfully", body = ApiResponse<Template>),
+        (status = 400, description = "Invalid request or a source document is not a PDF", body = ApiResponse<Template>),
+        (status = 404, description = "Template or folder not found", body = ApiResponse<Template>),
         (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
     security(("bearer_auth" = [])),
@@ -1870,10 +2380,173 @@ pub async fn create_template_from_docx(
 pub async fn merge_templates(
     State(state): State<AppState>,
     Extension(user_id): Extension<i64>,
-    Json(_payload): Json<MergeTemplatesRequest>,
+    Json(payload): Json<MergeTemplatesRequest>,
 ) -> (StatusCode, Json<ApiResponse<Template>>) {
-    // TODO: Implement template merging logic
-    ApiResponse::internal_error("Template merging not yet implemented".to_string())
+    let pool = &state.lock().await.db_pool;
+
+    if payload.template_ids.len() < 2 {
+        return ApiResponse::bad_request("At least two templates are required".to_string());
+    }
+    if payload.name.trim().is_empty() {
+        return ApiResponse::bad_request("Template name is required".to_string());
+    }
+
+    let user = match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
+        Ok(Some(user)) => user,
+        Ok(None) => return ApiResponse::not_found("User not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Database error: {}", e)),
+    };
+
+    if let Some(folder_id) = payload.folder_id {
+        match TemplateFolderQueries::get_folder_by_id(pool, folder_id).await {
+            Ok(Some(folder)) if has_folder_access(pool, &folder, user_id, AccessLevel::Edit).await => {}
+            Ok(_) => return ApiResponse::not_found("Folder not found".to_string()),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to get folder: {}", e)),
+        }
+    }
+
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+
+    // Collect every source document in order, with the fields placed on the merged document
+    let mut pdfs = Vec::new();
+    let mut fields = Vec::new();
+    let mut page_offset = 0;
+    for template_id in &payload.template_ids {
+        let db_template = match TemplateQueries::get_template_by_id(pool, *template_id).await {
+            Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => db_template,
+            Ok(_) => return ApiResponse::not_found(format!("Template {} not found", template_id)),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+        };
+        let documents = match template_documents::download_documents(&storage, &db_template).await {
+            Ok(documents) => documents,
+            Err(e) => return ApiResponse::internal_error(format!("Failed to download documents of template {}: {}", template_id, e)),
+        };
+        let document_list: Vec<_> = documents.iter().map(|(document, _)| document.clone()).collect();
+
+        // Page offset of each of this template's documents in the merged PDF
+        let mut document_offsets = Vec::new();
+        for (document, bytes) in documents {
+            let page_count = match lopdf::Document::load_mem(&bytes) {
+                Ok(doc) => doc.get_pages().len() as i64,
+                Err(_) => return ApiResponse::bad_request(format!("Document '{}' of template {} is not a PDF", document.filename, template_id)),
+            };
+            document_offsets.push(page_offset);
+            page_offset += page_count;
+            pdfs.push(bytes);
+        }
+
+        let template_fields = match TemplateFieldQueries::get_template_fields(pool, *template_id).await {
+            Ok(template_fields) => template_fields,
+            Err(e) => return ApiResponse::internal_error(format!("Failed to get template fields: {}", e)),
+        };
+        for field in template_fields {
+            let position = match field.position.clone() {
+                Some(serde_json::Value::Object(mut position)) => {
+                    let document_id = position.get("document_id").and_then(|d| d.as_str());
+                    let offset = match template_documents::document_index(&document_list, document_id) {
+                        Some(index) => document_offsets[index],
+                        None => {
+                            eprintln!("Skipping field {} of template {}: unknown document {:?}", field.name, template_id, document_id);
+                            continue;
+                        }
+                    };
+                    // The merged template has a single document
+                    position.remove("document_id");
+                    offset_page(&mut position, offset);
+                    if let Some(serde_json::Value::Object(suggested)) = position.get_mut("suggested") {
+                        offset_page(suggested, offset);
+                    }
+                    Some(serde_json::Value::Object(position))
+                }
+                other => other,
+            };
+            fields.push((field, position));
+        }
+    }
+
+    // Recipient roles with the same name (ignoring case and surrounding spaces) become one role,
+    // spelled as in the first template that has it
+    let mut roles: HashMap<String, String> = HashMap::new();
+    // Field names identify fields when documents are completed, so duplicates are numbered
+    let mut field_names: HashMap<String, usize> = HashMap::new();
+
+    let merged_pdf = match pdf_merge::merge_pdfs(&pdfs) {
+        Ok(merged_pdf) => merged_pdf,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to merge PDFs: {}", e)),
+    };
+    let size = merged_pdf.len() as i64;
+    let filename = format!("{}.pdf", payload.name.trim());
+    let file_key = match storage.upload_file(merged_pdf, &filename, "application/pdf").await {
+        Ok(key) => key,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to upload file: {}", e)),
+    };
+
+    let create_template = CreateTemplate {
+        name: payload.name.trim().to_string(),
+        slug: format!("merged-{}-{}", payload.name.trim().to_lowercase().replace(" ", "-"), chrono::Utc::now().timestamp()),
+        user_id,
+        account_id: user.account_id,
+        folder_id: payload.folder_id,
+        documents: Some(serde_json::json!([{
+            "id": uuid::Uuid::new_v4().to_string(),
+            "filename": filename,
+            "content_type": "application/pdf",
+            "size": size,
+            "url": file_key
+        }])),
+    };
+    let db_template = match TemplateQueries::create_template(pool, create_template).await {
+        Ok(db_template) => db_template,
+        Err(e) => {
+            let _ = storage.delete_file(&file_key).await;
+            return ApiResponse::internal_error(format!("Failed to create template: {}", e));
+        }
+    };
+    page_previews::pregenerate(&db_template);
+    template_search::index_in_background(pool, db_template.id);
+
+    for (display_order, (field, position)) in fields.into_iter().enumerate() {
+        let partner = field.partner.map(|partner| {
+            roles.entry(partner.trim().to_lowercase())
+                .or_insert_with(|| partner.trim().to_string())
+                .clone()
+        });
+        let count = field_names.entry(field.name.clone()).or_insert(0);
+        *count += 1;
+        let name = if *count == 1 { field.name } else { format!("{} ({})", field.name, count) };
+
+        let create_field = CreateTemplateField {
+            template_id: db_template.id,
+            name,
+            field_type: field.field_type,
+            required: field.required,
+            display_order: display_order as i32,
+            position,
+            options: field.options,
+            metadata: field.metadata,
+            partner,
+        };
+        if let Err(e) = TemplateFieldQueries::create_template_field(pool, create_field).await {
+            let _ = TemplateQueries::delete_template(pool, db_template.id).await;
+            let _ = storage.delete_file(&file_key).await;
+            return ApiResponse::internal_error(format!("Failed to create template field: {}", e));
+        }
+    }
+
+    match convert_db_template_to_template_with_fields(db_template, pool).await {
+        Ok(template) => ApiResponse::created(template, "Templates merged successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+    }
+}
+
+// Shift a field position's 1-based page by the pages of the documents before it
+fn offset_page(position: &mut serde_json::Map<String, serde_json::Value>, offset: i64) {
+    if let Some(page) = position.get("page").and_then(|p| p.as_i64()) {
+        position.insert("page".to_string(), serde_json::json!(page + offset));
+    }
 }
 
 // Helper function to create template without storage (for testing)
@@ -1925,9 +2598,11 @@ pub fn convert_db_template_to_template(db_template: crate::database::models::DbT
         user_id: db_template.user_id,
         user_name: None, // Will be set by caller if needed
         folder_id: db_template.folder_id,
+        tags: None, // Will be loaded separately if needed
         template_fields: None, // Will be loaded separately if needed
         submitters: None, // No longer stored in templates
         documents: db_template.documents.and_then(|v| serde_json::from_value(v).ok()),
+        settings: db_template.settings.and_then(|v| serde_json::from_value(v).ok()),
         created_at: db_template.created_at,
         updated_at: db_template.updated_at,
     }
@@ -1944,9 +2619,11 @@ pub fn convert_db_template_to_template_without_fields(
         user_id: db_template.user_id,
         user_name: None, // Will be set by caller if needed
         folder_id: db_template.folder_id,
+        tags: None,
         template_fields: None,
         submitters: None,
         documents: db_template.documents.and_then(|v| serde_json::from_value(v).ok()),
+        settings: db_template.settings.and_then(|v| serde_json::from_value(v).ok()),
         created_at: db_template.created_at,
         updated_at: db_template.updated_at,
     }
@@ -1974,6 +2651,7 @@ pub async fn convert_db_template_to_template_with_fields(
             updated_at: db_field.updated_at,
         })
         .collect::<Vec<_>>();
+    let tags = TemplateQueries::get_template_tags(pool, db_template.id).await?;
 
     Ok(Template {
         id: db_template.id,
@@ -1982,29 +2660,89 @@ pub async fn convert_db_template_to_template_with_fields(
         user_id: db_template.user_id,
         user_name: None, // Will be set by caller if needed
         folder_id: db_template.folder_id,
+        tags: Some(tags),
         template_fields: Some(template_fields),
         submitters: None, // No longer stored in templates
         documents: db_template.documents.and_then(|v| serde_json::from_value(v).ok()),
+        settings: db_template.settings.and_then(|v| serde_json::from_value(v).ok()),
         created_at: db_template.created_at,
         updated_at: db_template.updated_at,
     })
 }
 
+#[derive(serde::Deserialize)]
+pub struct FileQuery {
+    token: Option<String>,
+}
+
+// Template documents and their page previews are only served to users who can view a template
+// using them, and to signers with the token of a submitter signing them; other files are public
+// by key
+async fn can_read_file(
+    pool: &sqlx::PgPool,
+    key: &str,
+    headers: &axum::http::HeaderMap,
+    signer_token: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let key = key.trim_start_matches('/').to_string();
+    let mut keys = vec![key.clone()];
+    if let Some(request) = page_previews::PreviewConfig::from_env().parse_preview_key(&key) {
+        keys.push(request.file_key);
+    }
+    let user_id = crate::common::jwt::bearer_user_id(headers);
+    for key in TemplateQueries::get_referenced_storage_keys(pool, &keys).await? {
+        let readable = match (user_id, signer_token) {
+            (Some(user_id), _) if TemplateShareQueries::can_view_storage_key(pool, &key, user_id).await? => true,
+            (_, Some(token)) => SubmitterQueries::submitter_has_storage_key(pool, token, &key).await?,
+            _ => false,
+        };
+        if !readable {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Files the request can't read are reported as missing, so their keys aren't confirmed
+fn file_not_found() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::NOT_FOUND)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Expose-Headers", "*")
+        .body(Body::from("File not found"))
+        .unwrap()
+}
+
 #[utoipa::path(
     get,
     path = "/api/files/{key}",
     params(
-        ("key" = String, Path, description = "File path in storage (e.g., 'templates/1759746273_test.pdf')")
+        ("key" = String, Path, description = "File path in storage (e.g., 'templates/1759746273_test.pdf')"),
+        ("token" = Option<String>, Query, description = "Submitter token, for signers reading the documents they sign")
     ),
     responses(
         (status = 200, description = "File downloaded successfully"),
-        (status = 404, description = "File not found")
+        (status = 404, description = "File not found, or a template document the request has no access to")
     ),
     tag = "files"
 )]
 pub async fn download_file(
+    State(state): State<AppState>,
     Path(key): Path<String>,
+    Query(query): Query<FileQuery>,
+    headers: axum::http::HeaderMap,
 ) -> Response<Body> {
+    let pool = state.lock().await.db_pool.clone();
+    match can_read_file(&pool, &key, &headers, query.token.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return file_not_found(),
+        Err(e) => {
+            eprintln!("Failed to check access to file {}: {}", key, e);
+            return file_not_found();
+        }
+    }
+
     // Initialize storage service
     let storage = match StorageService::new().await {
         Ok(storage) => storage,
@@ -2061,17 +2799,31 @@ pub async fn download_file(
     get,
     path = "/api/files/previews/{key}",
     params(
-        ("key" = String, Path, description = "File path in storage (e.g., 'templates/previews/1759746273_test_page_1.jpg')")
+        ("key" = String, Path, description = "File path in storage (e.g., 'templates/previews/1759746273_test_page_1.jpg')"),
+        ("token" = Option<String>, Query, description = "Submitter token, for signers reading the documents they sign")
     ),
     responses(
         (status = 200, description = "File downloaded successfully"),
-        (status = 404, description = "File not found")
+        (status = 404, description = "File not found, or a preview of a template document the request has no access to")
     ),
     tag = "files"
 )]
 pub async fn download_file_public(
+    State(state): State<AppState>,
     Path(key): Path<String>,
+    Query(query): Query<FileQuery>,
+    headers: axum::http::HeaderMap,
 ) -> Response<Body> {
+    let pool = state.lock().await.db_pool.clone();
+    match can_read_file(&pool, &key, &headers, query.token.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return file_not_found(),
+        Err(e) => {
+            eprintln!("Failed to check access to file {}: {}", key, e);
+            return file_not_found();
+        }
+    }
+
     // Initialize storage service
     let storage = match StorageService::new().await {
         Ok(storage) => storage,
@@ -2107,12 +2859,29 @@ pub async fn download_file_public(
             return response;
         }
     };
+    // Lets clients revalidate cached previews instead of downloading them again
+    let etag = page_previews::etag(&file_data);
+    if headers.get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| page_previews::etag_matches(value, &etag))
+    {
+        return Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .header("Access-Control-Allow-Origin", "*")
+            .header("Access-Control-Expose-Headers", "*")
+            .header(header::ETAG, etag)
+            .body(Body::empty())
+            .unwrap();
+    }
+
     // Determine content type based on file extension
     let content_type = get_content_type_from_filename(&key);
 
     // Create response with file data
     let response = Response::builder()
         .status(StatusCode::OK)
+        .header(header::ETAG, etag)
+        .header("Cache-Control", "public, max-age=86400")
         .header(header::CONTENT_TYPE, content_type)
         .header(header::CONTENT_DISPOSITION, format!("inline; filename=\"{}\"", key))
         .header("Access-Control-Allow-Origin", "*")
@@ -2128,6 +2897,8 @@ pub async fn download_file_public(
 pub struct PreviewQuery {
     page: Option<i32>,
     format: Option<String>,
+    size: Option<String>,
+    token: Option<String>,
 }
 
 #[axum::debug_handler]
@@ -2135,70 +2906,77 @@ pub struct PreviewQuery {
     get,
     path = "/api/files/preview/{key}",
     params(
-        ("key" = String, Path, description = "File key in storage (e.g., 'templates/1234567890_document.pdf' or 'templates/1234567890_document_page_2.png')"),
+        ("key" = String, Path, description = "File key in storage (e.g., 'templates/1234567890_document.pdf' or 'templates/previews/1234567890_document_page_2.jpg')"),
         ("page" = Option<i32>, Query, description = "Page number - if not provided, returns JSON with all page URLs"),
-        ("format" = Option<String>, Query, description = "Image format: jpg or png (default: jpg)")
+        ("format" = Option<String>, Query, description = "Image format: jpg or png (default: PREVIEW_FORMAT)"),
+        ("size" = Option<String>, Query, description = "Preview size name from PREVIEW_SIZES (default: the first one)"),
+        ("token" = Option<String>, Query, description = "Submitter token, for signers reading the documents they sign")
     ),
     responses(
         (status = 200, description = "Preview image or JSON with all pages"),
-        (status = 404, description = "File not found"),
+        (status = 304, description = "Preview image not modified since the ETag in If-None-Match"),
+        (status = 400, description = "Unknown preview size"),
+        (status = 404, description = "File not found, or a template document the request has no access to"),
         (status = 500, description = "Internal server error")
     ),
     tag = "files"
 )]
 pub async fn preview_file(
-    State(_state): State<AppState>,
+    State(state): State<AppState>,
     Path(key): Path<String>,
     Query(query): Query<PreviewQuery>,
+    headers: axum::http::HeaderMap,
 ) -> impl IntoResponse {
-    // Wildcard paths include leading slash, so remove it
-    let key = key.trim_start_matches('/');
-    
-    // Parse page number and format from URL
-    let mut page_number: Option<i32> = None;
-    let mut image_format = "jpg";
-    let mut file_key = key.to_string();
-    
-    // Check if key contains page number in filename (e.g., "document_page_2.png")
-    if key.contains("_page_") {
-        // Extract page number and format from filename
-        if let Some(page_start) = key.rfind("_page_") {
-            let after_page = &key[page_start + 6..]; // Skip "_page_"
-            
-            // Try to extract page number
-            if let Some(dot_pos) = after_page.find('.') {
-                if let Ok(page) = after_page[..dot_pos].parse::<i32>() {
-                    page_number = Some(page);
-                }
-                
-                // Extract format from extension
-                let extension = &after_page[dot_pos + 1..];
-                if extension == "png" || extension == "jpg" || extension == "jpeg" {
-                    image_format = extension;
-                }
-                
-                // Remove the _page_X.ext suffix to get the original file key
-                // Also remove /previews/ from the path if present
-                let base_key = &key[..page_start];
-                file_key = if base_key.contains("/previews/") {
-                    base_key.replace("/previews/", "/") + ".pdf"
-                } else {
-                    base_key.to_string() + ".pdf"
-                };
-            }
+    let pool = state.lock().await.db_pool.clone();
+    match can_read_file(&pool, &key, &headers, query.token.as_deref()).await {
+        Ok(true) => {}
+        Ok(false) => return file_not_found(),
+        Err(e) => {
+            eprintln!("Failed to check access to file {}: {}", key, e);
+            return file_not_found();
         }
     }
-    
+
+    file_preview(&key, query, &headers).await
+}
+
+// Previews of a file the request may read: one page, or JSON with the URLs of all pages
+async fn file_preview(key: &str, query: PreviewQuery, headers: &axum::http::HeaderMap) -> Response<Body> {
+    // Wildcard paths include leading slash, so remove it
+    let key = key.trim_start_matches('/');
+    // Signers need their token on the URLs of the pages too
+    let token_query = query.token.as_deref()
+        .map(|token| format!("?token={}", urlencoding::encode(token)))
+        .unwrap_or_default();
+    let config = page_previews::PreviewConfig::from_env();
+
+    // Either a page preview key (e.g. "templates/previews/document_page_2.png") or the document itself
+    let (file_key, mut size, mut page_number, mut image_format) = match config.parse_preview_key(key) {
+        Some(request) => (request.file_key, request.size, Some(request.page), request.format),
+        None => (key.to_string(), config.default_size().clone(), None, config.format.clone()),
+    };
+
     // Override with query parameters if provided
     if let Some(page) = query.page {
         page_number = Some(page);
     }
-    if let Some(format_str) = &query.format {
-        if format_str == "png" || format_str == "jpg" || format_str == "jpeg" {
-            image_format = format_str;
+    if let Some(format) = query.format.as_deref().and_then(page_previews::normalize_format) {
+        image_format = format;
+    }
+    if let Some(size_name) = &query.size {
+        match config.size(size_name) {
+            Some(requested) => size = requested.clone(),
+            None => {
+                return Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .header("Access-Control-Allow-Origin", "*")
+                    .body(Body::from(format!("Unknown preview size: {}", size_name)))
+                    .unwrap();
+            }
         }
     }
-    
+
     // Initialize storage service
     let storage = match StorageService::new().await {
         Ok(storage) => storage,
@@ -2213,20 +2991,20 @@ pub async fn preview_file(
             return response;
         }
     };
-    
+
     // If no page number specified, check if file is PDF or image
-    if page_number.is_none() {
+    let Some(page_number) = page_number else {
         // Check file extension to determine if it's a PDF or image
         let is_pdf = file_key.to_lowercase().ends_with(".pdf");
-        
+
         if !is_pdf {
             // For non-PDF files (images), return URL immediately without downloading
-            let file_url = format!("/api/files/{}", file_key);
+            let file_url = format!("/api/files/{}{}", file_key, token_query);
             let json_response = serde_json::json!({
                 "url": file_url,
                 "type": "image"
             });
-            
+
             let response = Response::builder()
                 .status(StatusCode::OK)
                 .header(header::CONTENT_TYPE, "application/json")
@@ -2237,85 +3015,62 @@ pub async fn preview_file(
                 .unwrap();
             return response;
         }
-        
-        // For PDF files, download to get page count
-        let pdf_data = match storage.download_file(&file_key).await {
-            Ok(data) => data,
-            Err(e) => {
-                eprintln!("Failed to download PDF: {:?}", e);
-                let response = Response::builder()
-                    .status(StatusCode::NOT_FOUND)
-                    .header(header::CONTENT_TYPE, "application/json")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(Body::from(serde_json::json!({"error": "PDF file not found"}).to_string()))
-                    .unwrap();
-                return response;
-            }
-        };
-        
-        // Get total page count
-        let total_pages = match get_pdf_page_count(&pdf_data) {
-            Ok(count) => count,
-            Err(e) => {
-                eprintln!("Failed to read PDF: {:?}", e);
-                let response = Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .header(header::CONTENT_TYPE, "application/json")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(Body::from(serde_json::json!({"error": "Failed to read PDF"}).to_string()))
-                    .unwrap();
-                return response;
+
+        // The page count is in the manifest once previews are generated; until then read the PDF
+        // and have them generated in the background
+        let total_pages = match page_previews::manifest(&storage, &file_key).await {
+            Some(manifest) => manifest.pages,
+            None => {
+                let pdf_data = match storage.download_file(&file_key).await {
+                    Ok(data) => data,
+                    Err(e) => {
+                        eprintln!("Failed to download PDF: {:?}", e);
+                        let response = Response::builder()
+                            .status(StatusCode::NOT_FOUND)
+                            .header(header::CONTENT_TYPE, "application/json")
+                            .header("Access-Control-Allow-Origin", "*")
+                            .body(Body::from(serde_json::json!({"error": "PDF file not found"}).to_string()))
+                            .unwrap();
+                        return response;
+                    }
+                };
+                match get_pdf_page_count(&pdf_data) {
+                    Ok(count) => {
+                        page_previews::pregenerate_files(vec![file_key.clone()]);
+                        count
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to read PDF: {:?}", e);
+                        let response = Response::builder()
+                            .status(StatusCode::INTERNAL_SERVER_ERROR)
+                            .header(header::CONTENT_TYPE, "application/json")
+                            .header("Access-Control-Allow-Origin", "*")
+                            .body(Body::from(serde_json::json!({"error": "Failed to read PDF"}).to_string()))
+                            .unwrap();
+                        return response;
+                    }
+                }
             }
         };
-        
-        // OPTIMIZATION: Generate URLs for lazy loading - DON'T render all pages immediately
-        // Only generate first page preview, let frontend request others on-demand
-        let mut page_urls = Vec::new();
-        
-        // Extract base filename (without .pdf extension)
-        // file_key could be "templates/xyz.pdf" or "templates/previews/xyz.pdf"
-        let base_path = file_key.trim_end_matches(".pdf");
-        
-        // Determine the correct preview path
-        let (preview_base, base_filename) = if base_path.starts_with("templates/previews/") {
-            // Already in previews folder, use as-is
-            (base_path.to_string(), base_path.trim_start_matches("templates/previews/").to_string())
-        } else if base_path.starts_with("templates/") {
-            // In templates folder, move to previews
-            let filename = base_path.trim_start_matches("templates/");
-            (format!("templates/previews/{}", filename), filename.to_string())
-        } else {
-            // No templates prefix, add it
-            (format!("templates/previews/{}", base_path), base_path.to_string())
+
+        // Page URLs in every size; pages not rendered yet are rendered when requested
+        let page_urls = |size: &page_previews::PreviewSize| -> Vec<String> {
+            (1..=total_pages)
+                .map(|page| format!("/api/files/preview/{}{}", config.preview_key(&file_key, size, page, &image_format), token_query))
+                .collect()
         };
-        
-        // Generate first page only to show something immediately
-        let first_page_preview_key = format!("{}_page_1.{}", preview_base, image_format);
-        
-        // Check if first page preview exists, if not generate it
-        if storage.download_file(&first_page_preview_key).await.is_err() {
-            if let Ok(image_data) = render_pdf_page_to_image(&pdf_data, 1, image_format) {
-                let content_type = match image_format {
-                    "png" => "image/png",
-                    _ => "image/jpeg",
-                };
-                let _ = storage.upload_file_with_key(image_data, &first_page_preview_key, content_type).await;
-            }
-        }
-        
-        // Return URLs for all pages (they will be lazy-loaded by frontend)
-        for page_num in 1..=total_pages {
-            let preview_key = format!("{}_page_{}.{}", preview_base, page_num, image_format);
-            page_urls.push(format!("/api/files/previews/{}", preview_key));
-        }
-        
-        // Return JSON response with all page URLs for lazy loading
+        let sizes: serde_json::Map<String, serde_json::Value> = config.sizes.iter()
+            .map(|size| (size.name.clone(), serde_json::json!(page_urls(size))))
+            .collect();
+
         let json_response = serde_json::json!({
             "total_pages": total_pages,
             "format": image_format,
-            "pages": page_urls
+            "size": size.name,
+            "pages": page_urls(&size),
+            "sizes": sizes
         });
-        
+
         let response = Response::builder()
             .status(StatusCode::OK)
             .header(header::CONTENT_TYPE, "application/json")
@@ -2325,67 +3080,17 @@ pub async fn preview_file(
             .body(Body::from(json_response.to_string()))
             .unwrap();
         return response;
-    }
-    
-    // Single page request - return image
-    let page_number = page_number.unwrap();
-    
-    // Initialize storage service
-    let storage = match StorageService::new().await {
-        Ok(storage) => storage,
-        Err(e) => {
-            eprintln!("Failed to initialize storage: {:?}", e);
-            let response = Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .header(header::CONTENT_TYPE, "text/plain")
-                .header("Access-Control-Allow-Origin", "*")
-                .body(Body::from("Storage initialization failed"))
-                .unwrap();
-            return response;
-        }
-    };
-    
-    // Step 2: Check if preview image already exists
-    // Determine correct preview path (avoid duplication)
-    let base_path = file_key.trim_end_matches(".pdf");
-    let preview_key = if base_path.starts_with("templates/previews/") {
-        // Already in previews folder
-        format!("{}_page_{}.{}", base_path, page_number, image_format)
-    } else if base_path.starts_with("templates/") {
-        // In templates folder, move to previews
-        let filename = base_path.trim_start_matches("templates/");
-        format!("templates/previews/{}_page_{}.{}", filename, page_number, image_format)
-    } else {
-        // No templates prefix
-        format!("templates/previews/{}_page_{}.{}", base_path, page_number, image_format)
     };
-    
-    // Try to download existing preview
+
+    // Serve the stored preview if there is one
+    let preview_key = config.preview_key(&file_key, &size, page_number, &image_format);
     if let Ok(preview_data) = storage.download_file(&preview_key).await {
-        println!("Found existing preview: {}", preview_key);
-        let content_type = match image_format {
-            "jpg" | "jpeg" => "image/jpeg",
-            "png" => "image/png",
-            _ => "image/jpeg",
-        };
-        
-        let response = Response::builder()
-            .status(StatusCode::OK)
-            .header(header::CONTENT_TYPE, content_type)
-            .header(header::CONTENT_DISPOSITION, format!("inline; filename=\"page_{}.{}\"", page_number, image_format))
-            .header("Access-Control-Allow-Origin", "*")
-            .header("Access-Control-Expose-Headers", "*")
-            .header("Content-Length", preview_data.len().to_string())
-            .header("Cache-Control", "public, max-age=86400") // Cache for 24 hours
-            .header("ETag", format!("\"{}\"", preview_key)) // Add ETag for caching
-            .body(Body::from(preview_data))
-            .unwrap();
-        return response;
+        return preview_image_response(preview_data, page_number, &image_format, headers);
     }
-    
-    // Step 3: Preview doesn't exist, create it by rendering PDF page to image
+
+    // Not generated yet (or in a format that isn't pre-generated): render the page now
     println!("Preview not found, generating: {}", preview_key);
-    
+
     // Download the original PDF
     let pdf_data = match storage.download_file(&file_key).await {
         Ok(data) => data,
@@ -2400,9 +3105,13 @@ pub async fn preview_file(
             return response;
         }
     };
-    
-    // Render PDF page to image using pdf2image or similar
-    let image_data = match render_pdf_page_to_image(&pdf_data, page_number, image_format) {
+
+    let render_size = size.clone();
+    let render_format = image_format.clone();
+    let rendered = tokio::task::spawn_blocking(move || page_previews::render_page(&pdf_data, page_number, &render_size, &render_format))
+        .await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    let image_data = match rendered {
         Ok(data) => data,
         Err(e) => {
             eprintln!("Failed to render PDF page: {:?}", e);
@@ -2415,111 +3124,41 @@ pub async fn preview_file(
             return response;
         }
     };
-    
-    // Step 4: Upload the generated preview image to storage with exact key
-    let content_type = match image_format {
-        "jpg" | "jpeg" => "image/jpeg",
-        "png" => "image/png",
-        _ => "image/jpeg",
-    };
-    
-    match storage.upload_file_with_key(image_data.clone(), &preview_key, content_type).await {
+
+    match storage.upload_file_with_key(image_data.clone(), &preview_key, page_previews::content_type(&image_format)).await {
         Ok(_) => println!("Preview saved: {}", preview_key),
         Err(e) => eprintln!("Failed to save preview: {:?}", e),
     }
-    
-    // Step 5: Return the preview image
-    let response = Response::builder()
-        .status(StatusCode::OK)
-        .header(header::CONTENT_TYPE, content_type)
-        .header(header::CONTENT_DISPOSITION, format!("inline; filename=\"page_{}.{}\"", page_number, image_format))
-        .header("Access-Control-Allow-Origin", "*")
-        .header("Access-Control-Expose-Headers", "*")
-        .header("Content-Length", image_data.len().to_string())
-        .header("Cache-Control", "public, max-age=86400") // Cache for 24 hours
-        .header("ETag", format!("\"{}\"", preview_key)) // Add ETag for caching
-        .body(Body::from(image_data))
-        .unwrap();
+    // Render the remaining pages in the background
+    if image_format == config.format {
+        page_previews::pregenerate_files(vec![file_key.clone()]);
+    }
 
-    response
+    preview_image_response(image_data, page_number, &image_format, headers)
 }
 
-/// Helper function to render a PDF page to an image
-fn render_pdf_page_to_image(
-    pdf_bytes: &[u8],
-    page_number: i32,
-    format: &str,
-) -> Result<Vec<u8>, String> {
-    use pdfium_render::prelude::*;
-    use image::{ImageBuffer, Rgba, RgbaImage};
-
-    // Initialize PDFium - bind to library in lib folder or system
-    let pdfium = Pdfium::new(
-        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./lib/"))
-            .or_else(|_| Pdfium::bind_to_system_library())
-            .map_err(|e| format!("Failed to load PDFium library: {:?}", e))?,
-    );
-
-    // Load PDF document
-    let document = pdfium.load_pdf_from_byte_slice(pdf_bytes, None).map_err(|e| e.to_string())?;
-
-    // Get the page (page_number is 1-indexed)
-    let page_index = (page_number - 1) as u16;
-    if page_index >= document.pages().len() as u16 {
-        return Err(format!("Page {} not found in PDF", page_number));
-    }
-
-    let page = document.pages().get(page_index).map_err(|e| e.to_string())?;
-
-    // Render page to bitmap with high DPI for better quality
-    let bitmap = page.render_with_config(
-        &PdfRenderConfig::new()
-            .set_target_width(800)
-            .set_maximum_height(1100)
-            .rotate_if_landscape(PdfPageRenderRotation::None, true),
-    ).map_err(|e| e.to_string())?;
-
-    // Convert bitmap to RGBA image
-    let width = bitmap.width() as u32;
-    let height = bitmap.height() as u32;
-    let mut img: RgbaImage = ImageBuffer::new(width, height);
+// A preview image with its ETag, or 304 when the client has it already
+fn preview_image_response(data: Vec<u8>, page_number: i32, image_format: &str, headers: &axum::http::HeaderMap) -> Response<Body> {
+    let etag = page_previews::etag(&data);
+    let not_modified = headers.get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| page_previews::etag_matches(value, &etag));
 
-    // Copy pixel data from bitmap to image buffer
-    let bitmap_bytes = bitmap.as_raw_bytes();
-    let row_stride = (bitmap.width() * 4) as usize; // 4 bytes per pixel (RGBA)
-
-    for y in 0..height {
-        for x in 0..width {
-            let pixel_index = (y as usize * row_stride) + (x as usize * 4); // 4 bytes per pixel (RGBA)
-            if pixel_index + 3 < bitmap_bytes.len() {
-                let r = bitmap_bytes[pixel_index];
-                let g = bitmap_bytes[pixel_index + 1];
-                let b = bitmap_bytes[pixel_index + 2];
-                let a = bitmap_bytes[pixel_index + 3];
-
-                img.put_pixel(x, y, Rgba([r, g, b, a]));
-            }
-        }
-    }
-
-    // Encode image to bytes
-    let mut buffer = Vec::new();
-    match format {
-        "png" => {
-            img.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png).map_err(|e| e.to_string())?;
-        }
-        "jpeg" | "jpg" => {
-            // Convert RGBA to RGB for JPEG
-            let rgb_img = image::DynamicImage::ImageRgba8(img).to_rgb8();
-            rgb_img.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Jpeg).map_err(|e| e.to_string())?;
-        }
-        _ => {
-            // Default to PNG
-            img.write_to(&mut std::io::Cursor::new(&mut buffer), image::ImageFormat::Png).map_err(|e| e.to_string())?;
-        }
+    let builder = Response::builder()
+        .header("Access-Control-Allow-Origin", "*")
+        .header("Access-Control-Expose-Headers", "*")
+        .header("Cache-Control", "public, max-age=86400") // Cache for 24 hours
+        .header(header::ETAG, etag);
+    if not_modified {
+        return builder.status(StatusCode::NOT_MODIFIED).body(Body::empty()).unwrap();
     }
-
-    Ok(buffer)
+    builder
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, page_previews::content_type(image_format))
+        .header(header::CONTENT_DISPOSITION, format!("inline; filename=\"page_{}.{}\"", page_number, image_format))
+        .header("Content-Length", data.len().to_string())
+        .body(Body::from(data))
+        .unwrap()
 }
 
 /// Get PDF metadata (page count, dimensions, etc.)
@@ -2559,18 +3198,8 @@ pub async fn get_template_fields(
     // Verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin/Member role (Members can read fields from team templates)
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin | crate::models::role::Role::Member);
-                    
-                    if !has_access {
-                        return ApiResponse::not_found("Template not found".to_string());
-                    }
-                }
-                _ => return ApiResponse::not_found("User not found".to_string()),
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::View).await {
+                return ApiResponse::not_found("Template not found".to_string());
             }
         }
         Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
@@ -2627,18 +3256,8 @@ pub async fn create_template_field(
     // Verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' templates)
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
-                    }
-                }
-                _ => return ApiResponse::not_found("User not found".to_string()),
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
             }
         }
         Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
@@ -2701,6 +3320,7 @@ pub async fn create_template_field(
             Err(e) => return ApiResponse::internal_error(format!("Failed to create template field: {}", e)),
         }
     }
+    template_versions::record_edit(pool, template_id, user_id).await;
 
     ApiResponse::created(created_fields, "Template fields created successfully".to_string())
 }
@@ -2739,8 +3359,8 @@ pub async fn upload_template_field_file(
     // Verify template belongs to user
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(db_template)) => {
-            if db_template.user_id != user_id {
-                return ApiResponse::forbidden("Access denied: You can only upload files to your own templates".to_string());
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
             }
         }
         Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
@@ -2845,6 +3465,7 @@ pub async fn upload_template_field_file(
                         created_at: db_field.created_at,
                         updated_at: db_field.updated_at,
                     };
+                    template_versions::record_edit(pool, template_id, user_id).await;
                     ApiResponse::created(template_field, "Template field created successfully".to_string())
                 }
                 Err(e) => ApiResponse::internal_error(format!("Failed to create template field: {}", e)),
@@ -2881,18 +3502,8 @@ pub async fn update_template_field(
     // Verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' templates)
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
-                    }
-                }
-                _ => return ApiResponse::not_found("User not found".to_string()),
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
             }
         }
         Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
@@ -2926,6 +3537,7 @@ pub async fn update_template_field(
                 created_at: db_field.created_at,
                 updated_at: db_field.updated_at,
             };
+            template_versions::record_edit(pool, template_id, user_id).await;
 
             ApiResponse::success(template_field, "Template field updated successfully".to_string())
         }
@@ -2959,18 +3571,8 @@ pub async fn delete_template_field(
     // Verify user has permission to access this template
     match TemplateQueries::get_template_by_id(pool, template_id).await {
         Ok(Some(db_template)) => {
-            // Get user role to check permissions
-            match crate::database::queries::UserQueries::get_user_by_id(pool, user_id).await {
-                Ok(Some(user)) => {
-                    // Allow access if user is the owner OR if user has Editor/Admin role (Members have read-only access to others' templates)
-                    let has_access = db_template.user_id == user_id || 
-                                   matches!(user.role, crate::models::role::Role::Editor | crate::models::role::Role::Admin);
-                    
-                    if !has_access {
-                        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
-                    }
-                }
-                _ => return ApiResponse::not_found("User not found".to_string()),
+            if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+                return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
             }
         }
         Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
@@ -2978,201 +3580,1425 @@ pub async fn delete_template_field(
     }
 
     match crate::database::queries::TemplateFieldQueries::delete_template_field(pool, field_id).await {
-        Ok(true) => ApiResponse::success(serde_json::json!({"deleted": true}), "Template field deleted successfully".to_string()),
+        Ok(true) => {
+            template_versions::record_edit(pool, template_id, user_id).await;
+            ApiResponse::success(serde_json::json!({"deleted": true}), "Template field deleted successfully".to_string())
+        }
         Ok(false) => ApiResponse::not_found("Template field not found".to_string()),
         Err(e) => ApiResponse::internal_error(format!("Failed to delete template field: {}", e)),
     }
 }
 
-// ===== PUBLIC FILE UPLOAD ENDPOINT (for signing) =====
+// ===== TEMPLATE DOCUMENTS =====
+
+// Owners can always access their templates; others of the account get the access of their role
+// or of the sharing settings of the template and its folders (see
+// TemplateShareQueries::get_template_access_level)
+pub(crate) async fn has_template_access(pool: &sqlx::PgPool, db_template: &crate::database::models::DbTemplate, user_id: i64, level: AccessLevel) -> bool {
+    if db_template.user_id == user_id {
+        return true;
+    }
+    matches!(
+        TemplateShareQueries::get_template_access_level(pool, db_template.id, user_id).await,
+        Ok(Some(granted)) if granted >= level
+    )
+}
 
 #[utoipa::path(
     post,
-    path = "/api/files/upload/public",
-    request_body = FileUploadRequest,
+    path = "/api/templates/{id}/documents",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    request_body(content = String, description = "PDF file in the multipart field `file`, or JPEG/PNG/TIFF page images in repeated `file` fields with the optional `deskew` and `enhance` flags", content_type = "multipart/form-data"),
     responses(
-        (status = 201, description = "File uploaded successfully", body = ApiResponse<FileUploadResponse>),
-        (status = 400, description = "Bad request - No file provided or invalid file type. Supported types: Images (JPG, PNG, GIF, WEBP, BMP, TIFF), Documents (PDF, DOCX, DOC, TXT, HTML, XLSX, XLS), Data (JSON, CSV, XML)", body = ApiResponse<FileUploadResponse>),
-        (status = 500, description = "Internal server error", body = ApiResponse<FileUploadResponse>)
+        (status = 201, description = "Document added to the template", body = ApiResponse<Template>),
+        (status = 400, description = "No PDF or images uploaded", body = ApiResponse<Template>),
+        (status = 404, description = "Template not found", body = ApiResponse<Template>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
-    tag = "files"
+    security(("bearer_auth" = [])),
+    tag = "templates"
 )]
-pub async fn upload_file_public(
+pub async fn add_template_document(
     State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
     mut multipart: Multipart,
-) -> (StatusCode, Json<ApiResponse<FileUploadResponse>>) {
-    let _pool = &state.lock().await.db_pool;
+) -> (StatusCode, Json<ApiResponse<Template>>) {
+    let pool = &state.lock().await.db_pool;
 
-    // Initialize storage service
-    let storage = match StorageService::new().await {
-        Ok(storage) => storage,
-        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => db_template,
+        Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
     };
+    if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
+    }
 
-    let mut file_data = Vec::new();
+    let mut pdf_data = Vec::new();
     let mut filename = String::new();
-    let mut content_type = String::new();
-
-    // Parse multipart form data
+    let mut images = Vec::new();
+    let mut image_filename = None;
+    let mut cleanup = image_pdf::ImageCleanup::default();
     while let Some(field) = multipart.next_field().await.unwrap_or(None) {
-        let field_name = field.name().unwrap_or("").to_string();
-
-        match field_name.as_str() {
-            "file" => {
-                filename = field.file_name().unwrap_or("uploaded_file").to_string();
-                
-                // Determine content type from filename
-                content_type = get_content_type_from_filename(&filename).to_string();
-                
-                file_data = field.bytes().await.unwrap_or_default().to_vec();
+        match field.name() {
+            Some("file") => {
+                let name = field.file_name().unwrap_or("document.pdf").to_string();
+                let data = field.bytes().await.unwrap_or_default().to_vec();
+                if image_pdf::is_page_image(&data) {
+                    image_filename.get_or_insert(name);
+                    images.push(data);
+                } else {
+                    filename = name;
+                    pdf_data = data;
+                }
+            }
+            Some(flag @ ("deskew" | "enhance")) => {
+                let deskew = flag == "deskew";
+                let value = field.text().await.unwrap_or_default();
+                let enabled = matches!(value.trim().to_lowercase().as_str(), "true" | "1" | "on" | "yes");
+                if deskew {
+                    cleanup.deskew = enabled;
+                } else {
+                    cleanup.enhance = enabled;
+                }
             }
             _ => {}
         }
     }
-
-    if file_data.is_empty() {
-        return ApiResponse::bad_request("File is required".to_string());
+    if pdf_data.is_empty() && !images.is_empty() {
+        pdf_data = match image_pdf::convert_images_to_pdf(images, cleanup).await {
+            Ok(pdf) => pdf,
+            Err(e) => return ApiResponse::bad_request(format!("Failed to convert images to PDF: {}", e)),
+        };
+        let name = image_filename.unwrap_or_default();
+        let stem = std::path::Path::new(&name).file_stem().and_then(|stem| stem.to_str()).unwrap_or("scan");
+        filename = format!("{}.pdf", stem);
     }
-
-    // Validate file type - allow multiple file types including images, documents, and PDFs
-    let allowed_types = [
-        // Images
-        "image/jpeg",
-        "image/png", 
-        "image/gif",
-        "image/webp",
-        "image/bmp",
-        "image/tiff",
-        // Documents
-        "application/pdf",
-        "application/vnd.openxmlformats-officedocument.wordprocessingml.document", // DOCX
-        "application/msword", // DOC
-        "text/plain", // TXT
-        "text/html", // HTML
-        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", // XLSX
-        "application/vnd.ms-excel", // XLS
-        // Other common types
-        "application/json",
-        "text/csv",
-        "application/xml",
-        "text/xml"
-    ];
-
-    if !allowed_types.contains(&content_type.as_str()) {
-        return ApiResponse::bad_request(format!("File type not allowed. Supported types: Images (JPG, PNG, GIF, WEBP, BMP, TIFF), Documents (PDF, DOCX, DOC, TXT, HTML, XLSX, XLS), Data (JSON, CSV, XML). Detected type: {}", content_type));
+    if pdf_data.is_empty() {
+        return ApiResponse::bad_request("PDF file or images are required".to_string());
+    }
+    if lopdf::Document::load_mem(&pdf_data).is_err() {
+        return ApiResponse::bad_request("Uploaded file is not a valid PDF".to_string());
     }
 
-    // Upload file to storage
-    let file_key = match storage.upload_file(file_data.clone(), &filename, &content_type).await {
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+    let size = pdf_data.len() as i64;
+    let file_key = match storage.upload_file(pdf_data, &filename, "application/pdf").await {
         Ok(key) => key,
         Err(e) => return ApiResponse::internal_error(format!("Failed to upload file: {}", e)),
     };
 
-    // Determine file type category
-    let file_type = if content_type.starts_with("image/") {
-        "image".to_string()
-    } else if content_type == "application/pdf" {
-        "pdf".to_string()
-    } else if content_type.starts_with("application/vnd.openxmlformats-officedocument.wordprocessingml") || content_type == "application/msword" {
-        "document".to_string()
-    } else if content_type.starts_with("application/vnd.openxmlformats-officedocument.spreadsheetml") || content_type == "application/vnd.ms-excel" {
-        "spreadsheet".to_string()
-    } else if content_type == "text/plain" {
-        "text".to_string()
-    } else if content_type == "text/html" {
-        "html".to_string()
-    } else if content_type == "application/json" {
-        "json".to_string()
-    } else if content_type == "text/csv" {
-        "csv".to_string()
-    } else if content_type.contains("xml") {
-        "xml".to_string()
-    } else {
-        "file".to_string()
-    };
-
-    // Generate file URL (direct S3 URL)
-    let file_url = storage.get_public_url(&file_key);
-
-    // Create response
-    let upload_response = FileUploadResponse {
-        id: file_key.clone(),
-        filename: filename.clone(),
-        file_type,
-        file_size: file_data.len() as i64,
-        url: file_url,
-        content_type,
-        uploaded_at: chrono::Utc::now(),
-    };
-
-    ApiResponse::created(upload_response, "File uploaded successfully".to_string())
-}
+    let mut documents = template_documents::template_documents(&db_template).unwrap_or_default();
+    template_documents::assign_document_ids(&mut documents);
+    documents.push(crate::models::template::Document {
+        id: Some(uuid::Uuid::new_v4().to_string()),
+        filename,
+        content_type: "application/pdf".to_string(),
+        size,
+        url: file_key.clone(),
+        source: None,
+    });
+
+    let documents_json = serde_json::to_value(&documents).unwrap_or(serde_json::Value::Null);
+    if let Err(e) = TemplateQueries::update_template_documents(pool, id, &documents_json).await {
+        let _ = storage.delete_file(&file_key).await;
+        return ApiResponse::internal_error(format!("Failed to update template documents: {}", e));
+    }
+    page_previews::pregenerate_files(vec![file_key]);
+    template_versions::record_edit(pool, id, user_id).await;
+    template_search::index_in_background(pool, id);
 
-#[derive(Debug, Deserialize, ToSchema)]
-pub struct DeleteFileRequest {
-    pub file_url: String,
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => match convert_db_template_to_template_with_fields(db_template, pool).await {
+            Ok(template) => ApiResponse::created(template, "Document added successfully".to_string()),
+            Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+        },
+        Ok(None) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
 }
 
 #[utoipa::path(
-    delete,
-    path = "/api/files/delete/public",
-    request_body = DeleteFileRequest,
+    put,
+    path = "/api/templates/{id}/documents/{document_id}",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("document_id" = String, Path, description = "Document ID")
+    ),
+    request_body(content = String, description = "Revised PDF in the multipart field `file`", content_type = "multipart/form-data"),
     responses(
-        (status = 200, description = "File deleted successfully", body = ApiResponse<String>),
-        (status = 400, description = "Bad request - Invalid file URL", body = ApiResponse<String>),
-        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+        (status = 200, description = "Document replaced; fields are kept, those outside the new document have `out_of_bounds` set in their position", body = ApiResponse<Template>),
+        (status = 400, description = "No valid PDF uploaded", body = ApiResponse<Template>),
+        (status = 404, description = "Template or document not found", body = ApiResponse<Template>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
-    tag = "files"
+    security(("bearer_auth" = [])),
+    tag = "templates"
 )]
-pub async fn delete_file_public(
-    State(_state): State<AppState>,
-    Json(payload): Json<DeleteFileRequest>,
-) -> (StatusCode, Json<ApiResponse<String>>) {
-    // Extract file key from URL
-    // URL format: /api/files/templates/1234567890_filename.ext
-    let file_key = if payload.file_url.starts_with("/api/files/") {
-        payload.file_url.trim_start_matches("/api/files/")
-    } else if payload.file_url.contains("/api/files/") {
-        // Handle full URL
-        payload.file_url.split("/api/files/").nth(1).unwrap_or("")
-    } else {
-        // Assume it's already a key
-        &payload.file_url
+pub async fn replace_template_document(
+    State(state): State<AppState>,
+    Path((id, document_id)): Path<(i64, String)>,
+    Extension(user_id): Extension<i64>,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse<Template>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let mut pdf_data = Vec::new();
+    let mut filename = String::new();
+    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+        if field.name() == Some("file") {
+            filename = field.file_name().unwrap_or("document.pdf").to_string();
+            pdf_data = field.bytes().await.unwrap_or_default().to_vec();
+        }
+    }
+    if pdf_data.is_empty() {
+        return ApiResponse::bad_request("PDF file is required".to_string());
+    }
+    let page_count = match pdf_pages::page_count(&pdf_data) {
+        Ok(count) if count > 0 => count,
+        _ => return ApiResponse::bad_request("Uploaded file is not a valid PDF".to_string()),
     };
 
-    if file_key.is_empty() {
-        return ApiResponse::bad_request("Invalid file URL".to_string());
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => db_template,
+        Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    };
+    if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
     }
+    let mut documents = template_documents::template_documents(&db_template).unwrap_or_default();
+    let index = match template_documents::document_index(&documents, Some(&document_id)) {
+        Some(index) => index,
+        None => return ApiResponse::not_found("Document not found".to_string()),
+    };
+    template_documents::assign_document_ids(&mut documents);
 
-    // Initialize storage service
     let storage = match StorageService::new().await {
         Ok(storage) => storage,
         Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
     };
+    let size = pdf_data.len() as i64;
+    let file_key = match storage.upload_file(pdf_data, &filename, "application/pdf").await {
+        Ok(key) => key,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to upload file: {}", e)),
+    };
 
-    // Delete file from storage
-    match storage.delete_file(file_key).await {
-        Ok(_) => ApiResponse::success("File deleted successfully".to_string(), "File deleted successfully".to_string()),
+    // Fields stay where they were; those the new document has no room for are flagged for review
+    let fields = match TemplateFieldQueries::get_template_fields(pool, id).await {
+        Ok(fields) => fields,
         Err(e) => {
-            eprintln!("Failed to delete file {}: {:?}", file_key, e);
-            ApiResponse::internal_error(format!("Failed to delete file: {}", e))
+            let _ = storage.delete_file(&file_key).await;
+            return ApiResponse::internal_error(format!("Failed to get template fields: {}", e));
+        }
+    };
+    let mut flagged = 0;
+    let mut field_changes = Vec::new();
+    for field in fields {
+        let Some(serde_json::Value::Object(mut position)) = field.position else { continue };
+        let field_document_id = position.get("document_id").and_then(|d| d.as_str());
+        if template_documents::document_index(&documents, field_document_id) != Some(index) {
+            continue;
+        }
+        let was_flagged = position.contains_key("out_of_bounds");
+        let out_of_bounds = pdf_pages::mark_out_of_bounds(&mut position, page_count);
+        if out_of_bounds {
+            flagged += 1;
         }
+        if out_of_bounds != was_flagged {
+            field_changes.push((field.id, Some(serde_json::Value::Object(position))));
+        }
+    }
+
+    let previous = documents[index].clone();
+    documents[index] = crate::models::template::Document {
+        id: previous.id.clone(),
+        filename,
+        content_type: "application/pdf".to_string(),
+        size,
+        url: file_key.clone(),
+        source: None,
+    };
+    if let Err(e) = replace_template_documents(pool, &storage, &db_template, &documents, &[previous], &field_changes, user_id).await {
+        let _ = storage.delete_file(&file_key).await;
+        return ApiResponse::internal_error(e);
+    }
+    page_previews::pregenerate_files(vec![file_key]);
+
+    let message = match flagged {
+        0 => "Document replaced successfully".to_string(),
+        count => format!("Document replaced; {} field(s) are outside the new document and need to be placed again", count),
+    };
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => match convert_db_template_to_template_with_fields(db_template, pool).await {
+            Ok(template) => ApiResponse::success(template, message),
+            Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+        },
+        Ok(None) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
     }
 }
 
 #[utoipa::path(
-    post,
-    path = "/api/files/upload",
-    request_body = FileUploadRequest,
+    delete,
+    path = "/api/templates/{id}/documents/{document_id}",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("document_id" = String, Path, description = "Document ID")
+    ),
     responses(
-        (status = 201, description = "File uploaded successfully", body = ApiResponse<FileUploadResponse>),
-        (status = 400, description = "Bad request - No file provided or invalid file type", body = ApiResponse<FileUploadResponse>),
-        (status = 500, description = "Internal server error", body = ApiResponse<FileUploadResponse>)
+        (status = 200, description = "Document and its fields removed from the template", body = ApiResponse<Template>),
+        (status = 400, description = "A template needs at least one document", body = ApiResponse<Template>),
+        (status = 404, description = "Template or document not found", body = ApiResponse<Template>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Template>)
     ),
     security(("bearer_auth" = [])),
-    tag = "files"
+    tag = "templates"
 )]
-pub async fn upload_file(
+pub async fn delete_template_document(
+    State(state): State<AppState>,
+    Path((id, document_id)): Path<(i64, String)>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Template>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => db_template,
+        Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    };
+    if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
+    }
+
+    let mut documents = template_documents::template_documents(&db_template).unwrap_or_default();
+    let index = match template_documents::document_index(&documents, Some(&document_id)) {
+        Some(index) => index,
+        None => return ApiResponse::not_found("Document not found".to_string()),
+    };
+    if documents.len() == 1 {
+        return ApiResponse::bad_request("A template needs at least one document".to_string());
+    }
+
+    // Fields on the removed document go with it
+    let fields = match TemplateFieldQueries::get_template_fields(pool, id).await {
+        Ok(fields) => fields,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to get template fields: {}", e)),
+    };
+    let field_changes: Vec<(i64, Option<serde_json::Value>)> = fields.iter()
+        .filter(|field| {
+            let document_id = field.position.as_ref()
+                .and_then(|p| p.get("document_id"))
+                .and_then(|d| d.as_str());
+            template_documents::document_index(&documents, document_id) == Some(index)
+        })
+        .map(|field| (field.id, None))
+        .collect();
+
+    template_documents::assign_document_ids(&mut documents);
+    let removed = documents.remove(index);
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+    if let Err(e) = replace_template_documents(pool, &storage, &db_template, &documents, &[removed], &field_changes, user_id).await {
+        return ApiResponse::internal_error(e);
+    }
+
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => match convert_db_template_to_template_with_fields(db_template, pool).await {
+            Ok(template) => ApiResponse::success(template, "Document removed successfully".to_string()),
+            Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+        },
+        Ok(None) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/templates/{id}/documents/{document_id}/pages",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("document_id" = String, Path, description = "Document ID")
+    ),
+    request_body = ArrangeDocumentPagesRequest,
+    responses(
+        (status = 200, description = "Pages reordered, rotated or deleted; fields moved with their pages", body = ApiResponse<Template>),
+        (status = 400, description = "Invalid page list", body = ApiResponse<Template>),
+        (status = 404, description = "Template or document not found", body = ApiResponse<Template>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Template>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn arrange_template_document_pages(
+    State(state): State<AppState>,
+    Path((id, document_id)): Path<(i64, String)>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<ArrangeDocumentPagesRequest>,
+) -> (StatusCode, Json<ApiResponse<Template>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let (db_template, index, pdf_data) = match load_document_for_edit(pool, id, &document_id, user_id).await {
+        Ok(document) => document,
+        Err(response) => return response,
+    };
+    let count = match pdf_pages::page_count(&pdf_data) {
+        Ok(count) => count,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to read document: {}", e)),
+    };
+    if let Err(e) = pdf_pages::validate_layout(&payload.pages, count) {
+        return ApiResponse::bad_request(e);
+    }
+    let pdf = match pdf_pages::arrange_pages(&pdf_data, &payload.pages) {
+        Ok(pdf) => pdf,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to rearrange pages: {}", e)),
+    };
+
+    if let Err(e) = save_edited_document(pool, &db_template, index, pdf, &pdf_pages::layout_moves(&payload.pages), user_id).await {
+        return ApiResponse::internal_error(e);
+    }
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => match convert_db_template_to_template_with_fields(db_template, pool).await {
+            Ok(template) => ApiResponse::success(template, "Pages updated successfully".to_string()),
+            Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+        },
+        Ok(None) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates/{id}/documents/{document_id}/pages",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("document_id" = String, Path, description = "Document ID")
+    ),
+    request_body(content = String, description = "PDF whose pages to insert in the multipart field `file`, and `after`: the page to insert them after (0 for the beginning, default the end)", content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Pages inserted; fields on later pages moved along", body = ApiResponse<Template>),
+        (status = 400, description = "No valid PDF uploaded, or no such page to insert after", body = ApiResponse<Template>),
+        (status = 404, description = "Template or document not found", body = ApiResponse<Template>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Template>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn insert_template_document_pages(
+    State(state): State<AppState>,
+    Path((id, document_id)): Path<(i64, String)>,
+    Extension(user_id): Extension<i64>,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse<Template>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let mut insert_data = Vec::new();
+    let mut after: Option<i32> = None;
+    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+        match field.name() {
+            Some("file") => insert_data = field.bytes().await.unwrap_or_default().to_vec(),
+            Some("after") => {
+                let value = field.text().await.unwrap_or_default();
+                match value.trim().parse::<i32>() {
+                    Ok(page) => after = Some(page),
+                    Err(_) => return ApiResponse::bad_request(format!("Invalid page number: {}", value)),
+                }
+            }
+            _ => {}
+        }
+    }
+    if insert_data.is_empty() {
+        return ApiResponse::bad_request("PDF file is required".to_string());
+    }
+    if lopdf::Document::load_mem(&insert_data).is_err() {
+        return ApiResponse::bad_request("Uploaded file is not a valid PDF".to_string());
+    }
+
+    let (db_template, index, pdf_data) = match load_document_for_edit(pool, id, &document_id, user_id).await {
+        Ok(document) => document,
+        Err(response) => return response,
+    };
+    let count = match pdf_pages::page_count(&pdf_data) {
+        Ok(count) => count,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to read document: {}", e)),
+    };
+    let after = after.unwrap_or(count);
+    if after < 0 || after > count {
+        return ApiResponse::bad_request(format!("Cannot insert after page {}; the document has {} pages", after, count));
+    }
+    let (pdf, moves) = match pdf_pages::insert_pages(&pdf_data, &insert_data, after) {
+        Ok(result) => result,
+        Err(e) => return ApiResponse::bad_request(format!("Failed to insert pages: {}", e)),
+    };
+
+    if let Err(e) = save_edited_document(pool, &db_template, index, pdf, &moves, user_id).await {
+        return ApiResponse::internal_error(e);
+    }
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => match convert_db_template_to_template_with_fields(db_template, pool).await {
+            Ok(template) => ApiResponse::created(template, "Pages inserted successfully".to_string()),
+            Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e)),
+        },
+        Ok(None) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+}
+
+// A template document the user may modify: the template, the document's index and its PDF
+async fn load_document_for_edit(
+    pool: &sqlx::PgPool,
+    id: i64,
+    document_id: &str,
+    user_id: i64,
+) -> Result<(crate::database::models::DbTemplate, usize, Vec<u8>), (StatusCode, Json<ApiResponse<Template>>)> {
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => db_template,
+        Ok(None) => return Err(ApiResponse::not_found("Template not found".to_string())),
+        Err(e) => return Err(ApiResponse::internal_error(format!("Failed to retrieve template: {}", e))),
+    };
+    if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+        return Err(ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string()));
+    }
+
+    let documents = template_documents::template_documents(&db_template).unwrap_or_default();
+    let index = match template_documents::document_index(&documents, Some(document_id)) {
+        Some(index) => index,
+        None => return Err(ApiResponse::not_found("Document not found".to_string())),
+    };
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return Err(ApiResponse::internal_error(format!("Failed to initialize storage: {}", e))),
+    };
+    match storage.download_file(&documents[index].url).await {
+        Ok(pdf_data) => Ok((db_template, index, pdf_data)),
+        Err(e) => Err(ApiResponse::internal_error(format!("Failed to download document: {}", e))),
+    }
+}
+
+// Store the edited PDF of a template document, move the document's fields with their pages and
+// replace the old file and its previews. The PDF is the document from now on: a DOCX or HTML
+// source it was generated from no longer matches it and is removed.
+async fn save_edited_document(
+    pool: &sqlx::PgPool,
+    db_template: &crate::database::models::DbTemplate,
+    index: usize,
+    pdf: Vec<u8>,
+    moves: &pdf_pages::PageMoves,
+    user_id: i64,
+) -> Result<(), String> {
+    let mut documents = template_documents::template_documents(db_template).map_err(|e| e.to_string())?;
+    template_documents::assign_document_ids(&mut documents);
+    let storage = StorageService::new().await.map_err(|e| format!("Failed to initialize storage: {}", e))?;
+
+    let size = pdf.len() as i64;
+    let file_key = storage.upload_file(pdf, &documents[index].filename, "application/pdf").await
+        .map_err(|e| format!("Failed to upload file: {}", e))?;
+
+    let fields = match TemplateFieldQueries::get_template_fields(pool, db_template.id).await {
+        Ok(fields) => fields,
+        Err(e) => {
+            let _ = storage.delete_file(&file_key).await;
+            return Err(format!("Failed to get template fields: {}", e));
+        }
+    };
+    let mut field_changes = Vec::new();
+    for field in fields {
+        let Some(serde_json::Value::Object(mut position)) = field.position else { continue };
+        let document_id = position.get("document_id").and_then(|d| d.as_str());
+        if template_documents::document_index(&documents, document_id) != Some(index) {
+            continue;
+        }
+        // Fields on deleted pages go with them
+        let moved = pdf_pages::move_position(&mut position, moves);
+        field_changes.push((field.id, moved.then_some(serde_json::Value::Object(position))));
+    }
+
+    let previous = documents[index].clone();
+    documents[index].url = file_key.clone();
+    documents[index].size = size;
+    documents[index].source = None;
+    if let Err(e) = replace_template_documents(pool, &storage, db_template, &documents, &[previous], &field_changes, user_id).await {
+        let _ = storage.delete_file(&file_key).await;
+        return Err(e);
+    }
+    page_previews::pregenerate_files(vec![file_key]);
+    Ok(())
+}
+
+// Store a template's new document list, with the field positions that change with it (None
+// removes the field), and record the new version. Submissions already sent keep the documents
+// they were sent with; replaced documents no template, version or submission refers to any more
+// are deleted with their previews.
+async fn replace_template_documents(
+    pool: &sqlx::PgPool,
+    storage: &StorageService,
+    db_template: &crate::database::models::DbTemplate,
+    documents: &[crate::models::template::Document],
+    replaced: &[crate::models::template::Document],
+    field_changes: &[(i64, Option<serde_json::Value>)],
+    user_id: i64,
+) -> Result<(), String> {
+    let in_use = template_documents::pin_submitter_documents(pool, db_template).await
+        .map_err(|e| format!("Failed to keep documents of existing submissions: {}", e))?;
+
+    let documents_json = serde_json::to_value(documents).unwrap_or(serde_json::Value::Null);
+    TemplateQueries::update_template_documents_and_fields(pool, db_template.id, &documents_json, field_changes).await
+        .map_err(|e| format!("Failed to update template documents: {}", e))?;
+    template_versions::record_edit(pool, db_template.id, user_id).await;
+    template_search::index_in_background(pool, db_template.id);
+
+    let replaced_files = replaced.iter()
+        .filter(|document| !in_use.contains(&document.url))
+        .flat_map(|document| std::iter::once(document.url.clone()).chain(document.source.as_ref().map(|source| source.url.clone())))
+        .collect();
+    if let Err(e) = template_documents::delete_unreferenced_files(pool, storage, replaced_files).await {
+        eprintln!("Warning: Failed to delete replaced documents of template {}: {}", db_template.id, e);
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/documents/{document_id}/preview",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("document_id" = String, Path, description = "Document ID"),
+        ("page" = Option<i32>, Query, description = "Page number - if not provided, returns JSON with all page URLs"),
+        ("format" = Option<String>, Query, description = "Image format: jpg or png (default: PREVIEW_FORMAT)"),
+        ("size" = Option<String>, Query, description = "Preview size name from PREVIEW_SIZES (default: the first one)")
+    ),
+    responses(
+        (status = 200, description = "Preview image or JSON with all pages of the document"),
+        (status = 304, description = "Preview image not modified since the ETag in If-None-Match"),
+        (status = 404, description = "Template or document not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn preview_template_document(
+    State(state): State<AppState>,
+    Path((id, document_id)): Path<(i64, String)>,
+    Extension(user_id): Extension<i64>,
+    Query(query): Query<PreviewQuery>,
+    headers: axum::http::HeaderMap,
+) -> Response {
+    let document = {
+        let pool = &state.lock().await.db_pool;
+        let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+            Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => db_template,
+            Ok(_) => return ApiResponse::<()>::not_found("Template not found".to_string()).into_response(),
+            Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to retrieve template: {}", e)).into_response(),
+        };
+        let documents = template_documents::template_documents(&db_template).unwrap_or_default();
+        match template_documents::document_index(&documents, Some(&document_id)) {
+            Some(index) => documents[index].clone(),
+            None => return ApiResponse::<()>::not_found("Document not found".to_string()).into_response(),
+        }
+    };
+
+    file_preview(&document.url, query, &headers).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/placeholders",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    responses(
+        (status = 200, description = "Names of the {{placeholders}} in the template's DOCX documents, to send as submission values", body = ApiResponse<Vec<String>>),
+        (status = 404, description = "Template not found", body = ApiResponse<Vec<String>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<String>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_template_placeholders(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Vec<String>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => db_template,
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    };
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+
+    match template_documents::template_placeholders(&storage, &db_template).await {
+        Ok(placeholders) => ApiResponse::success(placeholders, "Placeholders retrieved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to read template documents: {}", e)),
+    }
+}
+
+// ===== TEMPLATE VERSIONS =====
+
+#[derive(serde::Deserialize)]
+pub struct VersionDiffQuery {
+    from: i32,
+    to: Option<i32>, // default: the latest version
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/versions",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    responses(
+        (status = 200, description = "Versions of the template, newest first", body = ApiResponse<Vec<TemplateVersion>>),
+        (status = 404, description = "Template not found", body = ApiResponse<Vec<TemplateVersion>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<TemplateVersion>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_template_versions(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Vec<TemplateVersion>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => {}
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+
+    match template_versions::list_versions(pool, id).await {
+        Ok(versions) => ApiResponse::success(versions, "Template versions retrieved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template versions: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/versions/{version}",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("version" = i32, Path, description = "Version number")
+    ),
+    responses(
+        (status = 200, description = "Documents and fields of the template version", body = ApiResponse<TemplateVersion>),
+        (status = 404, description = "Template or version not found", body = ApiResponse<TemplateVersion>),
+        (status = 500, description = "Internal server error", body = ApiResponse<TemplateVersion>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_template_version(
+    State(state): State<AppState>,
+    Path((id, version)): Path<(i64, i32)>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<TemplateVersion>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => {}
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+
+    match template_versions::list_versions(pool, id).await {
+        Ok(versions) => match versions.into_iter().find(|v| v.version == version) {
+            Some(version) => ApiResponse::success(version, "Template version retrieved successfully".to_string()),
+            None => ApiResponse::not_found("Template version not found".to_string()),
+        },
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve template versions: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/versions/diff",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("from" = i32, Query, description = "Version to compare from"),
+        ("to" = Option<i32>, Query, description = "Version to compare to (default: the latest)")
+    ),
+    responses(
+        (status = 200, description = "Fields and documents added, removed and changed between the versions", body = ApiResponse<TemplateVersionDiff>),
+        (status = 404, description = "Template or version not found", body = ApiResponse<TemplateVersionDiff>),
+        (status = 500, description = "Internal server error", body = ApiResponse<TemplateVersionDiff>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn diff_template_versions(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<VersionDiffQuery>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<TemplateVersionDiff>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => {}
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+
+    let versions = match template_versions::list_versions(pool, id).await {
+        Ok(versions) => versions,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template versions: {}", e)),
+    };
+    let from = versions.iter().find(|v| v.version == query.from);
+    let to = match query.to {
+        Some(to) => versions.iter().find(|v| v.version == to),
+        None => versions.first(),
+    };
+    match (from, to) {
+        (Some(from), Some(to)) => ApiResponse::success(template_versions::diff_versions(from, to), "Template versions compared successfully".to_string()),
+        _ => ApiResponse::not_found("Template version not found".to_string()),
+    }
+}
+
+// ===== TEMPLATE TAGS =====
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/tags",
+    responses(
+        (status = 200, description = "Tags used by the templates you can see, most used first", body = ApiResponse<Vec<String>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<String>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_template_tags(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<Vec<String>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    match TemplateQueries::get_team_template_tags(pool, user_id).await {
+        Ok(tags) => ApiResponse::success(tags, "Tags retrieved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve tags: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/templates/{id}/tags",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    request_body = UpdateTemplateTagsRequest,
+    responses(
+        (status = 200, description = "Tags replaced; they are stored trimmed and lowercase", body = ApiResponse<Vec<String>>),
+        (status = 400, description = "Too many or too long tags", body = ApiResponse<Vec<String>>),
+        (status = 403, description = "Access denied", body = ApiResponse<Vec<String>>),
+        (status = 404, description = "Template not found", body = ApiResponse<Vec<String>>),
+        (status = 500, description = "Internal server error", body = ApiResponse<Vec<String>>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn update_template_tags(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<UpdateTemplateTagsRequest>,
+) -> (StatusCode, Json<ApiResponse<Vec<String>>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) => db_template,
+        Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    };
+    if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied: You do not have permission to modify this template".to_string());
+    }
+
+    let tags = match template_search::normalize_tags(&payload.tags) {
+        Ok(tags) => tags,
+        Err(e) => return ApiResponse::bad_request(e),
+    };
+    match TemplateQueries::set_template_tags(pool, id, &tags).await {
+        Ok(true) => ApiResponse::success(tags, "Tags updated successfully".to_string()),
+        Ok(false) => ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to update tags: {}", e)),
+    }
+}
+
+// ===== SHARING =====
+
+// Shares must name either a user or a role, and users must be of the template's or folder's account
+async fn validate_shares(pool: &sqlx::PgPool, account_id: Option<i64>, shares: &[crate::models::template_share::ShareRequest]) -> Result<(), (StatusCode, Json<ApiResponse<SharingSettings>>)> {
+    if !shares.is_empty() && account_id.is_none() {
+        return Err(ApiResponse::bad_request("Only templates and folders of an account can be shared".to_string()));
+    }
+    for share in shares {
+        match (share.user_id, &share.role) {
+            (Some(shared_user_id), None) => match crate::database::queries::UserQueries::get_user_by_id(pool, shared_user_id).await {
+                Ok(Some(user)) if user.account_id == account_id => {}
+                Ok(_) => return Err(ApiResponse::bad_request(format!("User {} is not a member of this account", shared_user_id))),
+                Err(e) => return Err(ApiResponse::internal_error(format!("Failed to retrieve user: {}", e))),
+            },
+            (None, Some(_)) => {}
+            _ => return Err(ApiResponse::bad_request("Each share needs either a user_id or a role".to_string())),
+        }
+    }
+    Ok(())
+}
+
+fn sharing_settings(restricted: bool, shares: Vec<crate::database::models::DbTemplateShare>, access_level: Option<AccessLevel>) -> SharingSettings {
+    SharingSettings {
+        restricted,
+        shares: shares.into_iter().map(TemplateShare::from).collect(),
+        access_level,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/sharing",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    responses(
+        (status = 200, description = "Whether the template is restricted, who it is shared with and your access level; folder settings apply on top", body = ApiResponse<SharingSettings>),
+        (status = 404, description = "Template not found", body = ApiResponse<SharingSettings>),
+        (status = 500, description = "Internal server error", body = ApiResponse<SharingSettings>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_template_sharing(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<SharingSettings>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let access_level = match TemplateShareQueries::get_template_access_level(pool, id, user_id).await {
+        Ok(Some(access_level)) => access_level,
+        Ok(None) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to check template access: {}", e)),
+    };
+    match TemplateShareQueries::get_template_sharing(pool, id).await {
+        Ok((restricted, shares)) => ApiResponse::success(sharing_settings(restricted, shares, Some(access_level)), "Sharing settings retrieved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve sharing settings: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/templates/{id}/sharing",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    request_body = UpdateSharingRequest,
+    responses(
+        (status = 200, description = "Restriction and shares replaced", body = ApiResponse<SharingSettings>),
+        (status = 400, description = "Invalid share", body = ApiResponse<SharingSettings>),
+        (status = 403, description = "Access denied", body = ApiResponse<SharingSettings>),
+        (status = 404, description = "Template not found", body = ApiResponse<SharingSettings>),
+        (status = 500, description = "Internal server error", body = ApiResponse<SharingSettings>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn update_template_sharing(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<UpdateSharingRequest>,
+) -> (StatusCode, Json<ApiResponse<SharingSettings>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => db_template,
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    };
+    if !has_template_access(pool, &db_template, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied: You do not have permission to share this template".to_string());
+    }
+    if let Err(response) = validate_shares(pool, db_template.account_id, &payload.shares).await {
+        return response;
+    }
+
+    if let Err(e) = TemplateShareQueries::set_template_sharing(pool, id, payload.restricted, &payload.shares, user_id).await {
+        return ApiResponse::internal_error(format!("Failed to update sharing settings: {}", e));
+    }
+    match TemplateShareQueries::get_template_sharing(pool, id).await {
+        Ok((restricted, shares)) => ApiResponse::success(sharing_settings(restricted, shares, None), "Sharing settings updated successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve sharing settings: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/folders/{id}/sharing",
+    params(
+        ("id" = i64, Path, description = "Folder ID")
+    ),
+    responses(
+        (status = 200, description = "Whether the folder is restricted, who it is shared with and your access level; they apply to everything in the folder", body = ApiResponse<SharingSettings>),
+        (status = 404, description = "Folder not found", body = ApiResponse<SharingSettings>),
+        (status = 500, description = "Internal server error", body = ApiResponse<SharingSettings>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "folders"
+)]
+pub async fn get_folder_sharing(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<SharingSettings>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let access_level = match TemplateShareQueries::get_folder_access_level(pool, id, user_id).await {
+        Ok(Some(access_level)) => access_level,
+        Ok(None) => return ApiResponse::not_found("Folder not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to check folder access: {}", e)),
+    };
+    match TemplateShareQueries::get_folder_sharing(pool, id).await {
+        Ok((restricted, shares)) => ApiResponse::success(sharing_settings(restricted, shares, Some(access_level)), "Sharing settings retrieved successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve sharing settings: {}", e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/folders/{id}/sharing",
+    params(
+        ("id" = i64, Path, description = "Folder ID")
+    ),
+    request_body = UpdateSharingRequest,
+    responses(
+        (status = 200, description = "Restriction and shares replaced", body = ApiResponse<SharingSettings>),
+        (status = 400, description = "Invalid share", body = ApiResponse<SharingSettings>),
+        (status = 403, description = "Access denied", body = ApiResponse<SharingSettings>),
+        (status = 404, description = "Folder not found", body = ApiResponse<SharingSettings>),
+        (status = 500, description = "Internal server error", body = ApiResponse<SharingSettings>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "folders"
+)]
+pub async fn update_folder_sharing(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+    Json(payload): Json<UpdateSharingRequest>,
+) -> (StatusCode, Json<ApiResponse<SharingSettings>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let db_folder = match TemplateFolderQueries::get_folder_by_id(pool, id).await {
+        Ok(Some(db_folder)) if has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await => db_folder,
+        Ok(_) => return ApiResponse::not_found("Folder not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve folder: {}", e)),
+    };
+    if !has_folder_access(pool, &db_folder, user_id, AccessLevel::Edit).await {
+        return ApiResponse::forbidden("Access denied: You do not have permission to share this folder".to_string());
+    }
+    if let Err(response) = validate_shares(pool, db_folder.account_id, &payload.shares).await {
+        return response;
+    }
+
+    if let Err(e) = TemplateShareQueries::set_folder_sharing(pool, id, payload.restricted, &payload.shares, user_id).await {
+        return ApiResponse::internal_error(format!("Failed to update sharing settings: {}", e));
+    }
+    match TemplateShareQueries::get_folder_sharing(pool, id).await {
+        Ok((restricted, shares)) => ApiResponse::success(sharing_settings(restricted, shares, None), "Sharing settings updated successfully".to_string()),
+        Err(e) => ApiResponse::internal_error(format!("Failed to retrieve sharing settings: {}", e)),
+    }
+}
+
+// ===== TEMPLATE BUNDLES =====
+
+fn bundle_response(name: &str, bundle: Vec<u8>) -> Response<Body> {
+    let filename: String = name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect();
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/zip")
+        .header(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.zip\"", filename))
+        .header("Content-Length", bundle.len().to_string())
+        .body(Body::from(bundle))
+        .unwrap()
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/export",
+    params(
+        ("id" = i64, Path, description = "Template ID")
+    ),
+    responses(
+        (status = 200, description = "ZIP bundle with the template's documents, fields, recipient roles, folder path and email templates", content_type = "application/zip"),
+        (status = 404, description = "Template not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn export_template(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> Response<Body> {
+    let pool = &state.lock().await.db_pool;
+
+    let db_template = match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => db_template,
+        Ok(_) => return ApiResponse::<()>::not_found("Template not found".to_string()).into_response(),
+        Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to retrieve template: {}", e)).into_response(),
+    };
+    let folder_path = match template_bundles::folder_path(pool, db_template.folder_id).await {
+        Ok(path) => path,
+        Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to retrieve folder: {}", e)).into_response(),
+    };
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to initialize storage: {}", e)).into_response(),
+    };
+
+    let name = db_template.name.clone();
+    match template_bundles::export_templates(pool, &storage, user_id, vec![(db_template, folder_path)]).await {
+        Ok(bundle) => bundle_response(&name, bundle),
+        Err(e) => ApiResponse::<()>::internal_error(format!("Failed to export template: {}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/folders/{id}/export",
+    params(
+        ("id" = i64, Path, description = "Folder ID")
+    ),
+    responses(
+        (status = 200, description = "ZIP bundle with every template in the folder and its subfolders", content_type = "application/zip"),
+        (status = 404, description = "Folder not found"),
+        (status = 500, description = "Internal server error")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "folders"
+)]
+pub async fn export_folder(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Extension(user_id): Extension<i64>,
+) -> Response<Body> {
+    let pool = &state.lock().await.db_pool;
+
+    let db_folder = match TemplateFolderQueries::get_folder_by_id(pool, id).await {
+        Ok(Some(db_folder)) => db_folder,
+        Ok(None) => return ApiResponse::<()>::not_found("Folder not found".to_string()).into_response(),
+        Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to retrieve folder: {}", e)).into_response(),
+    };
+    if !has_folder_access(pool, &db_folder, user_id, AccessLevel::View).await {
+        return ApiResponse::<()>::not_found("Folder not found".to_string()).into_response();
+    }
+
+    // The folder and the subfolders the user can see, with their paths starting at the exported folder
+    let all_folders = match TemplateFolderQueries::get_descendant_folders(pool, &[db_folder.id], user_id).await {
+        Ok(folders) => folders,
+        Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to retrieve folders: {}", e)).into_response(),
+    };
+    let mut folders = vec![(db_folder.id, vec![db_folder.name.clone()])];
+    let mut index = 0;
+    while index < folders.len() {
+        let (parent_id, parent_path) = folders[index].clone();
+        for child in all_folders.iter().filter(|f| f.parent_folder_id == Some(parent_id)) {
+            if !folders.iter().any(|(id, _)| *id == child.id) {
+                let mut path = parent_path.clone();
+                path.push(child.name.clone());
+                folders.push((child.id, path));
+            }
+        }
+        index += 1;
+    }
+
+    let mut templates = Vec::new();
+    for (folder_id, path) in folders {
+        match TemplateFolderQueries::get_team_templates_in_folder(pool, user_id, folder_id).await {
+            Ok(db_templates) => templates.extend(db_templates.into_iter().map(|db_template| (db_template, path.clone()))),
+            Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to get folder templates: {}", e)).into_response(),
+        }
+    }
+    if templates.is_empty() {
+        return ApiResponse::<()>::bad_request("Folder has no templates to export".to_string()).into_response();
+    }
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::<()>::internal_error(format!("Failed to initialize storage: {}", e)).into_response(),
+    };
+
+    match template_bundles::export_templates(pool, &storage, user_id, templates).await {
+        Ok(bundle) => bundle_response(&db_folder.name, bundle),
+        Err(e) => ApiResponse::<()>::internal_error(format!("Failed to export folder: {}", e)).into_response(),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/templates/import",
+    request_body(content = String, description = "Bundle in the multipart field `file`; optional `folder_id` to import under, and `email_templates=replace` to overwrite your default email templates with the bundle's", content_type = "multipart/form-data"),
+    responses(
+        (status = 201, description = "Templates imported; clashes with existing slugs, names and email templates are listed in conflicts", body = ApiResponse<TemplateImportResult>),
+        (status = 400, description = "Not a valid template bundle", body = ApiResponse<TemplateImportResult>),
+        (status = 500, description = "Internal server error", body = ApiResponse<TemplateImportResult>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn import_templates(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<i64>,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse<TemplateImportResult>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let mut bundle = Vec::new();
+    let mut folder_id = None;
+    let mut replace_email_templates = false;
+    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+        match field.name() {
+            Some("file") => bundle = field.bytes().await.unwrap_or_default().to_vec(),
+            Some("folder_id") => match field.text().await.unwrap_or_default().trim() {
+                "" => {}
+                value => match value.parse::<i64>() {
+                    Ok(id) => folder_id = Some(id),
+                    Err(_) => return ApiResponse::bad_request("folder_id must be a number".to_string()),
+                },
+            },
+            Some("email_templates") => replace_email_templates = field.text().await.unwrap_or_default().trim() == "replace",
+            _ => {}
+        }
+    }
+    if bundle.is_empty() {
+        return ApiResponse::bad_request("Bundle file is required".to_string());
+    }
+    if let Some(folder_id) = folder_id {
+        match TemplateFolderQueries::get_folder_by_id(pool, folder_id).await {
+            Ok(Some(folder)) if has_folder_access(pool, &folder, user_id, AccessLevel::Edit).await => {}
+            Ok(_) => return ApiResponse::not_found("Folder not found".to_string()),
+            Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve folder: {}", e)),
+        }
+    }
+
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+    match template_bundles::import_bundle(pool, &storage, user_id, &bundle, folder_id, replace_email_templates).await {
+        Ok(result) => {
+            let message = format!("Imported {} template(s) with {} conflict(s)", result.templates.len(), result.conflicts.len());
+            ApiResponse::created(result, message)
+        }
+        Err(e) => match e.downcast_ref::<template_bundles::InvalidBundle>() {
+            Some(invalid) => ApiResponse::bad_request(invalid.to_string()),
+            None => ApiResponse::internal_error(format!("Failed to import templates: {}", e)),
+        },
+    }
+}
+
+// ===== PUBLIC FILE UPLOAD ENDPOINT (for signing) =====
+
+#[utoipa::path(
+    post,
+    path = "/api/files/upload/public",
+    request_body = FileUploadRequest,
+    responses(
+        (status = 201, description = "File uploaded successfully", body = ApiResponse<FileUploadResponse>),
+        (status = 400, description = "Bad request - No file provided or invalid file type. Supported types: Images (JPG, PNG, GIF, WEBP, BMP, TIFF), Documents (PDF, DOCX, DOC, TXT, HTML, XLSX, XLS), Data (JSON, CSV, XML)", body = ApiResponse<FileUploadResponse>),
+        (status = 500, description = "Internal server error", body = ApiResponse<FileUploadResponse>)
+    ),
+    tag = "files"
+)]
+pub async fn upload_file_public(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse<FileUploadResponse>>) {
+    let _pool = &state.lock().await.db_pool;
+
+    // Initialize storage service
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+
+    let mut file_data = Vec::new();
+    let mut filename = String::new();
+    let mut content_type = String::new();
+
+    // Parse multipart form data
+    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+        let field_name = field.name().unwrap_or("").to_string();
+
+        match field_name.as_str() {
+            "file" => {
+                filename = field.file_name().unwrap_or("uploaded_file").to_string();
+                
+                // Determine content type from filename
+                content_type = get_content_type_from_filename(&filename).to_string();
+                
+                file_data = field.bytes().await.unwrap_or_default().to_vec();
+            }
+            _ => {}
+        }
+    }
+
+    if file_data.is_empty() {
+        return ApiResponse::bad_request("File is required".to_string());
+    }
+
+    // Validate file type - allow multiple file types including images, documents, and PDFs
+    let allowed_types = [
+        // Images
+        "image/jpeg",
+        "image/png", 
+        "image/gif",
+        "image/webp",
+        "image/bmp",
+        "image/tiff",
+        // Documents
+        "application/pdf",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document", // DOCX
+        "application/msword", // DOC
+        "text/plain", // TXT
+        "text/html", // HTML
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet", // XLSX
+        "application/vnd.ms-excel", // XLS
+        // Other common types
+        "application/json",
+        "text/csv",
+        "application/xml",
+        "text/xml"
+    ];
+
+    if !allowed_types.contains(&content_type.as_str()) {
+        return ApiResponse::bad_request(format!("File type not allowed. Supported types: Images (JPG, PNG, GIF, WEBP, BMP, TIFF), Documents (PDF, DOCX, DOC, TXT, HTML, XLSX, XLS), Data (JSON, CSV, XML). Detected type: {}", content_type));
+    }
+
+    // Upload file to storage
+    let file_key = match storage.upload_file(file_data.clone(), &filename, &content_type).await {
+        Ok(key) => key,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to upload file: {}", e)),
+    };
+
+    // Determine file type category
+    let file_type = if content_type.starts_with("image/") {
+        "image".to_string()
+    } else if content_type == "application/pdf" {
+        "pdf".to_string()
+    } else if content_type.starts_with("application/vnd.openxmlformats-officedocument.wordprocessingml") || content_type == "application/msword" {
+        "document".to_string()
+    } else if content_type.starts_with("application/vnd.openxmlformats-officedocument.spreadsheetml") || content_type == "application/vnd.ms-excel" {
+        "spreadsheet".to_string()
+    } else if content_type == "text/plain" {
+        "text".to_string()
+    } else if content_type == "text/html" {
+        "html".to_string()
+    } else if content_type == "application/json" {
+        "json".to_string()
+    } else if content_type == "text/csv" {
+        "csv".to_string()
+    } else if content_type.contains("xml") {
+        "xml".to_string()
+    } else {
+        "file".to_string()
+    };
+
+    // Generate file URL (direct S3 URL)
+    let file_url = storage.get_public_url(&file_key);
+
+    // Create response
+    let upload_response = FileUploadResponse {
+        id: file_key.clone(),
+        filename: filename.clone(),
+        file_type,
+        file_size: file_data.len() as i64,
+        url: file_url,
+        content_type,
+        uploaded_at: chrono::Utc::now(),
+    };
+
+    ApiResponse::created(upload_response, "File uploaded successfully".to_string())
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct DeleteFileRequest {
+    pub file_url: String,
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/files/delete/public",
+    request_body = DeleteFileRequest,
+    responses(
+        (status = 200, description = "File deleted successfully", body = ApiResponse<String>),
+        (status = 400, description = "Bad request - Invalid file URL", body = ApiResponse<String>),
+        (status = 500, description = "Internal server error", body = ApiResponse<String>)
+    ),
+    tag = "files"
+)]
+pub async fn delete_file_public(
+    State(_state): State<AppState>,
+    Json(payload): Json<DeleteFileRequest>,
+) -> (StatusCode, Json<ApiResponse<String>>) {
+    // Extract file key from URL
+    // URL format: /api/files/templates/1234567890_filename.ext
+    let file_key = if payload.file_url.starts_with("/api/files/") {
+        payload.file_url.trim_start_matches("/api/files/")
+    } else if payload.file_url.contains("/api/files/") {
+        // Handle full URL
+        payload.file_url.split("/api/files/").nth(1).unwrap_or("")
+    } else {
+        // Assume it's already a key
+        &payload.file_url
+    };
+
+    if file_key.is_empty() {
+        return ApiResponse::bad_request("Invalid file URL".to_string());
+    }
+
+    // Initialize storage service
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to initialize storage: {}", e)),
+    };
+
+    // Delete file from storage
+    match storage.delete_file(file_key).await {
+        Ok(_) => ApiResponse::success("File deleted successfully".to_string(), "File deleted successfully".to_string()),
+        Err(e) => {
+            eprintln!("Failed to delete file {}: {:?}", file_key, e);
+            ApiResponse::internal_error(format!("Failed to delete file: {}", e))
+        }
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/files/upload",
+    request_body = FileUploadRequest,
+    responses(
+        (status = 201, description = "File uploaded successfully", body = ApiResponse<FileUploadResponse>),
+        (status = 400, description = "Bad request - No file provided or invalid file type", body = ApiResponse<FileUploadResponse>),
+        (status = 500, description = "Internal server error", body = ApiResponse<FileUploadResponse>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "files"
+)]
+pub async fn upload_file(
     State(state): State<AppState>,
     Extension(user_id): Extension<i64>,
     mut multipart: Multipart,
@@ -3344,6 +5170,8 @@ pub async fn create_template_from_file(
         }])),
     };    match TemplateQueries::create_template(pool, create_template).await {
         Ok(db_template) => {
+            page_previews::pregenerate(&db_template);
+            template_search::index_in_background(pool, db_template.id);
             match convert_db_template_to_template_with_fields(db_template, pool).await {
                 Ok(template) => ApiResponse::created(template, "Template created from file successfully".to_string()),
                 Err(e) => ApiResponse::internal_error(format!("Failed to load template fields: {}", e))
@@ -3351,4 +5179,346 @@ pub async fn create_template_from_file(
         }
         Err(e) => ApiResponse::internal_error(format!("Failed to create template: {}", e))
     }
-}
\ No newline at end of file
+}
+// ===== ANALYTICS =====
+
+#[derive(serde::Deserialize)]
+pub struct TemplateAnalyticsQuery {
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/templates/{id}/analytics",
+    params(
+        ("id" = i64, Path, description = "Template ID"),
+        ("from" = Option<String>, Query, description = "Only count submitters sent at or after this time (RFC 3339)"),
+        ("to" = Option<String>, Query, description = "Only count submitters sent before this time (RFC 3339)")
+    ),
+    responses(
+        (status = 200, description = "Usage statistics of the template over the date range", body = ApiResponse<TemplateAnalytics>),
+        (status = 400, description = "Invalid date range", body = ApiResponse<TemplateAnalytics>),
+        (status = 404, description = "Template not found", body = ApiResponse<TemplateAnalytics>),
+        (status = 500, description = "Internal server error", body = ApiResponse<TemplateAnalytics>)
+    ),
+    security(("bearer_auth" = [])),
+    tag = "templates"
+)]
+pub async fn get_template_analytics(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(query): Query<TemplateAnalyticsQuery>,
+    Extension(user_id): Extension<i64>,
+) -> (StatusCode, Json<ApiResponse<TemplateAnalytics>>) {
+    let pool = &state.lock().await.db_pool;
+
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from >= to {
+            return ApiResponse::bad_request("'from' must be before 'to'".to_string());
+        }
+    }
+    match TemplateQueries::get_template_by_id(pool, id).await {
+        Ok(Some(db_template)) if has_template_access(pool, &db_template, user_id, AccessLevel::View).await => {}
+        Ok(_) => return ApiResponse::not_found("Template not found".to_string()),
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve template: {}", e)),
+    }
+
+    let submitters = match SubmitterQueries::get_template_submitter_activity(pool, id, query.from, query.to).await {
+        Ok(submitters) => submitters,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve submitters: {}", e)),
+    };
+    // Fields are only needed to find where abandoned drafts stopped
+    let now = chrono::Utc::now();
+    let abandoned_ids: Vec<i64> = submitters.iter()
+        .filter(|s| template_analytics::is_abandoned(s, now))
+        .map(|s| s.id)
+        .collect();
+    let fields = if abandoned_ids.is_empty() {
+        Vec::new()
+    } else {
+        match SubmissionFieldQueries::get_submission_fields_by_submitter_ids(pool, &abandoned_ids).await {
+            Ok(fields) => fields,
+            Err(e) => return ApiResponse::internal_error(format!("Failed to retrieve submission fields: {}", e)),
+        }
+    };
+
+    let analytics = template_analytics::template_analytics(id, query.from, query.to, &submitters, &fields, now);
+    ApiResponse::success(analytics, "Template analytics retrieved successfully".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::role::Role;
+    use crate::models::template_share::ShareRequest;
+
+    async fn create_user(pool: &sqlx::PgPool, account_id: i64, role: &str) -> i64 {
+        sqlx::query_scalar("INSERT INTO users (name, email, password_hash, account_id, role) VALUES ('Access', $1, '', $2, $3::user_role) RETURNING id")
+            .bind(format!("access-{}@example.com", uuid::Uuid::new_v4()))
+            .bind(account_id)
+            .bind(role)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    async fn create_account(pool: &sqlx::PgPool) -> i64 {
+        sqlx::query_scalar("INSERT INTO accounts (name, slug) VALUES ('Access', $1) RETURNING id")
+            .bind(format!("access-{}", uuid::Uuid::new_v4()))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    fn bearer(user_id: i64) -> axum::http::HeaderMap {
+        let token = crate::common::jwt::generate_jwt(user_id, "access@example.com", &Role::Member, "templates-test-secret").unwrap();
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        headers
+    }
+
+    // Needs DATABASE_URL pointing at a migrated database, skipped without one
+    #[tokio::test]
+    async fn template_documents_are_only_served_to_those_with_access() {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping");
+            return;
+        };
+        std::env::set_var("JWT_SECRET", "templates-test-secret");
+        let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+        let account_id = create_account(&pool).await;
+        let other_account_id = create_account(&pool).await;
+        let owner_id = create_user(&pool, account_id, "editor").await;
+        let outsider_id = create_user(&pool, other_account_id, "admin").await;
+
+        let key = format!("templates/{}.pdf", uuid::Uuid::new_v4());
+        let documents = serde_json::json!([{ "id": "0", "filename": "offer.pdf", "content_type": "application/pdf", "size": 8, "url": key }]);
+        let template = TemplateQueries::create_template(&pool, crate::database::models::CreateTemplate {
+            name: "Offer".to_string(),
+            slug: format!("offer-{}", uuid::Uuid::new_v4()),
+            user_id: owner_id,
+            account_id: Some(account_id),
+            folder_id: None,
+            documents: Some(documents),
+        }).await.unwrap();
+        let signer = SubmitterQueries::create_submitter(&pool, crate::database::models::CreateSubmitter {
+            template_id: template.id,
+            user_id: owner_id,
+            name: "Signer".to_string(),
+            email: "signer@example.com".to_string(),
+            status: "pending".to_string(),
+            token: uuid::Uuid::new_v4().to_string(),
+            reminder_config: None,
+        }).await.unwrap();
+        let config = page_previews::PreviewConfig::from_env();
+        let preview_key = config.preview_key(&key, config.default_size(), 1, &config.format);
+        let anonymous = axum::http::HeaderMap::new();
+
+        // Files that aren't template documents stay public by key
+        let upload = format!("templates/{}.png", uuid::Uuid::new_v4());
+        assert!(can_read_file(&pool, &upload, &anonymous, None).await.unwrap());
+
+        for key in [&key, &preview_key] {
+            assert!(!can_read_file(&pool, key, &anonymous, None).await.unwrap());
+            assert!(!can_read_file(&pool, key, &bearer(outsider_id), None).await.unwrap());
+            assert!(!can_read_file(&pool, key, &anonymous, Some("not-a-token")).await.unwrap());
+            assert!(can_read_file(&pool, key, &bearer(owner_id), None).await.unwrap());
+            assert!(can_read_file(&pool, key, &anonymous, Some(&signer.token)).await.unwrap());
+            assert!(can_read_file(&pool, &format!("/{}", key), &bearer(outsider_id), Some(&signer.token)).await.unwrap());
+        }
+
+        sqlx::query("DELETE FROM templates WHERE id = $1").bind(template.id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE id = ANY($1)").bind(vec![owner_id, outsider_id]).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM accounts WHERE id = ANY($1)").bind(vec![account_id, other_account_id]).execute(&pool).await.unwrap();
+    }
+
+    // The highest level has_template_access grants, None without access
+    async fn template_access(pool: &sqlx::PgPool, template: &crate::database::models::DbTemplate, user_id: i64) -> Option<AccessLevel> {
+        let mut granted = None;
+        for level in [AccessLevel::View, AccessLevel::Use, AccessLevel::Edit] {
+            if has_template_access(pool, template, user_id, level).await {
+                granted = Some(level);
+            }
+        }
+        granted
+    }
+
+    async fn folder_access(pool: &sqlx::PgPool, folder: &crate::database::models::DbTemplateFolder, user_id: i64) -> Option<AccessLevel> {
+        let mut granted = None;
+        for level in [AccessLevel::View, AccessLevel::Use, AccessLevel::Edit] {
+            if has_folder_access(pool, folder, user_id, level).await {
+                granted = Some(level);
+            }
+        }
+        granted
+    }
+
+    // Needs DATABASE_URL pointing at a migrated database, skipped without one
+    #[tokio::test]
+    async fn access_follows_ownership_roles_and_restricted_folders() {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping");
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+        let account_id = create_account(&pool).await;
+        let other_account_id = create_account(&pool).await;
+        let owner_id = create_user(&pool, account_id, "viewer").await;
+        let admin_id = create_user(&pool, account_id, "admin").await;
+        let editor_id = create_user(&pool, account_id, "editor").await;
+        let member_id = create_user(&pool, account_id, "member").await;
+        let agent_id = create_user(&pool, account_id, "agent").await;
+        let viewer_id = create_user(&pool, account_id, "viewer").await;
+        let outsider_id = create_user(&pool, other_account_id, "admin").await;
+
+        let create_folder = |name: &str, parent_folder_id: Option<i64>| TemplateFolderQueries::create_folder(&pool, crate::database::models::CreateTemplateFolder {
+            name: name.to_string(),
+            user_id: owner_id,
+            account_id: Some(account_id),
+            parent_folder_id,
+        });
+        let hr = create_folder("HR", None).await.unwrap();
+        let contracts = create_folder("Contracts", Some(hr.id)).await.unwrap();
+        let create_template = |folder_id: Option<i64>| TemplateQueries::create_template(&pool, crate::database::models::CreateTemplate {
+            name: "Offer".to_string(),
+            slug: format!("offer-{}", uuid::Uuid::new_v4()),
+            user_id: owner_id,
+            account_id: Some(account_id),
+            folder_id,
+            documents: None,
+        });
+        let open = create_template(None).await.unwrap();
+        let private = create_template(Some(contracts.id)).await.unwrap();
+
+        // Without restrictions everyone of the account gets the access of their role
+        let expected = [
+            (owner_id, Some(AccessLevel::Edit)),
+            (admin_id, Some(AccessLevel::Edit)),
+            (editor_id, Some(AccessLevel::Edit)),
+            (member_id, Some(AccessLevel::Use)),
+            (agent_id, Some(AccessLevel::Use)),
+            (viewer_id, Some(AccessLevel::View)),
+            (outsider_id, None),
+        ];
+        for (user_id, level) in expected {
+            assert_eq!(template_access(&pool, &open, user_id).await, level, "template, user {}", user_id);
+            assert_eq!(template_access(&pool, &private, user_id).await, level, "template in folder, user {}", user_id);
+            assert_eq!(folder_access(&pool, &hr, user_id).await, level, "folder, user {}", user_id);
+        }
+
+        // A restricted folder closes itself, its subfolders and their templates to everyone but the
+        // owner, admins and what it's shared with
+        let shares = [
+            ShareRequest { user_id: Some(agent_id), role: None, access_level: AccessLevel::View },
+            ShareRequest { user_id: None, role: Some(Role::Viewer), access_level: AccessLevel::Use },
+        ];
+        TemplateShareQueries::set_folder_sharing(&pool, hr.id, true, &shares, owner_id).await.unwrap();
+        let expected = [
+            (owner_id, Some(AccessLevel::Edit)),
+            (admin_id, Some(AccessLevel::Edit)),
+            (editor_id, None),
+            (member_id, None),
+            (agent_id, Some(AccessLevel::View)),
+            (viewer_id, Some(AccessLevel::Use)),
+            (outsider_id, None),
+        ];
+        for (user_id, level) in expected {
+            assert_eq!(template_access(&pool, &private, user_id).await, level, "restricted template, user {}", user_id);
+            assert_eq!(folder_access(&pool, &hr, user_id).await, level, "restricted folder, user {}", user_id);
+            assert_eq!(folder_access(&pool, &contracts, user_id).await, level, "restricted subfolder, user {}", user_id);
+        }
+        assert_eq!(template_access(&pool, &open, editor_id).await, Some(AccessLevel::Edit));
+
+        sqlx::query("DELETE FROM templates WHERE id = ANY($1)").bind(vec![open.id, private.id]).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM template_folders WHERE id = ANY($1)").bind(vec![contracts.id, hr.id]).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE account_id = ANY($1)").bind(vec![account_id, other_account_id]).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM accounts WHERE id = ANY($1)").bind(vec![account_id, other_account_id]).execute(&pool).await.unwrap();
+    }
+
+    fn app_state(pool: &sqlx::PgPool) -> AppState {
+        Arc::new(Mutex::new(crate::routes::web::AppStateData {
+            db_pool: pool.clone(),
+            payment_queue: crate::services::queue::PaymentQueue::new(Arc::new(Mutex::new(pool.clone()))),
+            otp_cache: crate::services::cache::OtpCache::new(),
+        }))
+    }
+
+    // Needs DATABASE_URL pointing at a migrated database, skipped without one
+    #[tokio::test]
+    async fn folders_move_as_subtrees_and_hand_their_contents_to_the_parent() {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping");
+            return;
+        };
+        let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+        let state = app_state(&pool);
+        let user_id: i64 = sqlx::query_scalar("INSERT INTO users (name, email, password_hash) VALUES ('Folders', $1, '') RETURNING id")
+            .bind(format!("folders-{}@example.com", uuid::Uuid::new_v4()))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let create_folder = |name: &str, parent_folder_id: Option<i64>| TemplateFolderQueries::create_folder(&pool, crate::database::models::CreateTemplateFolder {
+            name: name.to_string(),
+            user_id,
+            account_id: None,
+            parent_folder_id,
+        });
+        let hr = create_folder("HR", None).await.unwrap();
+        let contracts = create_folder("Contracts", Some(hr.id)).await.unwrap();
+        let archive = create_folder("Archive", Some(contracts.id)).await.unwrap();
+        let create_template = |folder_id: i64| TemplateQueries::create_template(&pool, crate::database::models::CreateTemplate {
+            name: "Offer".to_string(),
+            slug: format!("offer-{}", uuid::Uuid::new_v4()),
+            user_id,
+            account_id: None,
+            folder_id: Some(folder_id),
+            documents: None,
+        });
+        let policy = create_template(hr.id).await.unwrap();
+        let offer = create_template(contracts.id).await.unwrap();
+        let parent_of = |id: i64| {
+            let pool = pool.clone();
+            async move { TemplateFolderQueries::get_folder_by_id(&pool, id).await.unwrap().map(|folder| folder.parent_folder_id) }
+        };
+        let folder_of = |id: i64| {
+            let pool = pool.clone();
+            async move { TemplateQueries::get_template_by_id(&pool, id).await.unwrap().unwrap().folder_id }
+        };
+
+        // A folder can't go into itself or anywhere below it
+        for parent_folder_id in [hr.id, contracts.id, archive.id] {
+            let (status, _) = move_folder(State(state.clone()), Path(hr.id), Extension(user_id), Json(MoveFolderRequest { parent_folder_id: Some(parent_folder_id) })).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "moved into folder {}", parent_folder_id);
+        }
+        assert_eq!(parent_of(hr.id).await, Some(None));
+        let (status, _) = move_folder(State(state.clone()), Path(archive.id), Extension(user_id), Json(MoveFolderRequest { parent_folder_id: None })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(parent_of(archive.id).await, Some(None));
+        let (status, _) = move_folder(State(state.clone()), Path(archive.id), Extension(user_id), Json(MoveFolderRequest { parent_folder_id: Some(contracts.id) })).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(parent_of(archive.id).await, Some(Some(contracts.id)));
+
+        // Folders that aren't empty are only deleted when the contents may move
+        let delete = |id: i64, contents: Option<&str>| delete_folder(State(state.clone()), Path(id), Query(DeleteFolderQuery { contents: contents.map(str::to_string) }), Extension(user_id));
+        assert_eq!(delete(hr.id, None).await.0, StatusCode::CONFLICT);
+        assert_eq!(delete(hr.id, Some("refuse")).await.0, StatusCode::CONFLICT);
+        assert_eq!(delete(hr.id, Some("everything")).await.0, StatusCode::BAD_REQUEST);
+        assert_eq!(parent_of(hr.id).await, Some(None));
+        assert_eq!(folder_of(policy.id).await, Some(hr.id));
+
+        assert_eq!(delete(contracts.id, Some("move_to_parent")).await.0, StatusCode::OK);
+        assert_eq!(parent_of(contracts.id).await, None);
+        assert_eq!(parent_of(archive.id).await, Some(Some(hr.id)));
+        assert_eq!(folder_of(offer.id).await, Some(hr.id));
+
+        assert_eq!(delete(hr.id, Some("move_to_parent")).await.0, StatusCode::OK);
+        assert_eq!(parent_of(archive.id).await, Some(None));
+        assert_eq!(folder_of(policy.id).await, None);
+        assert_eq!(folder_of(offer.id).await, None);
+        assert_eq!(delete(archive.id, None).await.0, StatusCode::OK);
+        assert_eq!(parent_of(archive.id).await, None);
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+}
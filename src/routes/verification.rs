@@ -0,0 +1,394 @@
+use axum::{
+    extract::{Multipart, Path, State},
+    http::StatusCode,
+    response::Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use utoipa::ToSchema;
+
+use crate::common::responses::ApiResponse;
+use crate::database::models::{DbDocumentHash, DbSubmitter};
+use crate::database::queries::{DocumentHashQueries, SubmitterQueries, TemplateQueries};
+use crate::routes::web::AppState;
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct VerifiedSigner {
+    pub name: String,
+    pub email: String,
+    pub status: String,
+    pub signed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DocumentVerificationResponse {
+    /// Whether the PDF or signature ID matches a document we issued
+    pub verified: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature_id: Option<String>,
+    /// signed_document or audit_log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issued_at: Option<DateTime<Utc>>,
+    /// completed, declined or pending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub submission_status: Option<String>,
+    /// Status of the signer a signature ID belongs to: signed, completed, declined or pending
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signer_status: Option<String>,
+    pub signers: Vec<VerifiedSigner>,
+}
+
+impl DocumentVerificationResponse {
+    fn not_found(sha256: Option<String>, signature_id: Option<String>) -> Self {
+        Self {
+            verified: false,
+            sha256,
+            signature_id,
+            document_type: None,
+            document_name: None,
+            issued_at: None,
+            submission_status: None,
+            signer_status: None,
+            signers: Vec::new(),
+        }
+    }
+}
+
+fn submission_status(submitters: &[DbSubmitter]) -> String {
+    if submitters.iter().any(|s| s.status == "declined") {
+        "declined".to_string()
+    } else if !submitters.is_empty() && submitters.iter().all(|s| s.status == "completed" || s.status == "signed") {
+        "completed".to_string()
+    } else {
+        "pending".to_string()
+    }
+}
+
+/// Hex SHA-256 of a document, as recorded when it's issued and looked up when it's verified
+pub(crate) fn document_sha256(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn to_signer(submitter: &DbSubmitter) -> VerifiedSigner {
+    VerifiedSigner {
+        name: submitter.name.clone(),
+        email: submitter.email.clone(),
+        status: submitter.status.clone(),
+        signed_at: submitter.signed_at,
+    }
+}
+
+/// Verify an uploaded PDF against the hashes of documents we issued
+#[utoipa::path(
+    post,
+    path = "/public/verify",
+    request_body(content = String, description = "PDF file in the multipart field `file`", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Verification result", body = ApiResponse<DocumentVerificationResponse>),
+        (status = 400, description = "No file uploaded"),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "verification"
+)]
+pub async fn verify_document(
+    State(state): State<AppState>,
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse<DocumentVerificationResponse>>) {
+    let pool = &state.lock().await.db_pool;
+
+    let mut data = None;
+    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+        if field.name() == Some("file") {
+            data = field.bytes().await.ok();
+        }
+    }
+    let data = match data {
+        Some(data) if !data.is_empty() => data,
+        _ => return ApiResponse::bad_request("No file uploaded".to_string()),
+    };
+
+    let sha256 = document_sha256(&data);
+    let record = match DocumentHashQueries::get_document_hash_by_sha256(pool, &sha256).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            return ApiResponse::success(
+                DocumentVerificationResponse::not_found(Some(sha256), None),
+                "Document does not match any issued document".to_string(),
+            )
+        }
+        Err(e) => return ApiResponse::internal_error(format!("Failed to verify document: {}", e)),
+    };
+
+    let template = match TemplateQueries::get_template_by_id(pool, record.template_id).await {
+        Ok(template) => template,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to get template: {}", e)),
+    };
+    let submitters = match SubmitterQueries::get_submitters_by_template_id(pool, record.template_id).await {
+        Ok(submitters) => submitters,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to get submitters: {}", e)),
+    };
+
+    let response = verified_document(record, template.map(|t| t.name), &submitters);
+    ApiResponse::success(response, "Document matches an issued document".to_string())
+}
+
+// Signature IDs are printed in upper case, but may be typed in any case
+fn normalize_signature_id(signature_id: &str) -> String {
+    signature_id.trim().to_uppercase()
+}
+
+fn verified_document(record: DbDocumentHash, document_name: Option<String>, submitters: &[DbSubmitter]) -> DocumentVerificationResponse {
+    // Per-submitter copies only list that submitter
+    let signers: Vec<VerifiedSigner> = submitters.iter()
+        .filter(|s| record.submitter_id.is_none_or(|id| s.id == id))
+        .map(to_signer)
+        .collect();
+
+    DocumentVerificationResponse {
+        verified: true,
+        sha256: Some(record.sha256),
+        signature_id: None,
+        document_type: Some(record.document_type),
+        document_name,
+        issued_at: Some(record.created_at),
+        submission_status: Some(submission_status(submitters)),
+        signer_status: None,
+        signers,
+    }
+}
+
+// Only a signer who signed verifies; one whose signature was since declined or reset doesn't
+fn verified_signature(
+    signature_id: String,
+    submitter: &DbSubmitter,
+    document_name: Option<String>,
+    submitters: &[DbSubmitter],
+) -> DocumentVerificationResponse {
+    DocumentVerificationResponse {
+        verified: submitter.status == "signed" || submitter.status == "completed",
+        sha256: None,
+        signature_id: Some(signature_id),
+        document_type: Some("signed_document".to_string()),
+        document_name,
+        issued_at: submitter.signed_at,
+        submission_status: Some(submission_status(submitters)),
+        signer_status: Some(submitter.status.clone()),
+        signers: vec![to_signer(submitter)],
+    }
+}
+
+/// Look up the signature ID printed next to a signature
+#[utoipa::path(
+    get,
+    path = "/public/verify/{signature_id}",
+    params(
+        ("signature_id" = String, Path, description = "Signature ID printed on the document")
+    ),
+    responses(
+        (status = 200, description = "Verification result", body = ApiResponse<DocumentVerificationResponse>),
+        (status = 500, description = "Internal server error")
+    ),
+    tag = "verification"
+)]
+pub async fn verify_signature_id(
+    State(state): State<AppState>,
+    Path(signature_id): Path<String>,
+) -> (StatusCode, Json<ApiResponse<DocumentVerificationResponse>>) {
+    let pool = &state.lock().await.db_pool;
+    let signature_id = normalize_signature_id(&signature_id);
+
+    let submitter = match SubmitterQueries::get_submitter_by_signature_id(pool, &signature_id).await {
+        Ok(Some(submitter)) => submitter,
+        Ok(None) => {
+            return ApiResponse::success(
+                DocumentVerificationResponse::not_found(None, Some(signature_id)),
+                "Signature ID does not match any signature".to_string(),
+            )
+        }
+        Err(e) => return ApiResponse::internal_error(format!("Failed to verify signature ID: {}", e)),
+    };
+
+    let template = match TemplateQueries::get_template_by_id(pool, submitter.template_id).await {
+        Ok(template) => template,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to get template: {}", e)),
+    };
+    let submitters = match SubmitterQueries::get_submitters_by_template_id(pool, submitter.template_id).await {
+        Ok(submitters) => submitters,
+        Err(e) => return ApiResponse::internal_error(format!("Failed to get submitters: {}", e)),
+    };
+
+    let response = verified_signature(signature_id, &submitter, template.map(|t| t.name), &submitters);
+    let message = if response.verified {
+        "Signature ID matches a signature"
+    } else {
+        "Signature ID belongs to a signer who has not signed"
+    };
+    ApiResponse::success(response, message.to_string())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct PdfaValidationResponse {
+    pub conformant: bool,
+    pub issues: Vec<String>,
+}
+
+/// Check an uploaded PDF against PDF/A-2b
+#[utoipa::path(
+    post,
+    path = "/api/documents/validate-pdfa",
+    request_body(content = String, description = "PDF file in the multipart field `file`", content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Validation report", body = ApiResponse<PdfaValidationResponse>),
+        (status = 400, description = "No file uploaded"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(("bearer_auth" = [])),
+    tag = "verification"
+)]
+pub async fn validate_pdfa(
+    mut multipart: Multipart,
+) -> (StatusCode, Json<ApiResponse<PdfaValidationResponse>>) {
+    let mut data = None;
+    while let Some(field) = multipart.next_field().await.unwrap_or(None) {
+        if field.name() == Some("file") {
+            data = field.bytes().await.ok();
+        }
+    }
+    let data = match data {
+        Some(data) if !data.is_empty() => data,
+        _ => return ApiResponse::bad_request("No file uploaded".to_string()),
+    };
+
+    let issues = crate::services::pdfa::validate_pdfa(&data);
+    let response = PdfaValidationResponse { conformant: issues.is_empty(), issues };
+    ApiResponse::success(response, "PDF/A validation completed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submitter(id: i64, status: &str) -> DbSubmitter {
+        DbSubmitter {
+            id,
+            template_id: 1,
+            user_id: 1,
+            name: format!("Signer {}", id),
+            email: format!("signer{}@example.com", id),
+            status: status.to_string(),
+            signed_at: None,
+            token: format!("token-{}", id),
+            bulk_signatures: None,
+            ip_address: None,
+            user_agent: None,
+            session_id: None,
+            viewed_at: None,
+            timezone: None,
+            reminder_config: None,
+            last_reminder_sent_at: None,
+            reminder_count: 0,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            decline_reason: None,
+            template_name: None,
+        }
+    }
+
+    // Needs DATABASE_URL pointing at a migrated database, skipped without one
+    #[tokio::test]
+    async fn issued_documents_and_signature_ids_are_found() {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping");
+            return;
+        };
+        std::env::set_var("SIGNATURE_ID_SECRET", "verification-test-secret");
+        let pool = sqlx::PgPool::connect(&database_url).await.unwrap();
+        let user_id: i64 = sqlx::query_scalar("INSERT INTO users (name, email, password_hash) VALUES ('Verify', $1, '') RETURNING id")
+            .bind(format!("verify-{}@example.com", uuid::Uuid::new_v4()))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let template = TemplateQueries::create_template(&pool, crate::database::models::CreateTemplate {
+            name: "Offer".to_string(),
+            slug: format!("offer-{}", uuid::Uuid::new_v4()),
+            user_id,
+            account_id: None,
+            folder_id: None,
+            documents: None,
+        }).await.unwrap();
+        let signer = SubmitterQueries::create_submitter(&pool, crate::database::models::CreateSubmitter {
+            template_id: template.id,
+            user_id,
+            name: "Signer".to_string(),
+            email: "signer@example.com".to_string(),
+            status: "pending".to_string(),
+            token: uuid::Uuid::new_v4().to_string(),
+            reminder_config: None,
+        }).await.unwrap();
+        let signature_id = crate::common::token::signature_id(signer.id);
+        SubmitterQueries::update_submitter_with_signatures(&pool, signer.id, &serde_json::json!([]), None, None, None, None, &signature_id)
+            .await.unwrap();
+
+        // Two copies of the same document, each signed at a different time
+        let copies = [b"%PDF-1.7\n% first copy\n%%EOF\n".to_vec(), b"%PDF-1.7\n% second copy\n%%EOF\n".to_vec()];
+        for copy in &copies {
+            DocumentHashQueries::create_document_hash(&pool, crate::database::models::CreateDocumentHash {
+                template_id: template.id,
+                submitter_id: Some(signer.id),
+                document_type: "signed_document".to_string(),
+                file_name: "offer.pdf".to_string(),
+                sha256: document_sha256(copy),
+                size_bytes: copy.len() as i64,
+            }).await.unwrap();
+        }
+        let submitters = SubmitterQueries::get_submitters_by_template_id(&pool, template.id).await.unwrap();
+        for copy in &copies {
+            let record = DocumentHashQueries::get_document_hash_by_sha256(&pool, &document_sha256(copy)).await.unwrap()
+                .expect("every issued copy verifies");
+            let response = verified_document(record, Some(template.name.clone()), &submitters);
+            assert_eq!(response.submission_status.as_deref(), Some("completed"));
+            assert_eq!(response.signers.iter().map(|s| s.email.as_str()).collect::<Vec<_>>(), vec!["signer@example.com"]);
+        }
+        let mut altered = copies[0].clone();
+        altered[10] ^= 1;
+        assert!(DocumentHashQueries::get_document_hash_by_sha256(&pool, &document_sha256(&altered)).await.unwrap().is_none());
+
+        let typed = normalize_signature_id(&signature_id.to_lowercase());
+        let found = SubmitterQueries::get_submitter_by_signature_id(&pool, &typed).await.unwrap();
+        assert_eq!(found.map(|s| s.id), Some(signer.id));
+
+        sqlx::query("DELETE FROM templates WHERE id = $1").bind(template.id).execute(&pool).await.unwrap();
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+    }
+
+    #[test]
+    fn only_signers_who_signed_verify() {
+        let submitters = vec![submitter(1, "signed"), submitter(2, "pending"), submitter(3, "declined")];
+        let verified: Vec<(bool, Option<String>)> = submitters.iter()
+            .map(|s| verified_signature("ID".to_string(), s, None, &submitters))
+            .map(|response| (response.verified, response.signer_status))
+            .collect();
+        assert_eq!(verified, vec![
+            (true, Some("signed".to_string())),
+            (false, Some("pending".to_string())),
+            (false, Some("declined".to_string())),
+        ]);
+    }
+
+    #[test]
+    fn signature_ids_are_found_in_any_case() {
+        std::env::set_var("SIGNATURE_ID_SECRET", "verification-test-secret");
+        let signature_id = crate::common::token::signature_id(7);
+        assert_eq!(signature_id.len(), 36);
+        assert_eq!(signature_id, crate::common::token::signature_id(7));
+        assert_ne!(signature_id, crate::common::token::signature_id(8));
+        assert_eq!(normalize_signature_id(&format!(" {} ", signature_id.to_lowercase())), signature_id);
+    }
+}
@@ -47,7 +47,10 @@ use crate::routes::stripe_webhook;
 use crate::routes::reminder_settings;
 use crate::routes::global_settings;
 use crate::routes::email_templates;
+use crate::routes::field_snippets;
 use crate::routes::team;
+use crate::routes::search;
+use crate::routes::verification;
 use crate::common::jwt::{generate_jwt, auth_middleware};
 
 pub fn create_router() -> Router<AppState> {
@@ -72,11 +75,14 @@ pub fn create_router() -> Router<AppState> {
         // .route("/subscription/payment-link", get(subscription::get_payment_link))
         .route("/auth/2fa/setup", get(setup_2fa_handler))
         .route("/auth/2fa/verify", post(verify_2fa_handler))
+        .route("/documents/validate-pdfa", post(verification::validate_pdfa))
         .merge(submissions::create_submission_router())
         .merge(reminder_settings::create_router())
         .merge(global_settings::create_router())
         .merge(email_templates::create_router())
         .merge(team::create_router())
+        .merge(search::create_router())
+        .merge(field_snippets::create_router())
         .layer(middleware::from_fn(auth_middleware));
 
     let public_routes = Router::new()
@@ -103,10 +109,13 @@ pub fn create_router() -> Router<AppState> {
         .route("/auth/google_oauth2/callback", get(google_oauth_callback))
         .route("/public/submissions/:token", get(submitters::get_public_submitter).put(submitters::update_public_submitter))
         .route("/public/submissions/:token/fields", get(submitters::get_public_submitter_fields))
+        .route("/public/submissions/:token/draft", get(submitters::get_public_submitter_draft).put(submitters::save_public_submitter_draft))
         .route("/public/submissions/:token/signatures", get(submitters::get_public_submitter_signatures))
         .route("/public/signatures/bulk/:token", post(submitters::submit_bulk_signatures))
         .route("/public/submissions/:token/resubmit", put(submitters::resubmit_submitter))
         .route("/public/submissions/:token/send-copy", post(submitters::send_copy_email))
+        .route("/public/verify", post(verification::verify_document))
+        .route("/public/verify/:signature_id", get(verification::verify_signature_id))
         .route("/api/submitters/:token/audit-log", get(submitters::get_submitter_audit_log));
     
     println!("Final router created");
@@ -697,6 +706,7 @@ pub async fn forgot_password_handler(
                                 message: "Internal server error".to_string(),
                                 data: None,
                                 error: Some("Email service unavailable".to_string()),
+                                pagination: None,
                             }));
                         }
                     };
@@ -710,6 +720,7 @@ pub async fn forgot_password_handler(
                                 "message": "Password reset OTP sent to your email"
                             })),
                             error: None,
+                            pagination: None,
                         })),
                         Err(e) => {
                             eprintln!("Failed to send OTP email: {:?}", e);
@@ -719,6 +730,7 @@ pub async fn forgot_password_handler(
                                 message: "Internal server error".to_string(),
                                 data: None,
                                 error: Some("Failed to send OTP email".to_string()),
+                                pagination: None,
                             }))
                         }
                     }
@@ -731,6 +743,7 @@ pub async fn forgot_password_handler(
                         message: "Internal server error".to_string(),
                         data: None,
                         error: Some("Failed to generate OTP".to_string()),
+                        pagination: None,
                     }))
                 }
             }
@@ -741,6 +754,7 @@ pub async fn forgot_password_handler(
             message: "Bad request".to_string(),
             data: None,
             error: Some("User with this email not found".to_string()),
+            pagination: None,
         })),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
             success: false,
@@ -748,6 +762,7 @@ pub async fn forgot_password_handler(
             message: "Internal server error".to_string(),
             data: None,
             error: Some(format!("Database error: {}", e)),
+            pagination: None,
         })),
     }
 }
@@ -779,6 +794,7 @@ pub async fn verify_reset_code_handler(
                 "message": "OTP is valid"
             })),
             error: None,
+            pagination: None,
         })),
         Ok(false) => (StatusCode::BAD_REQUEST, Json(ApiResponse {
             success: false,
@@ -786,6 +802,7 @@ pub async fn verify_reset_code_handler(
             message: "Bad request".to_string(),
             data: None,
             error: Some("Invalid or expired OTP".to_string()),
+            pagination: None,
         })),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
             success: false,
@@ -793,6 +810,7 @@ pub async fn verify_reset_code_handler(
             message: "Internal server error".to_string(),
             data: None,
             error: Some(format!("Verification error: {}", e)),
+            pagination: None,
         })),
     }
 }
@@ -834,6 +852,7 @@ pub async fn reset_password_handler(
                                         "message": "Password reset successfully"
                                     })),
                                     error: None,
+                                    pagination: None,
                                 })),
                                 Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
                                     success: false,
@@ -841,6 +860,7 @@ pub async fn reset_password_handler(
                                     message: "Internal server error".to_string(),
                                     data: None,
                                     error: Some(format!("Failed to update password: {}", e)),
+                                    pagination: None,
                                 })),
                             }
                         }
@@ -850,6 +870,7 @@ pub async fn reset_password_handler(
                             message: "Internal server error".to_string(),
                             data: None,
                             error: Some("Failed to hash new password".to_string()),
+                            pagination: None,
                         })),
                     }
                 }
@@ -859,6 +880,7 @@ pub async fn reset_password_handler(
                     message: "Bad request".to_string(),
                     data: None,
                     error: Some("User not found".to_string()),
+                    pagination: None,
                 })),
                 Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
                     success: false,
@@ -866,6 +888,7 @@ pub async fn reset_password_handler(
                     message: "Internal server error".to_string(),
                     data: None,
                     error: Some(format!("Database error: {}", e)),
+                    pagination: None,
                 })),
             }
         }
@@ -875,6 +898,7 @@ pub async fn reset_password_handler(
             message: "Bad request".to_string(),
             data: None,
             error: Some("Invalid or expired OTP".to_string()),
+            pagination: None,
         })),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
             success: false,
@@ -882,6 +906,7 @@ pub async fn reset_password_handler(
             message: "Internal server error".to_string(),
             data: None,
             error: Some(format!("Verification error: {}", e)),
+            pagination: None,
         })),
     }
 }
@@ -1669,6 +1694,9 @@ pub struct UpdateBasicSettingsRequest {
     pub completion_body: Option<String>,
     pub redirect_title: Option<String>,
     pub redirect_url: Option<String>,
+    pub pdf_render_mode: Option<String>,
+    pub flatten_form_fields: Option<bool>,
+    pub pdfa_output: Option<bool>,
 }
 
 // Get basic settings handler
@@ -1724,6 +1752,12 @@ pub async fn update_basic_settings_handler(
 ) -> (StatusCode, Json<ApiResponse<String>>) {
     let pool = &state.lock().await.db_pool;
 
+    if let Some(mode) = &payload.pdf_render_mode {
+        if !crate::services::acroform::is_valid_render_mode(mode) {
+            return ApiResponse::bad_request(format!("Invalid pdf_render_mode '{}', expected 'stamp' or 'acroform'", mode));
+        }
+    }
+
     // Get current settings
     let current_settings = match GlobalSettingsQueries::get_global_settings(pool).await {
         Ok(Some(settings)) => settings,
@@ -1762,6 +1796,9 @@ pub async fn update_basic_settings_handler(
         completion_body: payload.completion_body.or_else(|| current_settings.completion_body.clone()),
         redirect_title: payload.redirect_title.or_else(|| current_settings.redirect_title.clone()),
         redirect_url: payload.redirect_url.or_else(|| current_settings.redirect_url.clone()),
+        pdf_render_mode: payload.pdf_render_mode.or_else(|| Some(current_settings.pdf_render_mode.clone())),
+        flatten_form_fields: payload.flatten_form_fields.or(Some(current_settings.flatten_form_fields)),
+        pdfa_output: payload.pdfa_output.or(Some(current_settings.pdfa_output)),
     };
 
     match GlobalSettingsQueries::update_global_settings(pool, update_data).await {
@@ -0,0 +1,953 @@
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use std::collections::HashSet;
+
+// Render modes for completed documents
+pub const RENDER_MODE_STAMP: &str = "stamp";
+pub const RENDER_MODE_ACROFORM: &str = "acroform";
+
+pub fn is_valid_render_mode(mode: &str) -> bool {
+    mode == RENDER_MODE_STAMP || mode == RENDER_MODE_ACROFORM
+}
+
+// Field types whose values are images/drawings - these are always stamped
+const STAMP_ONLY_FIELD_TYPES: &[&str] = &["signature", "initials", "image", "file", "stamp"];
+
+// Field flags (PDF 32000-1, 12.7.3.1 / 12.7.4.2 / 12.7.4.3)
+const FF_MULTILINE: i64 = 1 << 12;
+const FF_RADIO: i64 = 1 << 15;
+const FF_PUSHBUTTON: i64 = 1 << 16;
+const FF_MULTISELECT: i64 = 1 << 21;
+// Annotation flags
+const ANNOT_HIDDEN: i64 = 1 << 1;
+
+const DEFAULT_FONT_NAME: &[u8] = b"Helv";
+const CHECK_FONT_NAME: &[u8] = b"ZaDb";
+
+// Value coming from bulk_signatures for a named template field
+pub struct FormFieldValue<'a> {
+    pub name: &'a str,
+    pub field_type: &'a str,
+    pub value: &'a str,
+}
+
+// A terminal AcroForm field with its inherited attributes resolved
+struct FormField {
+    id: ObjectId,
+    full_name: String,
+    partial_name: String,
+    field_type: Vec<u8>,
+    flags: i64,
+    default_appearance: Option<String>,
+    quadding: i64,
+    widgets: Vec<ObjectId>,
+}
+
+/// Write values into the PDF's native AcroForm fields.
+///
+/// Values are matched to fields by fully qualified name (falling back to the partial name),
+/// appearance streams are generated for every filled widget, and the form is optionally
+/// flattened into the page content. Returns the new PDF together with the names of the
+/// values that were written, so the caller can stamp whatever is left.
+pub fn fill_form_fields(
+    pdf_bytes: &[u8],
+    values: &[FormFieldValue],
+    flatten: bool,
+) -> Result<(Vec<u8>, HashSet<String>), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut doc = Document::load_mem(pdf_bytes)?;
+
+    let acroform = match get_acroform(&doc) {
+        Some(acroform) => acroform,
+        None => return Ok((pdf_bytes.to_vec(), HashSet::new())),
+    };
+
+    let root_fields = match acroform.get(b"Fields").and_then(|f| resolve(&doc, f).as_array()) {
+        Ok(fields) => fields.iter().filter_map(|f| f.as_reference().ok()).collect::<Vec<_>>(),
+        Err(_) => Vec::new(),
+    };
+    let acroform_da = acroform.get(b"DA").ok().and_then(|da| object_to_string(resolve(&doc, da)));
+    let acroform_q = acroform.get(b"Q").and_then(|q| q.as_i64()).unwrap_or(0);
+
+    let mut fields = Vec::new();
+    let mut visited = HashSet::new();
+    for field_id in root_fields {
+        collect_fields(&doc, field_id, "", None, 0, acroform_da.clone(), acroform_q, &mut fields, &mut visited);
+    }
+
+    let font_id = ensure_default_resources(&mut doc)?;
+
+    let mut filled = HashSet::new();
+    for value in values {
+        if STAMP_ONLY_FIELD_TYPES.contains(&value.field_type) {
+            continue;
+        }
+
+        let field = fields
+            .iter()
+            .find(|f| f.full_name == value.name)
+            .or_else(|| fields.iter().find(|f| f.partial_name == value.name));
+
+        let field = match field {
+            Some(field) => field,
+            None => continue,
+        };
+
+        let result = match field.field_type.as_slice() {
+            b"Tx" | b"Ch" => fill_text_field(&mut doc, field, value, font_id),
+            b"Btn" if field.flags & FF_PUSHBUTTON == 0 => fill_button_field(&mut doc, field, value),
+            _ => Ok(false),
+        };
+
+        // Values a field can't hold are left to the stamping fallback
+        match result {
+            Ok(true) => {
+                filled.insert(value.name.to_string());
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Failed to fill AcroForm field '{}': {}", field.full_name, e),
+        }
+    }
+
+    // XFA data would take precedence over the AcroForm values in some viewers
+    if let Some(acroform_dict) = get_acroform_mut(&mut doc) {
+        acroform_dict.remove(b"XFA");
+        acroform_dict.set("NeedAppearances", Object::Boolean(false));
+    }
+
+    if flatten {
+        flatten_form(&mut doc)?;
+    }
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok((output, filled))
+}
+
+fn resolve<'a>(doc: &'a Document, object: &'a Object) -> &'a Object {
+    doc.dereference(object).map(|(_, o)| o).unwrap_or(object)
+}
+
+fn get_acroform(doc: &Document) -> Option<Dictionary> {
+    let catalog = doc.catalog().ok()?;
+    let acroform = catalog.get(b"AcroForm").ok()?;
+    resolve(doc, acroform).as_dict().ok().cloned()
+}
+
+fn get_acroform_mut(doc: &mut Document) -> Option<&mut Dictionary> {
+    let acroform_ref = doc.catalog().ok()?.get(b"AcroForm").ok()?.as_reference().ok();
+    match acroform_ref {
+        Some(id) => doc.get_dictionary_mut(id).ok(),
+        None => doc.catalog_mut().ok()?.get_mut(b"AcroForm").ok()?.as_dict_mut().ok(),
+    }
+}
+
+fn object_to_string(object: &Object) -> Option<String> {
+    match object {
+        Object::String(bytes, _) => Some(decode_text_string(bytes)),
+        Object::Name(name) => Some(String::from_utf8_lossy(name).to_string()),
+        _ => None,
+    }
+}
+
+// Decode a PDF text string (UTF-16BE with BOM, otherwise PDFDocEncoding ~ Latin-1)
+fn decode_text_string(bytes: &[u8]) -> String {
+    if bytes.len() >= 2 && bytes[0] == 0xFE && bytes[1] == 0xFF {
+        let units: Vec<u16> = bytes[2..]
+            .chunks(2)
+            .map(|c| if c.len() == 2 { u16::from_be_bytes([c[0], c[1]]) } else { c[0] as u16 })
+            .collect();
+        String::from_utf16_lossy(&units)
+    } else {
+        bytes.iter().map(|&b| b as char).collect()
+    }
+}
+
+// Encode a text string for a /V entry
+fn encode_text_string(text: &str) -> Object {
+    if text.chars().all(|c| (c as u32) < 0x80) {
+        Object::String(text.as_bytes().to_vec(), StringFormat::Literal)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn collect_fields(
+    doc: &Document,
+    id: ObjectId,
+    parent_name: &str,
+    inherited_ft: Option<Vec<u8>>,
+    inherited_ff: i64,
+    inherited_da: Option<String>,
+    inherited_q: i64,
+    out: &mut Vec<FormField>,
+    visited: &mut HashSet<ObjectId>,
+) {
+    if !visited.insert(id) {
+        return;
+    }
+    let dict = match doc.get_dictionary(id) {
+        Ok(dict) => dict,
+        Err(_) => return,
+    };
+
+    let partial_name = dict.get(b"T").ok().and_then(|t| object_to_string(resolve(doc, t)));
+    let full_name = match &partial_name {
+        Some(name) if parent_name.is_empty() => name.clone(),
+        Some(name) => format!("{}.{}", parent_name, name),
+        None => parent_name.to_string(),
+    };
+    let field_type = dict.get(b"FT").ok().and_then(|ft| resolve(doc, ft).as_name().ok().map(|n| n.to_vec())).or(inherited_ft);
+    let flags = dict.get(b"Ff").and_then(|ff| resolve(doc, ff).as_i64()).unwrap_or(inherited_ff);
+    let default_appearance = dict.get(b"DA").ok().and_then(|da| object_to_string(resolve(doc, da))).or(inherited_da);
+    let quadding = dict.get(b"Q").and_then(|q| resolve(doc, q).as_i64()).unwrap_or(inherited_q);
+
+    let kids = dict
+        .get(b"Kids")
+        .and_then(|k| resolve(doc, k).as_array())
+        .map(|kids| kids.iter().filter_map(|k| k.as_reference().ok()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    // Kids carrying their own /T are fields, the rest are widget annotations of this field
+    let (child_fields, widgets): (Vec<ObjectId>, Vec<ObjectId>) = kids.into_iter().partition(|kid| {
+        doc.get_dictionary(*kid).map(|k| k.has(b"T")).unwrap_or(false)
+    });
+
+    if !child_fields.is_empty() {
+        // Non-terminal field - values live on the descendants
+        for child in child_fields {
+            collect_fields(doc, child, &full_name, field_type.clone(), flags, default_appearance.clone(), quadding, out, visited);
+        }
+        return;
+    }
+
+    let is_widget = dict.get(b"Subtype").and_then(|s| s.as_name()).map(|s| s == b"Widget").unwrap_or(false);
+    let mut widgets = widgets;
+    if is_widget {
+        widgets.insert(0, id);
+    }
+
+    if let Some(field_type) = field_type {
+        out.push(FormField {
+            id,
+            full_name,
+            partial_name: partial_name.unwrap_or_default(),
+            field_type,
+            flags,
+            default_appearance,
+            quadding,
+            widgets,
+        });
+    }
+}
+
+// Make sure /AcroForm /DR contains Helvetica and ZapfDingbats, returns the Helvetica font id
+fn ensure_default_resources(doc: &mut Document) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let dr = get_acroform(doc)
+        .and_then(|acroform| acroform.get(b"DR").ok().cloned())
+        .map(|dr| resolve(doc, &dr).clone());
+    let mut dr = match dr {
+        Some(Object::Dictionary(dict)) => dict,
+        _ => Dictionary::new(),
+    };
+    let mut fonts = match dr.get(b"Font").map(|f| resolve(doc, f).clone()) {
+        Ok(Object::Dictionary(dict)) => dict,
+        _ => Dictionary::new(),
+    };
+
+    let helv_id = match fonts.get(DEFAULT_FONT_NAME).and_then(|f| f.as_reference()) {
+        Ok(id) => id,
+        Err(_) => {
+            let id = doc.add_object(standard_font("Helvetica", true));
+            fonts.set(DEFAULT_FONT_NAME, Object::Reference(id));
+            id
+        }
+    };
+    if !fonts.has(CHECK_FONT_NAME) {
+        let id = doc.add_object(standard_font("ZapfDingbats", false));
+        fonts.set(CHECK_FONT_NAME, Object::Reference(id));
+    }
+    dr.set("Font", Object::Dictionary(fonts));
+
+    if let Some(acroform) = get_acroform_mut(doc) {
+        acroform.set("DR", Object::Dictionary(dr));
+    }
+    Ok(helv_id)
+}
+
+pub(crate) fn standard_font(base_font: &str, win_ansi: bool) -> Object {
+    let mut font = Dictionary::new();
+    font.set("Type", Object::Name(b"Font".to_vec()));
+    font.set("Subtype", Object::Name(b"Type1".to_vec()));
+    font.set("BaseFont", Object::Name(base_font.as_bytes().to_vec()));
+    if win_ansi {
+        font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+    }
+    Object::Dictionary(font)
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(value.trim().to_lowercase().as_str(), "true" | "1" | "yes" | "on" | "checked" | "x")
+}
+
+fn fill_text_field(
+    doc: &mut Document,
+    field: &FormField,
+    value: &FormFieldValue,
+    font_id: ObjectId,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let text = value.value.to_string();
+    // Appearances use Helvetica with WinAnsiEncoding; other scripts (Vietnamese, CJK, ...) are
+    // stamped with an embedded Unicode font instead of being written as '?'
+    if !is_win_ansi_text(&text) {
+        return Ok(false);
+    }
+
+    // Multi-select list boxes take an array of options
+    let field_value = if field.field_type == b"Ch" && field.flags & FF_MULTISELECT != 0 && text.contains(',') {
+        Object::Array(text.split(',').map(|v| encode_text_string(v.trim())).collect())
+    } else {
+        encode_text_string(&text)
+    };
+
+    {
+        let field_dict = doc.get_dictionary_mut(field.id)?;
+        field_dict.set("V", field_value);
+    }
+
+    let multiline = field.field_type == b"Tx" && field.flags & FF_MULTILINE != 0;
+    for widget_id in &field.widgets {
+        let (rect, rotation) = {
+            let widget = doc.get_dictionary(*widget_id)?;
+            (widget_rect(doc, widget), widget_rotation(doc, widget))
+        };
+        let rect = match rect {
+            Some(rect) => rect,
+            None => continue,
+        };
+        let appearance = build_text_appearance(&text, rect, rotation, field.default_appearance.as_deref(), field.quadding, multiline, font_id);
+        let appearance_id = doc.add_object(appearance);
+
+        let widget = doc.get_dictionary_mut(*widget_id)?;
+        let mut ap = Dictionary::new();
+        ap.set("N", Object::Reference(appearance_id));
+        widget.set("AP", Object::Dictionary(ap));
+    }
+
+    Ok(true)
+}
+
+fn fill_button_field(
+    doc: &mut Document,
+    field: &FormField,
+    value: &FormFieldValue,
+) -> Result<bool, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let is_radio = field.flags & FF_RADIO != 0;
+
+    // Export values are the non-Off appearance state names of each widget
+    let mut widget_states = Vec::new();
+    for widget_id in &field.widgets {
+        let widget = doc.get_dictionary(*widget_id)?;
+        let state = appearance_states(doc, widget).into_iter().find(|s| s != b"Off");
+        widget_states.push((*widget_id, state));
+    }
+
+    let selected: Option<Vec<u8>> = if is_radio {
+        let wanted = value.value.trim();
+        widget_states
+            .iter()
+            .filter_map(|(_, s)| s.clone())
+            .find(|s| String::from_utf8_lossy(s).eq_ignore_ascii_case(wanted))
+    } else if is_truthy(value.value) {
+        Some(widget_states.iter().find_map(|(_, s)| s.clone()).unwrap_or_else(|| b"Yes".to_vec()))
+    } else {
+        None
+    };
+
+    if is_radio && selected.is_none() && !value.value.trim().is_empty() {
+        // Unknown option - let the stamping fallback draw it
+        return Ok(false);
+    }
+
+    let v = selected.clone().unwrap_or_else(|| b"Off".to_vec());
+    doc.get_dictionary_mut(field.id)?.set("V", Object::Name(v));
+
+    for (widget_id, state) in widget_states {
+        let on = match (&selected, &state) {
+            (Some(sel), Some(state)) => sel == state,
+            (Some(_), None) => !is_radio,
+            _ => false,
+        };
+
+        if state.is_none() && on {
+            // No appearance for the on state - generate a check mark
+            let rect = {
+                let widget = doc.get_dictionary(widget_id)?;
+                widget_rect(doc, widget)
+            };
+            if let Some(rect) = rect {
+                let check = build_check_appearance(rect);
+                let check_id = doc.add_object(check);
+                let mut states = Dictionary::new();
+                states.set("Yes", Object::Reference(check_id));
+                let mut ap = Dictionary::new();
+                ap.set("N", Object::Dictionary(states));
+                doc.get_dictionary_mut(widget_id)?.set("AP", Object::Dictionary(ap));
+            }
+        }
+
+        let as_name = if on { state.unwrap_or_else(|| b"Yes".to_vec()) } else { b"Off".to_vec() };
+        doc.get_dictionary_mut(widget_id)?.set("AS", Object::Name(as_name));
+    }
+
+    Ok(true)
+}
+
+fn appearance_states(doc: &Document, widget: &Dictionary) -> Vec<Vec<u8>> {
+    widget
+        .get(b"AP")
+        .and_then(|ap| resolve(doc, ap).as_dict())
+        .and_then(|ap| ap.get(b"N"))
+        .and_then(|n| resolve(doc, n).as_dict())
+        .map(|n| n.iter().map(|(k, _)| k.clone()).collect())
+        .unwrap_or_default()
+}
+
+fn number(doc: &Document, object: &Object) -> Option<f64> {
+    match resolve(doc, object) {
+        Object::Integer(i) => Some(*i as f64),
+        Object::Real(r) => Some(*r as f64),
+        _ => None,
+    }
+}
+
+fn number_array<const N: usize>(doc: &Document, object: &Object) -> Option<[f64; N]> {
+    let array = resolve(doc, object).as_array().ok()?;
+    if array.len() != N {
+        return None;
+    }
+    let mut out = [0.0; N];
+    for (i, item) in array.iter().enumerate() {
+        out[i] = number(doc, item)?;
+    }
+    Some(out)
+}
+
+// Normalized [llx, lly, urx, ury]
+fn widget_rect(doc: &Document, widget: &Dictionary) -> Option<[f64; 4]> {
+    let r: [f64; 4] = number_array(doc, widget.get(b"Rect").ok()?)?;
+    Some([r[0].min(r[2]), r[1].min(r[3]), r[0].max(r[2]), r[1].max(r[3])])
+}
+
+fn widget_rotation(doc: &Document, widget: &Dictionary) -> i64 {
+    widget
+        .get(b"MK")
+        .and_then(|mk| resolve(doc, mk).as_dict())
+        .and_then(|mk| mk.get(b"R"))
+        .and_then(|r| r.as_i64())
+        .map(|r| r.rem_euclid(360))
+        .unwrap_or(0)
+}
+
+// Parse "/Helv 0 Tf 0 g" into (font name, size, remaining operators)
+fn parse_default_appearance(da: Option<&str>) -> (Vec<u8>, f64, String) {
+    let da = da.unwrap_or("/Helv 0 Tf 0 g");
+    let tokens: Vec<&str> = da.split_whitespace().collect();
+    let mut font = DEFAULT_FONT_NAME.to_vec();
+    let mut size = 0.0;
+    let mut rest = Vec::new();
+    let mut i = 0;
+    while i < tokens.len() {
+        if i + 2 < tokens.len() && tokens[i].starts_with('/') && tokens[i + 2] == "Tf" {
+            font = tokens[i].as_bytes()[1..].to_vec();
+            size = tokens[i + 1].parse().unwrap_or(0.0);
+            i += 3;
+        } else {
+            rest.push(tokens[i]);
+            i += 1;
+        }
+    }
+    let color = if rest.is_empty() { "0 g".to_string() } else { rest.join(" ") };
+    (font, size, color)
+}
+
+fn build_text_appearance(
+    text: &str,
+    rect: [f64; 4],
+    rotation: i64,
+    default_appearance: Option<&str>,
+    quadding: i64,
+    multiline: bool,
+    font_id: ObjectId,
+) -> Stream {
+    let (mut width, mut height) = (rect[2] - rect[0], rect[3] - rect[1]);
+    if rotation == 90 || rotation == 270 {
+        std::mem::swap(&mut width, &mut height);
+    }
+
+    let (da_font, da_size, color) = parse_default_appearance(default_appearance);
+    let padding = 2.0;
+    let lines: Vec<&str> = if multiline { text.lines().collect() } else { vec![text.lines().next().unwrap_or("")] };
+
+    let mut font_size = if da_size > 0.0 {
+        da_size
+    } else if multiline {
+        12.0
+    } else {
+        ((height - 2.0 * padding) / 1.15).clamp(4.0, 12.0)
+    };
+    if da_size <= 0.0 && !multiline {
+        // Auto-size: shrink until the text fits the field width
+        let text_width = helvetica_text_width(lines.first().copied().unwrap_or(""), 1.0);
+        if text_width > 0.0 {
+            font_size = font_size.min((width - 2.0 * padding) / text_width).max(4.0);
+        }
+    }
+
+    let leading = font_size * 1.15;
+    let mut content = String::new();
+    content.push_str("/Tx BMC\nq\n");
+    content.push_str(&format!("{} {} {} {} re W n\n", padding / 2.0, padding / 2.0, width - padding, height - padding));
+    content.push_str("BT\n");
+    content.push_str(&format!("/{} {} Tf\n{}\n", String::from_utf8_lossy(&da_font), format_number(font_size), color));
+
+    for (i, line) in lines.iter().enumerate() {
+        let line_width = helvetica_text_width(line, font_size);
+        let x = match quadding {
+            1 => (width - line_width) / 2.0,
+            2 => width - padding - line_width,
+            _ => padding,
+        };
+        let y = if multiline {
+            height - padding - font_size - i as f64 * leading
+        } else {
+            // Vertically centre the baseline (descender ~ 0.22em)
+            (height - font_size) / 2.0 + font_size * 0.22
+        };
+        content.push_str(&format!("1 0 0 1 {} {} Tm\n", format_number(x), format_number(y)));
+        content.push_str(&format!("({}) Tj\n", escape_literal(&encode_win_ansi(line))));
+    }
+    content.push_str("ET\nQ\nEMC\n");
+
+    let mut font_dict = Dictionary::new();
+    font_dict.set(da_font, Object::Reference(font_id));
+    let mut resources = Dictionary::new();
+    resources.set("Font", Object::Dictionary(font_dict));
+
+    form_xobject(content.into_bytes(), width, height, rotation, resources)
+}
+
+fn build_check_appearance(rect: [f64; 4]) -> Stream {
+    let (width, height) = (rect[2] - rect[0], rect[3] - rect[1]);
+    let size = width.min(height) * 0.8;
+    // ZapfDingbats "4" is the check mark glyph (~0.76em wide)
+    let x = (width - size * 0.76) / 2.0;
+    let y = (height - size) / 2.0 + size * 0.15;
+    let content = format!("q\nBT\n/ZaDb {} Tf\n0 g\n{} {} Td\n(4) Tj\nET\nQ\n", format_number(size), format_number(x), format_number(y));
+
+    let mut font_dict = Dictionary::new();
+    font_dict.set(CHECK_FONT_NAME, standard_font("ZapfDingbats", false));
+    let mut resources = Dictionary::new();
+    resources.set("Font", Object::Dictionary(font_dict));
+
+    form_xobject(content.into_bytes(), width, height, 0, resources)
+}
+
+fn form_xobject(content: Vec<u8>, width: f64, height: f64, rotation: i64, resources: Dictionary) -> Stream {
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Form".to_vec()));
+    dict.set("BBox", Object::Array(vec![0.into(), 0.into(), Object::Real(width as f32), Object::Real(height as f32)]));
+    let matrix: Option<[f64; 6]> = match rotation {
+        90 => Some([0.0, 1.0, -1.0, 0.0, height, 0.0]),
+        180 => Some([-1.0, 0.0, 0.0, -1.0, width, height]),
+        270 => Some([0.0, -1.0, 1.0, 0.0, 0.0, width]),
+        _ => None,
+    };
+    if let Some(m) = matrix {
+        dict.set("Matrix", Object::Array(m.iter().map(|v| Object::Real(*v as f32)).collect()));
+    }
+    dict.set("Resources", Object::Dictionary(resources));
+    Stream::new(dict, content)
+}
+
+pub(crate) fn format_number(value: f64) -> String {
+    let s = format!("{:.3}", value);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+pub(crate) fn escape_literal(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for &b in bytes {
+        match b {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(b as char);
+            }
+            0x20..=0x7E => out.push(b as char),
+            _ => out.push_str(&format!("\\{:03o}", b)),
+        }
+    }
+    out
+}
+
+// WinAnsiEncoding codes 0x80..=0x9F that differ from Latin-1
+const WIN_ANSI_SPECIALS: [(char, u8); 27] = [
+    ('€', 0x80), ('‚', 0x82), ('ƒ', 0x83), ('„', 0x84), ('…', 0x85), ('†', 0x86), ('‡', 0x87),
+    ('ˆ', 0x88), ('‰', 0x89), ('Š', 0x8A), ('‹', 0x8B), ('Œ', 0x8C), ('Ž', 0x8E), ('‘', 0x91),
+    ('’', 0x92), ('“', 0x93), ('”', 0x94), ('•', 0x95), ('–', 0x96), ('—', 0x97), ('˜', 0x98),
+    ('™', 0x99), ('š', 0x9A), ('›', 0x9B), ('œ', 0x9C), ('ž', 0x9E), ('Ÿ', 0x9F),
+];
+
+fn win_ansi_code(c: char) -> Option<u8> {
+    match c as u32 {
+        0x20..=0x7E | 0xA0..=0xFF => Some(c as u32 as u8),
+        _ => WIN_ANSI_SPECIALS.iter().find(|(special, _)| *special == c).map(|(_, code)| *code),
+    }
+}
+
+// Whether every character (line breaks aside) can be drawn with a WinAnsiEncoding font
+pub(crate) fn is_win_ansi_text(text: &str) -> bool {
+    text.chars().all(|c| c == '\n' || c == '\r' || win_ansi_code(c).is_some())
+}
+
+// Map text to WinAnsiEncoding, unsupported characters become '?'
+pub(crate) fn encode_win_ansi(text: &str) -> Vec<u8> {
+    text.chars().map(|c| win_ansi_code(c).unwrap_or(b'?')).collect()
+}
+
+// Character for a WinAnsiEncoding code, None for unassigned codes
+pub(crate) fn decode_win_ansi(code: u8) -> Option<char> {
+    match code {
+        0x20..=0x7E | 0xA0..=0xFF => Some(code as char),
+        _ => WIN_ANSI_SPECIALS.iter().find(|(_, c)| *c == code).map(|(special, _)| *special),
+    }
+}
+
+// Helvetica advance widths (1/1000 em) for ASCII 32..=126
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, // space - /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // 0 - 9
+    278, 278, 584, 584, 584, 556, 1015, // : - @
+    667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, // A - M
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, // N - Z
+    278, 278, 278, 469, 556, 333, // [ - `
+    556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, // a - m
+    556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, // n - z
+    334, 260, 334, 584, // { - ~
+];
+
+pub(crate) fn helvetica_text_width(text: &str, font_size: f64) -> f64 {
+    let units: u32 = encode_win_ansi(text)
+        .iter()
+        .map(|&b| if (32..=126).contains(&b) { HELVETICA_WIDTHS[(b - 32) as usize] as u32 } else { 556 })
+        .sum();
+    units as f64 * font_size / 1000.0
+}
+
+// Draw every visible widget appearance into its page and drop the interactive form
+fn flatten_form(doc: &mut Document) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let pages: Vec<ObjectId> = doc.get_pages().values().copied().collect();
+
+    for page_id in pages {
+        let annots = match doc.get_dictionary(page_id)?.get(b"Annots") {
+            Ok(annots) => resolve(doc, annots).as_array().cloned().unwrap_or_default(),
+            Err(_) => continue,
+        };
+
+        let mut kept = Vec::new();
+        let mut draws = Vec::new();
+        for annot in annots {
+            let annot_dict = match &annot {
+                Object::Reference(id) => match doc.get_dictionary(*id) {
+                    Ok(dict) => dict.clone(),
+                    Err(_) => continue,
+                },
+                Object::Dictionary(dict) => dict.clone(),
+                _ => continue,
+            };
+
+            let is_widget = annot_dict.get(b"Subtype").and_then(|s| s.as_name()).map(|s| s == b"Widget").unwrap_or(false);
+            if !is_widget {
+                kept.push(annot);
+                continue;
+            }
+
+            let hidden = annot_dict.get(b"F").and_then(|f| f.as_i64()).unwrap_or(0) & ANNOT_HIDDEN != 0;
+            if hidden {
+                continue;
+            }
+
+            if let (Some(appearance_id), Some(rect)) = (normal_appearance(doc, &annot_dict), widget_rect(doc, &annot_dict)) {
+                draws.push((appearance_id, rect));
+            }
+        }
+
+        if !draws.is_empty() {
+            let mut content = String::new();
+            for (i, (appearance_id, rect)) in draws.iter().enumerate() {
+                let matrix = match appearance_matrix(doc, *appearance_id, *rect) {
+                    Some(matrix) => matrix,
+                    None => continue,
+                };
+                let name = format!("FlatField{}", i);
+                add_page_xobject(doc, page_id, &name, *appearance_id)?;
+                content.push_str(&format!(
+                    "q {} {} {} {} {} {} cm /{} Do Q\n",
+                    format_number(matrix[0]), format_number(matrix[1]), format_number(matrix[2]), format_number(matrix[3]), format_number(matrix[4]), format_number(matrix[5]), name
+                ));
+            }
+            wrap_page_contents(doc, page_id)?;
+            doc.add_page_contents(page_id, content.into_bytes())?;
+        }
+
+        let page = doc.get_dictionary_mut(page_id)?;
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", Object::Array(kept));
+        }
+    }
+
+    doc.catalog_mut()?.remove(b"AcroForm");
+    Ok(())
+}
+
+// Resolve /AP /N for the widget's current state
+fn normal_appearance(doc: &Document, widget: &Dictionary) -> Option<ObjectId> {
+    let ap = resolve(doc, widget.get(b"AP").ok()?).as_dict().ok()?;
+    let n = ap.get(b"N").ok()?;
+    if let Ok(id) = n.as_reference() {
+        if doc.get_object(id).map(|o| o.as_stream().is_ok()).unwrap_or(false) {
+            return Some(id);
+        }
+    }
+    let states = resolve(doc, n).as_dict().ok()?;
+    let state = widget.get(b"AS").and_then(|s| s.as_name()).ok()?;
+    states.get(state).and_then(|s| s.as_reference()).ok()
+}
+
+// Map the appearance's (transformed) BBox onto the annotation rectangle (PDF 32000-1, 12.5.5)
+fn appearance_matrix(doc: &Document, appearance_id: ObjectId, rect: [f64; 4]) -> Option<[f64; 6]> {
+    let stream = doc.get_object(appearance_id).ok()?.as_stream().ok()?;
+    let bbox: [f64; 4] = number_array(doc, stream.dict.get(b"BBox").ok()?)?;
+    let m: [f64; 6] = stream
+        .dict
+        .get(b"Matrix")
+        .ok()
+        .and_then(|m| number_array(doc, m))
+        .unwrap_or([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    let corners = [(bbox[0], bbox[1]), (bbox[2], bbox[1]), (bbox[0], bbox[3]), (bbox[2], bbox[3])];
+    let transformed: Vec<(f64, f64)> = corners
+        .iter()
+        .map(|(x, y)| (m[0] * x + m[2] * y + m[4], m[1] * x + m[3] * y + m[5]))
+        .collect();
+    let min_x = transformed.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+    let max_x = transformed.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+    let min_y = transformed.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+    let max_y = transformed.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+
+    if max_x - min_x <= 0.0 || max_y - min_y <= 0.0 {
+        return None;
+    }
+    let sx = (rect[2] - rect[0]) / (max_x - min_x);
+    let sy = (rect[3] - rect[1]) / (max_y - min_y);
+    Some([sx, 0.0, 0.0, sy, rect[0] - min_x * sx, rect[1] - min_y * sy])
+}
+
+fn add_page_xobject(
+    doc: &mut Document,
+    page_id: ObjectId,
+    name: &str,
+    xobject_id: ObjectId,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    // Pages may inherit Resources from the page tree - copy them down before modifying
+    let resources_obj = match doc.get_dictionary(page_id)?.get(b"Resources") {
+        Ok(resources) => resources.clone(),
+        Err(_) => {
+            let (inherited, inherited_ids) = doc.get_page_resources(page_id);
+            let dict = match (inherited, inherited_ids.first()) {
+                (Some(dict), _) => dict.clone(),
+                (None, Some(id)) => doc.get_dictionary(*id).cloned().unwrap_or_default(),
+                _ => Dictionary::new(),
+            };
+            Object::Dictionary(dict)
+        }
+    };
+
+    let mut resources = match &resources_obj {
+        Object::Reference(id) => doc.get_dictionary(*id)?.clone(),
+        Object::Dictionary(dict) => dict.clone(),
+        _ => Dictionary::new(),
+    };
+    let mut xobjects = match resources.get(b"XObject").map(|x| resolve(doc, x).clone()) {
+        Ok(Object::Dictionary(dict)) => dict,
+        _ => Dictionary::new(),
+    };
+    xobjects.set(name.as_bytes().to_vec(), Object::Reference(xobject_id));
+    resources.set("XObject", Object::Dictionary(xobjects));
+
+    match resources_obj {
+        Object::Reference(id) => {
+            *doc.get_dictionary_mut(id)? = resources;
+        }
+        _ => {
+            doc.get_dictionary_mut(page_id)?.set("Resources", Object::Dictionary(resources));
+        }
+    }
+    Ok(())
+}
+
+// Isolate the existing page content in q/Q so its graphics state can't leak into what we append
+fn wrap_page_contents(doc: &mut Document, page_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let existing = match doc.get_dictionary(page_id)?.get(b"Contents") {
+        Ok(Object::Reference(id)) => vec![Object::Reference(*id)],
+        Ok(Object::Array(arr)) => arr.clone(),
+        _ => return Ok(()),
+    };
+    let open_id = doc.add_object(Stream::new(Dictionary::new(), b"q\n".to_vec()));
+    let close_id = doc.add_object(Stream::new(Dictionary::new(), b"\nQ\n".to_vec()));
+
+    let mut contents = vec![Object::Reference(open_id)];
+    contents.extend(existing);
+    contents.push(Object::Reference(close_id));
+    doc.get_dictionary_mut(page_id)?.set("Contents", Object::Array(contents));
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    // One page with a text field, a combo box, a check box and a second text field
+    fn form_pdf() -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let page_id = doc.new_object_id();
+        let check_on = doc.add_object(Stream::new(dictionary! { "BBox" => vec![0.into(), 0.into(), 12.into(), 12.into()] }, b"0 0 12 12 re f".to_vec()));
+        let check_off = doc.add_object(Stream::new(dictionary! { "BBox" => vec![0.into(), 0.into(), 12.into(), 12.into()] }, Vec::new()));
+        let widget = |name: &str, field_type: &str, y: i64| dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "FT" => field_type,
+            "T" => Object::string_literal(name),
+            "Rect" => vec![50.into(), y.into(), 250.into(), (y + 20).into()],
+            "P" => page_id,
+        };
+        let name_id = doc.add_object(widget("name", "Tx", 700));
+        let mut country = widget("country", "Ch", 650);
+        country.set("Ff", Object::Integer(1 << 17)); // combo box
+        country.set("Opt", vec![Object::string_literal("France"), Object::string_literal("Vietnam")]);
+        let country_id = doc.add_object(country);
+        let mut agree = widget("agree", "Btn", 600);
+        agree.set("AS", Object::Name(b"Off".to_vec()));
+        agree.set("AP", dictionary! { "N" => dictionary! { "Yes" => check_on, "Off" => check_off } });
+        let agree_id = doc.add_object(agree);
+        let city_id = doc.add_object(widget("city", "Tx", 550));
+        let fields: Vec<Object> = vec![name_id.into(), country_id.into(), agree_id.into(), city_id.into()];
+
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf 50 750 Td (Form) Tj ET".to_vec()));
+        doc.objects.insert(page_id, Object::Dictionary(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+            "Annots" => fields.clone(),
+        }));
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1,
+        }));
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "AcroForm" => dictionary! { "Fields" => fields, "DA" => Object::string_literal("/Helv 0 Tf 0 g") },
+        });
+        doc.trailer.set("Root", catalog_id);
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    fn fill(values: &[(&str, &str, &str)], flatten: bool) -> (Document, HashSet<String>) {
+        let values: Vec<FormFieldValue> = values.iter()
+            .map(|(name, field_type, value)| FormFieldValue { name, field_type, value })
+            .collect();
+        let (pdf, filled) = fill_form_fields(&form_pdf(), &values, flatten).unwrap();
+        (Document::load_mem(&pdf).unwrap(), filled)
+    }
+
+    fn field<'a>(doc: &'a Document, name: &str) -> &'a Dictionary {
+        doc.objects.values()
+            .filter_map(|o| o.as_dict().ok())
+            .find(|d| d.get(b"T").ok().and_then(object_to_string).as_deref() == Some(name))
+            .unwrap()
+    }
+
+    fn content(doc: &Document, id: ObjectId) -> String {
+        let stream = doc.get_object(id).unwrap().as_stream().unwrap();
+        String::from_utf8_lossy(&stream.decompressed_content().unwrap_or_else(|_| stream.content.clone())).to_string()
+    }
+
+    #[test]
+    fn text_choice_and_check_box_fields_are_filled() {
+        let (doc, filled) = fill(&[
+            ("name", "text", "Jane (Doe)"),
+            ("country", "select", "France"),
+            ("agree", "checkbox", "true"),
+            ("signature", "signature", "data:image/png;base64,"),
+            ("missing", "text", "nowhere"),
+        ], false);
+        assert_eq!(filled, HashSet::from(["name".to_string(), "country".to_string(), "agree".to_string()]));
+
+        let name = field(&doc, "name");
+        assert_eq!(name.get(b"V").ok().and_then(object_to_string).as_deref(), Some("Jane (Doe)"));
+        let appearance = resolve(&doc, name.get(b"AP").unwrap()).as_dict().unwrap().get(b"N").unwrap().as_reference().unwrap();
+        assert!(content(&doc, appearance).contains("(Jane \\(Doe\\)) Tj"));
+        assert_eq!(field(&doc, "country").get(b"V").ok().and_then(object_to_string).as_deref(), Some("France"));
+
+        let agree = field(&doc, "agree");
+        assert_eq!(agree.get(b"V").unwrap().as_name().unwrap(), b"Yes");
+        assert_eq!(agree.get(b"AS").unwrap().as_name().unwrap(), b"Yes");
+
+        let (doc, filled) = fill(&[("agree", "checkbox", "false")], false);
+        assert!(filled.contains("agree"));
+        assert_eq!(field(&doc, "agree").get(b"AS").unwrap().as_name().unwrap(), b"Off");
+    }
+
+    #[test]
+    fn text_outside_win_ansi_is_left_to_stamping() {
+        assert!(is_win_ansi_text("Zoë – “Müller”\nLine 2"));
+        assert!(!is_win_ansi_text("Hà Nội"));
+        assert!(!is_win_ansi_text("東京"));
+
+        let (doc, filled) = fill(&[("city", "text", "Hà Nội"), ("name", "text", "東京")], false);
+        assert!(filled.is_empty());
+        assert!(field(&doc, "city").get(b"V").is_err());
+        assert!(field(&doc, "name").get(b"AP").is_err());
+    }
+
+    #[test]
+    fn flattening_draws_widgets_into_the_page() {
+        let (doc, filled) = fill(&[("name", "text", "Jane Doe"), ("agree", "checkbox", "yes")], true);
+        assert_eq!(filled.len(), 2);
+        assert!(doc.catalog().unwrap().get(b"AcroForm").is_err());
+
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let page = doc.get_dictionary(page_id).unwrap();
+        assert!(page.get(b"Annots").is_err());
+        let page_content = String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).to_string();
+        // Every widget with an appearance is drawn, after the original content
+        assert!(page_content.find("(Form) Tj").unwrap() < page_content.find("/FlatField0 Do").unwrap());
+        let xobjects = resolve(&doc, page.get(b"Resources").unwrap()).as_dict().unwrap().get(b"XObject").unwrap().as_dict().unwrap();
+        let name_appearance = xobjects.iter()
+            .map(|(_, id)| content(&doc, id.as_reference().unwrap()))
+            .find(|c| c.contains("Tj"));
+        assert!(name_appearance.unwrap().contains("(Jane Doe) Tj"));
+    }
+}
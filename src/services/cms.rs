@@ -0,0 +1,335 @@
+// Minimal DER encoding and CMS (RFC 5652) SignedData construction.
+// Used for detached PDF signatures (PAdES) - only the pieces we need are implemented.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use openssl::hash::{hash, MessageDigest};
+use openssl::pkey::{Id, PKey, Private};
+use openssl::sign::Signer;
+use openssl::x509::X509;
+
+pub const OID_DATA: &str = "1.2.840.113549.1.7.1";
+pub const OID_SIGNED_DATA: &str = "1.2.840.113549.1.7.2";
+pub const OID_SHA256: &str = "2.16.840.1.101.3.4.2.1";
+pub const OID_RSA_ENCRYPTION: &str = "1.2.840.113549.1.1.1";
+pub const OID_ECDSA_WITH_SHA256: &str = "1.2.840.10045.4.3.2";
+pub const OID_ATTR_CONTENT_TYPE: &str = "1.2.840.113549.1.9.3";
+pub const OID_ATTR_MESSAGE_DIGEST: &str = "1.2.840.113549.1.9.4";
+pub const OID_ATTR_SIGNING_CERTIFICATE_V2: &str = "1.2.840.113549.1.9.16.2.47";
+pub const OID_ATTR_SIGNATURE_TIMESTAMP_TOKEN: &str = "1.2.840.113549.1.9.16.2.14";
+pub const OID_CT_TST_INFO: &str = "1.2.840.113549.1.9.16.1.4";
+
+pub const TAG_GENERALIZED_TIME: u8 = 0x18;
+
+// ===== DER encoding =====
+
+fn der_length(len: usize) -> Vec<u8> {
+    if len < 0x80 {
+        vec![len as u8]
+    } else {
+        let bytes: Vec<u8> = len.to_be_bytes().iter().copied().skip_while(|b| *b == 0).collect();
+        let mut out = vec![0x80 | bytes.len() as u8];
+        out.extend(bytes);
+        out
+    }
+}
+
+pub fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    out.extend(der_length(content.len()));
+    out.extend_from_slice(content);
+    out
+}
+
+pub fn der_sequence(items: &[Vec<u8>]) -> Vec<u8> {
+    der_tlv(0x30, &items.concat())
+}
+
+// SET OF - DER requires the encodings to be sorted
+pub fn der_set(items: &[Vec<u8>]) -> Vec<u8> {
+    let mut sorted = items.to_vec();
+    sorted.sort();
+    der_tlv(0x31, &sorted.concat())
+}
+
+// [n] EXPLICIT / constructed context-specific tag
+pub fn der_explicit(tag_number: u8, content: &[u8]) -> Vec<u8> {
+    der_tlv(0xA0 | tag_number, content)
+}
+
+pub fn der_null() -> Vec<u8> {
+    vec![0x05, 0x00]
+}
+
+pub fn der_boolean(value: bool) -> Vec<u8> {
+    der_tlv(0x01, &[if value { 0xFF } else { 0x00 }])
+}
+
+pub fn der_octet_string(bytes: &[u8]) -> Vec<u8> {
+    der_tlv(0x04, bytes)
+}
+
+// INTEGER from unsigned big-endian magnitude
+pub fn der_integer_bytes(magnitude: &[u8]) -> Vec<u8> {
+    let trimmed: Vec<u8> = magnitude.iter().copied().skip_while(|b| *b == 0).collect();
+    let mut content = if trimmed.is_empty() { vec![0] } else { trimmed };
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+    der_tlv(0x02, &content)
+}
+
+pub fn der_integer(value: u64) -> Vec<u8> {
+    der_integer_bytes(&value.to_be_bytes())
+}
+
+pub fn der_oid(dotted: &str) -> Vec<u8> {
+    let parts: Vec<u64> = dotted.split('.').filter_map(|p| p.parse().ok()).collect();
+    let mut content = vec![(parts[0] * 40 + parts[1]) as u8];
+    for &part in &parts[2..] {
+        let mut chunk = vec![(part & 0x7F) as u8];
+        let mut rest = part >> 7;
+        while rest > 0 {
+            chunk.insert(0, 0x80 | (rest & 0x7F) as u8);
+            rest >>= 7;
+        }
+        content.extend(chunk);
+    }
+    der_tlv(0x06, &content)
+}
+
+pub fn der_generalized_time(time: &DateTime<Utc>) -> Vec<u8> {
+    der_tlv(TAG_GENERALIZED_TIME, time.format("%Y%m%d%H%M%SZ").to_string().as_bytes())
+}
+
+pub fn algorithm_identifier(oid: &str, with_null_params: bool) -> Vec<u8> {
+    if with_null_params {
+        der_sequence(&[der_oid(oid), der_null()])
+    } else {
+        der_sequence(&[der_oid(oid)])
+    }
+}
+
+pub fn attribute(oid: &str, value: Vec<u8>) -> Vec<u8> {
+    der_sequence(&[der_oid(oid), der_set(&[value])])
+}
+
+// ===== DER decoding =====
+
+pub struct DerElement<'a> {
+    pub tag: u8,
+    pub content: &'a [u8],
+    // Full TLV encoding
+    pub raw: &'a [u8],
+}
+
+// Parse one TLV from the start of `input`, returning it and the remaining bytes
+pub fn der_parse(input: &[u8]) -> Result<(DerElement<'_>, &[u8]), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if input.len() < 2 {
+        return Err("Truncated DER element".into());
+    }
+    let tag = input[0];
+    let (len, header) = if input[1] & 0x80 == 0 {
+        (input[1] as usize, 2)
+    } else {
+        let count = (input[1] & 0x7F) as usize;
+        if count == 0 || count > 4 || input.len() < 2 + count {
+            return Err("Unsupported DER length".into());
+        }
+        let len = input[2..2 + count].iter().fold(0usize, |acc, b| (acc << 8) | *b as usize);
+        (len, 2 + count)
+    };
+    if input.len() < header + len {
+        return Err("Truncated DER element".into());
+    }
+    let element = DerElement { tag, content: &input[header..header + len], raw: &input[..header + len] };
+    Ok((element, &input[header + len..]))
+}
+
+// Parse all TLVs inside a constructed element's content
+pub fn der_children(content: &[u8]) -> Result<Vec<DerElement<'_>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut children = Vec::new();
+    let mut rest = content;
+    while !rest.is_empty() {
+        let (element, remaining) = der_parse(rest)?;
+        children.push(element);
+        rest = remaining;
+    }
+    Ok(children)
+}
+
+pub fn decode_oid(content: &[u8]) -> String {
+    let mut parts = Vec::new();
+    if let Some(first) = content.first() {
+        parts.push((first / 40) as u64);
+        parts.push((first % 40) as u64);
+    }
+    let mut value = 0u64;
+    for &b in content.iter().skip(1) {
+        value = (value << 7) | (b & 0x7F) as u64;
+        if b & 0x80 == 0 {
+            parts.push(value);
+            value = 0;
+        }
+    }
+    parts.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(".")
+}
+
+pub fn decode_generalized_time(content: &[u8]) -> Result<DateTime<Utc>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let text = std::str::from_utf8(content)?;
+    // Fractional seconds are optional, we only keep whole seconds
+    let trimmed = text.trim_end_matches('Z');
+    let whole = trimmed.split('.').next().unwrap_or(trimmed);
+    let naive = NaiveDateTime::parse_from_str(whole, "%Y%m%d%H%M%S")?;
+    Ok(DateTime::<Utc>::from_naive_utc_and_offset(naive, Utc))
+}
+
+// ===== CMS SignedData =====
+
+pub struct CmsSigner<'a> {
+    pub key: &'a PKey<Private>,
+    pub certificate: &'a X509,
+    // Additional certificates (issuer chain) embedded in the SignedData
+    pub chain: &'a [X509],
+}
+
+impl<'a> CmsSigner<'a> {
+    fn issuer_and_serial(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let issuer = self.certificate.issuer_name().to_der()?;
+        let serial = self.certificate.serial_number().to_bn()?.to_vec();
+        Ok(der_sequence(&[issuer, der_integer_bytes(&serial)]))
+    }
+
+    // ESS signing-certificate-v2 attribute (RFC 5035), required by PAdES baseline signatures
+    fn signing_certificate_v2(&self) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let cert_der = self.certificate.to_der()?;
+        let cert_hash = hash(MessageDigest::sha256(), &cert_der)?;
+        let issuer = self.certificate.issuer_name().to_der()?;
+        let serial = self.certificate.serial_number().to_bn()?.to_vec();
+        // IssuerSerial ::= SEQUENCE { issuer GeneralNames ([4] directoryName), serialNumber }
+        let issuer_serial = der_sequence(&[der_sequence(&[der_explicit(4, &issuer)]), der_integer_bytes(&serial)]);
+        let ess_cert_id = der_sequence(&[der_octet_string(&cert_hash), issuer_serial]);
+        Ok(der_sequence(&[der_sequence(&[ess_cert_id])]))
+    }
+
+    fn signature_algorithm(&self) -> Vec<u8> {
+        match self.key.id() {
+            Id::EC => algorithm_identifier(OID_ECDSA_WITH_SHA256, false),
+            _ => algorithm_identifier(OID_RSA_ENCRYPTION, true),
+        }
+    }
+
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let mut signer = Signer::new(MessageDigest::sha256(), self.key)?;
+        signer.update(data)?;
+        Ok(signer.sign_to_vec()?)
+    }
+}
+
+// Content being signed
+pub struct SignedContent<'a> {
+    // eContentType OID, id-data for detached document signatures
+    pub content_type: &'a str,
+    // SHA-256 of the content (message-digest attribute)
+    pub digest: &'a [u8],
+    // Encapsulated content, None for detached signatures
+    pub encapsulated: Option<&'a [u8]>,
+}
+
+// Signature value computed over the signed attributes, needed to request a signature timestamp
+pub struct SignerInfoParts {
+    pub signed_attributes: Vec<u8>,
+    pub signature: Vec<u8>,
+}
+
+pub fn compute_signature(
+    signer: &CmsSigner,
+    content: &SignedContent,
+) -> Result<SignerInfoParts, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let attributes = vec![
+        attribute(OID_ATTR_CONTENT_TYPE, der_oid(content.content_type)),
+        attribute(OID_ATTR_MESSAGE_DIGEST, der_octet_string(content.digest)),
+        attribute(OID_ATTR_SIGNING_CERTIFICATE_V2, signer.signing_certificate_v2()?),
+    ];
+    // The signature covers the DER SET OF encoding of the attributes
+    let signed_attributes = der_set(&attributes);
+    let signature = signer.sign(&signed_attributes)?;
+    Ok(SignerInfoParts { signed_attributes, signature })
+}
+
+/// Assemble a CMS ContentInfo(SignedData) from a computed signature.
+/// `unsigned_attributes` are complete Attribute encodings (e.g. a signature timestamp token).
+pub fn build_signed_data(
+    signer: &CmsSigner,
+    content: &SignedContent,
+    parts: &SignerInfoParts,
+    unsigned_attributes: &[Vec<u8>],
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let digest_algorithm = algorithm_identifier(OID_SHA256, true);
+
+    // signedAttrs are stored as [0] IMPLICIT - same content, different tag
+    let mut signed_attrs_implicit = parts.signed_attributes.clone();
+    signed_attrs_implicit[0] = 0xA0;
+
+    let mut signer_info = vec![
+        der_integer(1),
+        signer.issuer_and_serial()?,
+        digest_algorithm.clone(),
+        signed_attrs_implicit,
+        signer.signature_algorithm(),
+        der_octet_string(&parts.signature),
+    ];
+    if !unsigned_attributes.is_empty() {
+        let mut unsigned = der_set(unsigned_attributes);
+        unsigned[0] = 0xA1;
+        signer_info.push(unsigned);
+    }
+
+    let mut certificates = vec![signer.certificate.to_der()?];
+    for cert in signer.chain {
+        certificates.push(cert.to_der()?);
+    }
+    let mut certificates_implicit = der_set(&certificates);
+    certificates_implicit[0] = 0xA0;
+
+    let encap_content_info = match content.encapsulated {
+        Some(bytes) => der_sequence(&[der_oid(content.content_type), der_explicit(0, &der_octet_string(bytes))]),
+        None => der_sequence(&[der_oid(content.content_type)]),
+    };
+    // Version 3 when the encapsulated content isn't id-data (RFC 5652, 5.1)
+    let version = if content.content_type == OID_DATA { 1 } else { 3 };
+
+    let signed_data = der_sequence(&[
+        der_integer(version),
+        der_set(&[digest_algorithm]),
+        encap_content_info,
+        certificates_implicit,
+        der_set(&[der_sequence(&signer_info)]),
+    ]);
+
+    Ok(der_sequence(&[der_oid(OID_SIGNED_DATA), der_explicit(0, &signed_data)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn der_encoding_round_trips() {
+        let oid = der_oid(OID_ATTR_SIGNING_CERTIFICATE_V2);
+        let (element, rest) = der_parse(&oid).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!((element.tag, decode_oid(element.content)), (0x06, OID_ATTR_SIGNING_CERTIFICATE_V2.to_string()));
+
+        // Long-form lengths, and SET OF members sorted by encoding
+        let long = der_octet_string(&[0xAB; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2C]);
+        let set = der_set(&[der_integer(2), der_integer(1)]);
+        let members = der_children(der_parse(&set).unwrap().0.content).unwrap();
+        assert_eq!(members.iter().map(|m| m.content.to_vec()).collect::<Vec<_>>(), vec![vec![1], vec![2]]);
+
+        let time = DateTime::parse_from_rfc3339("2026-01-02T03:04:05Z").unwrap().with_timezone(&Utc);
+        let encoded = der_generalized_time(&time);
+        assert_eq!(decode_generalized_time(der_parse(&encoded).unwrap().0.content).unwrap(), time);
+        assert!(der_parse(&long[..10]).is_err());
+    }
+}
@@ -0,0 +1,168 @@
+// Conversion of uploaded office documents (DOCX) to PDF, so fields can be placed on them and
+// previews rendered.
+//
+// Configuration (environment):
+//   DOCX_CONVERTER               converter to use: "libreoffice" (default) or "none"
+//   LIBREOFFICE_PATH             soffice binary (default "soffice" from PATH)
+//   DOCX_CONVERSION_TIMEOUT_SECS give up on a conversion after this long (default 60)
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::process::Command;
+
+pub const DOCX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.wordprocessingml.document";
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+
+#[async_trait]
+pub trait DocumentConverter: Send + Sync {
+    // Name reported in errors and logs
+    fn name(&self) -> String;
+
+    /// Convert a DOCX document (named filename, for messages) to PDF
+    async fn convert_to_pdf(&self, data: &[u8], filename: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>>;
+}
+
+/// Configured DOCX converter, or None when conversion is disabled
+pub fn docx_converter_from_env() -> Option<Box<dyn DocumentConverter>> {
+    match std::env::var("DOCX_CONVERTER").map(|v| v.to_lowercase()).as_deref() {
+        Ok("none") | Ok("disabled") => None,
+        _ => {
+            let binary = std::env::var("LIBREOFFICE_PATH").unwrap_or_else(|_| "soffice".to_string());
+            let timeout = std::env::var("DOCX_CONVERSION_TIMEOUT_SECS").ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_TIMEOUT_SECS);
+            Some(Box::new(LibreOfficeConverter::new(binary, Duration::from_secs(timeout))))
+        }
+    }
+}
+
+/// Convert DOCX bytes with the configured converter
+pub async fn convert_docx_to_pdf(data: &[u8], filename: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let converter = docx_converter_from_env().ok_or("DOCX conversion is disabled")?;
+    converter.convert_to_pdf(data, filename).await
+        .map_err(|e| format!("{} failed to convert {}: {}", converter.name(), filename, e).into())
+}
+
+// ===== Headless LibreOffice =====
+
+pub struct LibreOfficeConverter {
+    binary: String,
+    timeout: Duration,
+}
+
+impl LibreOfficeConverter {
+    pub fn new(binary: String, timeout: Duration) -> Self {
+        Self { binary, timeout }
+    }
+
+    async fn run(&self, work_dir: &PathBuf, input: &PathBuf) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        // A profile per conversion: soffice refuses to run twice against the same profile
+        let profile = format!("-env:UserInstallation=file://{}", work_dir.join("profile").to_string_lossy());
+        let mut command = Command::new(&self.binary);
+        command.arg(profile)
+            .args(["--headless", "--norestore", "--nolockcheck", "--convert-to", "pdf", "--outdir"])
+            .arg(work_dir)
+            .arg(input)
+            .kill_on_drop(true);
+
+        let output = tokio::time::timeout(self.timeout, command.output()).await
+            .map_err(|_| format!("timed out after {}s", self.timeout.as_secs()))?
+            .map_err(|e| format!("failed to run {}: {}", self.binary, e))?;
+        if !output.status.success() {
+            return Err(format!("exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+
+        let pdf = tokio::fs::read(input.with_extension("pdf")).await
+            .map_err(|_| format!("produced no PDF: {}", String::from_utf8_lossy(&output.stderr).trim()))?;
+        if !pdf.starts_with(b"%PDF") {
+            return Err("produced an invalid PDF".into());
+        }
+        Ok(pdf)
+    }
+}
+
+#[async_trait]
+impl DocumentConverter for LibreOfficeConverter {
+    fn name(&self) -> String {
+        "LibreOffice".to_string()
+    }
+
+    async fn convert_to_pdf(&self, data: &[u8], _filename: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let work_dir = std::env::temp_dir().join(format!("docx_conversion_{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&work_dir).await?;
+
+        // Not the uploaded name: its extension selects LibreOffice's import filter, and a ".pdf"
+        // one would be overwritten by the output
+        let input = work_dir.join("document.docx");
+        let result = match tokio::fs::write(&input, data).await {
+            Ok(_) => self.run(&work_dir, &input).await,
+            Err(e) => Err(e.into()),
+        };
+
+        let _ = tokio::fs::remove_dir_all(&work_dir).await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    // A stand-in for soffice running the shell script; the input file is its last argument
+    fn stub_converter(script: &str, timeout: Duration) -> (LibreOfficeConverter, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("stub_soffice_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let binary = dir.join("soffice");
+        std::fs::write(&binary, format!("#!/bin/sh\nfor input; do :; done\n{}\n", script)).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        (LibreOfficeConverter::new(binary.to_string_lossy().to_string(), timeout), dir)
+    }
+
+    async fn convert(script: &str, timeout: Duration, data: &[u8], filename: &str) -> Result<Vec<u8>, String> {
+        let (converter, dir) = stub_converter(script, timeout);
+        let result = converter.convert_to_pdf(data, filename).await.map_err(|e| e.to_string());
+        std::fs::remove_dir_all(dir).unwrap();
+        result
+    }
+
+    #[tokio::test]
+    async fn converter_output_must_be_a_pdf() {
+        // Copies the input over, as if the DOCX were converted to itself
+        let copy = r#"case "$input" in *.docx) cp "$input" "${input%.docx}.pdf";; *) exit 1;; esac"#;
+        let timeout = Duration::from_secs(10);
+
+        assert_eq!(convert(copy, timeout, b"%PDF-1.7 converted", "contract.docx").await.unwrap(), b"%PDF-1.7 converted");
+        // Whatever the upload was called, the input is a .docx that the output doesn't overwrite
+        assert_eq!(convert(copy, timeout, b"%PDF-1.7 converted", "contract.pdf").await.unwrap(), b"%PDF-1.7 converted");
+        assert_eq!(convert(copy, timeout, b"PK not a PDF", "contract.docx").await.unwrap_err(), "produced an invalid PDF");
+        assert!(convert("exit 0", timeout, b"PK", "contract.docx").await.unwrap_err().starts_with("produced no PDF"));
+    }
+
+    #[tokio::test]
+    async fn converter_failures_and_timeouts_are_errors() {
+        let failed = convert("echo 'source file could not be loaded' >&2; exit 3", Duration::from_secs(10), b"PK", "contract.docx").await.unwrap_err();
+        assert!(failed.starts_with("exited with"), "{}", failed);
+        assert!(failed.ends_with("source file could not be loaded"), "{}", failed);
+
+        let timed_out = convert("sleep 10", Duration::from_millis(200), b"PK", "contract.docx").await.unwrap_err();
+        assert_eq!(timed_out, "timed out after 0s");
+
+        let missing = LibreOfficeConverter::new("/nonexistent/soffice".to_string(), Duration::from_secs(10));
+        let not_run = missing.convert_to_pdf(b"PK", "contract.docx").await.unwrap_err().to_string();
+        assert!(not_run.starts_with("failed to run /nonexistent/soffice"), "{}", not_run);
+    }
+
+    #[tokio::test]
+    async fn conversion_can_be_disabled() {
+        std::env::set_var("DOCX_CONVERTER", "none");
+        assert!(docx_converter_from_env().is_none());
+        let disabled = convert_docx_to_pdf(b"PK", "contract.docx").await.unwrap_err();
+        assert_eq!(disabled.to_string(), "DOCX conversion is disabled");
+        std::env::remove_var("DOCX_CONVERTER");
+        assert_eq!(docx_converter_from_env().map(|converter| converter.name()).as_deref(), Some("LibreOffice"));
+    }
+}
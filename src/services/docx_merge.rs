@@ -0,0 +1,403 @@
+// Mail merge for DOCX templates.
+//
+// {{name}} placeholders in the body, headers, footers and notes are replaced by submission
+// values; {{customer.name}} looks up nested objects. Word often splits what reads as one word
+// over several runs, so placeholders are matched against the text of a whole paragraph and the
+// value goes in the run the placeholder starts in, taking its formatting.
+//
+// A table row with {{items.description}}-style placeholders, where items is an array, is repeated
+// for each element ({{items}} alone is the element itself); an empty array removes the row.
+
+use std::collections::BTreeSet;
+use std::io::{Cursor, Read, Write};
+
+use serde_json::{Map, Value};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Placeholders no value was given for, sorted
+#[derive(Debug)]
+pub struct UnresolvedPlaceholders(pub Vec<String>);
+
+impl std::fmt::Display for UnresolvedPlaceholders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Unresolved placeholders: {}", self.0.join(", "))
+    }
+}
+
+impl std::error::Error for UnresolvedPlaceholders {}
+
+// Parts of the package that hold document text
+fn is_text_part(name: &str) -> bool {
+    name == "word/document.xml"
+        || name == "word/footnotes.xml"
+        || name == "word/endnotes.xml"
+        || ((name.starts_with("word/header") || name.starts_with("word/footer")) && name.ends_with(".xml"))
+}
+
+/// Names of the placeholders in a DOCX, sorted
+pub fn placeholders(docx: &[u8]) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut archive = ZipArchive::new(Cursor::new(docx))?;
+    let mut names = BTreeSet::new();
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if is_text_part(file.name()) {
+            let mut xml = String::new();
+            file.read_to_string(&mut xml)?;
+            replace_text(&xml, &mut |name| {
+                names.insert(name.to_string());
+                None
+            });
+        }
+    }
+    Ok(names.into_iter().collect())
+}
+
+/// Fill a DOCX's placeholders. Fails with UnresolvedPlaceholders when values are missing.
+pub fn merge_docx(docx: &[u8], values: &Map<String, Value>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut archive = ZipArchive::new(Cursor::new(docx))?;
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let mut unresolved = BTreeSet::new();
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if !is_text_part(file.name()) {
+            writer.raw_copy_file(file)?;
+            continue;
+        }
+        let name = file.name().to_string();
+        let mut xml = String::new();
+        file.read_to_string(&mut xml)?;
+        let xml = merge_part(&xml, values, &mut unresolved);
+        writer.start_file(name, SimpleFileOptions::default().compression_method(CompressionMethod::Deflated))?;
+        writer.write_all(xml.as_bytes())?;
+    }
+
+    if !unresolved.is_empty() {
+        return Err(Box::new(UnresolvedPlaceholders(unresolved.into_iter().collect())));
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+fn merge_part(xml: &str, values: &Map<String, Value>, unresolved: &mut BTreeSet<String>) -> String {
+    let xml = expand_rows(xml, values, unresolved);
+    replace_text(&xml, &mut |name| {
+        let value = lookup(values, name).and_then(value_text);
+        if value.is_none() {
+            unresolved.insert(name.to_string());
+        }
+        value
+    })
+}
+
+// Repeat table rows bound to an array value
+fn expand_rows(xml: &str, values: &Map<String, Value>, unresolved: &mut BTreeSet<String>) -> String {
+    let mut output = String::with_capacity(xml.len());
+    let mut position = 0;
+    let mut row_start = None;
+    let mut depth = 0;
+
+    for tag in Tags::new(xml) {
+        if tag.name != "w:tr" || tag.self_closing {
+            continue;
+        }
+        if !tag.closing {
+            if depth == 0 {
+                row_start = Some(tag.start);
+            }
+            depth += 1;
+            continue;
+        }
+        depth -= 1;
+        let Some(start) = row_start.filter(|_| depth == 0) else { continue };
+        let row = &xml[start..tag.end];
+        output.push_str(&xml[position..start]);
+        position = tag.end;
+
+        let Some((list, items)) = row_list(row, values) else {
+            // Rows of tables nested in this one may still repeat
+            let inner_start = row.find('>').map_or(0, |i| i + 1);
+            let inner_end = row.len() - (tag.end - tag.start);
+            output.push_str(&row[..inner_start]);
+            output.push_str(&expand_rows(&row[inner_start..inner_end], values, unresolved));
+            output.push_str(&row[inner_end..]);
+            continue;
+        };
+        for item in items {
+            output.push_str(&replace_text(row, &mut |name| {
+                let value = match name.strip_prefix(list.as_str()) {
+                    Some("") => Some(item),
+                    Some(path) if path.starts_with('.') => item.as_object().and_then(|item| lookup(item, &path[1..])),
+                    _ => lookup(values, name),
+                };
+                let value = value.and_then(value_text);
+                if value.is_none() {
+                    unresolved.insert(name.to_string());
+                }
+                value
+            }));
+        }
+    }
+    output.push_str(&xml[position..]);
+    output
+}
+
+// The array a row's placeholders refer to, if any
+fn row_list<'a>(row: &str, values: &'a Map<String, Value>) -> Option<(String, &'a Vec<Value>)> {
+    let mut list = None;
+    replace_text(row, &mut |name| {
+        if list.is_none() {
+            let prefix = name.split('.').next().unwrap_or(name);
+            if let Some(Value::Array(items)) = values.get(prefix) {
+                list = Some((prefix.to_string(), items));
+            }
+        }
+        None
+    });
+    list
+}
+
+// Value at a key, or a dotted path into nested objects
+fn lookup<'a>(values: &'a Map<String, Value>, name: &str) -> Option<&'a Value> {
+    if let Some(value) = values.get(name) {
+        return Some(value);
+    }
+    let (first, rest) = name.split_once('.')?;
+    lookup(values.get(first)?.as_object()?, rest)
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::String(text) => Some(text.clone()),
+        Value::Bool(_) | Value::Number(_) => Some(value.to_string()),
+        Value::Array(items) => items.iter()
+            .map(|item| match item {
+                Value::Array(_) | Value::Object(_) => None,
+                item => value_text(item),
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(|items| items.join(", ")),
+        Value::Object(_) => None,
+    }
+}
+
+// Replace the placeholders in each paragraph's text. resolve returns the text for a placeholder
+// name, or None to leave the placeholder as it is.
+fn replace_text(xml: &str, resolve: &mut dyn FnMut(&str) -> Option<String>) -> String {
+    // Text nodes (open tag start, content start, content end), grouped by paragraph
+    let mut paragraphs: Vec<Vec<(usize, usize, usize)>> = vec![Vec::new()];
+    let mut tags = Tags::new(xml);
+    while let Some(tag) = tags.next() {
+        match tag.name {
+            "w:p" => paragraphs.push(Vec::new()),
+            "w:t" if !tag.closing && !tag.self_closing => {
+                let Some(close) = xml[tag.end..].find("</w:t>") else { break };
+                paragraphs.last_mut().unwrap().push((tag.start, tag.end, tag.end + close));
+                tags.position = tag.end + close;
+            }
+            _ => {}
+        }
+    }
+
+    // New content of the text nodes that change
+    let mut edits = Vec::new();
+    for nodes in paragraphs.iter().filter(|nodes| !nodes.is_empty()) {
+        let text: String = nodes.iter().map(|&(_, start, end)| &xml[start..end]).collect();
+        let matches = find_placeholders(&text, resolve);
+        if matches.is_empty() {
+            continue;
+        }
+
+        let mut offset = 0;
+        for &(tag_start, start, end) in nodes {
+            let (node_start, node_end) = (offset, offset + end - start);
+            offset = node_end;
+            let overlapping: Vec<_> = matches.iter().filter(|m| m.0 < node_end && m.1 > node_start).collect();
+            if overlapping.is_empty() {
+                continue;
+            }
+            let mut content = String::new();
+            let mut position = node_start;
+            for (match_start, match_end, value) in overlapping {
+                if *match_start > position {
+                    content.push_str(&text[position..*match_start]);
+                }
+                if *match_start >= node_start {
+                    content.push_str(value);
+                }
+                position = position.max((*match_end).min(node_end));
+            }
+            if position < node_end {
+                content.push_str(&text[position..node_end]);
+            }
+            edits.push((tag_start, end, content));
+        }
+    }
+
+    let mut output = String::with_capacity(xml.len());
+    let mut position = 0;
+    for (tag_start, end, content) in edits {
+        output.push_str(&xml[position..tag_start]);
+        // Values may start or end with spaces
+        output.push_str("<w:t xml:space=\"preserve\">");
+        output.push_str(&content);
+        position = end;
+    }
+    output.push_str(&xml[position..]);
+    output
+}
+
+// Resolved placeholders in a paragraph's (XML escaped) text: start, end and the replacement XML
+fn find_placeholders(text: &str, resolve: &mut dyn FnMut(&str) -> Option<String>) -> Vec<(usize, usize, String)> {
+    let mut matches = Vec::new();
+    let mut position = 0;
+    while let Some(offset) = text[position..].find("{{") {
+        let start = position + offset;
+        let Some(length) = text[start + 2..].find("}}") else { break };
+        let end = start + 2 + length + 2;
+        let name = text[start + 2..end - 2].trim();
+        let valid = !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid {
+            position = start + 1;
+            continue;
+        }
+        if let Some(value) = resolve(name) {
+            matches.push((start, end, value_xml(&value)));
+        }
+        position = end;
+    }
+    matches
+}
+
+// Text as run content; line breaks become <w:br/>
+fn value_xml(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace("\r\n", "\n")
+        .replace('\n', "</w:t><w:br/><w:t xml:space=\"preserve\">")
+}
+
+struct Tag<'a> {
+    name: &'a str,
+    start: usize,
+    end: usize,
+    closing: bool,
+    self_closing: bool,
+}
+
+// Element tags of an XML document, in order
+struct Tags<'a> {
+    xml: &'a str,
+    position: usize,
+}
+
+impl<'a> Tags<'a> {
+    fn new(xml: &'a str) -> Self {
+        Self { xml, position: 0 }
+    }
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Tag<'a>> {
+        loop {
+            let start = self.position + self.xml[self.position..].find('<')?;
+            let end = start + self.xml[start..].find('>')? + 1;
+            self.position = end;
+            let tag = &self.xml[start + 1..end - 1];
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            let closing = tag.starts_with('/');
+            let name = tag.trim_start_matches('/');
+            let name = &name[..name.find(|c: char| c.is_whitespace() || c == '/').unwrap_or(name.len())];
+            return Some(Tag { name, start, end, closing, self_closing: tag.ends_with('/') });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn merge(xml: &str, values: Value) -> (String, Vec<String>) {
+        let mut unresolved = BTreeSet::new();
+        let xml = merge_part(xml, values.as_object().unwrap(), &mut unresolved);
+        (xml, unresolved.into_iter().collect())
+    }
+
+    #[test]
+    fn placeholders_split_over_runs_are_replaced() {
+        let xml = r#"<w:p><w:r><w:rPr><w:b/></w:rPr><w:t>Dear {{cust</w:t></w:r><w:r><w:t>omer_name}},</w:t></w:r></w:p>"#;
+        let (xml, unresolved) = merge(xml, json!({ "customer_name": "Ann & Bob" }));
+        assert_eq!(xml, r#"<w:p><w:r><w:rPr><w:b/></w:rPr><w:t xml:space="preserve">Dear Ann &amp; Bob</w:t></w:r><w:r><w:t xml:space="preserve">,</w:t></w:r></w:p>"#);
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn placeholders_do_not_span_paragraphs() {
+        let xml = r#"<w:p><w:r><w:t>{{a</w:t></w:r></w:p><w:p><w:r><w:t>}}</w:t></w:r></w:p>"#;
+        let (output, unresolved) = merge(xml, json!({ "a": "x" }));
+        assert_eq!(output, xml);
+        assert!(unresolved.is_empty());
+    }
+
+    #[test]
+    fn nested_values_and_line_breaks() {
+        let xml = r#"<w:p><w:r><w:t>{{ customer.address }}</w:t></w:r></w:p>"#;
+        let (xml, _) = merge(xml, json!({ "customer": { "address": "1 Main St\nSpringfield" } }));
+        assert_eq!(xml, r#"<w:p><w:r><w:t xml:space="preserve">1 Main St</w:t><w:br/><w:t xml:space="preserve">Springfield</w:t></w:r></w:p>"#);
+    }
+
+    #[test]
+    fn missing_values_are_reported() {
+        let xml = r#"<w:p><w:r><w:t>{{known}} {{unknown}} {{ not a placeholder }}</w:t></w:r></w:p>"#;
+        let (_, unresolved) = merge(xml, json!({ "known": 1 }));
+        assert_eq!(unresolved, vec!["unknown"]);
+    }
+
+    #[test]
+    fn table_rows_repeat_for_arrays() {
+        let row = r#"<w:tr><w:trPr/><w:tc><w:p><w:r><w:t>{{items.name}}</w:t></w:r></w:p></w:tc><w:tc><w:p><w:r><w:t>{{items.qty}} {{currency}}</w:t></w:r></w:p></w:tc></w:tr>"#;
+        let xml = format!("<w:tbl><w:tr><w:tc><w:p><w:r><w:t>Item</w:t></w:r></w:p></w:tc></w:tr>{}</w:tbl>", row);
+
+        let (output, unresolved) = merge(&xml, json!({
+            "currency": "EUR",
+            "items": [{ "name": "Pen", "qty": 2 }, { "name": "Ink", "qty": 1 }],
+        }));
+        assert!(unresolved.is_empty());
+        assert_eq!(output.matches("<w:tr>").count(), 3);
+        assert!(output.contains(">Pen<") && output.contains(">2 EUR<") && output.contains(">Ink<") && output.contains(">1 EUR<"));
+        assert!(!output.contains("{{"));
+
+        let (output, _) = merge(&xml, json!({ "currency": "EUR", "items": [] }));
+        assert_eq!(output.matches("<w:tr>").count(), 1);
+    }
+
+    #[test]
+    fn docx_round_trip() {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("[Content_Types].xml", SimpleFileOptions::default()).unwrap();
+        writer.write_all(b"<Types/>").unwrap();
+        writer.start_file("word/document.xml", SimpleFileOptions::default()).unwrap();
+        writer.write_all(br#"<w:document><w:body><w:p><w:r><w:t>Hello {{name}}</w:t></w:r></w:p></w:body></w:document>"#).unwrap();
+        writer.start_file("word/footer1.xml", SimpleFileOptions::default()).unwrap();
+        writer.write_all(br#"<w:ftr><w:p><w:r><w:t>{{company}}</w:t></w:r></w:p></w:ftr>"#).unwrap();
+        let docx = writer.finish().unwrap().into_inner();
+
+        assert_eq!(placeholders(&docx).unwrap(), vec!["company", "name"]);
+        let error = merge_docx(&docx, json!({ "name": "Ann" }).as_object().unwrap()).unwrap_err();
+        assert_eq!(error.downcast_ref::<UnresolvedPlaceholders>().unwrap().0, vec!["company"]);
+
+        let merged = merge_docx(&docx, json!({ "name": "Ann", "company": "ACME" }).as_object().unwrap()).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(merged)).unwrap();
+        let mut document = String::new();
+        archive.by_name("word/document.xml").unwrap().read_to_string(&mut document).unwrap();
+        assert!(document.contains("Hello Ann"));
+        assert!(archive.by_name("[Content_Types].xml").is_ok());
+    }
+}
@@ -1073,7 +1073,7 @@ impl EmailService {
         body_format: &str,
         attach_documents: bool,
         attach_audit_log: bool,
-        document_path: Option<&str>,
+        documents: &[(String, String)], // (attachment file name, path)
         audit_log_path: Option<&str>,
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
         if self.test_mode {
@@ -1100,19 +1100,18 @@ impl EmailService {
         let multipart_builder = MultiPart::mixed().singlepart(body_part);
 
         // Add attachments if requested
-        let multipart_builder = if attach_documents && document_path.is_some() {
-            if let Ok(content) = tokio::fs::read(document_path.unwrap()).await {
-                let attachment = SinglePart::builder()
-                    .header(lettre::message::header::ContentType::parse("application/pdf").unwrap())
-                    .header(ContentDisposition::attachment("signed_document.pdf"))
-                    .body(content);
-                multipart_builder.singlepart(attachment)
-            } else {
-                multipart_builder
+        let mut multipart_builder = multipart_builder;
+        if attach_documents {
+            for (filename, path) in documents {
+                if let Ok(content) = tokio::fs::read(path).await {
+                    let attachment = SinglePart::builder()
+                        .header(lettre::message::header::ContentType::parse("application/pdf").unwrap())
+                        .header(ContentDisposition::attachment(filename))
+                        .body(content);
+                    multipart_builder = multipart_builder.singlepart(attachment);
+                }
             }
-        } else {
-            multipart_builder
-        };
+        }
 
         let multipart_builder = if attach_audit_log && audit_log_path.is_some() {
             if let Ok(content) = tokio::fs::read(audit_log_path.unwrap()).await {
@@ -0,0 +1,178 @@
+// Field snippets: groups of template fields saved with their positions relative to each other,
+// to place them again as new fields on another template.
+//
+// Positions are saved relative to the top-left corner of the group's bounding box, and pages
+// relative to the group's first page, so a snippet spanning two pages can be applied to any
+// page and the ones after it.
+
+use std::collections::HashSet;
+
+use crate::database::models::{CreateTemplateField, DbTemplateField};
+use crate::models::field_snippet::{SnippetField, SnippetPosition};
+use crate::models::template::FieldPosition;
+
+/// The fields can't be saved as a snippet
+#[derive(Debug, PartialEq)]
+pub struct InvalidSnippet(pub String);
+
+impl std::fmt::Display for InvalidSnippet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidSnippet {}
+
+/// Snippet fields from template fields, in display order. The fields must all be placed, and
+/// on the same document.
+pub fn snippet_fields(fields: &[DbTemplateField]) -> Result<Vec<SnippetField>, InvalidSnippet> {
+    if fields.is_empty() {
+        return Err(InvalidSnippet("A snippet needs at least one field".to_string()));
+    }
+
+    let mut placed = Vec::with_capacity(fields.len());
+    for field in fields {
+        let position = field.position.clone()
+            .and_then(|position| serde_json::from_value::<FieldPosition>(position).ok())
+            .ok_or_else(|| InvalidSnippet(format!("Field '{}' is not placed on a page", field.name)))?;
+        placed.push((field, position));
+    }
+    if placed.iter().any(|(_, position)| position.document_id != placed[0].1.document_id) {
+        return Err(InvalidSnippet("All fields of a snippet must be on the same document".to_string()));
+    }
+    placed.sort_by_key(|(field, _)| (field.display_order, field.id));
+
+    let left = placed.iter().map(|(_, p)| p.x).fold(f64::INFINITY, f64::min);
+    let top = placed.iter().map(|(_, p)| p.y).fold(f64::INFINITY, f64::min);
+    let first_page = placed.iter().map(|(_, p)| p.page).min().unwrap_or(1);
+
+    Ok(placed.into_iter()
+        .map(|(field, position)| SnippetField {
+            name: field.name.clone(),
+            field_type: field.field_type.clone(),
+            required: field.required,
+            position: SnippetPosition {
+                x: position.x - left,
+                y: position.y - top,
+                width: position.width,
+                height: position.height,
+                page_offset: position.page - first_page,
+            },
+            options: field.options.clone(),
+            partner: field.partner.clone(),
+        })
+        .collect())
+}
+
+// The name, or the name followed by the first free number
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    if !taken.contains(name) {
+        return name.to_string();
+    }
+    (2..).map(|n| format!("{} {}", name, n)).find(|candidate| !taken.contains(candidate)).unwrap_or_default()
+}
+
+/// New template fields placing the snippet's top-left corner at (x, y) on `page`. Fields get
+/// names not in `taken_names` and display orders from `first_display_order`.
+#[allow(clippy::too_many_arguments)]
+pub fn place_fields(
+    template_id: i64,
+    fields: &[SnippetField],
+    page: i32,
+    x: f64,
+    y: f64,
+    document_id: Option<String>,
+    partner: Option<&str>,
+    taken_names: &HashSet<String>,
+    first_display_order: i32,
+) -> Vec<CreateTemplateField> {
+    let mut taken = taken_names.clone();
+    fields.iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let name = unique_name(&field.name, &taken);
+            taken.insert(name.clone());
+            let position = FieldPosition {
+                x: x + field.position.x,
+                y: y + field.position.y,
+                width: field.position.width,
+                height: field.position.height,
+                page: page + field.position.page_offset,
+                document_id: document_id.clone(),
+                suggested: None,
+                allow_custom: None,
+                out_of_bounds: None,
+            };
+            CreateTemplateField {
+                template_id,
+                name,
+                field_type: field.field_type.clone(),
+                required: field.required,
+                display_order: first_display_order + i as i32,
+                position: serde_json::to_value(position).ok(),
+                options: field.options.clone(),
+                metadata: None,
+                partner: partner.map(str::to_string).or_else(|| field.partner.clone()),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn field(id: i64, name: &str, display_order: i32, position: Option<serde_json::Value>) -> DbTemplateField {
+        DbTemplateField {
+            id,
+            template_id: 1,
+            name: name.to_string(),
+            field_type: "text".to_string(),
+            required: true,
+            display_order,
+            position,
+            options: None,
+            metadata: None,
+            partner: Some("Client".to_string()),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn snippets_keep_relative_positions() {
+        let fields = vec![
+            field(2, "Date", 2, Some(serde_json::json!({"x": 300.0, "y": 540.0, "width": 80.0, "height": 20.0, "page": 3}))),
+            field(1, "Signature", 1, Some(serde_json::json!({"x": 100.0, "y": 500.0, "width": 150.0, "height": 40.0, "page": 2}))),
+        ];
+        let snippet = snippet_fields(&fields).unwrap();
+        assert_eq!(snippet[0].name, "Signature");
+        assert_eq!(snippet[0].position, SnippetPosition { x: 0.0, y: 0.0, width: 150.0, height: 40.0, page_offset: 0 });
+        assert_eq!(snippet[1].position, SnippetPosition { x: 200.0, y: 40.0, width: 80.0, height: 20.0, page_offset: 1 });
+
+        let taken = HashSet::from(["Signature".to_string(), "Signature 2".to_string()]);
+        let placed = place_fields(9, &snippet, 4, 50.0, 60.0, Some("doc-1".to_string()), Some("Employee"), &taken, 10);
+        assert_eq!(placed[0].name, "Signature 3");
+        assert_eq!(placed[1].name, "Date");
+        assert_eq!((placed[0].display_order, placed[1].display_order), (10, 11));
+        assert_eq!(placed[1].partner.as_deref(), Some("Employee"));
+        let position: FieldPosition = serde_json::from_value(placed[1].position.clone().unwrap()).unwrap();
+        assert_eq!((position.x, position.y, position.page, position.document_id.as_deref()), (250.0, 100.0, 5, Some("doc-1")));
+    }
+
+    #[test]
+    fn snippets_need_placed_fields_on_one_document() {
+        assert!(snippet_fields(&[]).is_err());
+        assert_eq!(
+            snippet_fields(&[field(1, "Title", 1, None)]),
+            Err(InvalidSnippet("Field 'Title' is not placed on a page".to_string()))
+        );
+        let fields = vec![
+            field(1, "Name", 1, Some(serde_json::json!({"x": 0.1, "y": 0.1, "width": 0.2, "height": 0.05, "page": 1}))),
+            field(2, "Title", 2, Some(serde_json::json!({"x": 0.1, "y": 0.2, "width": 0.2, "height": 0.05, "page": 1, "document_id": "2"}))),
+        ];
+        assert!(snippet_fields(&fields).is_err());
+    }
+}
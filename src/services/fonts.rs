@@ -0,0 +1,867 @@
+// TrueType fonts embedded into generated PDFs.
+//
+// Configuration (environment):
+//   PDF_FONTS_DIR       directory with .ttf/.otf/.ttc files (default "fonts"). A font named after
+//                       the PDF base font (e.g. Arial.ttf, Helvetica-Bold.ttf) is used for that
+//                       font, default.ttf for everything else. Common system fonts are the last
+//                       resort.
+//   PDF_FONT_FALLBACKS  comma-separated font files (in PDF_FONTS_DIR, or absolute paths) tried
+//                       first for text fields, e.g. "NotoSans-Regular.ttf,NotoSansSC-Regular.otf".
+//                       Characters missing from all of them fall back to default.ttf, the other
+//                       fonts in PDF_FONTS_DIR and then the system fonts.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use ab_glyph::{Font, FontRef};
+use lopdf::content::Operation;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use rustybuzz::ttf_parser::GlyphId;
+use rustybuzz::{Direction, UnicodeBuffer};
+use subsetter::GlyphRemapper;
+use unicode_bidi::BidiInfo;
+
+use crate::services::acroform::{decode_win_ansi, encode_win_ansi};
+use crate::services::page_geometry::inherited_attribute;
+
+const SYSTEM_FONTS: [&str; 4] = [
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+];
+
+// Scripts the Latin system fonts don't cover (CJK, Arabic)
+const UNICODE_SYSTEM_FONTS: [&str; 6] = [
+    "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoNaskhArabic-Regular.ttf",
+    "/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc",
+    "/usr/share/fonts/truetype/wqy/wqy-microhei.ttc",
+];
+
+// FontDescriptor flags: Symbolic (CID fonts), Nonsymbolic (WinAnsi fonts)
+pub(crate) const FLAG_SYMBOLIC: i64 = 4;
+pub(crate) const FLAG_NONSYMBOLIC: i64 = 32;
+
+// Average Helvetica width, for layout when no font file is available at all
+const STANDARD_FONT_WIDTH: f64 = 556.0;
+
+// MacRomanEncoding codes 128..=255
+const MAC_ROMAN_HIGH: &str = "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü\u{2020}\u{b0}\u{a2}\u{a3}\u{a7}\u{2022}\u{b6}ß\u{ae}\u{a9}\u{2122}\u{b4}\u{a8}\u{2260}ÆØ\u{221e}\u{b1}\u{2264}\u{2265}\u{a5}\u{b5}\u{2202}\u{2211}\u{220f}\u{3c0}\u{222b}\u{aa}\u{ba}\u{3a9}æø\u{bf}\u{a1}\u{ac}\u{221a}ƒ\u{2248}\u{2206}\u{ab}\u{bb}\u{2026}\u{a0}ÀÃÕŒœ\u{2013}\u{2014}\u{201c}\u{201d}\u{2018}\u{2019}\u{f7}\u{25ca}ÿŸ\u{2044}\u{a4}\u{2039}\u{203a}\u{fb01}\u{fb02}\u{2021}\u{b7}\u{201a}\u{201e}\u{2030}ÂÊÁËÈÍÎÏÌÓÔ\u{f8ff}ÒÚÛÙı\u{2c6}\u{2dc}\u{af}\u{2d8}\u{2d9}\u{2da}\u{b8}\u{2dd}\u{2db}\u{2c7}";
+
+pub struct FontFile {
+    // PostScript-safe name derived from the file name
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+pub fn fonts_dir() -> PathBuf {
+    PathBuf::from(std::env::var("PDF_FONTS_DIR").unwrap_or_else(|_| "fonts".to_string()))
+}
+
+/// Find a TrueType font for a PDF base font name (subset prefixes like ABCDEF+ are ignored)
+pub fn resolve_font(base_font: &str) -> Option<FontFile> {
+    let base_font = base_font.split('+').next_back().unwrap_or(base_font);
+    let dir = fonts_dir();
+    let candidates = [
+        dir.join(format!("{}.ttf", base_font)),
+        dir.join(format!("{}.ttf", base_font.replace(',', "-"))),
+        dir.join("default.ttf"),
+    ];
+    candidates.iter()
+        .map(|p| p.as_path())
+        .chain(SYSTEM_FONTS.iter().map(Path::new))
+        .find_map(load_truetype)
+}
+
+fn load_truetype(path: &Path) -> Option<FontFile> {
+    let font = load_font(path)?;
+    // FontFile2 only takes TrueType outlines
+    if !(font.data.starts_with(&[0x00, 0x01, 0x00, 0x00]) || font.data.starts_with(b"true")) {
+        return None;
+    }
+    FontRef::try_from_slice(&font.data).ok()?;
+    Some(font)
+}
+
+// Any OpenType font (TrueType or CFF outlines, first font of a collection)
+fn load_font(path: &Path) -> Option<FontFile> {
+    let data = std::fs::read(path).ok()?;
+    rustybuzz::ttf_parser::Face::parse(&data, 0).ok()?;
+    let name: String = path.file_stem()?.to_string_lossy()
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .collect();
+    Some(FontFile { name, data })
+}
+
+/// Fonts tried in order for each character of Unicode text, loaded once
+pub fn font_chain() -> &'static [FontFile] {
+    static CHAIN: OnceLock<Vec<FontFile>> = OnceLock::new();
+    CHAIN.get_or_init(load_font_chain)
+}
+
+fn load_font_chain() -> Vec<FontFile> {
+    let dir = fonts_dir();
+    let mut paths: Vec<PathBuf> = std::env::var("PDF_FONT_FALLBACKS").unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(|name| dir.join(name))
+        .collect();
+    paths.push(dir.join("default.ttf"));
+
+    let mut dir_fonts: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    dir_fonts.retain(|path| {
+        let extension = path.extension().map(|e| e.to_string_lossy().to_lowercase());
+        matches!(extension.as_deref(), Some("ttf" | "otf" | "ttc"))
+    });
+    dir_fonts.sort();
+    paths.extend(dir_fonts);
+    paths.extend(SYSTEM_FONTS.iter().chain(UNICODE_SYSTEM_FONTS.iter()).map(PathBuf::from));
+
+    let mut seen = HashSet::new();
+    paths.into_iter()
+        .filter(|path| seen.insert(path.clone()))
+        .filter_map(|path| load_font(&path))
+        .collect()
+}
+
+/// Encoding of a simple font: WinAnsi or MacRoman (the base encodings PDF/A allows for TrueType
+/// fonts), with the codes /Differences maps to other glyphs by name
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SimpleEncoding {
+    pub mac_roman: bool,
+    pub differences: BTreeMap<u8, String>,
+}
+
+impl SimpleEncoding {
+    /// Encoding of a font's /Encoding entry. Other base encodings are read as WinAnsi.
+    pub fn from_object(doc: &Document, encoding: Option<&Object>) -> Self {
+        let encoding = match encoding.map(|e| doc.dereference(e).map(|(_, e)| e)) {
+            Some(Ok(encoding)) => encoding,
+            _ => return Self::default(),
+        };
+        let (base, differences) = match encoding {
+            Object::Name(name) => (Some(name.as_slice()), None),
+            Object::Dictionary(dict) => (
+                dict.get(b"BaseEncoding").and_then(|b| b.as_name()).ok(),
+                dict.get(b"Differences").and_then(|d| d.as_array()).ok(),
+            ),
+            _ => (None, None),
+        };
+
+        // [code /name /name code /name ...]: names are assigned to consecutive codes
+        let mut mapped = BTreeMap::new();
+        let mut code = 0i64;
+        for item in differences.into_iter().flatten() {
+            match item {
+                Object::Integer(start) => code = *start,
+                Object::Name(name) => {
+                    if let Ok(byte) = u8::try_from(code) {
+                        mapped.insert(byte, String::from_utf8_lossy(name).to_string());
+                    }
+                    code += 1;
+                }
+                _ => {}
+            }
+        }
+        Self { mac_roman: base == Some(b"MacRomanEncoding".as_slice()), differences: mapped }
+    }
+
+    fn base_name(&self) -> &'static [u8] {
+        if self.mac_roman { b"MacRomanEncoding" } else { b"WinAnsiEncoding" }
+    }
+
+    fn to_object(&self) -> Object {
+        if self.differences.is_empty() {
+            return Object::Name(self.base_name().to_vec());
+        }
+        let mut differences = Vec::new();
+        let mut next = None;
+        for (code, name) in &self.differences {
+            if next != Some(*code as i64) {
+                differences.push(Object::Integer(*code as i64));
+            }
+            differences.push(Object::Name(name.as_bytes().to_vec()));
+            next = Some(*code as i64 + 1);
+        }
+        let mut encoding = Dictionary::new();
+        encoding.set("Type", Object::Name(b"Encoding".to_vec()));
+        encoding.set("BaseEncoding", Object::Name(self.base_name().to_vec()));
+        encoding.set("Differences", Object::Array(differences));
+        Object::Dictionary(encoding)
+    }
+
+    // Character a code stands for in the base encoding
+    fn base_char(&self, code: u8) -> Option<char> {
+        match (self.mac_roman, code) {
+            (true, 0x20..=0x7E) => Some(code as char),
+            (true, 0x80..=0xFF) => MAC_ROMAN_HIGH.chars().nth((code - 0x80) as usize),
+            (true, _) => None,
+            (false, _) => decode_win_ansi(code),
+        }
+    }
+}
+
+/// Embeds TrueType fonts as simple fonts, sharing font programs between fonts
+#[derive(Default)]
+pub struct FontEmbedder {
+    fonts: HashMap<(String, SimpleEncoding), ObjectId>,
+    // Font descriptor (with the font program) per font file
+    descriptors: HashMap<String, ObjectId>,
+}
+
+impl FontEmbedder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Embedded replacement for a (non-embedded) simple font, as a new object
+    pub fn embed_simple_font(
+        &mut self,
+        doc: &mut Document,
+        base_font: &str,
+        encoding: &SimpleEncoding,
+    ) -> Result<ObjectId, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let key = (base_font.to_string(), encoding.clone());
+        if let Some(id) = self.fonts.get(&key) {
+            return Ok(*id);
+        }
+        let font = self.simple_font_dictionary(doc, base_font, encoding)?;
+        let id = doc.add_object(font);
+        self.fonts.insert(key, id);
+        Ok(id)
+    }
+
+    /// Font dictionary (Subtype TrueType) with an embedded font program, keeping the encoding
+    pub fn simple_font_dictionary(
+        &mut self,
+        doc: &mut Document,
+        base_font: &str,
+        encoding: &SimpleEncoding,
+    ) -> Result<Dictionary, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let file = resolve_font(base_font)
+            .ok_or_else(|| format!("No TrueType font available to embed for {} (set PDF_FONTS_DIR)", base_font))?;
+        let parsed = FontRef::try_from_slice(&file.data)?;
+        let face = rustybuzz::ttf_parser::Face::parse(&file.data, 0)?;
+        let scale = 1000.0 / parsed.units_per_em().unwrap_or(1000.0);
+
+        // Glyphs renamed by /Differences are found by their name in the font's post table
+        let first_char = encoding.differences.keys().next().map_or(32, |code| (*code).min(32));
+        let widths: Vec<Object> = (first_char..=255)
+            .map(|code| {
+                let glyph = match encoding.differences.get(&code) {
+                    Some(name) => face.glyph_index_by_name(name).map(|id| ab_glyph::GlyphId(id.0)),
+                    None => encoding.base_char(code).map(|c| parsed.glyph_id(c)),
+                };
+                let width = glyph.map(|g| parsed.h_advance_unscaled(g) * scale).unwrap_or(0.0);
+                Object::Integer(width.round() as i64)
+            })
+            .collect();
+
+        let descriptor_id = match self.descriptors.get(&file.name) {
+            Some(id) => *id,
+            None => {
+                let descriptor = font_descriptor(doc, &file, &parsed, scale);
+                let id = doc.add_object(descriptor);
+                self.descriptors.insert(file.name.clone(), id);
+                id
+            }
+        };
+
+        let mut font = Dictionary::new();
+        font.set("Type", Object::Name(b"Font".to_vec()));
+        font.set("Subtype", Object::Name(b"TrueType".to_vec()));
+        font.set("BaseFont", Object::Name(file.name.into_bytes()));
+        font.set("FirstChar", Object::Integer(first_char as i64));
+        font.set("LastChar", Object::Integer(255));
+        font.set("Widths", Object::Array(widths));
+        font.set("Encoding", encoding.to_object());
+        font.set("FontDescriptor", Object::Reference(descriptor_id));
+        Ok(font)
+    }
+}
+
+fn font_descriptor(doc: &mut Document, file: &FontFile, font: &FontRef, scale: f32) -> Dictionary {
+    // Bounding box over the WinAnsi glyphs
+    let mut bbox = [0f32, 0f32, 0f32, 0f32];
+    for c in (32u8..=255).filter_map(decode_win_ansi) {
+        if let Some(outline) = font.outline(font.glyph_id(c)) {
+            bbox[0] = bbox[0].min(outline.bounds.min.x);
+            bbox[1] = bbox[1].min(outline.bounds.min.y);
+            bbox[2] = bbox[2].max(outline.bounds.max.x);
+            bbox[3] = bbox[3].max(outline.bounds.max.y);
+        }
+    }
+    let cap_height = font.outline(font.glyph_id('H'))
+        .map(|o| o.bounds.max.y)
+        .unwrap_or(font.ascent_unscaled());
+
+    let mut font_file_dict = Dictionary::new();
+    font_file_dict.set("Length1", Object::Integer(file.data.len() as i64));
+    let mut font_file = Stream::new(font_file_dict, file.data.clone());
+    let _ = font_file.compress();
+    let font_file_id = doc.add_object(font_file);
+
+    let mut descriptor = Dictionary::new();
+    descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
+    descriptor.set("FontName", Object::Name(file.name.clone().into_bytes()));
+    descriptor.set("Flags", Object::Integer(FLAG_NONSYMBOLIC));
+    descriptor.set("FontBBox", Object::Array(bbox.iter().map(|v| Object::Integer((v * scale).round() as i64)).collect()));
+    descriptor.set("ItalicAngle", Object::Real(font.italic_angle()));
+    descriptor.set("Ascent", Object::Integer((font.ascent_unscaled() * scale).round() as i64));
+    descriptor.set("Descent", Object::Integer((font.descent_unscaled() * scale).round() as i64));
+    descriptor.set("CapHeight", Object::Integer((cap_height * scale).round() as i64));
+    descriptor.set("StemV", Object::Integer(80));
+    descriptor.set("FontFile2", Object::Reference(font_file_id));
+    descriptor
+}
+
+/// Per-document Unicode text. Every character is drawn with the first font of the font chain that
+/// has a glyph for it, shaped with rustybuzz (ligatures, combining marks, Arabic joining) and laid
+/// out right to left where the bidi algorithm says so. Fonts are embedded as subsetted Type0/CID
+/// fonts with a ToUnicode map, so the text stays searchable. Call finish() before saving.
+pub struct UnicodeFonts {
+    chain: &'static [FontFile],
+    faces: Vec<rustybuzz::Face<'static>>,
+    subsets: HashMap<usize, FontSubset>,
+    // Helvetica, only used when no font file is available at all
+    standard: Option<ObjectId>,
+}
+
+struct FontSubset {
+    // Reserved for the Type0 font, written by finish()
+    id: ObjectId,
+    resource: Vec<u8>,
+    remapper: GlyphRemapper,
+    // Width (1/1000 em) and text of each CID
+    widths: BTreeMap<u16, i64>,
+    to_unicode: BTreeMap<u16, String>,
+}
+
+/// A line of shaped text, left to right in visual order
+pub struct TextLine {
+    runs: Vec<GlyphRun>,
+}
+
+struct GlyphRun {
+    resource: Vec<u8>,
+    glyphs: Vec<PlacedGlyph>,
+}
+
+struct PlacedGlyph {
+    // 2-byte CID, or a WinAnsi byte for the standard font
+    code: Vec<u8>,
+    // 1/1000 em: width from the font dictionary, shaped advance and offsets
+    width: f64,
+    advance: f64,
+    x_offset: f64,
+    y_offset: f64,
+}
+
+impl TextLine {
+    /// Advance width in points
+    pub fn width(&self, font_size: f64) -> f64 {
+        self.runs.iter()
+            .flat_map(|run| &run.glyphs)
+            .map(|glyph| glyph.advance)
+            .sum::<f64>() * font_size / 1000.0
+    }
+
+    /// Tf/TJ operations showing the line at the current text position (inside BT/ET)
+    pub fn operations(&self, font_size: f64) -> Vec<Operation> {
+        let mut operations = Vec::new();
+        for run in &self.runs {
+            operations.push(Operation::new("Tf", vec![
+                Object::Name(run.resource.clone()),
+                Object::Real(font_size as f32),
+            ]));
+            let mut items = Vec::new();
+            let mut rise = 0.0;
+            for glyph in &run.glyphs {
+                if glyph.y_offset != rise {
+                    flush_show_text(&mut operations, &mut items);
+                    rise = glyph.y_offset;
+                    operations.push(Operation::new("Ts", vec![Object::Real((rise * font_size / 1000.0) as f32)]));
+                }
+                // TJ numbers move the pen left, in 1/1000 em
+                if glyph.x_offset != 0.0 {
+                    push_adjustment(&mut items, -glyph.x_offset);
+                }
+                match items.last_mut() {
+                    Some(Object::String(bytes, _)) => bytes.extend_from_slice(&glyph.code),
+                    _ => items.push(Object::String(glyph.code.clone(), StringFormat::Hexadecimal)),
+                }
+                let remaining = glyph.advance - glyph.x_offset - glyph.width;
+                if remaining.abs() > 0.5 {
+                    push_adjustment(&mut items, -remaining);
+                }
+            }
+            flush_show_text(&mut operations, &mut items);
+            if rise != 0.0 {
+                operations.push(Operation::new("Ts", vec![Object::Real(0.0)]));
+            }
+        }
+        operations
+    }
+}
+
+fn push_adjustment(items: &mut Vec<Object>, amount: f64) {
+    match items.last_mut() {
+        Some(Object::Real(value)) => *value += amount as f32,
+        _ => items.push(Object::Real(amount as f32)),
+    }
+}
+
+fn flush_show_text(operations: &mut Vec<Operation>, items: &mut Vec<Object>) {
+    if !items.is_empty() {
+        operations.push(Operation::new("TJ", vec![Object::Array(std::mem::take(items))]));
+    }
+}
+
+impl Default for UnicodeFonts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnicodeFonts {
+    pub fn new() -> Self {
+        let chain = font_chain();
+        let faces = chain.iter()
+            .filter_map(|font| rustybuzz::Face::from_slice(&font.data, 0))
+            .collect();
+        Self { chain, faces, subsets: HashMap::new(), standard: None }
+    }
+
+    /// Shape a single line of text (line breaks and tabs become spaces)
+    pub fn layout(&mut self, doc: &mut Document, text: &str) -> TextLine {
+        let text: String = text.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+        if self.faces.is_empty() {
+            return self.layout_standard(doc, &text);
+        }
+
+        let bidi = BidiInfo::new(&text, None);
+        let mut runs = Vec::new();
+        for paragraph in &bidi.paragraphs {
+            let (levels, level_runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for range in level_runs {
+                let rtl = levels[range.start].is_rtl();
+                let mut shaped: Vec<GlyphRun> = self.font_segments(&text[range])
+                    .into_iter()
+                    .map(|(font, segment)| self.shape(doc, font, segment, rtl))
+                    .collect();
+                // Segments are in logical order
+                if rtl {
+                    shaped.reverse();
+                }
+                runs.extend(shaped);
+            }
+        }
+        TextLine { runs }
+    }
+
+    /// Replace `Tj` operations whose operand is UTF-8 text (Object::string_literal of a Rust
+    /// string) by shaped text, sized by the preceding `Tf`. `Tf` operations are consumed, since
+    /// every line sets its own fonts.
+    pub fn encode_operations(&mut self, doc: &mut Document, operations: Vec<Operation>) -> Vec<Operation> {
+        let mut font_size = 12.0;
+        let mut encoded = Vec::with_capacity(operations.len());
+        for operation in operations {
+            match (operation.operator.as_str(), operation.operands.as_slice()) {
+                ("Tf", [_, size]) => {
+                    font_size = size.as_float().map(f64::from).unwrap_or(font_size);
+                }
+                ("Tj", [Object::String(bytes, _)]) => {
+                    let text = String::from_utf8_lossy(bytes).to_string();
+                    encoded.extend(self.layout(doc, &text).operations(font_size));
+                }
+                _ => encoded.push(operation),
+            }
+        }
+        encoded
+    }
+
+    /// Add every font used so far to the page's resources
+    pub fn add_to_page(&self, doc: &mut Document, page_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let standard = self.standard.map(|id| (format!("LmsU{}", id.0).into_bytes(), id));
+        for (resource, id) in self.subsets.values().map(|s| (s.resource.clone(), s.id)).chain(standard) {
+            add_font_resource(doc, page_id, &resource, id)?;
+        }
+        Ok(())
+    }
+
+    /// Write the subsetted fonts for everything laid out so far
+    pub fn finish(self, doc: &mut Document) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        for (font, subset) in self.subsets {
+            let type0 = cid_font(doc, &self.chain[font], &self.faces[font], &subset)?;
+            doc.objects.insert(subset.id, Object::Dictionary(type0));
+        }
+        Ok(())
+    }
+
+    // Split text into runs of characters drawn with the same font of the chain
+    fn font_segments<'t>(&self, text: &'t str) -> Vec<(usize, &'t str)> {
+        let has_glyph = |font: usize, c: char| self.faces[font].glyph_index(c).is_some();
+        let mut segments = Vec::new();
+        let mut current: Option<(usize, usize)> = None;
+        for (offset, c) in text.char_indices() {
+            // Spaces, combining marks and joiners stay with the current font when it has them
+            let font = match current {
+                Some((font, _)) if has_glyph(font, c) => font,
+                _ => (0..self.faces.len()).find(|&font| has_glyph(font, c)).unwrap_or_else(|| {
+                    eprintln!("No font has a glyph for U+{:04X}; add one to PDF_FONTS_DIR", c as u32);
+                    0
+                }),
+            };
+            match current {
+                Some((previous, start)) if previous != font => {
+                    segments.push((previous, &text[start..offset]));
+                    current = Some((font, offset));
+                }
+                None => current = Some((font, offset)),
+                _ => {}
+            }
+        }
+        if let Some((font, start)) = current {
+            segments.push((font, &text[start..]));
+        }
+        segments
+    }
+
+    fn shape(&mut self, doc: &mut Document, font: usize, text: &str, rtl: bool) -> GlyphRun {
+        let face = &self.faces[font];
+        let mut buffer = UnicodeBuffer::new();
+        buffer.push_str(text);
+        buffer.guess_segment_properties();
+        buffer.set_direction(if rtl { Direction::RightToLeft } else { Direction::LeftToRight });
+        let shaped = rustybuzz::shape(face, &[], buffer);
+        let scale = 1000.0 / face.units_per_em() as f64;
+
+        // Text of each cluster, for the ToUnicode map
+        let mut cluster_starts: Vec<usize> = shaped.glyph_infos().iter().map(|info| info.cluster as usize).collect();
+        cluster_starts.sort_unstable();
+        cluster_starts.dedup();
+        let cluster_text = |start: usize| {
+            let end = cluster_starts.iter().copied().find(|&s| s > start).unwrap_or(text.len());
+            &text[start..end]
+        };
+
+        let subset = self.subsets.entry(font).or_insert_with(|| {
+            let id = doc.new_object_id();
+            FontSubset {
+                id,
+                resource: format!("LmsU{}", id.0).into_bytes(),
+                remapper: GlyphRemapper::new(),
+                widths: BTreeMap::new(),
+                to_unicode: BTreeMap::new(),
+            }
+        });
+
+        let mut mapped_clusters = HashSet::new();
+        let glyphs = shaped.glyph_infos().iter()
+            .zip(shaped.glyph_positions())
+            .map(|(info, position)| {
+                let gid = info.glyph_id as u16;
+                let cid = subset.remapper.remap(gid);
+                let width = *subset.widths.entry(cid).or_insert_with(|| {
+                    (face.glyph_hor_advance(GlyphId(gid)).unwrap_or(0) as f64 * scale).round() as i64
+                });
+                // Ligatures map to all their characters, the other glyphs of a cluster (and
+                // .notdef, which stands for many characters) to nothing
+                if gid != 0 && mapped_clusters.insert(info.cluster) {
+                    subset.to_unicode.entry(cid).or_insert_with(|| cluster_text(info.cluster as usize).to_string());
+                }
+                PlacedGlyph {
+                    code: cid.to_be_bytes().to_vec(),
+                    width: width as f64,
+                    advance: position.x_advance as f64 * scale,
+                    x_offset: position.x_offset as f64 * scale,
+                    y_offset: position.y_offset as f64 * scale,
+                }
+            })
+            .collect();
+        GlyphRun { resource: subset.resource.clone(), glyphs }
+    }
+
+    fn layout_standard(&mut self, doc: &mut Document, text: &str) -> TextLine {
+        let id = *self.standard.get_or_insert_with(|| {
+            let mut font = Dictionary::new();
+            font.set("Type", Object::Name(b"Font".to_vec()));
+            font.set("Subtype", Object::Name(b"Type1".to_vec()));
+            font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+            font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+            doc.add_object(font)
+        });
+        let glyphs = encode_win_ansi(text).into_iter()
+            .map(|code| PlacedGlyph {
+                code: vec![code],
+                width: STANDARD_FONT_WIDTH,
+                advance: STANDARD_FONT_WIDTH,
+                x_offset: 0.0,
+                y_offset: 0.0,
+            })
+            .collect();
+        TextLine { runs: vec![GlyphRun { resource: format!("LmsU{}", id.0).into_bytes(), glyphs }] }
+    }
+}
+
+// Type0 font with a subsetted CIDFontType2 (TrueType) or CIDFontType0 (CFF) descendant
+fn cid_font(doc: &mut Document, file: &FontFile, face: &rustybuzz::Face, subset: &FontSubset) -> Result<Dictionary, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let program = subsetter::subset(&file.data, 0, &subset.remapper)
+        .map_err(|e| format!("Failed to subset font {}: {:?}", file.name, e))?;
+    let cff = face.tables().cff.is_some();
+    let scale = 1000.0 / face.units_per_em() as f64;
+
+    // Subset tag derived from the glyphs, so identical subsets get identical names
+    let glyphs: Vec<u16> = subset.remapper.remapped_gids().collect();
+    let digest = md5::compute(format!("{}{:?}", file.name, glyphs));
+    let tag: String = digest.0.iter().take(6).map(|b| (b'A' + b % 26) as char).collect();
+    let base_font = format!("{}+{}", tag, file.name).into_bytes();
+
+    let mut font_file_dict = Dictionary::new();
+    if cff {
+        font_file_dict.set("Subtype", Object::Name(b"OpenType".to_vec()));
+    } else {
+        font_file_dict.set("Length1", Object::Integer(program.len() as i64));
+    }
+    let mut font_file = Stream::new(font_file_dict, program);
+    let _ = font_file.compress();
+    let font_file_id = doc.add_object(font_file);
+
+    let bbox = face.global_bounding_box();
+    let cap_height = face.capital_height().unwrap_or(face.ascender());
+    let mut descriptor = Dictionary::new();
+    descriptor.set("Type", Object::Name(b"FontDescriptor".to_vec()));
+    descriptor.set("FontName", Object::Name(base_font.clone()));
+    descriptor.set("Flags", Object::Integer(FLAG_SYMBOLIC));
+    descriptor.set("FontBBox", Object::Array(
+        [bbox.x_min, bbox.y_min, bbox.x_max, bbox.y_max].iter()
+            .map(|v| Object::Integer((*v as f64 * scale).round() as i64))
+            .collect(),
+    ));
+    descriptor.set("ItalicAngle", Object::Real(face.italic_angle()));
+    descriptor.set("Ascent", Object::Integer((face.ascender() as f64 * scale).round() as i64));
+    descriptor.set("Descent", Object::Integer((face.descender() as f64 * scale).round() as i64));
+    descriptor.set("CapHeight", Object::Integer((cap_height as f64 * scale).round() as i64));
+    descriptor.set("StemV", Object::Integer(80));
+    descriptor.set(if cff { "FontFile3" } else { "FontFile2" }, Object::Reference(font_file_id));
+    let descriptor_id = doc.add_object(descriptor);
+
+    let mut system_info = Dictionary::new();
+    system_info.set("Registry", Object::string_literal("Adobe"));
+    system_info.set("Ordering", Object::string_literal("Identity"));
+    system_info.set("Supplement", Object::Integer(0));
+
+    let mut cid_font = Dictionary::new();
+    cid_font.set("Type", Object::Name(b"Font".to_vec()));
+    cid_font.set("Subtype", Object::Name(if cff { b"CIDFontType0".to_vec() } else { b"CIDFontType2".to_vec() }));
+    cid_font.set("BaseFont", Object::Name(base_font.clone()));
+    cid_font.set("CIDSystemInfo", Object::Dictionary(system_info));
+    cid_font.set("FontDescriptor", Object::Reference(descriptor_id));
+    cid_font.set("W", Object::Array(cid_widths(&subset.widths)));
+    if !cff {
+        // The subsetter numbers glyphs by CID
+        cid_font.set("CIDToGIDMap", Object::Name(b"Identity".to_vec()));
+    }
+    let cid_font_id = doc.add_object(cid_font);
+
+    let mut to_unicode = Stream::new(Dictionary::new(), to_unicode_cmap(&subset.to_unicode).into_bytes());
+    let _ = to_unicode.compress();
+    let to_unicode_id = doc.add_object(to_unicode);
+
+    let mut font = Dictionary::new();
+    font.set("Type", Object::Name(b"Font".to_vec()));
+    font.set("Subtype", Object::Name(b"Type0".to_vec()));
+    font.set("BaseFont", Object::Name(base_font));
+    font.set("Encoding", Object::Name(b"Identity-H".to_vec()));
+    font.set("DescendantFonts", Object::Array(vec![Object::Reference(cid_font_id)]));
+    font.set("ToUnicode", Object::Reference(to_unicode_id));
+    Ok(font)
+}
+
+// W array, with consecutive CIDs grouped: [first [w1 w2 ...] ...]
+fn cid_widths(widths: &BTreeMap<u16, i64>) -> Vec<Object> {
+    let mut array = Vec::new();
+    let mut group: Option<(u16, Vec<Object>)> = None;
+    for (&cid, &width) in widths {
+        match &mut group {
+            Some((first, group_widths)) if *first as usize + group_widths.len() == cid as usize => {
+                group_widths.push(Object::Integer(width));
+            }
+            _ => {
+                if let Some((first, group_widths)) = group.take() {
+                    array.push(Object::Integer(first as i64));
+                    array.push(Object::Array(group_widths));
+                }
+                group = Some((cid, vec![Object::Integer(width)]));
+            }
+        }
+    }
+    if let Some((first, group_widths)) = group {
+        array.push(Object::Integer(first as i64));
+        array.push(Object::Array(group_widths));
+    }
+    array
+}
+
+fn to_unicode_cmap(mappings: &BTreeMap<u16, String>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let mappings: Vec<_> = mappings.iter().filter(|(_, text)| !text.is_empty()).collect();
+    // At most 100 entries per bfchar block
+    for chunk in mappings.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (cid, text) in chunk {
+            let utf16: String = text.encode_utf16().map(|unit| format!("{:04X}", unit)).collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", cid, utf16));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// Add a font to a page's /Resources. Inherited resources are copied onto the page first; shared
+/// resource and font dictionaries are updated in place.
+pub fn add_font_resource(doc: &mut Document, page_id: ObjectId, name: &[u8], font_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if !doc.get_dictionary(page_id)?.has(b"Resources") {
+        let inherited = inherited_attribute(doc, page_id, b"Resources")
+            .and_then(|r| r.as_dict().ok().cloned())
+            .unwrap_or_default();
+        doc.get_dictionary_mut(page_id)?.set("Resources", Object::Dictionary(inherited));
+    }
+    let resources_id = match doc.get_dictionary(page_id)?.get(b"Resources")? {
+        Object::Reference(id) => Some(*id),
+        _ => None,
+    };
+
+    fn resources_mut(doc: &mut Document, page_id: ObjectId, resources_id: Option<ObjectId>) -> lopdf::Result<&mut Dictionary> {
+        match resources_id {
+            Some(id) => doc.get_dictionary_mut(id),
+            None => doc.get_dictionary_mut(page_id)?.get_mut(b"Resources")?.as_dict_mut(),
+        }
+    }
+
+    let font_map_id = {
+        let resources = resources_mut(doc, page_id, resources_id)?;
+        match resources.get(b"Font") {
+            Ok(Object::Reference(id)) => Some(*id),
+            Ok(Object::Dictionary(_)) => None,
+            _ => {
+                resources.set("Font", Object::Dictionary(Dictionary::new()));
+                None
+            }
+        }
+    };
+    let font_map = match font_map_id {
+        Some(id) => doc.get_dictionary_mut(id)?,
+        None => resources_mut(doc, page_id, resources_id)?.get_mut(b"Font")?.as_dict_mut()?,
+    };
+    font_map.set(name.to_vec(), Object::Reference(font_id));
+    Ok(())
+}
+
+/// Whether a font dictionary carries its own font program
+pub fn is_font_embedded(doc: &Document, font: &Dictionary) -> bool {
+    let descriptor = match font.get(b"FontDescriptor").ok().and_then(|d| doc.dereference(d).ok()) {
+        Some((_, Object::Dictionary(descriptor))) => descriptor,
+        _ => return false,
+    };
+    [b"FontFile".as_slice(), b"FontFile2", b"FontFile3"].iter().any(|key| descriptor.has(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // CID to text, from a ToUnicode CMap with bfchar entries
+    fn parse_to_unicode(cmap: &str) -> HashMap<u16, String> {
+        let mut mappings = HashMap::new();
+        let mut in_bfchar = false;
+        for line in cmap.lines() {
+            if line.ends_with("beginbfchar") {
+                in_bfchar = true;
+            } else if line == "endbfchar" {
+                in_bfchar = false;
+            } else if in_bfchar {
+                let hex: Vec<&str> = line.split(['<', '>', ' ']).filter(|s| !s.is_empty()).collect();
+                let cid = u16::from_str_radix(hex[0], 16).unwrap();
+                let units: Vec<u16> = (0..hex[1].len()).step_by(4).map(|i| u16::from_str_radix(&hex[1][i..i + 4], 16).unwrap()).collect();
+                mappings.insert(cid, String::from_utf16(&units).unwrap());
+            }
+        }
+        mappings
+    }
+
+    // CID and text of each shown glyph, in the order the line draws them, through the fonts'
+    // ToUnicode maps
+    fn extracted_text(doc: &Document, fonts: &HashMap<Vec<u8>, ObjectId>, line: &TextLine) -> Vec<(u16, String)> {
+        let mut glyphs = Vec::new();
+        let mut to_unicode = HashMap::new();
+        for operation in line.operations(12.0) {
+            match operation.operator.as_str() {
+                "Tf" => {
+                    let font = doc.get_dictionary(fonts[operation.operands[0].as_name().unwrap()]).unwrap();
+                    let stream = doc.get_object(font.get(b"ToUnicode").unwrap().as_reference().unwrap()).unwrap().as_stream().unwrap();
+                    to_unicode = parse_to_unicode(&String::from_utf8(stream.decompressed_content().unwrap()).unwrap());
+                }
+                "TJ" => {
+                    for item in operation.operands[0].as_array().unwrap() {
+                        if let Object::String(codes, _) = item {
+                            for code in codes.chunks(2) {
+                                let cid = u16::from_be_bytes([code[0], code[1]]);
+                                glyphs.push((cid, to_unicode.get(&cid).cloned().unwrap_or_default()));
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        glyphs
+    }
+
+    #[test]
+    fn to_unicode_maps_shaped_glyphs_back_to_the_text() {
+        let mut doc = Document::with_version("1.7");
+        let mut fonts = UnicodeFonts::new();
+        assert!(!fonts.faces.is_empty(), "no font file found; install DejaVu Sans or set PDF_FONTS_DIR");
+
+        let latin = "Cafe\u{301} Nguye\u{302}\u{303}n";
+        let hebrew = "\u{5E9}\u{5C1}\u{5B8}\u{5DC}\u{5D5}\u{5B9}\u{5DD}";
+        let arabic = "\u{633}\u{644}\u{627}\u{645}";
+        let isolated = "\u{633} \u{644} \u{627} \u{645}";
+        let lines: Vec<(&str, bool, TextLine)> = [(latin, false), (hebrew, true), (arabic, true), (isolated, true)].into_iter()
+            .map(|(text, rtl)| (text, rtl, fonts.layout(&mut doc, text)))
+            .collect();
+        let resources: HashMap<Vec<u8>, ObjectId> = fonts.subsets.values().map(|s| (s.resource.clone(), s.id)).collect();
+        fonts.finish(&mut doc).unwrap();
+
+        for (text, rtl, line) in &lines {
+            let mut glyphs: Vec<String> = extracted_text(&doc, &resources, line).into_iter().map(|(_, text)| text).collect();
+            // Right-to-left lines are drawn in visual order
+            if *rtl {
+                glyphs.reverse();
+            }
+            assert_eq!(glyphs.concat(), *text);
+        }
+
+        // Joined Arabic letters are drawn with other glyphs than the isolated ones, and still map
+        // to the letters typed
+        let cids = |line: &TextLine| -> HashSet<u16> {
+            extracted_text(&doc, &resources, line).into_iter().filter(|(_, text)| text != " ").map(|(cid, _)| cid).collect()
+        };
+        assert_ne!(cids(&lines[2].2), cids(&lines[3].2));
+    }
+}
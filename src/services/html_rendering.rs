@@ -0,0 +1,567 @@
+// Rendering of HTML templates to PDF.
+//
+// Inputs marked with data-field become template fields at the position they're rendered at:
+//   <input data-field="Full name" data-type="text" data-role="Signer" required>
+// data-type defaults to the input's type (checkbox, date, otherwise text), data-role sets the
+// recipient role and data-options (comma separated) the choices of select/radio fields. Each
+// marker is replaced by a link to a reserved URL before rendering; the renderer turns links into
+// PDF link annotations, whose rectangles give the field positions. The annotations are removed
+// from the stored PDF. Images, styles and fonts have to be inlined as data: URLs: the renderer
+// loads nothing from the network or from other files.
+//
+// Configuration (environment):
+//   HTML_RENDERER                renderer to use: "chromium" (default) or "none"
+//   CHROMIUM_PATH                headless Chromium/Chrome binary (default "chromium" from PATH)
+//   CHROMIUM_NO_SANDBOX          "true" to pass --no-sandbox (needed when running as root in containers)
+//   HTML_RENDER_TIMEOUT_SECS     give up on a render after this long (default 60)
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lopdf::{Document, Object, ObjectId};
+use tokio::process::Command;
+
+use crate::services::page_geometry::PageGeometry;
+
+const DEFAULT_TIMEOUT_SECS: u64 = 60;
+const MARKER_URL_PREFIX: &str = "https://letmesign.invalid/field/";
+// Size of a marker without its own width/height, about that of an empty text input
+const MARKER_DEFAULT_STYLE: &str = "display:inline-block;width:150px;height:1.5em;vertical-align:bottom;text-decoration:none;color:inherit;";
+// Images, styles and fonts have to be inlined as data: URLs; nothing is fetched from the network
+// or from other files
+const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; img-src data:; style-src 'unsafe-inline' data:; font-src data:";
+
+#[async_trait]
+pub trait HtmlRenderer: Send + Sync {
+    // Name reported in errors and logs
+    fn name(&self) -> String;
+
+    /// Render an HTML document to PDF, keeping links as link annotations
+    async fn render_to_pdf(&self, html: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>>;
+}
+
+/// Configured HTML renderer, or None when rendering is disabled
+pub fn html_renderer_from_env() -> Option<Box<dyn HtmlRenderer>> {
+    match std::env::var("HTML_RENDERER").map(|v| v.to_lowercase()).as_deref() {
+        Ok("none") | Ok("disabled") => None,
+        _ => {
+            let binary = std::env::var("CHROMIUM_PATH").unwrap_or_else(|_| "chromium".to_string());
+            let no_sandbox = std::env::var("CHROMIUM_NO_SANDBOX").map(|v| v == "true").unwrap_or(false);
+            let timeout = std::env::var("HTML_RENDER_TIMEOUT_SECS").ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(DEFAULT_TIMEOUT_SECS);
+            Some(Box::new(ChromiumRenderer::new(binary, no_sandbox, Duration::from_secs(timeout))))
+        }
+    }
+}
+
+/// Page size and margins, as CSS @page values ("A4", "Letter landscape", "20mm", ...).
+/// Unset values are left to the HTML's own @page rules.
+#[derive(Debug, Clone, Default)]
+pub struct PageLayout {
+    pub size: Option<String>,
+    pub margin: Option<String>,
+}
+
+impl PageLayout {
+    // Values are copied into a style sheet, so anything that could end the rule is refused
+    pub fn validate(&self) -> Result<(), String> {
+        for (property, value) in [("size", &self.size), ("margin", &self.margin)] {
+            if let Some(value) = value {
+                if value.contains(['{', '}', ';', '<', '>', '\\']) {
+                    return Err(format!("Invalid page {}: {}", property, value));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A data-field input found in the HTML
+#[derive(Debug, Clone, PartialEq)]
+pub struct HtmlFieldMarker {
+    pub name: String,
+    pub field_type: String,
+    pub required: bool,
+    pub partner: Option<String>,
+    pub options: Option<Vec<String>>,
+}
+
+/// A marker and where it was rendered: 1-based page and fractions of the displayed page,
+/// top-left origin (the format of FieldPosition)
+#[derive(Debug, Clone)]
+pub struct PlacedField {
+    pub marker: HtmlFieldMarker,
+    pub page: i32,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+pub struct RenderedHtml {
+    pub pdf: Vec<u8>,
+    pub fields: Vec<PlacedField>,
+}
+
+/// Render an HTML template with the configured renderer and locate its field markers
+pub async fn render_html_template(html: &str, layout: &PageLayout) -> Result<RenderedHtml, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let renderer = html_renderer_from_env().ok_or("HTML rendering is disabled")?;
+    let html = apply_page_layout(html, layout)?;
+    let (html, markers) = replace_field_markers(&html);
+
+    let pdf = renderer.render_to_pdf(&html).await
+        .map_err(|e| format!("{} failed to render HTML: {}", renderer.name(), e))?;
+    let (pdf, positions) = take_marker_positions(&pdf)?;
+
+    let fields = markers.into_iter().enumerate()
+        .filter_map(|(index, marker)| match positions.get(&index) {
+            Some(&(page, [x, y, width, height])) => Some(PlacedField { marker, page, x, y, width, height }),
+            None => {
+                // Hidden markers (display: none, ...) aren't rendered
+                eprintln!("Field marker '{}' wasn't rendered, skipping it", marker.name);
+                None
+            }
+        })
+        .collect();
+    Ok(RenderedHtml { pdf, fields })
+}
+
+// Add an @page rule for the requested layout, after the document's own styles so it wins
+fn apply_page_layout(html: &str, layout: &PageLayout) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    layout.validate()?;
+    let mut rules = String::new();
+    for (property, value) in [("size", &layout.size), ("margin", &layout.margin)] {
+        if let Some(value) = value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+            rules.push_str(&format!("{}: {}; ", property, value));
+        }
+    }
+    if rules.is_empty() {
+        return Ok(html.to_string());
+    }
+
+    let style = format!("<style>@page {{ {}}}</style>", rules);
+    Ok(match html.to_ascii_lowercase().rfind("</head>") {
+        Some(index) => format!("{}{}{}", &html[..index], style, &html[index..]),
+        None => format!("{}{}", style, html),
+    })
+}
+
+// Replace <input data-field=...> markers by links the renderer reports the position of
+fn replace_field_markers(html: &str) -> (String, Vec<HtmlFieldMarker>) {
+    let lower = html.to_ascii_lowercase();
+    let mut output = String::with_capacity(html.len());
+    let mut markers = Vec::new();
+    let mut position = 0;
+
+    while let Some(offset) = lower[position..].find("<input") {
+        let start = position + offset;
+        let Some(length) = tag_length(&html[start..]) else { break };
+        let end = start + length;
+        let attributes = parse_attributes(&html[start + "<input".len()..end - 1]);
+        let attribute = |name: &str| attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+
+        output.push_str(&html[position..start]);
+        match attribute("data-field") {
+            Some(name) => {
+                let index = markers.len();
+                let field_type = attribute("data-type").filter(|t| !t.is_empty()).unwrap_or_else(|| {
+                    match attribute("type").unwrap_or_default().to_lowercase().as_str() {
+                        "checkbox" => "checkbox".to_string(),
+                        "date" => "date".to_string(),
+                        _ => "text".to_string(),
+                    }
+                });
+                markers.push(HtmlFieldMarker {
+                    name: if name.trim().is_empty() { format!("Field {}", index + 1) } else { name.trim().to_string() },
+                    field_type,
+                    required: attribute("required").is_some(),
+                    partner: attribute("data-role").map(|r| r.trim().to_string()).filter(|r| !r.is_empty()),
+                    options: attribute("data-options").map(|o| {
+                        o.split(',').map(|option| option.trim().to_string()).filter(|option| !option.is_empty()).collect()
+                    }),
+                });
+                output.push_str(&format!(
+                    "<a href=\"{}{}\" class=\"{}\" style=\"{}{}\">&nbsp;</a>",
+                    MARKER_URL_PREFIX,
+                    index,
+                    escape_attribute(&attribute("class").unwrap_or_default()),
+                    MARKER_DEFAULT_STYLE,
+                    escape_attribute(&attribute("style").unwrap_or_default()),
+                ));
+            }
+            None => output.push_str(&html[start..end]),
+        }
+        position = end;
+    }
+    output.push_str(&html[position..]);
+    (output, markers)
+}
+
+// Length of the tag at the start of html, up to its closing '>' (which may not be quoted)
+fn tag_length(html: &str) -> Option<usize> {
+    let mut quote = None;
+    for (index, c) in html.char_indices() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(index + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+// Attributes of a tag (the part between its name and '>'), names lowercased, entities decoded
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut chars = tag.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace() || *c == '/') {
+            chars.next();
+        }
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '/' {
+                break;
+            }
+            name.push(c.to_ascii_lowercase());
+            chars.next();
+        }
+        if name.is_empty() {
+            break;
+        }
+
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                chars.next();
+            }
+            match chars.peek().copied() {
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    chars.next();
+                    value = chars.by_ref().take_while(|c| *c != quote).collect();
+                }
+                _ => {
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() {
+                            break;
+                        }
+                        value.push(c);
+                        chars.next();
+                    }
+                }
+            }
+        }
+        attributes.push((name, decode_entities(&value)));
+    }
+    attributes
+}
+
+fn decode_entities(value: &str) -> String {
+    value.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn escape_attribute(value: &str) -> String {
+    value.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+}
+
+// Add a Content-Security-Policy that only allows inlined (data:) subresources. It goes right after
+// the doctype, before anything that could load. Meta refreshes are refused: they navigate the
+// page itself, which the policy doesn't cover.
+fn restrict_subresources(html: &str) -> Result<String, String> {
+    let lower = html.to_ascii_lowercase();
+    let mut position = 0;
+    while let Some(offset) = lower[position..].find("<meta") {
+        let start = position + offset;
+        let Some(length) = tag_length(&html[start..]) else { break };
+        let attributes = parse_attributes(&html[start + "<meta".len()..start + length - 1]);
+        if attributes.iter().any(|(key, value)| key == "http-equiv" && value.trim().eq_ignore_ascii_case("refresh")) {
+            return Err("HTML templates can't use meta refresh".to_string());
+        }
+        position = start + length;
+    }
+
+    let policy = format!("<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">", CONTENT_SECURITY_POLICY);
+    let trimmed = lower.trim_start_matches(|c: char| c.is_whitespace() || c == '\u{feff}');
+    let doctype_end = trimmed.starts_with("<!doctype")
+        .then(|| tag_length(&html[html.len() - trimmed.len()..]))
+        .flatten()
+        .map(|length| html.len() - trimmed.len() + length);
+    Ok(match doctype_end {
+        Some(end) => format!("{}{}{}", &html[..end], policy, &html[end..]),
+        None => format!("{}{}", policy, html),
+    })
+}
+
+type MarkerPositions = std::collections::HashMap<usize, (i32, [f64; 4])>;
+
+// Remove the marker link annotations from a rendered PDF. Returns the PDF and, per marker index,
+// the page and [x, y, width, height] of the first place it was rendered at.
+fn take_marker_positions(pdf: &[u8]) -> Result<(Vec<u8>, MarkerPositions), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut doc = Document::load_mem(pdf)?;
+    let mut positions = std::collections::HashMap::new();
+    let mut removed: Vec<ObjectId> = Vec::new();
+
+    for (page_number, page_id) in doc.get_pages() {
+        let geometry = PageGeometry::from_page(&doc, page_id);
+        let (page_width, page_height) = geometry.display_size();
+
+        let annots = match doc.get_dictionary(page_id)?.get(b"Annots") {
+            Ok(annots) => doc.dereference(annots)?.1.as_array()?.clone(),
+            Err(_) => continue,
+        };
+        let mut kept = Vec::new();
+        for annot in annots {
+            let marker = doc.dereference(&annot).ok()
+                .and_then(|(_, annot)| annot.as_dict().ok())
+                .and_then(|annot| Some((marker_index(&doc, annot)?, annot.get(b"Rect").ok()?.as_array().ok()?.clone())));
+            let Some((index, rect)) = marker else {
+                kept.push(annot);
+                continue;
+            };
+
+            if let Ok(id) = annot.as_reference() {
+                removed.push(id);
+            }
+            let values: Vec<f64> = rect.iter().filter_map(|v| v.as_float().ok().map(f64::from)).collect();
+            if values.len() != 4 || positions.contains_key(&index) {
+                continue;
+            }
+            let rect = [values[0].min(values[2]), values[1].min(values[3]), values[0].max(values[2]), values[1].max(values[3])];
+            let [x0, y0, x1, y1] = geometry.rect_to_display_space(rect);
+            positions.insert(index, (page_number as i32, [
+                x0 / page_width,
+                (page_height - y1) / page_height,
+                (x1 - x0) / page_width,
+                (y1 - y0) / page_height,
+            ]));
+        }
+
+        let page = doc.get_dictionary_mut(page_id)?;
+        if kept.is_empty() {
+            page.remove(b"Annots");
+        } else {
+            page.set("Annots", Object::Array(kept));
+        }
+    }
+
+    for id in removed {
+        doc.objects.remove(&id);
+    }
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok((output, positions))
+}
+
+// Marker index of a link annotation pointing to a marker URL
+fn marker_index(doc: &Document, annot: &lopdf::Dictionary) -> Option<usize> {
+    if annot.get(b"Subtype").ok()?.as_name().ok()? != b"Link" {
+        return None;
+    }
+    let action = doc.dereference(annot.get(b"A").ok()?).ok()?.1.as_dict().ok()?;
+    let uri = action.get(b"URI").ok()?.as_str().ok()?;
+    std::str::from_utf8(uri).ok()?.strip_prefix(MARKER_URL_PREFIX)?.parse().ok()
+}
+
+// ===== Headless Chromium =====
+
+pub struct ChromiumRenderer {
+    binary: String,
+    no_sandbox: bool,
+    timeout: Duration,
+}
+
+impl ChromiumRenderer {
+    pub fn new(binary: String, no_sandbox: bool, timeout: Duration) -> Self {
+        Self { binary, no_sandbox, timeout }
+    }
+
+    fn command(&self, work_dir: &std::path::Path, input: &std::path::Path, output_path: &std::path::Path) -> Command {
+        let mut command = Command::new(&self.binary);
+        command.args(["--headless", "--disable-gpu", "--no-first-run", "--no-pdf-header-footer", "--print-to-pdf-no-header"])
+            // Templates are documents, not applications
+            .arg("--blink-settings=scriptEnabled=false")
+            // No network at all, loopback included, should the content security policy miss a request
+            .args(["--proxy-server=127.0.0.1:0", "--proxy-bypass-list=<-loopback>", "--host-resolver-rules=MAP * ~NOTFOUND"])
+            .arg(format!("--user-data-dir={}", work_dir.join("profile").to_string_lossy()))
+            .arg(format!("--print-to-pdf={}", output_path.to_string_lossy()))
+            .kill_on_drop(true);
+        if self.no_sandbox {
+            command.arg("--no-sandbox");
+        }
+        command.arg(format!("file://{}", input.to_string_lossy()));
+        command
+    }
+
+    async fn run(&self, work_dir: &std::path::Path, html: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let input = work_dir.join("document.html");
+        let output_path = work_dir.join("document.pdf");
+        tokio::fs::write(&input, restrict_subresources(html)?).await?;
+
+        let mut command = self.command(work_dir, &input, &output_path);
+        let output = tokio::time::timeout(self.timeout, command.output()).await
+            .map_err(|_| format!("timed out after {}s", self.timeout.as_secs()))?
+            .map_err(|e| format!("failed to run {}: {}", self.binary, e))?;
+        if !output.status.success() {
+            return Err(format!("exited with {}: {}", output.status, String::from_utf8_lossy(&output.stderr).trim()).into());
+        }
+
+        let pdf = tokio::fs::read(&output_path).await
+            .map_err(|_| format!("produced no PDF: {}", String::from_utf8_lossy(&output.stderr).trim()))?;
+        if !pdf.starts_with(b"%PDF") {
+            return Err("produced an invalid PDF".into());
+        }
+        Ok(pdf)
+    }
+}
+
+#[async_trait]
+impl HtmlRenderer for ChromiumRenderer {
+    fn name(&self) -> String {
+        "Chromium".to_string()
+    }
+
+    async fn render_to_pdf(&self, html: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let work_dir = std::env::temp_dir().join(format!("html_rendering_{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&work_dir).await?;
+        let result = self.run(&work_dir, html).await;
+        let _ = tokio::fs::remove_dir_all(&work_dir).await;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    #[test]
+    fn markers_are_replaced_by_links() {
+        let html = r#"<p>Name: <input type="text" data-field="Full name" data-role=" Signer " required style="width:200px"></p>
+<input name="plain"><INPUT TYPE=checkbox data-field='Agree &amp; sign'/> <input data-field="Plan" data-type="select" data-options="Basic, Pro,">"#;
+        let (output, markers) = replace_field_markers(html);
+
+        assert_eq!(markers, vec![
+            HtmlFieldMarker { name: "Full name".into(), field_type: "text".into(), required: true, partner: Some("Signer".into()), options: None },
+            HtmlFieldMarker { name: "Agree & sign".into(), field_type: "checkbox".into(), required: false, partner: None, options: None },
+            HtmlFieldMarker { name: "Plan".into(), field_type: "select".into(), required: false, partner: None, options: Some(vec!["Basic".into(), "Pro".into()]) },
+        ]);
+        assert!(output.contains(&format!("<a href=\"{}0\" class=\"\" style=\"{}width:200px\">&nbsp;</a>", MARKER_URL_PREFIX, MARKER_DEFAULT_STYLE)));
+        assert!(output.contains("<input name=\"plain\">"));
+        assert!(output.contains(&format!("{}2", MARKER_URL_PREFIX)));
+        assert!(!output.contains("data-field"));
+    }
+
+    #[test]
+    fn page_layout_is_added_after_document_styles() {
+        let layout = PageLayout { size: Some("A4 landscape".into()), margin: Some("20mm".into()) };
+        let html = apply_page_layout("<html><HEAD><style>p {}</style></HEAD><body></body></html>", &layout).unwrap();
+        assert_eq!(html, "<html><HEAD><style>p {}</style><style>@page { size: A4 landscape; margin: 20mm; }</style></HEAD><body></body></html>");
+
+        let layout = PageLayout { size: Some("A4; } body { display: none".into()), margin: None };
+        assert!(apply_page_layout("<p></p>", &layout).is_err());
+    }
+
+    #[test]
+    fn marker_annotations_give_positions_and_are_removed() {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let link = |uri: &str, rect: [i64; 4]| dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Rect" => rect.iter().map(|v| Object::Integer(*v)).collect::<Vec<_>>(),
+            "A" => dictionary! { "S" => "URI", "URI" => Object::string_literal(uri) },
+        };
+        let marker_id = doc.add_object(link(&format!("{}0", MARKER_URL_PREFIX), [100, 692, 250, 712]));
+        let other_id = doc.add_object(link("https://example.com", [0, 0, 10, 10]));
+        let content_id = doc.add_object(Stream::new(lopdf::Dictionary::new(), Vec::new()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 600.into(), 800.into()],
+            "Contents" => content_id,
+            "Annots" => vec![marker_id.into(), other_id.into()],
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let mut pdf = Vec::new();
+        doc.save_to(&mut pdf).unwrap();
+
+        let (pdf, positions) = take_marker_positions(&pdf).unwrap();
+        let (page, [x, y, width, height]) = positions[&0];
+        assert_eq!(page, 1);
+        assert!((x - 100.0 / 600.0).abs() < 1e-9 && (y - 88.0 / 800.0).abs() < 1e-9);
+        assert!((width - 150.0 / 600.0).abs() < 1e-9 && (height - 20.0 / 800.0).abs() < 1e-9);
+
+        let doc = Document::load_mem(&pdf).unwrap();
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap();
+        assert_eq!(annots.len(), 1);
+    }
+
+    #[test]
+    fn only_inlined_subresources_are_allowed() {
+        let policy = format!("<meta http-equiv=\"Content-Security-Policy\" content=\"{}\">", CONTENT_SECURITY_POLICY);
+        assert_eq!(
+            restrict_subresources("\n<!DOCTYPE html><html><img src=\"https://example.com/a.png\"></html>").unwrap(),
+            format!("\n<!DOCTYPE html>{}<html><img src=\"https://example.com/a.png\"></html>", policy)
+        );
+        assert_eq!(restrict_subresources("<p>Hi</p>").unwrap(), format!("{}<p>Hi</p>", policy));
+        assert!(restrict_subresources("<META HTTP-EQUIV='Refresh' content='0; url=file:///etc/passwd'>").is_err());
+        assert!(restrict_subresources("<meta charset=\"utf-8\">").is_ok());
+
+        let renderer = ChromiumRenderer::new("chromium".into(), false, Duration::from_secs(1));
+        let work_dir = std::path::Path::new("/tmp/render");
+        let command = renderer.command(work_dir, &work_dir.join("document.html"), &work_dir.join("document.pdf"));
+        let args: Vec<String> = command.as_std().get_args().map(|a| a.to_string_lossy().to_string()).collect();
+        for flag in ["--proxy-server=127.0.0.1:0", "--proxy-bypass-list=<-loopback>", "--host-resolver-rules=MAP * ~NOTFOUND"] {
+            assert!(args.iter().any(|a| a == flag), "{} missing from {:?}", flag, args);
+        }
+    }
+
+    // Needs a Chromium binary (CHROMIUM_PATH or chromium on the PATH), skipped without one
+    #[tokio::test]
+    async fn external_images_and_file_frames_are_not_loaded() {
+        let binary = std::env::var("CHROMIUM_PATH").unwrap_or_else(|_| "chromium".to_string());
+        if std::process::Command::new(&binary).arg("--version").output().is_err() {
+            eprintln!("{} not found, skipping", binary);
+            return;
+        }
+        let no_sandbox = std::env::var("CHROMIUM_NO_SANDBOX").map(|v| v == "true").unwrap_or(false);
+        let renderer = ChromiumRenderer::new(binary, no_sandbox, Duration::from_secs(60));
+
+        // Any request for the image would connect here
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        // The framed file holds a marker link, which would end up in the PDF if the frame loaded
+        let frame = std::env::temp_dir().join(format!("html_rendering_frame_{}.html", uuid::Uuid::new_v4()));
+        std::fs::write(&frame, format!("<a href=\"{}0\" style=\"display:block;width:100px;height:20px\">secret</a>", MARKER_URL_PREFIX)).unwrap();
+
+        let html = format!(
+            "<!DOCTYPE html><html><body><p>Body</p><img src=\"http://127.0.0.1:{}/probe.png\"><iframe src=\"file://{}\"></iframe></body></html>",
+            port,
+            frame.to_string_lossy(),
+        );
+        let pdf = renderer.render_to_pdf(&html).await;
+        let _ = std::fs::remove_file(&frame);
+
+        let (_, positions) = take_marker_positions(&pdf.unwrap()).unwrap();
+        assert!(positions.is_empty(), "the file:// frame was loaded");
+        assert_eq!(listener.accept().map_err(|e| e.kind()).err(), Some(std::io::ErrorKind::WouldBlock), "the external image was requested");
+    }
+}
@@ -0,0 +1,480 @@
+// Build PDFs from photographed or scanned pages (JPEG, PNG, TIFF), one page per image.
+//
+// EXIF orientation is applied so pages come out upright. Pages take the image's physical size
+// when it records a plausible scan resolution; photos (whose 72 dpi says nothing about the paper)
+// are fitted to A4. Optional cleanup straightens slightly rotated scans and stretches the contrast
+// of faded or grey photos into black on white.
+
+use image::{DynamicImage, GenericImageView, GrayImage, ImageFormat};
+use imageproc::geometric_transformations::{rotate_about_center, Interpolation};
+use lopdf::{dictionary, Dictionary, Document, Object, Stream};
+
+const A4_SIZE: (f64, f64) = (595.0, 842.0);
+// Lower resolutions are camera defaults rather than a measured scan resolution
+const MIN_SCAN_DPI: f64 = 100.0;
+const MAX_SKEW_DEGREES: f32 = 10.0;
+const SKEW_STEP_DEGREES: f32 = 0.2;
+const JPEG_QUALITY: u8 = 90;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ImageCleanup {
+    /// Straighten pages photographed or scanned at a slight angle
+    pub deskew: bool,
+    /// Convert to greyscale and stretch the contrast
+    pub enhance: bool,
+}
+
+/// Whether the data is an image format a page can be built from
+pub fn is_page_image(data: &[u8]) -> bool {
+    matches!(image::guess_format(data), Ok(ImageFormat::Jpeg | ImageFormat::Png | ImageFormat::Tiff))
+}
+
+/// `images_to_pdf` off the async runtime; decoding and cleanup of large scans take a while
+pub async fn convert_images_to_pdf(images: Vec<Vec<u8>>, cleanup: ImageCleanup) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    tokio::task::spawn_blocking(move || images_to_pdf(&images, cleanup)).await?
+}
+
+/// One PDF page per image, in order
+pub fn images_to_pdf(images: &[Vec<u8>], cleanup: ImageCleanup) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if images.is_empty() {
+        return Err("No images to convert".into());
+    }
+
+    let mut doc = Document::with_version("1.7");
+    let pages_id = doc.new_object_id();
+    let mut kids = Vec::new();
+
+    for (index, data) in images.iter().enumerate() {
+        let page = page_image(data, cleanup).map_err(|e| format!("Image {}: {}", index + 1, e))?;
+        let (width, height) = page.size;
+        let image_id = doc.add_object(page.stream);
+        let content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q", width, height);
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), content.into_bytes()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), Object::Real(width as f32), Object::Real(height as f32)],
+            "Resources" => dictionary! { "XObject" => dictionary! { "Im0" => image_id } },
+            "Contents" => content_id,
+        });
+        kids.push(Object::Reference(page_id));
+    }
+
+    let count = kids.len() as i64;
+    doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+        "Type" => "Pages",
+        "Kids" => kids,
+        "Count" => count,
+    }));
+    let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+    doc.trailer.set("Root", catalog_id);
+
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok(output)
+}
+
+struct PageImage {
+    stream: Stream,
+    // Page size in points
+    size: (f64, f64),
+}
+
+fn page_image(data: &[u8], cleanup: ImageCleanup) -> Result<PageImage, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let format = image::guess_format(data)?;
+    let info = ImageInfo::read(data, format);
+    let decoded = image::load_from_memory_with_format(data, format)?;
+    let size = page_size(decoded.dimensions(), info.orientation, info.dpi);
+
+    // Baseline JPEGs that need no changes are embedded as they are
+    if format == ImageFormat::Jpeg && info.orientation == 1 && !cleanup.deskew && !cleanup.enhance {
+        if let Some(color_space) = match info.jpeg_components {
+            Some(1) => Some("DeviceGray"),
+            Some(3) => Some("DeviceRGB"),
+            _ => None,
+        } {
+            let (width, height) = decoded.dimensions();
+            return Ok(PageImage { stream: image_stream(width, height, color_space, "DCTDecode", data.to_vec()), size });
+        }
+    }
+
+    let mut image = apply_orientation(decoded, info.orientation);
+    if cleanup.enhance {
+        image = DynamicImage::ImageLuma8(enhance_contrast(&image.to_luma8()));
+    }
+    if cleanup.deskew {
+        let angle = skew_angle(&image.to_luma8());
+        if angle != 0.0 {
+            image = match image {
+                DynamicImage::ImageLuma8(gray) => DynamicImage::ImageLuma8(rotate_about_center(&gray, -angle, Interpolation::Bilinear, image::Luma([255]))),
+                other => DynamicImage::ImageRgb8(rotate_about_center(&other.to_rgb8(), -angle, Interpolation::Bilinear, image::Rgb([255, 255, 255]))),
+            };
+        }
+    }
+
+    let (width, height) = image.dimensions();
+    let stream = match image {
+        DynamicImage::ImageLuma8(gray) => {
+            let mut stream = image_stream(width, height, "DeviceGray", "", gray.into_raw());
+            stream.compress()?;
+            stream
+        }
+        // Photos stay JPEG, anything else (line art, transparency) is stored losslessly
+        other if format == ImageFormat::Jpeg => {
+            let mut jpeg = Vec::new();
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY).encode_image(&other.to_rgb8())?;
+            image_stream(width, height, "DeviceRGB", "DCTDecode", jpeg)
+        }
+        other => {
+            let mut stream = image_stream(width, height, "DeviceRGB", "", flatten_alpha(other).into_raw());
+            stream.compress()?;
+            stream
+        }
+    };
+    Ok(PageImage { stream, size })
+}
+
+fn image_stream(width: u32, height: u32, color_space: &str, filter: &str, content: Vec<u8>) -> Stream {
+    let mut dict = dictionary! {
+        "Type" => "XObject",
+        "Subtype" => "Image",
+        "Width" => width as i64,
+        "Height" => height as i64,
+        "ColorSpace" => Object::Name(color_space.as_bytes().to_vec()),
+        "BitsPerComponent" => 8,
+    };
+    if !filter.is_empty() {
+        dict.set("Filter", Object::Name(filter.as_bytes().to_vec()));
+    }
+    let mut stream = Stream::new(dict, content);
+    if !filter.is_empty() {
+        stream.allows_compression = false;
+    }
+    stream
+}
+
+// Transparent areas become white paper
+fn flatten_alpha(image: DynamicImage) -> image::RgbImage {
+    if !image.color().has_alpha() {
+        return image.to_rgb8();
+    }
+    let rgba = image.to_rgba8();
+    image::RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        image::Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+// Page size in points for an image of the given (stored) dimensions
+fn page_size((width, height): (u32, u32), orientation: u16, dpi: Option<f64>) -> (f64, f64) {
+    // Orientations 5-8 swap width and height
+    let (width, height) = if orientation >= 5 { (height as f64, width as f64) } else { (width as f64, height as f64) };
+    match dpi {
+        Some(dpi) if dpi >= MIN_SCAN_DPI => (width * 72.0 / dpi, height * 72.0 / dpi),
+        _ => {
+            let (page_width, page_height) = if width > height { (A4_SIZE.1, A4_SIZE.0) } else { A4_SIZE };
+            let scale = (page_width / width).min(page_height / height);
+            (width * scale, height * scale)
+        }
+    }
+}
+
+fn apply_orientation(image: DynamicImage, orientation: u16) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+// Map the 2nd to 98th percentile of brightness onto black to white
+fn enhance_contrast(gray: &GrayImage) -> GrayImage {
+    let mut histogram = [0u64; 256];
+    for pixel in gray.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let total = gray.width() as u64 * gray.height() as u64;
+    let percentile = |fraction: f64| {
+        let target = (total as f64 * fraction) as u64;
+        let mut seen = 0;
+        histogram.iter().position(|count| {
+            seen += count;
+            seen > target
+        }).unwrap_or(255) as u8
+    };
+    let (lower, upper) = (percentile(0.02), percentile(0.98));
+    if upper <= lower.saturating_add(16) {
+        // Nearly uniform; stretching would only amplify noise
+        return gray.clone();
+    }
+    imageproc::contrast::stretch_contrast(gray, lower, upper)
+}
+
+/// Clockwise angle (radians) the text lines of a page are rotated by, 0 when it looks straight.
+/// Found by projecting dark pixels onto lines at candidate angles: lines of text line up with
+/// the projection, which is then most uneven.
+fn skew_angle(gray: &GrayImage) -> f32 {
+    // Estimate on a small copy
+    let scale = (1000.0 / gray.width().max(gray.height()) as f32).min(1.0);
+    let small = image::imageops::resize(
+        gray,
+        ((gray.width() as f32 * scale) as u32).max(1),
+        ((gray.height() as f32 * scale) as u32).max(1),
+        image::imageops::FilterType::Triangle,
+    );
+    let level = imageproc::contrast::otsu_level(&small);
+    let dark: Vec<(f32, f32)> = small.enumerate_pixels()
+        .filter(|(_, _, pixel)| pixel.0[0] < level)
+        .map(|(x, y, _)| (x as f32, y as f32))
+        .collect();
+    // Blank pages, or pages that are mostly dark, have no lines to find
+    let pixel_count = small.width() as usize * small.height() as usize;
+    if dark.len() < 100 || dark.len() > pixel_count / 2 {
+        return 0.0;
+    }
+
+    let diagonal = (small.width() as f32).hypot(small.height() as f32) as usize + 1;
+    let mut best = (0.0f32, 0.0f64);
+    let steps = (MAX_SKEW_DEGREES / SKEW_STEP_DEGREES).round() as i32;
+    for step in -steps..=steps {
+        let angle = (step as f32 * SKEW_STEP_DEGREES).to_radians();
+        let (sin, cos) = angle.sin_cos();
+        let mut bins = vec![0u32; diagonal * 2];
+        for &(x, y) in &dark {
+            let offset = (y * cos - x * sin) as isize + diagonal as isize;
+            if let Some(bin) = bins.get_mut(offset.max(0) as usize) {
+                *bin += 1;
+            }
+        }
+        let score = bins.iter().map(|&count| (count as f64) * (count as f64)).sum::<f64>();
+        if score > best.1 || (score == best.1 && angle.abs() < best.0.abs()) {
+            best = (angle, score);
+        }
+    }
+
+    if best.0.abs() < SKEW_STEP_DEGREES.to_radians() / 2.0 {
+        0.0
+    } else {
+        best.0
+    }
+}
+
+// Metadata read from the file: EXIF orientation, resolution and JPEG component count
+struct ImageInfo {
+    orientation: u16,
+    dpi: Option<f64>,
+    jpeg_components: Option<u8>,
+}
+
+impl ImageInfo {
+    fn read(data: &[u8], format: ImageFormat) -> Self {
+        let mut info = ImageInfo { orientation: 1, dpi: None, jpeg_components: None };
+        match format {
+            ImageFormat::Jpeg => info.read_jpeg(data),
+            ImageFormat::Png => info.read_png(data),
+            ImageFormat::Tiff => info.read_tiff(data),
+            _ => {}
+        }
+        if !(1..=8).contains(&info.orientation) {
+            info.orientation = 1;
+        }
+        info
+    }
+
+    fn read_jpeg(&mut self, data: &[u8]) {
+        let mut position = 2;
+        while position + 4 <= data.len() && data[position] == 0xFF {
+            let marker = data[position + 1];
+            // Start of scan: the headers are over
+            if marker == 0xDA || marker == 0xD9 {
+                break;
+            }
+            let length = u16::from_be_bytes([data[position + 2], data[position + 3]]) as usize;
+            let Some(segment) = data.get(position + 4..position + 2 + length) else { break };
+            match marker {
+                0xE0 if segment.starts_with(b"JFIF\0") && segment.len() >= 14 && self.dpi.is_none() => {
+                    let density = u16::from_be_bytes([segment[8], segment[9]]) as f64;
+                    self.dpi = match segment[7] {
+                        1 => Some(density),
+                        2 => Some(density * 2.54),
+                        _ => None,
+                    };
+                }
+                0xE1 if segment.starts_with(b"Exif\0\0") => self.read_tiff(&segment[6..]),
+                // Start of frame (except DHT, JPG and DAC, which share the range)
+                0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) && segment.len() >= 6 => {
+                    self.jpeg_components = Some(segment[5]);
+                }
+                _ => {}
+            }
+            position += 2 + length;
+        }
+    }
+
+    fn read_png(&mut self, data: &[u8]) {
+        let mut position = 8;
+        while position + 8 <= data.len() {
+            let length = u32::from_be_bytes([data[position], data[position + 1], data[position + 2], data[position + 3]]) as usize;
+            let kind = &data[position + 4..position + 8];
+            let Some(chunk) = data.get(position + 8..position + 8 + length) else { break };
+            match kind {
+                // Pixels per unit; unit 1 is the metre
+                b"pHYs" if chunk.len() >= 9 && chunk[8] == 1 => {
+                    self.dpi = Some(u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]) as f64 * 0.0254);
+                }
+                b"eXIf" => self.read_tiff(chunk),
+                b"IDAT" | b"IEND" => break,
+                _ => {}
+            }
+            position += 12 + length;
+        }
+    }
+
+    // IFD0 of a TIFF structure (a TIFF file, or the EXIF block of a JPEG or PNG)
+    fn read_tiff(&mut self, data: &[u8]) {
+        let big_endian = match data.get(0..2) {
+            Some(b"MM") => true,
+            Some(b"II") => false,
+            _ => return,
+        };
+        let u16_at = |offset: usize| data.get(offset..offset + 2).map(|b| {
+            if big_endian { u16::from_be_bytes([b[0], b[1]]) } else { u16::from_le_bytes([b[0], b[1]]) }
+        });
+        let u32_at = |offset: usize| data.get(offset..offset + 4).map(|b| {
+            if big_endian { u32::from_be_bytes([b[0], b[1], b[2], b[3]]) } else { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) }
+        });
+
+        let Some(ifd) = u32_at(4).map(|offset| offset as usize) else { return };
+        let Some(count) = u16_at(ifd) else { return };
+        let mut resolution = None;
+        let mut unit = 2;
+        for entry in (0..count as usize).map(|i| ifd + 2 + i * 12) {
+            let Some(tag) = u16_at(entry) else { break };
+            match tag {
+                0x0112 => self.orientation = u16_at(entry + 8).unwrap_or(1),
+                // XResolution, a RATIONAL stored at an offset
+                0x011A => {
+                    resolution = u32_at(entry + 8).and_then(|offset| {
+                        let (numerator, denominator) = (u32_at(offset as usize)?, u32_at(offset as usize + 4)?);
+                        (denominator != 0).then(|| numerator as f64 / denominator as f64)
+                    });
+                }
+                0x0128 => unit = u16_at(entry + 8).unwrap_or(2),
+                _ => {}
+            }
+        }
+        // Units: 2 is the inch, 3 the centimetre
+        if let Some(resolution) = resolution {
+            match unit {
+                2 => self.dpi = Some(resolution),
+                3 => self.dpi = Some(resolution * 2.54),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use image::{Luma, Rgb, RgbImage};
+
+    fn encode(image: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut data = Vec::new();
+        image.write_to(&mut Cursor::new(&mut data), format).unwrap();
+        data
+    }
+
+    // A JPEG with an EXIF block holding only an orientation tag
+    fn jpeg_with_orientation(image: &DynamicImage, orientation: u16) -> Vec<u8> {
+        let jpeg = encode(image, ImageFormat::Jpeg);
+        let mut exif = b"Exif\0\0MM\0\x2a\0\0\0\x08\0\x01\x01\x12\0\x03\0\0\0\x01".to_vec();
+        exif.extend_from_slice(&orientation.to_be_bytes());
+        exif.extend_from_slice(&[0, 0, 0, 0, 0, 0]);
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        data.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&jpeg[2..]);
+        data
+    }
+
+    fn page_sizes(pdf: &[u8]) -> Vec<(f32, f32)> {
+        let doc = Document::load_mem(pdf).unwrap();
+        doc.get_pages().values().map(|page_id| {
+            let media_box = doc.get_dictionary(*page_id).unwrap().get(b"MediaBox").unwrap().as_array().unwrap();
+            (media_box[2].as_float().unwrap(), media_box[3].as_float().unwrap())
+        }).collect()
+    }
+
+    #[test]
+    fn exif_orientation_turns_the_page() {
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(200, 100, Rgb([200, 10, 10])));
+        let data = jpeg_with_orientation(&image, 6);
+        let info = ImageInfo::read(&data, ImageFormat::Jpeg);
+        assert_eq!(info.orientation, 6);
+        assert_eq!(info.jpeg_components, Some(3));
+
+        // Stored landscape, displayed portrait: fitted to A4 portrait
+        let pdf = images_to_pdf(&[data], ImageCleanup::default()).unwrap();
+        let (width, height) = page_sizes(&pdf)[0];
+        assert!((width - 421.0).abs() < 0.5 && (height - 842.0).abs() < 0.5, "{} x {}", width, height);
+    }
+
+    #[test]
+    fn scan_resolution_gives_the_physical_size() {
+        // 300 dpi PNG, 2.5 x 3.5 inches
+        let image = DynamicImage::ImageLuma8(GrayImage::from_pixel(750, 1050, Luma([255])));
+        let mut png = encode(&image, ImageFormat::Png);
+        let mut phys = b"pHYs".to_vec();
+        phys.extend_from_slice(&11811u32.to_be_bytes());
+        phys.extend_from_slice(&11811u32.to_be_bytes());
+        phys.push(1);
+        let mut chunk = 9u32.to_be_bytes().to_vec();
+        chunk.extend_from_slice(&phys);
+        chunk.extend_from_slice(&[0, 0, 0, 0]); // CRC isn't checked by our reader
+        // After the IHDR chunk (8 byte signature + 25 byte chunk)
+        png.splice(33..33, chunk);
+
+        assert!((ImageInfo::read(&png, ImageFormat::Png).dpi.unwrap() - 300.0).abs() < 0.1);
+        let sizes = page_sizes(&images_to_pdf(&[png.clone(), png], ImageCleanup::default()).unwrap());
+        assert_eq!(sizes.len(), 2);
+        assert!((sizes[0].0 - 180.0).abs() < 0.1 && (sizes[0].1 - 252.0).abs() < 0.1, "{:?}", sizes[0]);
+    }
+
+    #[test]
+    fn skewed_lines_are_detected() {
+        let mut page = GrayImage::from_pixel(800, 1000, Luma([255]));
+        for line in 0..20 {
+            for x in 100..700 {
+                for y in 0..4 {
+                    page.put_pixel(x, 100 + line * 40 + y, Luma([0]));
+                }
+            }
+        }
+        assert_eq!(skew_angle(&page), 0.0);
+
+        let skewed = rotate_about_center(&page, 3f32.to_radians(), Interpolation::Bilinear, Luma([255]));
+        let angle = skew_angle(&skewed).to_degrees();
+        assert!((angle - 3.0).abs() <= 0.2, "{}", angle);
+    }
+
+    #[test]
+    fn contrast_is_stretched() {
+        let mut page = GrayImage::from_pixel(100, 100, Luma([170]));
+        for x in 0..100 {
+            for y in 40..60 {
+                page.put_pixel(x, y, Luma([90]));
+            }
+        }
+        let enhanced = enhance_contrast(&page);
+        assert_eq!(enhanced.get_pixel(0, 0).0[0], 255);
+        assert_eq!(enhanced.get_pixel(0, 50).0[0], 0);
+    }
+}
@@ -2,4 +2,25 @@ pub mod storage;
 pub mod email;
 pub mod queue;
 pub mod cache;
-pub mod reminder_queue;
\ No newline at end of file
+pub mod reminder_queue;
+pub mod acroform;
+pub mod cms;
+pub mod pdf_signing;
+pub mod timestamp;
+pub mod fonts;
+pub mod pdfa;
+pub mod page_geometry;
+pub mod pdf_merge;
+pub mod template_documents;
+pub mod document_conversion;
+pub mod html_rendering;
+pub mod docx_merge;
+pub mod image_pdf;
+pub mod page_previews;
+pub mod pdf_pages;
+pub mod template_versions;
+pub mod template_trash;
+pub mod template_bundles;
+pub mod template_search;
+pub mod template_analytics;
+pub mod field_snippets;
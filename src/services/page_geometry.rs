@@ -0,0 +1,353 @@
+// Page geometry as a viewer shows it. Field positions from the editor are relative to the displayed
+// page: the CropBox (MediaBox when there is none), turned clockwise by /Rotate, with a top-left
+// origin. MediaBox, CropBox, Rotate and Resources are inherited from the page tree when a page
+// doesn't set them itself.
+
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+
+// US Letter, for pages without a usable MediaBox
+const DEFAULT_MEDIA_BOX: [f64; 4] = [0.0, 0.0, 612.0, 792.0];
+
+// Guards against cyclic page trees
+const MAX_TREE_DEPTH: usize = 32;
+
+/// Value of a page attribute, looked up through the page's ancestors (dereferenced)
+pub fn inherited_attribute<'a>(doc: &'a Document, page_id: ObjectId, key: &[u8]) -> Option<&'a Object> {
+    let mut node = Some(page_id);
+    for _ in 0..MAX_TREE_DEPTH {
+        let dict = doc.get_dictionary(node?).ok()?;
+        if let Ok(value) = dict.get(key) {
+            return doc.dereference(value).ok().map(|(_, object)| object);
+        }
+        node = dict.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+    None
+}
+
+fn inherited_rectangle(doc: &Document, page_id: ObjectId, key: &[u8]) -> Option<[f64; 4]> {
+    let values: Vec<f64> = inherited_attribute(doc, page_id, key)?
+        .as_array().ok()?
+        .iter()
+        .map(|value| doc.dereference(value).ok().and_then(|(_, v)| v.as_float().ok()).map(f64::from))
+        .collect::<Option<_>>()?;
+    match values[..] {
+        // Any two opposite corners are allowed
+        [x0, y0, x1, y1] => Some([x0.min(x1), y0.min(y1), x0.max(x1), y0.max(y1)]),
+        _ => None,
+    }
+}
+
+/// Helper function to normalize position coordinates (matching frontend logic)
+/// Converts pixel coordinates to decimal (0-1) format using 600x800 reference dimensions.
+/// Fractions are relative to the page as displayed (CropBox, after /Rotate), top-left origin.
+pub fn normalize_position(x: f64, y: f64, width: f64, height: f64) -> (f64, f64, f64, f64) {
+    const PAGE_WIDTH: f64 = 600.0;  // Default A4 width in pixels (matching frontend)
+    const PAGE_HEIGHT: f64 = 800.0; // Default A4 height in pixels (matching frontend)
+
+    // Check if position is in pixels (values > 1) or already in decimal (0-1)
+    if x > 1.0 || y > 1.0 || width > 1.0 || height > 1.0 {
+        // Position is in pixels, convert to decimal (0-1)
+        (
+            x / PAGE_WIDTH,
+            y / PAGE_HEIGHT,
+            width / PAGE_WIDTH,
+            height / PAGE_HEIGHT,
+        )
+    } else {
+        // Already in decimal format
+        (x, y, width, height)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PageGeometry {
+    /// Visible area in user space: [llx, lly, urx, ury]
+    pub crop_box: [f64; 4],
+    /// Clockwise rotation when displayed: 0, 90, 180 or 270
+    pub rotation: i64,
+}
+
+impl PageGeometry {
+    pub fn from_page(doc: &Document, page_id: ObjectId) -> Self {
+        let media_box = inherited_rectangle(doc, page_id, b"MediaBox")
+            .filter(|b| b[2] > b[0] && b[3] > b[1])
+            .unwrap_or(DEFAULT_MEDIA_BOX);
+        // The CropBox is clipped to the MediaBox
+        let crop_box = inherited_rectangle(doc, page_id, b"CropBox")
+            .map(|b| [b[0].max(media_box[0]), b[1].max(media_box[1]), b[2].min(media_box[2]), b[3].min(media_box[3])])
+            .filter(|b| b[2] > b[0] && b[3] > b[1])
+            .unwrap_or(media_box);
+        let rotation = inherited_attribute(doc, page_id, b"Rotate")
+            .and_then(|r| r.as_float().ok())
+            .map(|r| ((r / 90.0).round() as i64 * 90).rem_euclid(360))
+            .unwrap_or(0);
+        Self { crop_box, rotation }
+    }
+
+    /// Width and height of the page as displayed
+    pub fn display_size(&self) -> (f64, f64) {
+        let width = self.crop_box[2] - self.crop_box[0];
+        let height = self.crop_box[3] - self.crop_box[1];
+        if self.rotation % 180 == 0 {
+            (width, height)
+        } else {
+            (height, width)
+        }
+    }
+
+    /// Matrix [a b c d e f] from display space (origin at the bottom-left corner of the displayed
+    /// page, y up) to user space
+    pub fn display_matrix(&self) -> [f64; 6] {
+        let [x0, y0, x1, y1] = self.crop_box;
+        match self.rotation {
+            90 => [0.0, 1.0, -1.0, 0.0, x1, y0],
+            180 => [-1.0, 0.0, 0.0, -1.0, x1, y1],
+            270 => [0.0, -1.0, 1.0, 0.0, x0, y1],
+            _ => [1.0, 0.0, 0.0, 1.0, x0, y0],
+        }
+    }
+
+    /// Map a point from display space to user space
+    pub fn to_user_space(self, x: f64, y: f64) -> (f64, f64) {
+        let [a, b, c, d, e, f] = self.display_matrix();
+        (a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Map a rectangle [x0, y0, x1, y1] from display space to user space
+    pub fn rect_to_user_space(&self, rect: [f64; 4]) -> [f64; 4] {
+        let (ax, ay) = self.to_user_space(rect[0], rect[1]);
+        let (bx, by) = self.to_user_space(rect[2], rect[3]);
+        [ax.min(bx), ay.min(by), ax.max(bx), ay.max(by)]
+    }
+
+    /// Map a point from user space to display space
+    pub fn to_display_space(self, x: f64, y: f64) -> (f64, f64) {
+        // The display matrix only rotates by multiples of 90°, so its inverse is the transpose
+        let [a, b, c, d, e, f] = self.display_matrix();
+        let (dx, dy) = (x - e, y - f);
+        (a * dx + b * dy, c * dx + d * dy)
+    }
+
+    /// Map a rectangle [x0, y0, x1, y1] from user space to display space
+    pub fn rect_to_display_space(&self, rect: [f64; 4]) -> [f64; 4] {
+        let (ax, ay) = self.to_display_space(rect[0], rect[1]);
+        let (bx, by) = self.to_display_space(rect[2], rect[3]);
+        [ax.min(bx), ay.min(by), ax.max(bx), ay.max(by)]
+    }
+}
+
+/// Prepare a page for drawing in display space. The existing content is wrapped in q/Q so a
+/// graphics state it leaves behind can't shift what is drawn after it, then the display matrix is
+/// set. Content streams appended to /Contents afterwards draw in display space until
+/// end_overlay() restores the state.
+pub fn begin_overlay(doc: &mut Document, page_id: ObjectId) -> Result<PageGeometry, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let geometry = PageGeometry::from_page(doc, page_id);
+
+    // Contents may be a stream, an array of streams or a reference to such an array
+    let mut contents = match doc.get_dictionary(page_id)?.get(b"Contents") {
+        Ok(Object::Reference(id)) => match doc.get_object(*id)? {
+            Object::Array(streams) => streams.clone(),
+            _ => vec![Object::Reference(*id)],
+        },
+        Ok(Object::Array(streams)) => streams.clone(),
+        _ => Vec::new(),
+    };
+
+    let matrix = geometry.display_matrix().iter()
+        .map(|v| format!("{}", v))
+        .collect::<Vec<_>>()
+        .join(" ");
+    contents.insert(0, Object::Reference(content_stream(doc, "q\n")));
+    contents.push(Object::Reference(content_stream(doc, "Q\n")));
+    contents.push(Object::Reference(content_stream(doc, &format!("q {} cm\n", matrix))));
+    doc.get_dictionary_mut(page_id)?.set("Contents", Object::Array(contents));
+    Ok(geometry)
+}
+
+/// Restore the graphics state saved by begin_overlay()
+pub fn end_overlay(doc: &mut Document, page_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let stream_id = content_stream(doc, "Q\n");
+    doc.get_dictionary_mut(page_id)?
+        .get_mut(b"Contents")?
+        .as_array_mut()?
+        .push(Object::Reference(stream_id));
+    Ok(())
+}
+
+fn content_stream(doc: &mut Document, content: &str) -> ObjectId {
+    doc.add_object(Stream::new(Dictionary::new(), content.as_bytes().to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    // Single page under a Pages node; attributes can be set on either
+    fn fixture(pages_attrs: Dictionary, page_attrs: Dictionary) -> (Document, ObjectId) {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let content_id = doc.add_object(Stream::new(Dictionary::new(), b"0 0 10 10 re f".to_vec()));
+        let mut page = dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+        };
+        for (key, value) in page_attrs.iter() {
+            page.set(key.clone(), value.clone());
+        }
+        let page_id = doc.add_object(page);
+        let mut pages = dictionary! {
+            "Type" => "Pages",
+            "Kids" => vec![page_id.into()],
+            "Count" => 1,
+        };
+        for (key, value) in pages_attrs.iter() {
+            pages.set(key.clone(), value.clone());
+        }
+        doc.objects.insert(pages_id, Object::Dictionary(pages));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        (doc, page_id)
+    }
+
+    fn rect(values: [i64; 4]) -> Object {
+        Object::Array(values.iter().map(|v| Object::Integer(*v)).collect())
+    }
+
+    fn geometry(page_attrs: Dictionary) -> PageGeometry {
+        let (doc, page_id) = fixture(Dictionary::new(), page_attrs);
+        PageGeometry::from_page(&doc, page_id)
+    }
+
+    // Top-left corner of a field at fractional (x, y) of the displayed page, in user space
+    fn field_origin(geometry: &PageGeometry, x: f64, y: f64) -> (f64, f64) {
+        let (width, height) = geometry.display_size();
+        geometry.to_user_space(x * width, height - y * height)
+    }
+
+    #[test]
+    fn unrotated_page_maps_directly() {
+        let geometry = geometry(dictionary! { "MediaBox" => rect([0, 0, 612, 792]) });
+        assert_eq!(geometry.display_size(), (612.0, 792.0));
+        assert_eq!(field_origin(&geometry, 0.0, 0.0), (0.0, 792.0));
+        assert_eq!(field_origin(&geometry, 0.5, 0.5), (306.0, 396.0));
+    }
+
+    #[test]
+    fn missing_media_box_defaults_to_letter() {
+        let geometry = geometry(Dictionary::new());
+        assert_eq!(geometry, PageGeometry { crop_box: [0.0, 0.0, 612.0, 792.0], rotation: 0 });
+    }
+
+    #[test]
+    fn non_zero_media_box_origin() {
+        let geometry = geometry(dictionary! { "MediaBox" => rect([100, 200, 712, 992]) });
+        assert_eq!(geometry.display_size(), (612.0, 792.0));
+        assert_eq!(field_origin(&geometry, 0.0, 0.0), (100.0, 992.0));
+        assert_eq!(field_origin(&geometry, 1.0, 1.0), (712.0, 200.0));
+    }
+
+    #[test]
+    fn crop_box_is_the_visible_area() {
+        let geometry = geometry(dictionary! {
+            "MediaBox" => rect([0, 0, 612, 792]),
+            "CropBox" => rect([50, 100, 550, 700]),
+        });
+        assert_eq!(geometry.display_size(), (500.0, 600.0));
+        assert_eq!(field_origin(&geometry, 0.0, 0.0), (50.0, 700.0));
+        assert_eq!(field_origin(&geometry, 1.0, 1.0), (550.0, 100.0));
+    }
+
+    #[test]
+    fn crop_box_is_clipped_to_media_box() {
+        let geometry = geometry(dictionary! {
+            "MediaBox" => rect([0, 0, 612, 792]),
+            "CropBox" => rect([-20, 700, 700, -10]),
+        });
+        assert_eq!(geometry.crop_box, [0.0, 0.0, 612.0, 700.0]);
+    }
+
+    #[test]
+    fn rotated_90() {
+        let geometry = geometry(dictionary! { "MediaBox" => rect([0, 0, 612, 792]), "Rotate" => 90 });
+        assert_eq!(geometry.display_size(), (792.0, 612.0));
+        // Displayed top-left is the bottom-left of the unrotated page
+        assert_eq!(field_origin(&geometry, 0.0, 0.0), (0.0, 0.0));
+        assert_eq!(field_origin(&geometry, 1.0, 0.0), (0.0, 792.0));
+        assert_eq!(field_origin(&geometry, 0.0, 1.0), (612.0, 0.0));
+    }
+
+    #[test]
+    fn rotated_180() {
+        let geometry = geometry(dictionary! { "MediaBox" => rect([0, 0, 612, 792]), "Rotate" => 180 });
+        assert_eq!(geometry.display_size(), (612.0, 792.0));
+        assert_eq!(field_origin(&geometry, 0.0, 0.0), (612.0, 0.0));
+        assert_eq!(field_origin(&geometry, 1.0, 1.0), (0.0, 792.0));
+    }
+
+    #[test]
+    fn rotated_270_with_crop_box() {
+        let geometry = geometry(dictionary! {
+            "MediaBox" => rect([0, 0, 612, 792]),
+            "CropBox" => rect([10, 20, 610, 780]),
+            "Rotate" => -90,
+        });
+        assert_eq!(geometry.rotation, 270);
+        assert_eq!(geometry.display_size(), (760.0, 600.0));
+        assert_eq!(field_origin(&geometry, 0.0, 0.0), (610.0, 780.0));
+        assert_eq!(field_origin(&geometry, 1.0, 1.0), (10.0, 20.0));
+    }
+
+    #[test]
+    fn attributes_are_inherited_from_the_page_tree() {
+        let (doc, page_id) = fixture(
+            dictionary! { "MediaBox" => rect([0, 0, 842, 595]), "Rotate" => 90 },
+            Dictionary::new(),
+        );
+        let geometry = PageGeometry::from_page(&doc, page_id);
+        assert_eq!(geometry, PageGeometry { crop_box: [0.0, 0.0, 842.0, 595.0], rotation: 90 });
+
+        // The page's own value wins
+        let (doc, page_id) = fixture(
+            dictionary! { "MediaBox" => rect([0, 0, 842, 595]), "Rotate" => 90 },
+            dictionary! { "Rotate" => 0 },
+        );
+        assert_eq!(PageGeometry::from_page(&doc, page_id).rotation, 0);
+    }
+
+    #[test]
+    fn rect_to_user_space_normalizes_corners() {
+        let geometry = geometry(dictionary! { "MediaBox" => rect([0, 0, 612, 792]), "Rotate" => 90 });
+        assert_eq!(geometry.rect_to_user_space([10.0, 20.0, 110.0, 70.0]), [542.0, 10.0, 592.0, 110.0]);
+    }
+
+    #[test]
+    fn to_display_space_inverts_display_mapping() {
+        for rotation in [0, 90, 180, 270] {
+            let geometry = geometry(dictionary! {
+                "MediaBox" => rect([0, 0, 612, 792]),
+                "CropBox" => rect([10, 20, 610, 780]),
+                "Rotate" => rotation,
+            });
+            let display = [10.0, 20.0, 110.0, 70.0];
+            assert_eq!(geometry.rect_to_display_space(geometry.rect_to_user_space(display)), display, "rotation {}", rotation);
+        }
+    }
+
+    #[test]
+    fn overlay_wraps_existing_content() {
+        let (mut doc, page_id) = fixture(Dictionary::new(), dictionary! { "Rotate" => 180 });
+        begin_overlay(&mut doc, page_id).unwrap();
+        end_overlay(&mut doc, page_id).unwrap();
+
+        let contents = doc.get_dictionary(page_id).unwrap().get(b"Contents").unwrap().as_array().unwrap();
+        let streams: Vec<String> = contents.iter()
+            .map(|c| {
+                let stream = doc.get_object(c.as_reference().unwrap()).unwrap().as_stream().unwrap();
+                String::from_utf8(stream.content.clone()).unwrap()
+            })
+            .collect();
+        assert_eq!(streams, vec!["q\n", "0 0 10 10 re f", "Q\n", "q -1 0 0 -1 612 792 cm\n", "Q\n"]);
+    }
+}
@@ -0,0 +1,392 @@
+// Page images of template documents, rendered with PDFium in the background after upload and
+// kept in storage next to the document, so viewing a template never waits for rasterisation.
+//
+// Previews of a document `templates/<name>.pdf` are stored as
+//   templates/previews/<name>_page_<n>.<ext>          (the default size)
+//   templates/previews/<size>/<name>_page_<n>.<ext>   (other configured sizes)
+//   templates/previews/<name>.json                    (manifest: source checksum, page count)
+// The manifest ties the images to the document content; previews are regenerated when it changes
+// and removed with the document.
+//
+// Environment:
+//   PREVIEW_SIZES        comma separated name=size list, the first being the default; a size is a
+//                        width in pixels ("800") or a resolution ("150dpi").
+//                        Default: "preview=800,thumbnail=200"
+//   PREVIEW_FORMAT       jpg or png (default jpg)
+//   PREVIEW_PREGENERATE  false to render pages on first request only (default true)
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::Semaphore;
+
+use crate::database::models::DbTemplate;
+use crate::services::storage::StorageService;
+use crate::services::template_documents;
+
+const DEFAULT_SIZES: &str = "preview=800,thumbnail=200";
+// Width to height ratio of the pixel sizes, leaving room for A4 and Letter pages
+const MAX_HEIGHT_RATIO: f32 = 1.375;
+// Background renders at once; each holds a whole document in memory
+static GENERATION_SLOTS: Semaphore = Semaphore::const_new(2);
+
+type PreviewError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreviewScale {
+    /// Target width in pixels
+    Width(u32),
+    /// Resolution; the image size follows the page size
+    Dpi(u32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewSize {
+    pub name: String,
+    pub scale: PreviewScale,
+}
+
+#[derive(Debug, Clone)]
+pub struct PreviewConfig {
+    /// Configured sizes, the default first
+    pub sizes: Vec<PreviewSize>,
+    /// Image format extension, "jpg" or "png"
+    pub format: String,
+    pub pregenerate: bool,
+}
+
+impl PreviewConfig {
+    pub fn from_env() -> Self {
+        let sizes = std::env::var("PREVIEW_SIZES").ok()
+            .and_then(|spec| match parse_sizes(&spec) {
+                Ok(sizes) => Some(sizes),
+                Err(e) => {
+                    eprintln!("Invalid PREVIEW_SIZES, using the defaults: {}", e);
+                    None
+                }
+            })
+            .unwrap_or_else(|| parse_sizes(DEFAULT_SIZES).unwrap_or_default());
+        let format = match std::env::var("PREVIEW_FORMAT").unwrap_or_default().to_lowercase().as_str() {
+            "png" => "png",
+            _ => "jpg",
+        }.to_string();
+        let pregenerate = std::env::var("PREVIEW_PREGENERATE").map(|v| v != "false").unwrap_or(true);
+        PreviewConfig { sizes, format, pregenerate }
+    }
+
+    pub fn default_size(&self) -> &PreviewSize {
+        &self.sizes[0]
+    }
+
+    pub fn size(&self, name: &str) -> Option<&PreviewSize> {
+        self.sizes.iter().find(|size| size.name == name)
+    }
+
+    /// Storage key of a page preview
+    pub fn preview_key(&self, file_key: &str, size: &PreviewSize, page: i32, format: &str) -> String {
+        let name = document_name(file_key);
+        if size.name == self.default_size().name {
+            format!("templates/previews/{}_page_{}.{}", name, page, format)
+        } else {
+            format!("templates/previews/{}/{}_page_{}.{}", size.name, name, page, format)
+        }
+    }
+
+    /// The document, size, page and format a preview key stands for
+    pub fn parse_preview_key(&self, key: &str) -> Option<PreviewRequest> {
+        let page_start = key.rfind("_page_")?;
+        let (page, extension) = key[page_start + 6..].split_once('.')?;
+        let page = page.parse::<i32>().ok()?;
+        let format = normalize_format(extension)?;
+
+        let base = &key[..page_start];
+        let name = base.strip_prefix("templates/previews/")
+            .or_else(|| base.strip_prefix("templates/"))
+            .unwrap_or(base);
+        let (size, name) = match name.split_once('/') {
+            Some((size, rest)) if self.size(size).is_some() => (self.size(size)?.clone(), rest),
+            _ => (self.default_size().clone(), name),
+        };
+        Some(PreviewRequest { file_key: format!("templates/{}.pdf", name), size, page, format })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreviewRequest {
+    pub file_key: String,
+    pub size: PreviewSize,
+    pub page: i32,
+    pub format: String,
+}
+
+// What was rendered for a document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreviewManifest {
+    /// SHA-256 of the document the previews were rendered from
+    pub checksum: String,
+    pub pages: i32,
+    pub format: String,
+    pub sizes: Vec<String>,
+    /// Storage keys of the images
+    pub keys: Vec<String>,
+}
+
+/// "jpg" or "png" for the formats previews can be rendered in
+pub fn normalize_format(format: &str) -> Option<String> {
+    match format.to_lowercase().as_str() {
+        "jpg" | "jpeg" => Some("jpg".to_string()),
+        "png" => Some("png".to_string()),
+        _ => None,
+    }
+}
+
+pub fn content_type(format: &str) -> &'static str {
+    match format {
+        "png" => "image/png",
+        _ => "image/jpeg",
+    }
+}
+
+/// Strong ETag of a cached file
+pub fn etag(data: &[u8]) -> String {
+    format!("\"{}\"", &hex::encode(Sha256::digest(data))[..32])
+}
+
+/// Whether an If-None-Match header value matches the ETag
+pub fn etag_matches(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',').map(str::trim).any(|candidate| {
+        candidate == "*" || candidate.trim_start_matches("W/") == etag
+    })
+}
+
+// Name of a document without the templates/ prefix and .pdf extension
+fn document_name(file_key: &str) -> &str {
+    let name = file_key.strip_suffix(".pdf").unwrap_or(file_key);
+    name.strip_prefix("templates/previews/")
+        .or_else(|| name.strip_prefix("templates/"))
+        .unwrap_or(name)
+}
+
+fn manifest_key(file_key: &str) -> String {
+    format!("templates/previews/{}.json", document_name(file_key))
+}
+
+fn parse_sizes(spec: &str) -> Result<Vec<PreviewSize>, String> {
+    let mut sizes: Vec<PreviewSize> = Vec::new();
+    for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+        let (name, value) = entry.split_once('=').ok_or_else(|| format!("expected name=size, got '{}'", entry))?;
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(format!("invalid size name '{}'", name));
+        }
+        if sizes.iter().any(|size| size.name == name) {
+            return Err(format!("size '{}' is listed twice", name));
+        }
+        let value = value.trim().to_lowercase();
+        let scale = match value.strip_suffix("dpi") {
+            Some(dpi) => PreviewScale::Dpi(dpi.trim().parse().map_err(|_| format!("invalid resolution '{}'", value))?),
+            None => PreviewScale::Width(value.strip_suffix("px").unwrap_or(&value).parse().map_err(|_| format!("invalid width '{}'", value))?),
+        };
+        match scale {
+            PreviewScale::Width(width) if !(16..=4000).contains(&width) => return Err(format!("width {} is out of range", width)),
+            PreviewScale::Dpi(dpi) if !(10..=600).contains(&dpi) => return Err(format!("resolution {} is out of range", dpi)),
+            _ => {}
+        }
+        sizes.push(PreviewSize { name: name.to_string(), scale });
+    }
+    if sizes.is_empty() {
+        return Err("no sizes".to_string());
+    }
+    Ok(sizes)
+}
+
+/// Render one page (1-based) of a PDF
+pub fn render_page(pdf: &[u8], page: i32, size: &PreviewSize, format: &str) -> Result<Vec<u8>, String> {
+    let pdfium = bind_pdfium()?;
+    let document = pdfium.load_pdf_from_byte_slice(pdf, None).map_err(|e| e.to_string())?;
+    if page < 1 || page as usize > document.pages().len() as usize {
+        return Err(format!("Page {} not found in PDF", page));
+    }
+    let page = document.pages().get((page - 1) as u16).map_err(|e| e.to_string())?;
+    render_loaded_page(&page, size, format)
+}
+
+/// Render every page of a PDF in every size: (size index, 1-based page, image)
+fn render_all(pdf: &[u8], sizes: &[PreviewSize], format: &str) -> Result<Vec<(usize, i32, Vec<u8>)>, String> {
+    let pdfium = bind_pdfium()?;
+    let document = pdfium.load_pdf_from_byte_slice(pdf, None).map_err(|e| e.to_string())?;
+    let mut images = Vec::new();
+    for (index, page) in document.pages().iter().enumerate() {
+        for (size_index, size) in sizes.iter().enumerate() {
+            images.push((size_index, index as i32 + 1, render_loaded_page(&page, size, format)?));
+        }
+    }
+    Ok(images)
+}
+
+fn bind_pdfium() -> Result<pdfium_render::prelude::Pdfium, String> {
+    use pdfium_render::prelude::*;
+
+    // Bind to the library in the lib folder, or the system one
+    Ok(Pdfium::new(
+        Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path("./lib/"))
+            .or_else(|_| Pdfium::bind_to_system_library())
+            .map_err(|e| format!("Failed to load PDFium library: {:?}", e))?,
+    ))
+}
+
+fn render_loaded_page(page: &pdfium_render::prelude::PdfPage, size: &PreviewSize, format: &str) -> Result<Vec<u8>, String> {
+    use pdfium_render::prelude::*;
+
+    let config = match size.scale {
+        PreviewScale::Width(width) => PdfRenderConfig::new()
+            .set_target_width(width as i32)
+            .set_maximum_height((width as f32 * MAX_HEIGHT_RATIO) as i32),
+        PreviewScale::Dpi(dpi) => PdfRenderConfig::new().scale_page_by_factor(dpi as f32 / 72.0),
+    };
+    let bitmap = page.render_with_config(&config).map_err(|e| e.to_string())?;
+    let image = image::RgbaImage::from_raw(bitmap.width() as u32, bitmap.height() as u32, bitmap.as_rgba_bytes())
+        .ok_or("Rendered bitmap has an unexpected size")?;
+
+    let mut buffer = Vec::new();
+    let mut cursor = std::io::Cursor::new(&mut buffer);
+    match format {
+        "png" => image.write_to(&mut cursor, image::ImageFormat::Png),
+        // JPEG has no alpha channel
+        _ => image::DynamicImage::ImageRgba8(image).to_rgb8().write_to(&mut cursor, image::ImageFormat::Jpeg),
+    }.map_err(|e| e.to_string())?;
+    Ok(buffer)
+}
+
+/// Manifest of a document's previews, if they have been generated
+pub async fn manifest(storage: &StorageService, file_key: &str) -> Option<PreviewManifest> {
+    let data = storage.download_file(&manifest_key(file_key)).await.ok()?;
+    serde_json::from_slice(&data).ok()
+}
+
+/// Render and store all previews of a document, unless the stored ones match its content
+pub async fn generate_previews(storage: &StorageService, config: &PreviewConfig, file_key: &str) -> Result<PreviewManifest, PreviewError> {
+    let pdf = storage.download_file(file_key).await?;
+    let checksum = hex::encode(Sha256::digest(&pdf));
+    let size_names: Vec<String> = config.sizes.iter().map(|size| size.name.clone()).collect();
+    if let Some(existing) = manifest(storage, file_key).await {
+        if existing.checksum == checksum && existing.format == config.format && existing.sizes == size_names {
+            return Ok(existing);
+        }
+        remove_previews(storage, file_key).await;
+    }
+
+    let sizes = config.sizes.clone();
+    let format = config.format.clone();
+    let images = tokio::task::spawn_blocking(move || render_all(&pdf, &sizes, &format)).await??;
+
+    let pages = images.iter().map(|(_, page, _)| *page).max().unwrap_or(0);
+    let mut keys = Vec::new();
+    for (size_index, page, image) in images {
+        let key = config.preview_key(file_key, &config.sizes[size_index], page, &config.format);
+        storage.upload_file_with_key(image, &key, content_type(&config.format)).await?;
+        keys.push(key);
+    }
+
+    // Written last: a manifest means the whole set is in place
+    let manifest = PreviewManifest { checksum, pages, format: config.format.clone(), sizes: size_names, keys };
+    storage.upload_file_with_key(serde_json::to_vec(&manifest)?, &manifest_key(file_key), "application/json").await?;
+    Ok(manifest)
+}
+
+/// Generate the previews of a template's PDF documents in the background
+pub fn pregenerate(template: &DbTemplate) {
+    let keys: Vec<String> = template_documents::template_documents(template)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|document| document.content_type == "application/pdf" || document.url.to_lowercase().ends_with(".pdf"))
+        .map(|document| document.url)
+        .collect();
+    pregenerate_files(keys);
+}
+
+/// Generate the previews of PDF files in the background
+pub fn pregenerate_files(file_keys: Vec<String>) {
+    let config = PreviewConfig::from_env();
+    if !config.pregenerate || file_keys.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        let Ok(_slot) = GENERATION_SLOTS.acquire().await else { return };
+        let storage = match StorageService::new().await {
+            Ok(storage) => storage,
+            Err(e) => return eprintln!("Failed to initialize storage for previews: {}", e),
+        };
+        for file_key in file_keys {
+            if let Err(e) = generate_previews(&storage, &config, &file_key).await {
+                eprintln!("Failed to generate previews for {}: {}", file_key, e);
+            }
+        }
+    });
+}
+
+/// Delete the stored previews of a document
+pub async fn remove_previews(storage: &StorageService, file_key: &str) {
+    let Some(manifest) = manifest(storage, file_key).await else { return };
+    for key in &manifest.keys {
+        let _ = storage.delete_file(key).await;
+    }
+    let _ = storage.delete_file(&manifest_key(file_key)).await;
+}
+
+/// Delete the previews of several documents in the background
+pub fn remove_previews_of(file_keys: Vec<String>) {
+    if file_keys.is_empty() {
+        return;
+    }
+    tokio::spawn(async move {
+        if let Ok(storage) = StorageService::new().await {
+            for file_key in file_keys {
+                remove_previews(&storage, &file_key).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> PreviewConfig {
+        PreviewConfig { sizes: parse_sizes("preview=800,thumbnail=200,print=150dpi").unwrap(), format: "jpg".to_string(), pregenerate: true }
+    }
+
+    #[test]
+    fn sizes_are_parsed() {
+        let sizes = config().sizes;
+        assert_eq!(sizes[0], PreviewSize { name: "preview".to_string(), scale: PreviewScale::Width(800) });
+        assert_eq!(sizes[2].scale, PreviewScale::Dpi(150));
+        assert!(parse_sizes("preview").is_err());
+        assert!(parse_sizes("a=100,a=200").is_err());
+        assert!(parse_sizes("a=5").is_err());
+    }
+
+    #[test]
+    fn preview_keys_round_trip() {
+        let config = config();
+        let key = config.preview_key("templates/1_contract.pdf", &config.sizes[0], 2, "jpg");
+        assert_eq!(key, "templates/previews/1_contract_page_2.jpg");
+        let request = config.parse_preview_key(&key).unwrap();
+        assert_eq!((request.file_key.as_str(), request.size.name.as_str(), request.page), ("templates/1_contract.pdf", "preview", 2));
+
+        let key = config.preview_key("templates/1_contract.pdf", &config.sizes[1], 3, "png");
+        assert_eq!(key, "templates/previews/thumbnail/1_contract_page_3.png");
+        let request = config.parse_preview_key(&key).unwrap();
+        assert_eq!((request.file_key.as_str(), request.size.name.as_str(), request.format.as_str()), ("templates/1_contract.pdf", "thumbnail", "png"));
+
+        // Keys of the document itself name no page
+        assert!(config.parse_preview_key("templates/1_contract.pdf").is_none());
+    }
+
+    #[test]
+    fn etags_match_if_none_match_lists() {
+        let tag = etag(b"page");
+        assert!(etag_matches(&tag, &tag));
+        assert!(etag_matches(&format!("\"other\", W/{}", tag), &tag));
+        assert!(!etag_matches("\"other\"", &tag));
+    }
+}
@@ -0,0 +1,194 @@
+// Concatenate PDFs into one document. Each source is renumbered into the merged object space and
+// its pages are re-parented under a single page tree; AcroForm fields are combined so filled
+// forms keep working. Outlines and other catalog-level structures of the sources are dropped.
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+
+use crate::services::page_geometry::inherited_attribute;
+
+// Page attributes a page can inherit from its ancestors, copied onto the page before it's moved
+const INHERITABLE_ATTRIBUTES: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+pub fn merge_pdfs(pdfs: &[Vec<u8>]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    match pdfs {
+        [] => return Err("No PDFs to merge".into()),
+        [single] => return Ok(single.clone()),
+        _ => {}
+    }
+
+    let mut merged = Document::with_version("1.4");
+    let pages_id = merged.new_object_id();
+    let mut kids = Vec::new();
+    let mut acroform: Option<Dictionary> = None;
+    let mut form_fields = Vec::new();
+
+    for bytes in pdfs {
+        let mut doc = Document::load_mem(bytes)?;
+        doc.renumber_objects_with(merged.max_id + 1);
+        if doc.version > merged.version {
+            merged.version = doc.version.clone();
+        }
+
+        for page_id in doc.get_pages().into_values() {
+            pin_inherited_attributes(&mut doc, page_id)?;
+            doc.get_dictionary_mut(page_id)?.set("Parent", Object::Reference(pages_id));
+            kids.push(Object::Reference(page_id));
+        }
+
+        let form = doc.catalog().ok()
+            .and_then(|catalog| catalog.get(b"AcroForm").ok())
+            .and_then(|form| doc.dereference(form).ok())
+            .and_then(|(_, form)| form.as_dict().ok().cloned());
+        if let Some(form) = form {
+            if let Some(fields) = form.get(b"Fields").ok()
+                .and_then(|fields| doc.dereference(fields).ok())
+                .and_then(|(_, fields)| fields.as_array().ok().cloned())
+            {
+                form_fields.extend(fields);
+            }
+            // The first form's defaults (DA, DR) are used for the merged form
+            acroform.get_or_insert(form);
+        }
+
+        merged.max_id = doc.max_id;
+        merged.objects.extend(doc.objects);
+    }
+
+    let count = kids.len() as i64;
+    let mut pages = Dictionary::new();
+    pages.set("Type", Object::Name(b"Pages".to_vec()));
+    pages.set("Kids", Object::Array(kids));
+    pages.set("Count", Object::Integer(count));
+    merged.objects.insert(pages_id, Object::Dictionary(pages));
+
+    let mut catalog = Dictionary::new();
+    catalog.set("Type", Object::Name(b"Catalog".to_vec()));
+    catalog.set("Pages", Object::Reference(pages_id));
+    if let Some(mut form) = acroform {
+        form.set("Fields", Object::Array(form_fields));
+        catalog.set("AcroForm", Object::Dictionary(form));
+    }
+    let catalog_id = merged.add_object(catalog);
+    merged.trailer.set("Root", Object::Reference(catalog_id));
+
+    // Drops the sources' catalogs, page tree nodes and anything only they referenced
+    merged.prune_objects();
+    merged.renumber_objects();
+    merged.compress();
+
+    let mut output = Vec::new();
+    merged.save_to(&mut output)?;
+    Ok(output)
+}
+
+/// Copy the attributes a page inherits from its ancestors onto the page, so it keeps them when
+/// moved to another parent
+pub fn pin_inherited_attributes(doc: &mut Document, page_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let inherited: Vec<(&[u8], Object)> = INHERITABLE_ATTRIBUTES.iter()
+        .filter(|key| doc.get_dictionary(page_id).is_ok_and(|page| !page.has(key)))
+        .filter_map(|key| inherited_attribute(doc, page_id, key).map(|value| (*key, value.clone())))
+        .collect();
+    let page = doc.get_dictionary_mut(page_id)?;
+    for (key, value) in inherited {
+        page.set(key, value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+
+    // Pages showing the given labels in one font; the page tree holds the MediaBox and resources
+    // the pages inherit. The last page gets a text field when `with_field` is set.
+    fn source_pdf(labels: &[&str], base_font: &str, with_field: bool) -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => base_font });
+        let mut kids = Vec::new();
+        let mut fields = Vec::new();
+        for (i, label) in labels.iter().enumerate() {
+            let content_id = doc.add_object(Stream::new(dictionary! {}, format!("BT /F1 12 Tf 50 750 Td ({}) Tj ET", label).into_bytes()));
+            let page_id = doc.add_object(dictionary! { "Type" => "Page", "Parent" => pages_id, "Contents" => content_id });
+            if with_field && i == labels.len() - 1 {
+                let field_id = doc.add_object(dictionary! {
+                    "Type" => "Annot", "Subtype" => "Widget", "FT" => "Tx",
+                    "T" => Object::string_literal(*label),
+                    "Rect" => vec![50.into(), 700.into(), 250.into(), 720.into()],
+                    "P" => page_id,
+                });
+                doc.get_dictionary_mut(page_id).unwrap().set("Annots", vec![field_id.into()]);
+                fields.push(Object::Reference(field_id));
+            }
+            kids.push(Object::Reference(page_id));
+        }
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => labels.len() as i64,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        }));
+        let mut catalog = dictionary! { "Type" => "Catalog", "Pages" => pages_id };
+        if with_field {
+            catalog.set("AcroForm", dictionary! { "Fields" => fields });
+        }
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", catalog_id);
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    fn page_text(doc: &Document, page_id: ObjectId) -> String {
+        String::from_utf8_lossy(&doc.get_page_content(page_id).unwrap()).to_string()
+    }
+
+    fn page_font(doc: &Document, page_id: ObjectId) -> String {
+        let resources = doc.get_dictionary(page_id).unwrap().get(b"Resources").unwrap();
+        let fonts = doc.dereference(resources).unwrap().1.as_dict().unwrap().get(b"Font").unwrap();
+        let font = doc.dereference(fonts).unwrap().1.as_dict().unwrap().get(b"F1").unwrap();
+        let font = doc.dereference(font).unwrap().1.as_dict().unwrap();
+        String::from_utf8_lossy(font.get(b"BaseFont").unwrap().as_name().unwrap()).to_string()
+    }
+
+    #[test]
+    fn pages_are_appended_in_order_without_id_collisions() {
+        let first = source_pdf(&["A1", "A2"], "Helvetica", false);
+        let second = source_pdf(&["B1"], "Courier", true);
+        // Both sources number their objects from 1
+        let first_ids: Vec<ObjectId> = Document::load_mem(&first).unwrap().objects.keys().copied().collect();
+        assert!(Document::load_mem(&second).unwrap().objects.keys().any(|id| first_ids.contains(id)));
+
+        let merged = Document::load_mem(&merge_pdfs(&[first, second]).unwrap()).unwrap();
+        let pages = merged.get_pages();
+        assert_eq!(pages.len(), 3);
+        let pages_id = merged.catalog().unwrap().get(b"Pages").unwrap().as_reference().unwrap();
+        assert_eq!(merged.get_dictionary(pages_id).unwrap().get(b"Count").unwrap().as_i64().unwrap(), 3);
+
+        let expected = [("A1", "Helvetica"), ("A2", "Helvetica"), ("B1", "Courier")];
+        for (number, (label, font)) in (1..).zip(expected) {
+            let page_id = pages[&number];
+            let page = merged.get_dictionary(page_id).unwrap();
+            assert_eq!(page.get(b"Parent").unwrap().as_reference().unwrap(), pages_id);
+            assert!(page.has(b"MediaBox"));
+            assert!(page_text(&merged, page_id).contains(&format!("({})", label)), "page {} should show {}", number, label);
+            assert_eq!(page_font(&merged, page_id), font);
+        }
+
+        // The form field still points at its page
+        let form = merged.catalog().unwrap().get(b"AcroForm").unwrap().as_dict().unwrap();
+        let fields = form.get(b"Fields").unwrap().as_array().unwrap();
+        assert_eq!(fields.len(), 1);
+        let field = merged.get_dictionary(fields[0].as_reference().unwrap()).unwrap();
+        assert_eq!(field.get(b"P").unwrap().as_reference().unwrap(), pages[&3]);
+    }
+
+    #[test]
+    fn a_single_pdf_is_returned_as_is() {
+        let pdf = source_pdf(&["A1"], "Helvetica", false);
+        assert_eq!(merge_pdfs(std::slice::from_ref(&pdf)).unwrap(), pdf);
+        assert!(merge_pdfs(&[]).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+// Page-level editing of template documents: reorder, rotate, delete and insert pages, and moving
+// field positions (1-based page, fractions of the displayed page, or pixels of a 600x800 page as
+// older templates store them) along with their pages.
+
+use std::collections::{HashMap, HashSet};
+
+use lopdf::{Document, Object, ObjectId};
+use serde_json::{Map, Value};
+
+use crate::models::template::DocumentPage;
+use crate::services::page_geometry::{normalize_position, PageGeometry};
+use crate::services::pdf_merge::{merge_pdfs, pin_inherited_attributes};
+
+type PageError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Where each page of a document ends up: old page -> (new page, clockwise rotation added).
+/// Pages that aren't in the map were deleted.
+pub type PageMoves = HashMap<i32, (i32, i32)>;
+
+pub fn page_count(pdf: &[u8]) -> Result<i32, PageError> {
+    Ok(Document::load_mem(pdf)?.get_pages().len() as i32)
+}
+
+/// Check a page layout against a document with `count` pages
+pub fn validate_layout(pages: &[DocumentPage], count: i32) -> Result<(), String> {
+    if pages.is_empty() {
+        return Err("A document needs at least one page".to_string());
+    }
+    let mut seen = HashSet::new();
+    for page in pages {
+        if page.page < 1 || page.page > count {
+            return Err(format!("Page {} does not exist; the document has {} pages", page.page, count));
+        }
+        if !seen.insert(page.page) {
+            return Err(format!("Page {} is listed more than once", page.page));
+        }
+        if page.rotate % 90 != 0 {
+            return Err(format!("Rotation of page {} must be a multiple of 90 degrees", page.page));
+        }
+    }
+    Ok(())
+}
+
+pub fn layout_moves(pages: &[DocumentPage]) -> PageMoves {
+    pages.iter().enumerate()
+        .map(|(index, page)| (page.page, (index as i32 + 1, page.rotate.rem_euclid(360))))
+        .collect()
+}
+
+/// Rebuild a PDF with the pages in the given order and rotation; pages left out are removed,
+/// along with form fields that only had widgets on them
+pub fn arrange_pages(pdf: &[u8], pages: &[DocumentPage]) -> Result<Vec<u8>, PageError> {
+    let mut doc = Document::load_mem(pdf)?;
+    let page_ids = doc.get_pages();
+    validate_layout(pages, page_ids.len() as i32)?;
+
+    let pages_id = doc.catalog()?.get(b"Pages")?.as_reference()?;
+    let mut kids = Vec::new();
+    for page in pages {
+        let page_id = page_ids[&(page.page as u32)];
+        let rotation = PageGeometry::from_page(&doc, page_id).rotation;
+        pin_inherited_attributes(&mut doc, page_id)?;
+        let dict = doc.get_dictionary_mut(page_id)?;
+        dict.set("Parent", Object::Reference(pages_id));
+        dict.set("Rotate", Object::Integer((rotation + page.rotate as i64).rem_euclid(360)));
+        kids.push(Object::Reference(page_id));
+    }
+
+    let kept: HashSet<ObjectId> = pages.iter().map(|page| page_ids[&(page.page as u32)]).collect();
+    let deleted: HashSet<ObjectId> = page_ids.values().copied().filter(|id| !kept.contains(id)).collect();
+    remove_form_fields_on(&mut doc, &deleted);
+
+    let count = kids.len() as i64;
+    let root = doc.get_dictionary_mut(pages_id)?;
+    root.set("Kids", Object::Array(kids));
+    root.set("Count", Object::Integer(count));
+    root.remove(b"Parent");
+
+    // Drops deleted pages, intermediate page tree nodes and anything only they referenced
+    doc.prune_objects();
+    let mut output = Vec::new();
+    doc.save_to(&mut output)?;
+    Ok(output)
+}
+
+/// Insert the pages of another PDF after page `after` (0 puts them first)
+pub fn insert_pages(pdf: &[u8], insert: &[u8], after: i32) -> Result<(Vec<u8>, PageMoves), PageError> {
+    let count = page_count(pdf)?;
+    let inserted = page_count(insert)?;
+    if after < 0 || after > count {
+        return Err(format!("Cannot insert after page {}; the document has {} pages", after, count).into());
+    }
+    if inserted == 0 {
+        return Err("The inserted PDF has no pages".into());
+    }
+
+    // Merged, the new pages follow the document's; move them into place
+    let merged = merge_pdfs(&[pdf.to_vec(), insert.to_vec()])?;
+    let order: Vec<DocumentPage> = (1..=after)
+        .chain(count + 1..=count + inserted)
+        .chain(after + 1..=count)
+        .map(|page| DocumentPage { page, rotate: 0 })
+        .collect();
+    let output = arrange_pages(&merged, &order)?;
+
+    let moves = (1..=count)
+        .map(|page| (page, (if page > after { page + inserted } else { page }, 0)))
+        .collect();
+    Ok((output, moves))
+}
+
+/// Move a field position with its page. Returns false when the page was deleted.
+pub fn move_position(position: &mut Map<String, Value>, moves: &PageMoves) -> bool {
+    if !move_rect(position, moves) {
+        return false;
+    }
+    // A suggested position on a deleted page is dropped, the field itself stays
+    if let Some(Value::Object(suggested)) = position.get_mut("suggested") {
+        if !move_rect(suggested, moves) {
+            position.remove("suggested");
+        }
+    }
+    true
+}
+
+fn move_rect(position: &mut Map<String, Value>, moves: &PageMoves) -> bool {
+    let page = position.get("page").and_then(Value::as_i64).unwrap_or(1) as i32;
+    let Some(&(new_page, rotate)) = moves.get(&page) else { return false };
+    position.insert("page".to_string(), Value::from(new_page));
+
+    // Rotated rectangles are stored as fractions, whatever they were stored as before
+    if let Some((x, y, width, height)) = normalized_rect(position).filter(|_| rotate != 0) {
+        let (x, y, width, height) = rotate_rect(x, y, width, height, rotate);
+        for (key, value) in [("x", x), ("y", y), ("width", width), ("height", height)] {
+            position.insert(key.to_string(), Value::from(value));
+        }
+    }
+    true
+}
+
+// A position's rectangle as fractions of the page
+fn normalized_rect(position: &Map<String, Value>) -> Option<(f64, f64, f64, f64)> {
+    let value = |key: &str| position.get(key).and_then(Value::as_f64);
+    Some(normalize_position(value("x")?, value("y")?, value("width")?, value("height")?))
+}
+
+/// Flag (or unflag) a field position as outside a document with `page_count` pages: on a page it
+/// doesn't have, or extending past the page edges. Returns whether it is outside.
+pub fn mark_out_of_bounds(position: &mut Map<String, Value>, page_count: i32) -> bool {
+    let page = position.get("page").and_then(Value::as_i64).unwrap_or(1);
+    let (x, y, width, height) = normalized_rect(position).unwrap_or_default();
+    // Some slack for rounding in the editor
+    let inside = |start: f64, length: f64| start >= -0.001 && start + length <= 1.001;
+    let out_of_bounds = page < 1 || page > page_count as i64 || !inside(x, width) || !inside(y, height);
+    if out_of_bounds {
+        position.insert("out_of_bounds".to_string(), Value::Bool(true));
+    } else {
+        position.remove("out_of_bounds");
+    }
+    out_of_bounds
+}
+
+// A rectangle (fractions of the page, top-left origin) after turning the page clockwise
+fn rotate_rect(x: f64, y: f64, width: f64, height: f64, degrees: i32) -> (f64, f64, f64, f64) {
+    match degrees.rem_euclid(360) {
+        90 => (1.0 - y - height, x, height, width),
+        180 => (1.0 - x - width, 1.0 - y - height, width, height),
+        270 => (y, 1.0 - x - width, height, width),
+        _ => (x, y, width, height),
+    }
+}
+
+// Drop AcroForm fields whose widgets are all on deleted pages
+fn remove_form_fields_on(doc: &mut Document, deleted: &HashSet<ObjectId>) {
+    if deleted.is_empty() {
+        return;
+    }
+    // Widgets are found through the pages' annotations; /P is optional
+    let deleted_annotations: HashSet<ObjectId> = deleted.iter()
+        .filter_map(|page_id| doc.get_dictionary(*page_id).ok()?.get(b"Annots").ok())
+        .filter_map(|annots| doc.dereference(annots).ok()?.1.as_array().ok())
+        .flatten()
+        .filter_map(|annot| annot.as_reference().ok())
+        .collect();
+    let on_deleted_page = |doc: &Document, widget: &Object| -> bool {
+        if widget.as_reference().is_ok_and(|id| deleted_annotations.contains(&id)) {
+            return true;
+        }
+        doc.dereference(widget).ok()
+            .and_then(|(_, widget)| widget.as_dict().ok())
+            .and_then(|widget| widget.get(b"P").ok())
+            .and_then(|page| page.as_reference().ok())
+            .is_some_and(|page| deleted.contains(&page))
+    };
+
+    let Some(form) = doc.catalog().ok()
+        .and_then(|catalog| catalog.get(b"AcroForm").ok())
+        .and_then(|form| doc.dereference(form).ok())
+        .and_then(|(id, form)| Some((id, form.as_dict().ok()?.clone())))
+    else { return };
+    let Some(fields) = form.1.get(b"Fields").ok()
+        .and_then(|fields| doc.dereference(fields).ok())
+        .and_then(|(_, fields)| fields.as_array().ok().cloned())
+    else { return };
+
+    let kept: Vec<Object> = fields.into_iter().filter(|field| {
+        let kids = doc.dereference(field).ok()
+            .and_then(|(_, field)| field.as_dict().ok())
+            .and_then(|field| field.get(b"Kids").ok())
+            .and_then(|kids| doc.dereference(kids).ok())
+            .and_then(|(_, kids)| kids.as_array().ok().cloned());
+        match kids {
+            Some(kids) if !kids.is_empty() => !kids.iter().all(|kid| on_deleted_page(doc, kid)),
+            _ => !on_deleted_page(doc, field),
+        }
+    }).collect();
+
+    let mut form_dict = form.1;
+    form_dict.set("Fields", Object::Array(kept));
+    match form.0 {
+        Some(id) => {
+            doc.objects.insert(id, Object::Dictionary(form_dict));
+        }
+        None => {
+            if let Ok(catalog) = doc.catalog_mut() {
+                catalog.set("AcroForm", Object::Dictionary(form_dict));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::{dictionary, Stream};
+    use serde_json::json;
+
+    // A PDF whose page n is n * 100 points wide, under a nested page tree that sets the height
+    fn numbered_pdf(count: i32) -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let root_id = doc.new_object_id();
+        let inner_id = doc.new_object_id();
+        let kids: Vec<Object> = (1..=count).map(|n| {
+            let content_id = doc.add_object(Stream::new(dictionary! {}, format!("% page {}", n).into_bytes()));
+            doc.add_object(dictionary! {
+                "Type" => "Page",
+                "Parent" => inner_id,
+                "MediaBox" => vec![0.into(), 0.into(), (n * 100).into(), 500.into()],
+                "Contents" => content_id,
+            }).into()
+        }).collect();
+        doc.objects.insert(inner_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages", "Parent" => root_id, "Kids" => kids, "Count" => count as i64, "Rotate" => 90,
+        }));
+        doc.objects.insert(root_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages", "Kids" => vec![inner_id.into()], "Count" => count as i64,
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => root_id });
+        doc.trailer.set("Root", catalog_id);
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    // (width, rotation) of each page
+    fn pages(pdf: &[u8]) -> Vec<(f64, i64)> {
+        let doc = Document::load_mem(pdf).unwrap();
+        doc.get_pages().values().map(|id| {
+            let geometry = PageGeometry::from_page(&doc, *id);
+            (geometry.crop_box[2], geometry.rotation)
+        }).collect()
+    }
+
+    #[test]
+    fn pages_are_reordered_rotated_and_deleted() {
+        let layout = [DocumentPage { page: 3, rotate: 0 }, DocumentPage { page: 1, rotate: 90 }];
+        let pdf = arrange_pages(&numbered_pdf(3), &layout).unwrap();
+        // Rotation inherited from the page tree is kept and added to
+        assert_eq!(pages(&pdf), vec![(300.0, 90), (100.0, 180)]);
+
+        assert!(arrange_pages(&numbered_pdf(3), &[DocumentPage { page: 4, rotate: 0 }]).is_err());
+        assert!(arrange_pages(&numbered_pdf(3), &[DocumentPage { page: 1, rotate: 45 }]).is_err());
+    }
+
+    #[test]
+    fn pages_are_inserted() {
+        let (pdf, moves) = insert_pages(&numbered_pdf(3), &numbered_pdf(2), 1).unwrap();
+        assert_eq!(pages(&pdf).iter().map(|page| page.0).collect::<Vec<_>>(), vec![100.0, 100.0, 200.0, 200.0, 300.0]);
+        assert_eq!(moves[&1], (1, 0));
+        assert_eq!(moves[&2], (4, 0));
+    }
+
+    #[test]
+    fn fields_follow_their_pages() {
+        let moves = layout_moves(&[DocumentPage { page: 2, rotate: 90 }, DocumentPage { page: 1, rotate: 0 }]);
+
+        let mut position = json!({"x": 0.1, "y": 0.2, "width": 0.3, "height": 0.05, "page": 2,
+            "suggested": {"x": 0.0, "y": 0.0, "width": 0.1, "height": 0.1, "page": 3}});
+        assert!(move_position(position.as_object_mut().unwrap(), &moves));
+        assert_eq!(position["page"], 1);
+        // Turned clockwise: the left edge becomes the top
+        let rounded = |key: &str| (position[key].as_f64().unwrap() * 1000.0).round() / 1000.0;
+        assert_eq!((rounded("x"), rounded("y"), rounded("width"), rounded("height")), (0.75, 0.1, 0.05, 0.3));
+        assert!(position.get("suggested").is_none());
+
+        let mut position = json!({"x": 0.1, "y": 0.2, "width": 0.3, "height": 0.05, "page": 3});
+        assert!(!move_position(position.as_object_mut().unwrap(), &moves));
+    }
+
+    #[test]
+    fn fields_outside_the_document_are_flagged() {
+        let mut position = json!({"x": 0.1, "y": 0.2, "width": 0.3, "height": 0.05, "page": 3, "out_of_bounds": true});
+        assert!(!mark_out_of_bounds(position.as_object_mut().unwrap(), 3));
+        assert!(position.get("out_of_bounds").is_none());
+
+        assert!(mark_out_of_bounds(position.as_object_mut().unwrap(), 2));
+        assert_eq!(position["out_of_bounds"], true);
+
+        let mut position = json!({"x": 0.9, "y": 0.2, "width": 0.3, "height": 0.05, "page": 1});
+        assert!(mark_out_of_bounds(position.as_object_mut().unwrap(), 1));
+    }
+
+    #[test]
+    fn pixel_positions_are_moved_and_checked_as_fractions() {
+        // 600x800 pixels: the same rectangle as 0.1, 0.2, 0.3, 0.05
+        let mut position = json!({"x": 60.0, "y": 160.0, "width": 180.0, "height": 40.0, "page": 2});
+        assert!(!mark_out_of_bounds(position.as_object_mut().unwrap(), 2));
+
+        let moves = layout_moves(&[DocumentPage { page: 2, rotate: 90 }, DocumentPage { page: 1, rotate: 0 }]);
+        assert!(move_position(position.as_object_mut().unwrap(), &moves));
+        let rounded = |key: &str| (position[key].as_f64().unwrap() * 1000.0).round() / 1000.0;
+        assert_eq!((rounded("x"), rounded("y"), rounded("width"), rounded("height")), (0.75, 0.1, 0.05, 0.3));
+
+        let mut position = json!({"x": 500.0, "y": 160.0, "width": 180.0, "height": 40.0, "page": 1});
+        assert!(mark_out_of_bounds(position.as_object_mut().unwrap(), 1));
+    }
+}
@@ -0,0 +1,556 @@
+// PAdES (ETSI.CAdES.detached) digital signatures for completed documents.
+//
+// Configuration (environment):
+//   PDF_SIGNING_P12_PATH        server-wide PKCS#12 file with the signing certificate and key
+//   PDF_SIGNING_CERTS_DIR       optional directory with per-account files named account_{id}.p12
+//   PDF_SIGNING_P12_PASSWORD    password for the PKCS#12 files
+//   PDF_SIGNATURE_APPEARANCE    "invisible" (default) or "visible"
+//
+// When a timestamp authority is configured (see services::timestamp) the signature gets an
+// RFC 3161 signature-time-stamp; documents that aren't signed can get a document timestamp.
+
+use chrono::{DateTime, Utc};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+use openssl::hash::{hash, MessageDigest};
+use openssl::nid::Nid;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::{PKey, Private};
+use openssl::x509::X509;
+
+use crate::services::acroform::{encode_win_ansi, escape_literal, format_number, standard_font};
+use crate::services::cms::{self, CmsSigner, SignedContent};
+use crate::services::fonts::{FontEmbedder, SimpleEncoding};
+use crate::services::page_geometry::PageGeometry;
+use crate::services::pdfa;
+use crate::services::timestamp::{TimestampAuthority, TimestampToken};
+
+// Bytes reserved in /Contents for the CMS blob (certificate chain + signature)
+const SIGNATURE_CONTENTS_SIZE: usize = 16384;
+// Extra room for an embedded timestamp token (TSA certificate chain + signature)
+const TIMESTAMP_CONTENTS_SIZE: usize = 16384;
+// Wide enough for any real offset, patched in place once the file layout is known
+const BYTE_RANGE_PLACEHOLDER: i64 = 9_999_999_999;
+
+// Annotation flags: Print (4) + Locked (128)
+const SIGNATURE_WIDGET_FLAGS: i64 = 132;
+// AcroForm SigFlags: SignaturesExist (1) + AppendOnly (2)
+const SIG_FLAGS: i64 = 3;
+
+const APPEARANCE_WIDTH: f64 = 220.0;
+const APPEARANCE_HEIGHT: f64 = 56.0;
+const APPEARANCE_MARGIN: f64 = 24.0;
+
+pub struct SigningCredentials {
+    key: PKey<Private>,
+    certificate: X509,
+    chain: Vec<X509>,
+}
+
+impl SigningCredentials {
+    pub fn new(key: PKey<Private>, certificate: X509, chain: Vec<X509>) -> Self {
+        Self { key, certificate, chain }
+    }
+
+    pub fn from_pkcs12(der: &[u8], password: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let parsed = Pkcs12::from_der(der)?.parse2(password)?;
+        let key = parsed.pkey.ok_or("PKCS#12 file has no private key")?;
+        let certificate = parsed.cert.ok_or("PKCS#12 file has no certificate")?;
+        let chain = parsed.ca.map(|stack| stack.into_iter().collect()).unwrap_or_default();
+        Ok(Self::new(key, certificate, chain))
+    }
+
+    /// Load the signing credentials for an account.
+    /// A per-account file in PDF_SIGNING_CERTS_DIR wins over the server-wide PDF_SIGNING_P12_PATH.
+    /// Returns None when no certificate is configured (documents are then left unsigned).
+    pub fn load_for_account(account_id: Option<i64>) -> Result<Option<Self>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let account_path = match (account_id, std::env::var("PDF_SIGNING_CERTS_DIR")) {
+            (Some(id), Ok(dir)) => {
+                let path = std::path::Path::new(&dir).join(format!("account_{}.p12", id));
+                if path.exists() { Some(path) } else { None }
+            }
+            _ => None,
+        };
+        let path = match account_path {
+            Some(path) => path,
+            None => match std::env::var("PDF_SIGNING_P12_PATH") {
+                Ok(path) if !path.is_empty() => std::path::PathBuf::from(path),
+                _ => return Ok(None),
+            },
+        };
+
+        let password = std::env::var("PDF_SIGNING_P12_PASSWORD").unwrap_or_default();
+        let der = std::fs::read(&path)
+            .map_err(|e| format!("Failed to read signing certificate {}: {}", path.display(), e))?;
+        Ok(Some(Self::from_pkcs12(&der, &password)?))
+    }
+
+    // Common name of the signing certificate, shown in the signature panel
+    pub fn signer_name(&self) -> String {
+        self.certificate
+            .subject_name()
+            .entries_by_nid(Nid::COMMONNAME)
+            .next()
+            .map(|entry| String::from_utf8_lossy(entry.data().as_slice()).to_string())
+            .unwrap_or_else(|| "Unknown signer".to_string())
+    }
+
+    pub fn certificate(&self) -> &X509 {
+        &self.certificate
+    }
+
+    pub fn cms_signer(&self) -> CmsSigner<'_> {
+        CmsSigner { key: &self.key, certificate: &self.certificate, chain: &self.chain }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignatureAppearance {
+    Visible,
+    Invisible,
+}
+
+impl SignatureAppearance {
+    pub fn from_env() -> Self {
+        match std::env::var("PDF_SIGNATURE_APPEARANCE").map(|v| v.to_lowercase()) {
+            Ok(v) if v == "visible" => SignatureAppearance::Visible,
+            _ => SignatureAppearance::Invisible,
+        }
+    }
+}
+
+pub struct SignatureOptions {
+    pub appearance: SignatureAppearance,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+    pub contact_info: Option<String>,
+    // PDF/A output: the visible appearance uses an embedded font
+    pub pdfa: bool,
+}
+
+pub struct SignedPdf {
+    pub bytes: Vec<u8>,
+    // Signature timestamp, present when a TSA was used
+    pub timestamp: Option<TimestampToken>,
+}
+
+/// Apply a PAdES signature to the whole document (B-B, or B-T when a TSA is given).
+/// The document is rewritten with a signature field whose /Contents holds a detached CMS
+/// signature over everything except the /Contents value itself.
+pub async fn sign_pdf(
+    pdf_bytes: &[u8],
+    credentials: &SigningCredentials,
+    options: &SignatureOptions,
+    tsa: Option<&dyn TimestampAuthority>,
+) -> Result<SignedPdf, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let signing_time = Utc::now();
+    let contents_size = if tsa.is_some() { SIGNATURE_CONTENTS_SIZE + TIMESTAMP_CONTENTS_SIZE } else { SIGNATURE_CONTENTS_SIZE };
+    let prepared = prepare_signature_placeholder(pdf_bytes, credentials, options, &signing_time, contents_size)?;
+
+    let digest = hash(MessageDigest::sha256(), &prepared.signed_bytes())?;
+    let signer = credentials.cms_signer();
+    let content = SignedContent { content_type: cms::OID_DATA, digest: &digest, encapsulated: None };
+    let parts = cms::compute_signature(&signer, &content)?;
+
+    // The signature-time-stamp covers the signature value (RFC 3161, appendix A)
+    let mut unsigned_attributes = Vec::new();
+    let timestamp = match tsa {
+        Some(tsa) => {
+            let signature_digest = hash(MessageDigest::sha256(), &parts.signature)?;
+            let token = tsa.timestamp(&signature_digest).await?;
+            unsigned_attributes.push(cms::attribute(cms::OID_ATTR_SIGNATURE_TIMESTAMP_TOKEN, token.token.clone()));
+            Some(token)
+        }
+        None => None,
+    };
+    let cms = cms::build_signed_data(&signer, &content, &parts, &unsigned_attributes)?;
+
+    Ok(SignedPdf { bytes: prepared.embed(&cms)?, timestamp })
+}
+
+/// Apply a document timestamp (/DocTimeStamp, ETSI.RFC3161) covering the whole document.
+/// Rewrites the file, so it must not be used on documents that already carry signatures.
+pub async fn timestamp_pdf(
+    pdf_bytes: &[u8],
+    tsa: &dyn TimestampAuthority,
+) -> Result<SignedPdf, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut doc = Document::load_mem(pdf_bytes)?;
+    let page_id = *doc.get_pages().values().last().ok_or("PDF has no pages")?;
+
+    let mut timestamp = Dictionary::new();
+    timestamp.set("Type", Object::Name(b"DocTimeStamp".to_vec()));
+    timestamp.set("Filter", Object::Name(b"Adobe.PPKLite".to_vec()));
+    timestamp.set("SubFilter", Object::Name(b"ETSI.RFC3161".to_vec()));
+    let field_name = format!("DocTimeStamp{}", Utc::now().timestamp());
+    let prepared = insert_signature(&mut doc, page_id, timestamp, &field_name, [0.0, 0.0, 0.0, 0.0], None, TIMESTAMP_CONTENTS_SIZE)?;
+
+    let digest = hash(MessageDigest::sha256(), &prepared.signed_bytes())?;
+    let token = tsa.timestamp(&digest).await?;
+
+    Ok(SignedPdf { bytes: prepared.embed(&token.token)?, timestamp: Some(token) })
+}
+
+// PDF with the signature dictionary written and /ByteRange patched, waiting for its CMS blob
+pub struct PreparedSignature {
+    bytes: Vec<u8>,
+    contents_start: usize,
+    contents_end: usize,
+}
+
+impl PreparedSignature {
+    // Everything covered by /ByteRange
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.bytes.len());
+        data.extend_from_slice(&self.bytes[..self.contents_start]);
+        data.extend_from_slice(&self.bytes[self.contents_end..]);
+        data
+    }
+
+    pub fn embed(mut self, cms: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let hex_signature = hex::encode_upper(cms);
+        // Keep the surrounding '<' and '>' intact
+        let capacity = self.contents_end - self.contents_start - 2;
+        if hex_signature.len() > capacity {
+            return Err(format!("Signature is {} bytes, only {} reserved", cms.len(), capacity / 2).into());
+        }
+        let start = self.contents_start + 1;
+        self.bytes[start..start + hex_signature.len()].copy_from_slice(hex_signature.as_bytes());
+        Ok(self.bytes)
+    }
+}
+
+pub fn prepare_signature_placeholder(
+    pdf_bytes: &[u8],
+    credentials: &SigningCredentials,
+    options: &SignatureOptions,
+    signing_time: &DateTime<Utc>,
+    contents_size: usize,
+) -> Result<PreparedSignature, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut doc = Document::load_mem(pdf_bytes)?;
+    let signer_name = credentials.signer_name();
+
+    let page_id = *doc.get_pages().values().last().ok_or("PDF has no pages")?;
+
+    // Signature value dictionary
+    let mut signature = Dictionary::new();
+    signature.set("Type", Object::Name(b"Sig".to_vec()));
+    signature.set("Filter", Object::Name(b"Adobe.PPKLite".to_vec()));
+    signature.set("SubFilter", Object::Name(b"ETSI.CAdES.detached".to_vec()));
+    signature.set("M", Object::string_literal(pdf_date(signing_time)));
+    signature.set("Name", Object::string_literal(encode_win_ansi(&signer_name)));
+    if let Some(reason) = &options.reason {
+        signature.set("Reason", Object::string_literal(encode_win_ansi(reason)));
+    }
+    if let Some(location) = &options.location {
+        signature.set("Location", Object::string_literal(encode_win_ansi(location)));
+    }
+    if let Some(contact_info) = &options.contact_info {
+        signature.set("ContactInfo", Object::string_literal(encode_win_ansi(contact_info)));
+    }
+
+    let (rect, appearance) = match options.appearance {
+        SignatureAppearance::Visible => {
+            // Bottom-right corner of the page as displayed
+            let geometry = PageGeometry::from_page(&doc, page_id);
+            let (page_width, _) = geometry.display_size();
+            let x = page_width - APPEARANCE_MARGIN - APPEARANCE_WIDTH;
+            let y = APPEARANCE_MARGIN;
+            let rect = geometry.rect_to_user_space([x, y, x + APPEARANCE_WIDTH, y + APPEARANCE_HEIGHT]);
+            let font = if options.pdfa {
+                Object::Reference(FontEmbedder::new().embed_simple_font(&mut doc, "Helvetica", &SimpleEncoding::default())?)
+            } else {
+                standard_font("Helvetica", true)
+            };
+            let mut appearance = build_visible_appearance(&signer_name, options, signing_time, font);
+            if geometry.rotation != 0 {
+                // Counter the page rotation so the text reads upright
+                let [a, b, c, d, _, _] = geometry.display_matrix();
+                appearance.dict.set("Matrix", Object::Array([a, b, c, d, 0.0, 0.0].iter().map(|v| Object::Real(*v as f32)).collect()));
+            }
+            (rect, Some(appearance))
+        }
+        SignatureAppearance::Invisible => ([0.0, 0.0, 0.0, 0.0], None),
+    };
+
+    let field_name = format!("Signature{}", signing_time.timestamp());
+    insert_signature(&mut doc, page_id, signature, &field_name, rect, appearance, contents_size)
+}
+
+// Add the signature dictionary with /ByteRange and /Contents placeholders plus its field/widget,
+// serialize, and patch /ByteRange now that the file layout is known
+fn insert_signature(
+    doc: &mut Document,
+    page_id: ObjectId,
+    mut signature: Dictionary,
+    field_name: &str,
+    rect: [f64; 4],
+    appearance: Option<Stream>,
+    contents_size: usize,
+) -> Result<PreparedSignature, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    // Keep the binary header comment (required for PDF/A) when the file is rewritten
+    pdfa::set_binary_header(doc);
+
+    signature.set("ByteRange", Object::Array(vec![
+        Object::Integer(0),
+        Object::Integer(BYTE_RANGE_PLACEHOLDER),
+        Object::Integer(BYTE_RANGE_PLACEHOLDER),
+        Object::Integer(BYTE_RANGE_PLACEHOLDER),
+    ]));
+    signature.set("Contents", Object::String(vec![0u8; contents_size], StringFormat::Hexadecimal));
+    let signature_id = doc.add_object(signature);
+
+    // Signature field + widget annotation
+    let mut widget = Dictionary::new();
+    widget.set("Type", Object::Name(b"Annot".to_vec()));
+    widget.set("Subtype", Object::Name(b"Widget".to_vec()));
+    widget.set("FT", Object::Name(b"Sig".to_vec()));
+    widget.set("T", Object::string_literal(field_name));
+    widget.set("V", Object::Reference(signature_id));
+    widget.set("F", Object::Integer(SIGNATURE_WIDGET_FLAGS));
+    widget.set("P", Object::Reference(page_id));
+    widget.set("Rect", Object::Array(rect.iter().map(|v| Object::Real(*v as f32)).collect()));
+    if let Some(appearance) = appearance {
+        let appearance_id = doc.add_object(appearance);
+        let mut ap = Dictionary::new();
+        ap.set("N", Object::Reference(appearance_id));
+        widget.set("AP", Object::Dictionary(ap));
+    }
+    let widget_id = doc.add_object(widget);
+
+    add_page_annotation(doc, page_id, widget_id)?;
+    add_acroform_field(doc, widget_id)?;
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)?;
+
+    // Locate the placeholders in the serialized file
+    let contents_placeholder = format!("<{}>", "00".repeat(contents_size));
+    let contents_start = find_bytes(&bytes, contents_placeholder.as_bytes()).ok_or("Signature /Contents placeholder not found")?;
+    let contents_end = contents_start + contents_placeholder.len();
+
+    let byte_range_placeholder = format!("[0 {} {} {}]", BYTE_RANGE_PLACEHOLDER, BYTE_RANGE_PLACEHOLDER, BYTE_RANGE_PLACEHOLDER);
+    let byte_range_start = find_bytes(&bytes, byte_range_placeholder.as_bytes()).ok_or("Signature /ByteRange placeholder not found")?;
+
+    let byte_range = format!("[0 {} {} {}", contents_start, contents_end, bytes.len() - contents_end);
+    let padded = format!("{:<width$}]", byte_range, width = byte_range_placeholder.len() - 1);
+    bytes[byte_range_start..byte_range_start + padded.len()].copy_from_slice(padded.as_bytes());
+
+    Ok(PreparedSignature { bytes, contents_start, contents_end })
+}
+
+fn find_bytes(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+// PDF date string, e.g. D:20240131120000+00'00'
+fn pdf_date(time: &DateTime<Utc>) -> String {
+    format!("D:{}+00'00'", time.format("%Y%m%d%H%M%S"))
+}
+
+fn build_visible_appearance(signer_name: &str, options: &SignatureOptions, signing_time: &DateTime<Utc>, font: Object) -> Stream {
+    let mut lines = vec![
+        format!("Digitally signed by {}", signer_name),
+        format!("Date: {}", signing_time.format("%Y-%m-%d %H:%M:%S UTC")),
+    ];
+    if let Some(reason) = &options.reason {
+        lines.push(format!("Reason: {}", reason));
+    }
+    if let Some(location) = &options.location {
+        lines.push(format!("Location: {}", location));
+    }
+
+    let font_size = 8.0;
+    let leading = 10.0;
+    let mut content = String::new();
+    content.push_str("q\n0.2 0.4 0.8 RG 1 w\n");
+    content.push_str(&format!("0.5 0.5 {} {} re S\n", format_number(APPEARANCE_WIDTH - 1.0), format_number(APPEARANCE_HEIGHT - 1.0)));
+    content.push_str(&format!("BT\n/Helv {} Tf\n0 g\n", format_number(font_size)));
+    for (i, line) in lines.iter().take(5).enumerate() {
+        let y = APPEARANCE_HEIGHT - 6.0 - font_size - i as f64 * leading;
+        content.push_str(&format!("1 0 0 1 6 {} Tm\n({}) Tj\n", format_number(y), escape_literal(&encode_win_ansi(line))));
+    }
+    content.push_str("ET\nQ\n");
+
+    let mut fonts = Dictionary::new();
+    fonts.set("Helv", font);
+    let mut resources = Dictionary::new();
+    resources.set("Font", Object::Dictionary(fonts));
+
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Form".to_vec()));
+    dict.set("BBox", Object::Array(vec![0.into(), 0.into(), Object::Real(APPEARANCE_WIDTH as f32), Object::Real(APPEARANCE_HEIGHT as f32)]));
+    dict.set("Resources", Object::Dictionary(resources));
+    Stream::new(dict, content.into_bytes())
+}
+
+fn add_page_annotation(doc: &mut Document, page_id: ObjectId, annotation_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let annots_ref = doc.get_dictionary(page_id)?.get(b"Annots").and_then(|a| a.as_reference()).ok();
+    match annots_ref {
+        Some(id) => {
+            doc.get_object_mut(id)?.as_array_mut()?.push(Object::Reference(annotation_id));
+        }
+        None => {
+            let page = doc.get_dictionary_mut(page_id)?;
+            match page.get_mut(b"Annots") {
+                Ok(Object::Array(annots)) => annots.push(Object::Reference(annotation_id)),
+                _ => page.set("Annots", Object::Array(vec![Object::Reference(annotation_id)])),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn add_acroform_field(doc: &mut Document, field_id: ObjectId) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let acroform_ref = doc.catalog()?.get(b"AcroForm").and_then(|a| a.as_reference()).ok();
+    let acroform_id = match acroform_ref {
+        Some(id) => id,
+        None => {
+            // Move an inline (or missing) AcroForm into its own object
+            let existing = match doc.catalog()?.get(b"AcroForm") {
+                Ok(Object::Dictionary(dict)) => dict.clone(),
+                _ => Dictionary::new(),
+            };
+            let id = doc.add_object(existing);
+            doc.catalog_mut()?.set("AcroForm", Object::Reference(id));
+            id
+        }
+    };
+
+    let fields_ref = doc.get_dictionary(acroform_id)?.get(b"Fields").and_then(|f| f.as_reference()).ok();
+    match fields_ref {
+        Some(id) => {
+            doc.get_object_mut(id)?.as_array_mut()?.push(Object::Reference(field_id));
+        }
+        None => {
+            let acroform = doc.get_dictionary_mut(acroform_id)?;
+            match acroform.get_mut(b"Fields") {
+                Ok(Object::Array(fields)) => fields.push(Object::Reference(field_id)),
+                _ => acroform.set("Fields", Object::Array(vec![Object::Reference(field_id)])),
+            }
+        }
+    }
+    doc.get_dictionary_mut(acroform_id)?.set("SigFlags", Object::Integer(SIG_FLAGS));
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::cms::{CMSOptions, CmsContentInfo};
+    use openssl::rsa::Rsa;
+    use openssl::stack::Stack;
+    use openssl::x509::store::X509StoreBuilder;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+    use crate::services::timestamp::LocalTimestampAuthority;
+
+    fn credentials(common_name: &str) -> SigningCredentials {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", common_name).unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(7).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        SigningCredentials::new(key, builder.build(), Vec::new())
+    }
+
+    fn one_page_pdf() -> Vec<u8> {
+        let mut doc = Document::with_version("1.7");
+        let pages_id = doc.new_object_id();
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf 72 720 Td (Signed) Tj ET".to_vec()));
+        let page_id = doc.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+        });
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1,
+        }));
+        let catalog_id = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages_id });
+        doc.trailer.set("Root", catalog_id);
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    // The signature dictionary's /ByteRange and the DER CMS blob from /Contents
+    fn signature(pdf: &[u8]) -> (Vec<usize>, Vec<u8>) {
+        let doc = Document::load_mem(pdf).unwrap();
+        let signature = doc.objects.values()
+            .filter_map(|o| o.as_dict().ok())
+            .find(|d| d.get(b"Type").and_then(|t| t.as_name()).ok() == Some(b"Sig".as_slice()))
+            .unwrap();
+        let byte_range = signature.get(b"ByteRange").unwrap().as_array().unwrap()
+            .iter().map(|v| v.as_i64().unwrap() as usize).collect();
+        let contents = signature.get(b"Contents").unwrap().as_str().unwrap();
+        // /Contents is zero-padded after the DER encoding
+        let (_, rest) = cms::der_parse(contents).unwrap();
+        (byte_range, contents[..contents.len() - rest.len()].to_vec())
+    }
+
+    fn verify(cms_der: &[u8], credentials: &SigningCredentials, covered: &[u8]) -> bool {
+        let mut certs = Stack::new().unwrap();
+        certs.push(credentials.certificate().clone()).unwrap();
+        let store = X509StoreBuilder::new().unwrap().build();
+        let mut content_info = CmsContentInfo::from_der(cms_der).unwrap();
+        content_info
+            .verify(Some(&certs), Some(&store), Some(covered), None, CMSOptions::NO_SIGNER_CERT_VERIFY | CMSOptions::BINARY)
+            .is_ok()
+    }
+
+    fn check_signed(pdf: &[u8], credentials: &SigningCredentials) -> Vec<u8> {
+        let (byte_range, cms_der) = signature(pdf);
+        // Two ranges around the hex /Contents string, covering the rest of the file
+        assert_eq!(byte_range.len(), 4);
+        assert_eq!(byte_range[0], 0);
+        assert_eq!(pdf[byte_range[1]], b'<');
+        assert_eq!(pdf[byte_range[2] - 1], b'>');
+        assert_eq!(byte_range[2] + byte_range[3], pdf.len());
+
+        let mut covered = pdf[..byte_range[1]].to_vec();
+        covered.extend_from_slice(&pdf[byte_range[2]..]);
+        assert!(verify(&cms_der, credentials, &covered));
+        covered[byte_range[1] / 2] ^= 0x01;
+        assert!(!verify(&cms_der, credentials, &covered));
+        cms_der
+    }
+
+    fn options(appearance: SignatureAppearance) -> SignatureOptions {
+        SignatureOptions { appearance, reason: Some("Completed".to_string()), location: None, contact_info: None, pdfa: false }
+    }
+
+    #[tokio::test]
+    async fn signatures_cover_the_byte_range() {
+        let credentials = credentials("Test Signer");
+        let signed = sign_pdf(&one_page_pdf(), &credentials, &options(SignatureAppearance::Invisible), None).await.unwrap();
+        assert!(signed.timestamp.is_none());
+        check_signed(&signed.bytes, &credentials);
+
+        let signed = sign_pdf(&one_page_pdf(), &credentials, &options(SignatureAppearance::Visible), None).await.unwrap();
+        check_signed(&signed.bytes, &credentials);
+    }
+
+    #[tokio::test]
+    async fn timestamped_signatures_still_verify() {
+        let credentials = credentials("Test Signer");
+        let tsa = LocalTimestampAuthority::new(self::credentials("Test TSA"), "1.2.3.4.1".to_string());
+        let signed = sign_pdf(&one_page_pdf(), &credentials, &options(SignatureAppearance::Invisible), Some(&tsa)).await.unwrap();
+
+        let cms_der = check_signed(&signed.bytes, &credentials);
+        let token = signed.timestamp.unwrap();
+        // The token is embedded as an unsigned attribute of the signature
+        assert!(cms_der.windows(token.token.len()).any(|w| w == token.token.as_slice()));
+    }
+}
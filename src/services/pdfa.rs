@@ -0,0 +1,913 @@
+// PDF/A-2b archival output.
+//
+// Converts generated documents in place: fonts are embedded (see services::fonts), XMP metadata
+// and an sRGB output intent are added, and features PDF/A forbids (JavaScript, launch actions,
+// LZW compression, hidden annotations, ...) are removed. `validate_pdfa` reports what is still
+// not conformant, e.g. symbolic fonts (ZapfDingbats check marks) and fonts that couldn't be
+// embedded, or DeviceCMYK content.
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, StringFormat};
+
+use crate::services::fonts::{self, FontEmbedder, SimpleEncoding};
+
+pub const PDFA_PRODUCER: &str = "LetMeSign";
+const OUTPUT_CONDITION: &str = "sRGB IEC61966-2.1";
+
+// Annotation flags
+const ANNOT_INVISIBLE: i64 = 1;
+const ANNOT_HIDDEN: i64 = 2;
+const ANNOT_PRINT: i64 = 4;
+const ANNOT_NOVIEW: i64 = 32;
+
+// Standard and common symbolic fonts, which have no text font substitute
+const SYMBOLIC_FONTS: [&str; 5] = ["Symbol", "ZapfDingbats", "Dingbats", "Wingdings", "Webdings"];
+
+const FORBIDDEN_ACTIONS: [&[u8]; 9] = [
+    b"JavaScript", b"Launch", b"Sound", b"Movie", b"ResetForm", b"ImportData", b"Hide", b"Rendition", b"Trans",
+];
+const FORBIDDEN_ANNOTATIONS: [&[u8]; 5] = [b"Sound", b"Movie", b"Screen", b"3D", b"RichMedia"];
+
+/// Convert a PDF to PDF/A-2b. Returns the converted file and the conformance issues left.
+pub fn convert_to_pdfa(
+    pdf_bytes: &[u8],
+    title: &str,
+) -> Result<(Vec<u8>, Vec<String>), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut doc = Document::load_mem(pdf_bytes)?;
+    if doc.is_encrypted() {
+        return Err("Encrypted PDFs can't be converted to PDF/A".into());
+    }
+
+    doc.version = "1.7".to_string();
+    set_binary_header(&mut doc);
+
+    let mut issues = Vec::new();
+    remove_forbidden_features(&mut doc);
+    embed_fonts(&mut doc, &mut FontEmbedder::new(), &mut issues);
+    add_output_intent(&mut doc)?;
+    add_metadata(&mut doc, title, Utc::now())?;
+    set_document_id(&mut doc, pdf_bytes);
+
+    let mut bytes = Vec::new();
+    doc.save_to(&mut bytes)?;
+
+    issues.extend(validate_pdfa(&bytes));
+    issues.dedup();
+    Ok((bytes, issues))
+}
+
+/// PDF/A wants a comment with binary characters right after the header.
+/// lopdf writes the version verbatim after "%PDF-", so the comment rides along with it.
+pub fn set_binary_header(doc: &mut Document) {
+    if !doc.version.contains('\n') {
+        doc.version = format!("{}\n%\u{e2}\u{e3}\u{cf}\u{d3}", doc.version);
+    }
+}
+
+// ===== Conversion =====
+
+fn remove_forbidden_features(doc: &mut Document) {
+    let ids: Vec<ObjectId> = doc.objects.keys().copied().collect();
+    for id in ids {
+        let object = match doc.objects.get_mut(&id) {
+            Some(object) => object,
+            None => continue,
+        };
+        if let Object::Stream(stream) = object {
+            // LZW isn't allowed, store the data uncompressed instead
+            let filters = stream.filters().unwrap_or_default();
+            if filters.iter().any(|f| f == "LZWDecode") {
+                stream.decompress();
+                if stream.dict.has(b"Filter") {
+                    stream.dict.remove(b"Filter");
+                    stream.dict.remove(b"DecodeParms");
+                }
+            }
+            strip_dictionary(&mut stream.dict);
+        } else if let Object::Dictionary(dict) = object {
+            strip_dictionary(dict);
+        }
+    }
+
+    // Resolve action references now that all objects were visited
+    let ids: Vec<ObjectId> = doc.objects.keys().copied().collect();
+    for id in ids {
+        let action_ref = doc.get_dictionary(id).ok()
+            .and_then(|d| d.get(b"A").ok())
+            .and_then(|a| a.as_reference().ok());
+        if let Some(action_id) = action_ref {
+            if doc.get_dictionary(action_id).map(is_forbidden_action).unwrap_or(false) {
+                if let Ok(dict) = doc.get_dictionary_mut(id) {
+                    dict.remove(b"A");
+                }
+            }
+        }
+    }
+
+    // Annotation types PDF/A doesn't allow, and annotations that aren't shown: PDF/A has them
+    // all visible, which would show what the document hides
+    let mut removed_annots = HashSet::new();
+    let pages: Vec<ObjectId> = doc.get_pages().values().copied().collect();
+    for page_id in pages {
+        let annots: Vec<Object> = match doc.get_dictionary(page_id).ok().and_then(|p| p.get(b"Annots").ok()).cloned() {
+            Some(Object::Array(annots)) => annots,
+            Some(Object::Reference(id)) => doc.get_object(id).ok().and_then(|o| o.as_array().ok()).cloned().unwrap_or_default(),
+            _ => continue,
+        };
+        let (kept, removed): (Vec<Object>, Vec<Object>) = annots.into_iter()
+            .partition(|annot| !doc.dereference(annot).ok().and_then(|(_, o)| o.as_dict().ok()).is_some_and(is_removed_annotation));
+        removed_annots.extend(removed.iter().filter_map(|annot| annot.as_reference().ok()));
+        if let Ok(page) = doc.get_dictionary_mut(page_id) {
+            page.set("Annots", Object::Array(kept));
+        }
+    }
+    // Form fields go with their removed widget annotations
+    if !removed_annots.is_empty() {
+        if let Some(fields) = acroform_fields(doc) {
+            let fields = remove_field_widgets(doc, fields, &removed_annots);
+            set_acroform_fields(doc, fields);
+        }
+    }
+
+    // Document level: JavaScript and embedded files name trees, XFA, NeedAppearances
+    let names_id = doc.catalog().ok().and_then(|c| c.get(b"Names").ok()).and_then(|n| n.as_reference().ok());
+    let acroform_id = doc.catalog().ok().and_then(|c| c.get(b"AcroForm").ok()).and_then(|a| a.as_reference().ok());
+    if let Ok(catalog) = doc.catalog_mut() {
+        if let Ok(Object::Dictionary(names)) = catalog.get_mut(b"Names") {
+            names.remove(b"JavaScript");
+            names.remove(b"EmbeddedFiles");
+        }
+        if let Ok(Object::Dictionary(acroform)) = catalog.get_mut(b"AcroForm") {
+            acroform.remove(b"XFA");
+            acroform.remove(b"NeedAppearances");
+        }
+    }
+    if let Some(id) = names_id {
+        if let Ok(names) = doc.get_dictionary_mut(id) {
+            names.remove(b"JavaScript");
+            names.remove(b"EmbeddedFiles");
+        }
+    }
+    if let Some(id) = acroform_id {
+        if let Ok(acroform) = doc.get_dictionary_mut(id) {
+            acroform.remove(b"XFA");
+            acroform.remove(b"NeedAppearances");
+        }
+    }
+
+    // Actions and annotations removed above would otherwise stay in the file unreferenced
+    doc.prune_objects();
+}
+
+// Annotations PDF/A doesn't allow, and ones not shown that PDF/A would have visible
+fn is_removed_annotation(dict: &Dictionary) -> bool {
+    let subtype = dict.get(b"Subtype").and_then(|s| s.as_name()).ok();
+    let flags = dict.get(b"F").and_then(|f| f.as_i64()).unwrap_or(0);
+    subtype.is_some_and(|s| FORBIDDEN_ANNOTATIONS.contains(&s))
+        || (subtype != Some(b"Popup".as_slice()) && flags & (ANNOT_HIDDEN | ANNOT_NOVIEW) != 0)
+}
+
+fn acroform_fields(doc: &Document) -> Option<Vec<Object>> {
+    let (_, acroform) = doc.dereference(doc.catalog().ok()?.get(b"AcroForm").ok()?).ok()?;
+    let (_, fields) = doc.dereference(acroform.as_dict().ok()?.get(b"Fields").ok()?).ok()?;
+    fields.as_array().ok().cloned()
+}
+
+fn set_acroform_fields(doc: &mut Document, fields: Vec<Object>) {
+    let acroform_id = doc.catalog().ok().and_then(|c| c.get(b"AcroForm").ok()).and_then(|a| a.as_reference().ok());
+    let acroform = match acroform_id {
+        Some(id) => doc.get_dictionary_mut(id).ok(),
+        None => doc.catalog_mut().ok().and_then(|c| c.get_mut(b"AcroForm").ok()).and_then(|a| a.as_dict_mut().ok()),
+    };
+    if let Some(acroform) = acroform {
+        acroform.set("Fields", Object::Array(fields));
+    }
+}
+
+// Fields without the removed widgets; a widget is a field's kid, or the field itself when merged
+// with it. Fields left without kids are removed too.
+fn remove_field_widgets(doc: &mut Document, fields: Vec<Object>, removed: &HashSet<ObjectId>) -> Vec<Object> {
+    fields.into_iter()
+        .filter(|field| {
+            let Ok(id) = field.as_reference() else {
+                return true;
+            };
+            if removed.contains(&id) {
+                return false;
+            }
+            let kids = match doc.get_dictionary(id).ok().and_then(|d| d.get(b"Kids").ok()) {
+                Some(Object::Array(kids)) => kids.clone(),
+                _ => return true,
+            };
+            let kept = remove_field_widgets(doc, kids, removed);
+            let has_kids = !kept.is_empty();
+            if let Ok(dict) = doc.get_dictionary_mut(id) {
+                dict.set("Kids", Object::Array(kept));
+            }
+            has_kids
+        })
+        .collect()
+}
+
+fn is_forbidden_action(dict: &Dictionary) -> bool {
+    dict.get(b"S").and_then(|s| s.as_name()).map(|s| FORBIDDEN_ACTIONS.contains(&s)).unwrap_or(false)
+}
+
+// Per-dictionary clean-up: additional actions, inline forbidden actions, image and
+// graphics state entries, annotation print flags
+fn strip_dictionary(dict: &mut Dictionary) {
+    dict.remove(b"AA");
+    for key in [b"A".as_slice(), b"OpenAction"] {
+        if let Ok(Object::Dictionary(action)) = dict.get(key) {
+            if is_forbidden_action(action) {
+                dict.remove(key);
+            }
+        }
+    }
+
+    let subtype = dict.get(b"Subtype").and_then(|s| s.as_name()).ok().map(|s| s.to_vec());
+    let type_name = dict.get(b"Type").and_then(|s| s.as_name()).ok().map(|s| s.to_vec());
+
+    if subtype.as_deref() == Some(b"Image") {
+        dict.remove(b"Interpolate");
+        dict.remove(b"Alternates");
+        dict.remove(b"OPI");
+    }
+    if type_name.as_deref() == Some(b"ExtGState") {
+        dict.remove(b"TR");
+        if dict.get(b"TR2").and_then(|t| t.as_name()).map(|n| n != b"Default").unwrap_or(false) {
+            dict.remove(b"TR2");
+        }
+    }
+    if subtype.as_deref() == Some(b"Form") {
+        dict.remove(b"OPI");
+        dict.remove(b"PS");
+    }
+    let is_annotation = type_name.as_deref() == Some(b"Annot") || (dict.has(b"Rect") && subtype.is_some() && dict.has(b"P"));
+    if is_annotation && subtype.as_deref() != Some(b"Popup") {
+        let flags = dict.get(b"F").and_then(|f| f.as_i64()).unwrap_or(0);
+        dict.set("F", Object::Integer((flags | ANNOT_PRINT) & !ANNOT_INVISIBLE));
+    }
+}
+
+// Base font and encoding of a simple font that has no embedded font program. Symbolic fonts
+// are left alone: their codes stand for pictograms no substitute text font draws.
+fn missing_simple_font(doc: &Document, dict: &Dictionary) -> Option<(String, SimpleEncoding)> {
+    if dict.get(b"Type").and_then(|t| t.as_name()).ok() != Some(b"Font".as_slice()) {
+        return None;
+    }
+    let subtype = dict.get(b"Subtype").and_then(|s| s.as_name()).ok()?;
+    if ![b"Type1".as_slice(), b"MMType1", b"TrueType"].contains(&subtype) || fonts::is_font_embedded(doc, dict) {
+        return None;
+    }
+    let base_font = dict.get(b"BaseFont").and_then(|b| b.as_name()).ok()
+        .map(|b| String::from_utf8_lossy(b).to_string())
+        .unwrap_or_else(|| "Helvetica".to_string());
+    if is_symbolic_font(doc, dict, &base_font) {
+        return None;
+    }
+    Some((base_font, SimpleEncoding::from_object(doc, dict.get(b"Encoding").ok())))
+}
+
+fn is_symbolic_font(doc: &Document, dict: &Dictionary, base_font: &str) -> bool {
+    let name = base_font.split('+').next_back().unwrap_or(base_font);
+    if SYMBOLIC_FONTS.iter().any(|symbolic| name.starts_with(symbolic)) {
+        return true;
+    }
+    let flags = dict.get(b"FontDescriptor").ok()
+        .and_then(|d| doc.dereference(d).ok())
+        .and_then(|(_, d)| d.as_dict().ok())
+        .and_then(|d| d.get(b"Flags").and_then(|f| f.as_i64()).ok())
+        .unwrap_or(0);
+    flags & fonts::FLAG_SYMBOLIC != 0 && flags & fonts::FLAG_NONSYMBOLIC == 0
+}
+
+/// Embed every simple font that isn't embedded yet. Fonts that can't be embedded are reported.
+pub fn embed_fonts(doc: &mut Document, embedder: &mut FontEmbedder, issues: &mut Vec<String>) {
+    // Font objects are replaced in place so existing references keep working
+    let ids: Vec<ObjectId> = doc.objects.keys().copied().collect();
+    for id in ids {
+        let missing = match doc.get_object(id) {
+            Ok(Object::Dictionary(dict)) => missing_simple_font(doc, dict),
+            _ => None,
+        };
+        if let Some((base_font, encoding)) = missing {
+            match embedder.simple_font_dictionary(doc, &base_font, &encoding) {
+                Ok(font) => {
+                    doc.objects.insert(id, Object::Dictionary(font));
+                }
+                Err(e) => issues.push(e.to_string()),
+            }
+        }
+    }
+
+    // Direct font dictionaries inside resource dictionaries
+    let ids: Vec<ObjectId> = doc.objects.keys().copied().collect();
+    for id in ids {
+        if let Some(mut object) = doc.objects.remove(&id) {
+            embed_direct_fonts(&mut object, doc, embedder, issues);
+            doc.objects.insert(id, object);
+        }
+    }
+}
+
+fn embed_direct_fonts(object: &mut Object, doc: &mut Document, embedder: &mut FontEmbedder, issues: &mut Vec<String>) {
+    let dict = match object {
+        Object::Dictionary(dict) => dict,
+        Object::Stream(stream) => &mut stream.dict,
+        Object::Array(items) => {
+            for item in items.iter_mut() {
+                embed_direct_fonts(item, doc, embedder, issues);
+            }
+            return;
+        }
+        _ => return,
+    };
+
+    if let Ok(Object::Dictionary(font_map)) = dict.get_mut(b"Font") {
+        for (_, font) in font_map.iter_mut() {
+            let missing = match font {
+                Object::Dictionary(font_dict) => missing_simple_font(doc, font_dict),
+                _ => None,
+            };
+            if let Some((base_font, encoding)) = missing {
+                match embedder.embed_simple_font(doc, &base_font, &encoding) {
+                    Ok(font_id) => *font = Object::Reference(font_id),
+                    Err(e) => issues.push(e.to_string()),
+                }
+            }
+        }
+    }
+    for (_, value) in dict.iter_mut() {
+        embed_direct_fonts(value, doc, embedder, issues);
+    }
+}
+
+fn add_output_intent(doc: &mut Document) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut profile_dict = Dictionary::new();
+    profile_dict.set("N", Object::Integer(3));
+    let profile_id = doc.add_object(Stream::new(profile_dict, srgb_icc_profile()));
+
+    let mut intent = Dictionary::new();
+    intent.set("Type", Object::Name(b"OutputIntent".to_vec()));
+    intent.set("S", Object::Name(b"GTS_PDFA1".to_vec()));
+    intent.set("OutputConditionIdentifier", Object::string_literal(OUTPUT_CONDITION));
+    intent.set("Info", Object::string_literal(OUTPUT_CONDITION));
+    intent.set("DestOutputProfile", Object::Reference(profile_id));
+    let intent_id = doc.add_object(intent);
+
+    doc.catalog_mut()?.set("OutputIntents", Object::Array(vec![Object::Reference(intent_id)]));
+    Ok(())
+}
+
+fn add_metadata(doc: &mut Document, title: &str, now: DateTime<Utc>) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    // Document info and XMP have to agree, so both are rewritten from the same values
+    let pdf_date = format!("D:{}Z", now.format("%Y%m%d%H%M%S"));
+    let mut info = Dictionary::new();
+    info.set("Title", Object::String(utf16_text(title), StringFormat::Hexadecimal));
+    info.set("Producer", Object::string_literal(PDFA_PRODUCER));
+    info.set("Creator", Object::string_literal(PDFA_PRODUCER));
+    info.set("CreationDate", Object::string_literal(pdf_date.clone()));
+    info.set("ModDate", Object::string_literal(pdf_date));
+    let info_id = doc.add_object(info);
+    doc.trailer.set("Info", Object::Reference(info_id));
+
+    let mut metadata_dict = Dictionary::new();
+    metadata_dict.set("Type", Object::Name(b"Metadata".to_vec()));
+    metadata_dict.set("Subtype", Object::Name(b"XML".to_vec()));
+    // The metadata stream must stay uncompressed
+    let metadata = Stream::new(metadata_dict, xmp_metadata(title, &now).into_bytes()).with_compression(false);
+    let metadata_id = doc.add_object(metadata);
+    doc.catalog_mut()?.set("Metadata", Object::Reference(metadata_id));
+    Ok(())
+}
+
+fn set_document_id(doc: &mut Document, source: &[u8]) {
+    let id = md5::compute(source).0.to_vec();
+    doc.trailer.set("ID", Object::Array(vec![
+        Object::String(id.clone(), StringFormat::Hexadecimal),
+        Object::String(id, StringFormat::Hexadecimal),
+    ]));
+}
+
+// Text string as UTF-16BE with BOM
+fn utf16_text(text: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend_from_slice(&unit.to_be_bytes());
+    }
+    bytes
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn xmp_metadata(title: &str, now: &DateTime<Utc>) -> String {
+    let date = now.format("%Y-%m-%dT%H:%M:%SZ");
+    format!(
+        r#"<?xpacket begin="{bom}" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:pdfaid="http://www.aiim.org/pdfa/ns/id/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:pdf="http://ns.adobe.com/pdf/1.3/">
+   <pdfaid:part>2</pdfaid:part>
+   <pdfaid:conformance>B</pdfaid:conformance>
+   <dc:format>application/pdf</dc:format>
+   <dc:title><rdf:Alt><rdf:li xml:lang="x-default">{title}</rdf:li></rdf:Alt></dc:title>
+   <xmp:CreateDate>{date}</xmp:CreateDate>
+   <xmp:ModifyDate>{date}</xmp:ModifyDate>
+   <xmp:MetadataDate>{date}</xmp:MetadataDate>
+   <xmp:CreatorTool>{producer}</xmp:CreatorTool>
+   <pdf:Producer>{producer}</pdf:Producer>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#,
+        bom = '\u{feff}',
+        title = xml_escape(title),
+        date = date,
+        producer = PDFA_PRODUCER,
+    )
+}
+
+// Minimal ICC v2 display profile for sRGB (D50-adapted primaries, gamma 2.2 curves)
+fn srgb_icc_profile() -> Vec<u8> {
+    fn s15_fixed16(value: f64) -> [u8; 4] {
+        ((value * 65536.0).round() as i32).to_be_bytes()
+    }
+    fn xyz_tag(x: f64, y: f64, z: f64) -> Vec<u8> {
+        let mut tag = b"XYZ \0\0\0\0".to_vec();
+        for v in [x, y, z] {
+            tag.extend_from_slice(&s15_fixed16(v));
+        }
+        tag
+    }
+    fn desc_tag(text: &str) -> Vec<u8> {
+        let mut tag = b"desc\0\0\0\0".to_vec();
+        tag.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+        tag.extend_from_slice(text.as_bytes());
+        tag.push(0);
+        // Empty Unicode and ScriptCode descriptions
+        tag.extend_from_slice(&[0u8; 8]);
+        tag.extend_from_slice(&[0u8; 3]);
+        tag.extend_from_slice(&[0u8; 67]);
+        tag
+    }
+    fn text_tag(text: &str) -> Vec<u8> {
+        let mut tag = b"text\0\0\0\0".to_vec();
+        tag.extend_from_slice(text.as_bytes());
+        tag.push(0);
+        tag
+    }
+    // gamma 2.2 as u8Fixed8Number
+    let curve = b"curv\0\0\0\0\0\0\0\x01\x02\x33\0\0".to_vec();
+
+    let tags: Vec<(&[u8; 4], Vec<u8>)> = vec![
+        (b"desc", desc_tag(OUTPUT_CONDITION)),
+        (b"cprt", text_tag("No copyright, use freely")),
+        (b"wtpt", xyz_tag(0.9642, 1.0, 0.8249)),
+        (b"rXYZ", xyz_tag(0.4361, 0.2225, 0.0139)),
+        (b"gXYZ", xyz_tag(0.3851, 0.7169, 0.0971)),
+        (b"bXYZ", xyz_tag(0.1431, 0.0606, 0.7141)),
+        (b"rTRC", curve.clone()),
+        (b"gTRC", curve.clone()),
+        (b"bTRC", curve),
+    ];
+
+    let table_size = 4 + tags.len() * 12;
+    let mut offset = 128 + table_size;
+    let mut table = (tags.len() as u32).to_be_bytes().to_vec();
+    let mut data = Vec::new();
+    for (signature, tag) in &tags {
+        table.extend_from_slice(*signature);
+        table.extend_from_slice(&(offset as u32).to_be_bytes());
+        table.extend_from_slice(&(tag.len() as u32).to_be_bytes());
+        data.extend_from_slice(tag);
+        // Tags start on 4-byte boundaries
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset = 128 + table_size + data.len();
+    }
+
+    let total = 128 + table.len() + data.len();
+    let mut header = Vec::with_capacity(128);
+    header.extend_from_slice(&(total as u32).to_be_bytes());
+    header.extend_from_slice(&[0u8; 4]); // preferred CMM
+    header.extend_from_slice(&[0x02, 0x10, 0x00, 0x00]); // version 2.1
+    header.extend_from_slice(b"mntrRGB XYZ ");
+    for part in [2024u16, 1, 1, 0, 0, 0] {
+        header.extend_from_slice(&part.to_be_bytes());
+    }
+    header.extend_from_slice(b"acsp");
+    header.extend_from_slice(&[0u8; 24]); // platform, flags, manufacturer, model, attributes
+    header.extend_from_slice(&[0u8; 4]); // perceptual rendering intent
+    for v in [0.9642, 1.0, 0.8249] {
+        header.extend_from_slice(&s15_fixed16(v));
+    }
+    header.resize(128, 0);
+
+    let mut profile = header;
+    profile.extend(table);
+    profile.extend(data);
+    profile
+}
+
+// ===== Validation =====
+
+/// Check a PDF against the PDF/A-2b requirements this service knows how to produce.
+/// Returns human readable issues, empty when nothing was found.
+pub fn validate_pdfa(pdf_bytes: &[u8]) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    let second_line = pdf_bytes.split(|b| *b == b'\n').nth(1).unwrap_or(&[]);
+    if !(second_line.starts_with(b"%") && second_line.iter().filter(|b| **b > 127).count() >= 4) {
+        issues.push("File header isn't followed by a binary comment".to_string());
+    }
+
+    let doc = match Document::load_mem(pdf_bytes) {
+        Ok(doc) => doc,
+        Err(e) => {
+            issues.push(format!("PDF can't be parsed: {}", e));
+            return issues;
+        }
+    };
+    if doc.is_encrypted() {
+        issues.push("Encryption is not allowed".to_string());
+    }
+    if !doc.trailer.has(b"ID") {
+        issues.push("Trailer has no document ID".to_string());
+    }
+
+    let catalog = match doc.catalog() {
+        Ok(catalog) => catalog,
+        Err(_) => {
+            issues.push("Document catalog is missing".to_string());
+            return issues;
+        }
+    };
+
+    // XMP metadata with PDF/A identification
+    let metadata = catalog.get(b"Metadata").ok()
+        .and_then(|m| doc.dereference(m).ok())
+        .and_then(|(_, o)| o.as_stream().ok());
+    match metadata {
+        Some(stream) => {
+            if stream.dict.has(b"Filter") {
+                issues.push("Metadata stream must not be compressed".to_string());
+            }
+            let xmp = String::from_utf8_lossy(&stream.content);
+            if !(xmp.contains("<pdfaid:part>2</pdfaid:part>") || xmp.contains("pdfaid:part=\"2\"")) {
+                issues.push("XMP metadata doesn't declare PDF/A-2 (pdfaid:part)".to_string());
+            }
+            if !(xmp.contains("<pdfaid:conformance>B</pdfaid:conformance>") || xmp.contains("pdfaid:conformance=\"B\"")) {
+                issues.push("XMP metadata doesn't declare conformance level B".to_string());
+            }
+        }
+        None => issues.push("Document has no XMP metadata".to_string()),
+    }
+
+    // Output intent
+    let intents = catalog.get(b"OutputIntents").ok()
+        .and_then(|i| doc.dereference(i).ok())
+        .and_then(|(_, o)| o.as_array().ok().cloned())
+        .unwrap_or_default();
+    let pdfa_intent = intents.iter()
+        .filter_map(|i| doc.dereference(i).ok().and_then(|(_, o)| o.as_dict().ok()))
+        .find(|i| i.get(b"S").and_then(|s| s.as_name()).ok() == Some(b"GTS_PDFA1".as_slice()));
+    let intent_components = pdfa_intent
+        .and_then(|i| i.get(b"DestOutputProfile").ok())
+        .and_then(|p| doc.dereference(p).ok())
+        .and_then(|(_, o)| o.as_stream().ok())
+        .and_then(|s| s.dict.get(b"N").and_then(|n| n.as_i64()).ok());
+    match intent_components {
+        Some(_) => {}
+        None => issues.push("No PDF/A output intent with an ICC profile".to_string()),
+    }
+
+    if let Ok(Object::Dictionary(names)) = catalog.get(b"Names").and_then(|n| doc.dereference(n).map(|(_, o)| o)) {
+        if names.has(b"JavaScript") {
+            issues.push("Document contains JavaScript".to_string());
+        }
+        if names.has(b"EmbeddedFiles") {
+            issues.push("Document contains embedded files".to_string());
+        }
+    }
+    if let Ok(Object::Dictionary(acroform)) = catalog.get(b"AcroForm").and_then(|a| doc.dereference(a).map(|(_, o)| o)) {
+        if acroform.has(b"XFA") {
+            issues.push("XFA forms are not allowed".to_string());
+        }
+        if acroform.get(b"NeedAppearances").and_then(|n| n.as_bool()).unwrap_or(false) {
+            issues.push("NeedAppearances must not be true".to_string());
+        }
+    }
+
+    for (id, object) in &doc.objects {
+        let dict = match object {
+            Object::Dictionary(dict) => dict,
+            Object::Stream(stream) => {
+                if stream.filters().unwrap_or_default().iter().any(|f| f == "LZWDecode") {
+                    issues.push(format!("Object {} uses LZW compression", id.0));
+                }
+                if stream.dict.has(b"F") {
+                    issues.push(format!("Object {} references external stream data", id.0));
+                }
+                &stream.dict
+            }
+            _ => continue,
+        };
+        check_dictionary(&doc, *id, dict, intent_components, &mut issues);
+    }
+
+    issues.dedup();
+    issues
+}
+
+fn check_dictionary(doc: &Document, id: ObjectId, dict: &Dictionary, intent_components: Option<i64>, issues: &mut Vec<String>) {
+    let type_name = dict.get(b"Type").and_then(|t| t.as_name()).ok();
+    let subtype = dict.get(b"Subtype").and_then(|s| s.as_name()).ok();
+
+    if dict.has(b"AA") {
+        issues.push(format!("Object {} has additional actions", id.0));
+    }
+    if is_forbidden_action(dict) {
+        issues.push(format!("Object {} is a {} action", id.0, String::from_utf8_lossy(dict.get(b"S").and_then(|s| s.as_name()).unwrap_or(b""))));
+    }
+
+    if type_name == Some(b"Font".as_slice()) {
+        let base_font = dict.get(b"BaseFont").and_then(|b| b.as_name()).map(|b| String::from_utf8_lossy(b).to_string()).unwrap_or_default();
+        match subtype {
+            Some(b"Type0") => {
+                let embedded = dict.get(b"DescendantFonts").ok()
+                    .and_then(|d| doc.dereference(d).ok())
+                    .and_then(|(_, o)| o.as_array().ok())
+                    .and_then(|a| a.first())
+                    .and_then(|f| doc.dereference(f).ok())
+                    .and_then(|(_, o)| o.as_dict().ok())
+                    .map(|f| fonts::is_font_embedded(doc, f))
+                    .unwrap_or(false);
+                if !embedded {
+                    issues.push(format!("Font {} is not embedded", base_font));
+                }
+            }
+            Some(b"Type3") | None => {}
+            Some(_) => {
+                if !fonts::is_font_embedded(doc, dict) {
+                    issues.push(format!("Font {} is not embedded", base_font));
+                }
+            }
+        }
+    }
+
+    if type_name == Some(b"Annot".as_slice()) {
+        if let Some(subtype) = subtype {
+            if FORBIDDEN_ANNOTATIONS.contains(&subtype) {
+                issues.push(format!("{} annotations are not allowed", String::from_utf8_lossy(subtype)));
+            }
+            if subtype != b"Popup" {
+                let flags = dict.get(b"F").and_then(|f| f.as_i64()).unwrap_or(0);
+                if flags & ANNOT_PRINT == 0 || flags & (ANNOT_INVISIBLE | ANNOT_HIDDEN | ANNOT_NOVIEW) != 0 {
+                    issues.push(format!("Annotation {} must be printable and visible", id.0));
+                }
+            }
+            let zero_area = dict.get(b"Rect").and_then(|r| r.as_array()).map(|r| {
+                let v: Vec<f32> = r.iter().filter_map(|n| n.as_float().ok()).collect();
+                v.len() == 4 && ((v[2] - v[0]).abs() < f32::EPSILON || (v[3] - v[1]).abs() < f32::EPSILON)
+            }).unwrap_or(false);
+            if subtype != b"Popup" && subtype != b"Link" && !zero_area && !dict.has(b"AP") {
+                issues.push(format!("Annotation {} has no appearance stream", id.0));
+            }
+        }
+    }
+
+    if subtype == Some(b"Image".as_slice()) && dict.get(b"Interpolate").and_then(|i| i.as_bool()).unwrap_or(false) {
+        issues.push(format!("Image {} requests interpolation", id.0));
+    }
+    if type_name == Some(b"ExtGState".as_slice()) && dict.has(b"TR") {
+        issues.push(format!("Graphics state {} uses a transfer function", id.0));
+    }
+
+    // DeviceCMYK needs a CMYK output intent
+    if intent_components != Some(4) {
+        let uses_cmyk = dict.get(b"ColorSpace").and_then(|c| c.as_name()).ok() == Some(b"DeviceCMYK".as_slice());
+        if uses_cmyk {
+            issues.push(format!("Object {} uses DeviceCMYK without a CMYK output intent", id.0));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lopdf::dictionary;
+
+    // One page showing text in a non-embedded Helvetica, plus whatever `extra` adds to the page
+    // and catalog
+    fn source_pdf(extra: impl FnOnce(&mut Document, ObjectId, &mut Dictionary, &mut Dictionary)) -> Vec<u8> {
+        let mut doc = Document::with_version("1.4");
+        let pages_id = doc.new_object_id();
+        let page_id = doc.new_object_id();
+        let font_id = doc.add_object(dictionary! { "Type" => "Font", "Subtype" => "Type1", "BaseFont" => "Helvetica" });
+        let content_id = doc.add_object(Stream::new(dictionary! {}, b"BT /F1 12 Tf 50 750 Td (Archived) Tj ET".to_vec()));
+        let mut page = dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            "Contents" => content_id,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+        };
+        let mut catalog = dictionary! { "Type" => "Catalog", "Pages" => pages_id };
+        extra(&mut doc, page_id, &mut page, &mut catalog);
+        doc.objects.insert(page_id, Object::Dictionary(page));
+        doc.objects.insert(pages_id, Object::Dictionary(dictionary! {
+            "Type" => "Pages", "Kids" => vec![page_id.into()], "Count" => 1,
+        }));
+        let catalog_id = doc.add_object(catalog);
+        doc.trailer.set("Root", catalog_id);
+        let mut output = Vec::new();
+        doc.save_to(&mut output).unwrap();
+        output
+    }
+
+    fn catalog_entry<'a>(doc: &'a Document, key: &[u8]) -> &'a Object {
+        let entry = doc.catalog().unwrap().get(key).unwrap();
+        doc.dereference(entry).unwrap().1
+    }
+
+    #[test]
+    fn conversion_adds_output_intent_and_metadata() {
+        let source = source_pdf(|_, _, _, _| {});
+        assert!(!validate_pdfa(&source).is_empty());
+
+        let (pdf, issues) = convert_to_pdfa(&source, "Offer & Terms").unwrap();
+        assert_eq!(issues, Vec::<String>::new());
+        assert_eq!(validate_pdfa(&pdf), Vec::<String>::new());
+
+        let doc = Document::load_mem(&pdf).unwrap();
+        let intents = catalog_entry(&doc, b"OutputIntents").as_array().unwrap();
+        let intent = doc.dereference(&intents[0]).unwrap().1.as_dict().unwrap();
+        assert_eq!(intent.get(b"S").unwrap().as_name().unwrap(), b"GTS_PDFA1");
+        assert_eq!(intent.get(b"OutputConditionIdentifier").unwrap().as_str().unwrap(), OUTPUT_CONDITION.as_bytes());
+        let profile = doc.dereference(intent.get(b"DestOutputProfile").unwrap()).unwrap().1.as_stream().unwrap();
+        assert_eq!(profile.dict.get(b"N").unwrap().as_i64().unwrap(), 3);
+        assert_eq!(&profile.decompressed_content().unwrap_or_else(|_| profile.content.clone())[36..40], b"acsp");
+
+        let metadata = catalog_entry(&doc, b"Metadata").as_stream().unwrap();
+        assert!(!metadata.dict.has(b"Filter"));
+        let xmp = String::from_utf8(metadata.content.clone()).unwrap();
+        assert!(xmp.contains("<pdfaid:part>2</pdfaid:part>") && xmp.contains("<pdfaid:conformance>B</pdfaid:conformance>"));
+        assert!(xmp.contains("Offer &amp; Terms"));
+        let info = doc.dereference(doc.trailer.get(b"Info").unwrap()).unwrap().1.as_dict().unwrap();
+        assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), utf16_text("Offer & Terms").as_slice());
+        assert!(doc.trailer.has(b"ID"));
+    }
+
+    #[test]
+    fn forbidden_features_are_removed() {
+        let source = source_pdf(|doc, page_id, page, catalog| {
+            let launch_id = doc.add_object(dictionary! { "S" => "Launch", "F" => Object::string_literal("calc.exe") });
+            let link_id = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Link",
+                "Rect" => vec![50.into(), 700.into(), 150.into(), 720.into()],
+                "P" => page_id,
+                "A" => launch_id,
+                "AA" => dictionary! { "E" => dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(1)") } },
+            });
+            let movie_id = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Movie",
+                "Rect" => vec![50.into(), 600.into(), 150.into(), 650.into()],
+                "P" => page_id,
+            });
+            let hidden_id = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "FT" => "Tx",
+                "T" => Object::string_literal("Internal reference"),
+                "Rect" => vec![50.into(), 500.into(), 150.into(), 520.into()],
+                "P" => page_id,
+                "F" => 2, // hidden
+            });
+            page.set("Annots", vec![link_id.into(), movie_id.into(), hidden_id.into()]);
+            let image_id = doc.add_object(Stream::new(dictionary! {
+                "Type" => "XObject", "Subtype" => "Image", "Width" => 1, "Height" => 1,
+                "ColorSpace" => "DeviceRGB", "BitsPerComponent" => 8, "Interpolate" => true,
+            }, vec![0, 0, 0]));
+            let gs_id = doc.add_object(dictionary! { "Type" => "ExtGState", "TR" => "Identity" });
+            if let Ok(Object::Dictionary(resources)) = page.get_mut(b"Resources") {
+                resources.set("XObject", dictionary! { "Im1" => image_id });
+                resources.set("ExtGState", dictionary! { "GS1" => gs_id });
+            }
+
+            catalog.set("OpenAction", dictionary! { "S" => "JavaScript", "JS" => Object::string_literal("app.alert(2)") });
+            catalog.set("Names", dictionary! {
+                "JavaScript" => dictionary! { "Names" => Vec::<Object>::new() },
+                "EmbeddedFiles" => dictionary! { "Names" => Vec::<Object>::new() },
+            });
+            catalog.set("AcroForm", dictionary! { "Fields" => vec![hidden_id.into()], "XFA" => Object::string_literal("<xdp/>"), "NeedAppearances" => true });
+        });
+        let found = validate_pdfa(&source);
+        for issue in ["Document contains JavaScript", "Document contains embedded files", "XFA forms are not allowed", "NeedAppearances must not be true"] {
+            assert!(found.iter().any(|i| i == issue), "{} not in {:?}", issue, found);
+        }
+        assert!(found.iter().any(|i| i.ends_with("is a Launch action")));
+        assert!(found.iter().any(|i| i == "Movie annotations are not allowed"));
+
+        let (pdf, issues) = convert_to_pdfa(&source, "Cleaned").unwrap();
+        assert_eq!(issues, Vec::<String>::new());
+        assert_eq!(validate_pdfa(&pdf), Vec::<String>::new());
+
+        let doc = Document::load_mem(&pdf).unwrap();
+        let catalog = doc.catalog().unwrap();
+        assert!(!catalog.has(b"OpenAction"));
+        let names = catalog_entry(&doc, b"Names").as_dict().unwrap();
+        assert!(!names.has(b"JavaScript") && !names.has(b"EmbeddedFiles"));
+        let acroform = catalog_entry(&doc, b"AcroForm").as_dict().unwrap();
+        assert!(!acroform.has(b"XFA") && !acroform.has(b"NeedAppearances"));
+        // The hidden field is removed rather than shown
+        assert!(acroform.get(b"Fields").unwrap().as_array().unwrap().is_empty());
+
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let annots = doc.get_dictionary(page_id).unwrap().get(b"Annots").unwrap().as_array().unwrap();
+        assert_eq!(annots.len(), 1);
+        let link = doc.dereference(&annots[0]).unwrap().1.as_dict().unwrap();
+        assert!(!link.has(b"A") && !link.has(b"AA"));
+        assert_eq!(link.get(b"F").unwrap().as_i64().unwrap(), ANNOT_PRINT);
+        for object in doc.objects.values() {
+            let dict = match object {
+                Object::Stream(stream) => &stream.dict,
+                Object::Dictionary(dict) => dict,
+                _ => continue,
+            };
+            assert!(!dict.has(b"Interpolate") && !dict.has(b"TR"));
+            assert!(!dict.has(b"T"), "the hidden field is still in the file");
+        }
+    }
+
+    #[test]
+    fn check_marks_and_font_encodings_survive_conversion() {
+        let source = source_pdf(|doc, page_id, page, catalog| {
+            let agree_id = doc.add_object(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "FT" => "Btn",
+                "T" => Object::string_literal("agree"),
+                "Rect" => vec![50.into(), 600.into(), 70.into(), 620.into()],
+                "P" => page_id,
+                "F" => 4,
+            });
+            page.set("Annots", vec![agree_id.into()]);
+            catalog.set("AcroForm", dictionary! { "Fields" => vec![agree_id.into()] });
+
+            // Code 65 shows a bullet instead of "A"
+            let bullet_font_id = doc.add_object(dictionary! {
+                "Type" => "Font",
+                "Subtype" => "TrueType",
+                "BaseFont" => "Arial",
+                "Encoding" => dictionary! { "Type" => "Encoding", "BaseEncoding" => "WinAnsiEncoding", "Differences" => vec![65.into(), "bullet".into()] },
+            });
+            if let Ok(Object::Dictionary(resources)) = page.get_mut(b"Resources") {
+                if let Ok(Object::Dictionary(fonts)) = resources.get_mut(b"Font") {
+                    fonts.set("F2", bullet_font_id);
+                }
+            }
+        });
+        let values = [crate::services::acroform::FormFieldValue { name: "agree", field_type: "checkbox", value: "true" }];
+        let (filled, _) = crate::services::acroform::fill_form_fields(&source, &values, false).unwrap();
+
+        let (pdf, issues) = convert_to_pdfa(&filled, "Agreement").unwrap();
+        assert_eq!(issues, vec!["Font ZapfDingbats is not embedded".to_string()]);
+
+        // The check mark is still drawn with ZapfDingbats, where "4" is the check mark glyph
+        let doc = Document::load_mem(&pdf).unwrap();
+        let check = doc.objects.values()
+            .filter_map(|o| o.as_stream().ok())
+            .find(|stream| stream.content.windows(6).any(|w| w == b"(4) Tj"))
+            .expect("the check mark appearance");
+        let resources = doc.dereference(check.dict.get(b"Resources").unwrap()).unwrap().1.as_dict().unwrap();
+        let check_font = resources.get(b"Font").unwrap().as_dict().unwrap().iter().next().unwrap().1;
+        let check_font = doc.dereference(check_font).unwrap().1.as_dict().unwrap();
+        assert_eq!(check_font.get(b"BaseFont").unwrap().as_name().unwrap(), b"ZapfDingbats");
+        assert!(!check_font.has(b"FontDescriptor"));
+
+        // The substitute of the other font keeps its /Differences, with the bullet's width
+        let page_id = *doc.get_pages().get(&1).unwrap();
+        let fonts = doc.get_page_fonts(page_id);
+        let bullet_font = fonts.get(b"F2".as_slice()).unwrap();
+        let encoding = bullet_font.get(b"Encoding").unwrap().as_dict().unwrap();
+        assert_eq!(encoding.get(b"Differences").unwrap().as_array().unwrap(), &vec![65.into(), "bullet".into()]);
+        let file = fonts::resolve_font("Arial").unwrap();
+        let face = rustybuzz::ttf_parser::Face::parse(&file.data, 0).unwrap();
+        let bullet = face.glyph_index_by_name("bullet").unwrap();
+        let bullet_width = (face.glyph_hor_advance(bullet).unwrap() as f64 * 1000.0 / face.units_per_em() as f64).round() as i64;
+        let widths = bullet_font.get(b"Widths").unwrap().as_array().unwrap();
+        assert_eq!(widths[65 - 32].as_i64().unwrap(), bullet_width);
+    }
+}
@@ -6,6 +6,7 @@ use chrono::Utc;
 use crate::database::connection::DbPool;
 use crate::database::queries::{SubmitterQueries, EmailTemplateQueries};
 use crate::services::email::EmailService;
+use crate::services::template_documents;
 
 fn replace_template_variables(content: &str, variables: &std::collections::HashMap<&str, &str>) -> String {
     let mut result = content.to_string();
@@ -54,7 +55,7 @@ impl ReminderQueue {
     pub async fn process_pending_reminders(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let submitters = {
             let db_guard = self.db_pool.lock().await;
-            SubmitterQueries::get_pending_reminders(&*db_guard).await?
+            SubmitterQueries::get_pending_reminders(&db_guard).await?
         };
         
         if submitters.is_empty() {
@@ -169,26 +170,11 @@ impl ReminderQueue {
                         let subject = replace_template_variables(&email_template.subject, &variables);
                         let body = replace_template_variables(&email_template.body, &variables);
 
-                        // Generate attachments if needed
-                        let mut document_path = None;
-
+                        // Attach the original documents if needed
+                        let mut documents = Vec::new();
                         if email_template.attach_documents {
-                            // Get template to access documents
                             if let Ok(Some(db_template)) = crate::database::queries::TemplateQueries::get_template_by_id(&pool, submitter.template_id).await {
-                                if let Ok(storage_service) = crate::services::storage::StorageService::new().await {
-                                    if let Some(documents) = &db_template.documents {
-                                        if let Ok(docs) = serde_json::from_value::<Vec<crate::models::template::Document>>(documents.clone()) {
-                                            if let Some(first_doc) = docs.first() {
-                                                if let Ok(pdf_bytes) = storage_service.download_file(&first_doc.url).await {
-                                                    let temp_file = std::env::temp_dir().join(format!("original_document_{}.pdf", submitter.template_id));
-                                                    if let Ok(_) = tokio::fs::write(&temp_file, pdf_bytes).await {
-                                                        document_path = Some(temp_file.to_string_lossy().to_string());
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+                                documents = template_documents::original_document_attachments(&pool, &db_template, &[submitter.id]).await;
                             }
                         }
 
@@ -200,7 +186,7 @@ impl ReminderQueue {
                             &email_template.body_format,
                             email_template.attach_documents,
                             email_template.attach_audit_log,
-                            document_path.as_deref(),
+                            &documents,
                             None, // No audit log for reminder
                         ).await {
                             Ok(_) => {
@@ -218,10 +204,8 @@ impl ReminderQueue {
                             }
                         }
 
-                        // Clean up temporary file
-                        if let Some(path) = document_path {
-                            let _ = tokio::fs::remove_file(path).await;
-                        }
+                        // Clean up temporary files
+                        template_documents::remove_attachments(&documents).await;
                     },
                     _ => {
                         // Fall back to default hardcoded reminder email
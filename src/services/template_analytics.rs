@@ -0,0 +1,227 @@
+// Usage statistics of a template, computed from the timestamps of the submitters it was sent to
+// and the drafts saved on the signing page.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::database::models::{DbSubmissionField, DbSubmitterActivity};
+use crate::models::analytics::{AbandonmentField, DeclineReasonCount, ReminderEffectiveness, TemplateAnalytics};
+
+// A draft not saved for this long, and not signed or declined since, counts as abandoned
+const ABANDONED_AFTER_HOURS: i64 = 24;
+
+fn is_completed(status: &str) -> bool {
+    status == "signed" || status == "completed"
+}
+
+fn median(mut values: Vec<i64>) -> Option<i64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[middle - 1] + values[middle]) / 2)
+    } else {
+        Some(values[middle])
+    }
+}
+
+fn rate(count: i64, total: i64) -> f64 {
+    if total == 0 { 0.0 } else { count as f64 / total as f64 }
+}
+
+fn is_empty_value(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Null => true,
+        serde_json::Value::String(s) => s.trim().is_empty(),
+        serde_json::Value::Array(values) => values.is_empty(),
+        _ => false,
+    }
+}
+
+pub fn is_abandoned(submitter: &DbSubmitterActivity, now: DateTime<Utc>) -> bool {
+    !is_completed(&submitter.status)
+        && submitter.status != "declined"
+        && submitter.draft_saved_at.is_some_and(|saved_at| now - saved_at >= Duration::hours(ABANDONED_AFTER_HOURS))
+}
+
+/// The first required field, in display order, that the submitter's draft leaves empty. Only the
+/// fields shown to the submitter count, matched by partner like the signing page does.
+pub fn abandoned_at<'a>(submitter: &DbSubmitterActivity, fields: &'a [DbSubmissionField]) -> Option<&'a DbSubmissionField> {
+    let values = submitter.draft_values.as_ref().and_then(|values| values.as_object());
+    fields.iter()
+        .filter(|field| field.submitter_id == submitter.id && field.required)
+        .filter(|field| field.partner.as_ref().is_none_or(|partner| partner == &submitter.name || partner == &submitter.email))
+        .find(|field| values.and_then(|values| values.get(&field.id.to_string())).is_none_or(is_empty_value))
+}
+
+/// Statistics of the submitters sent in [from, to); `fields` are the submission fields of at least
+/// the abandoned ones
+pub fn template_analytics(
+    template_id: i64,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    submitters: &[DbSubmitterActivity],
+    fields: &[DbSubmissionField],
+    now: DateTime<Utc>,
+) -> TemplateAnalytics {
+    let sends = submitters.len() as i64;
+    let completed = submitters.iter().filter(|s| is_completed(&s.status)).count() as i64;
+    let declined = submitters.iter().filter(|s| s.status == "declined").count() as i64;
+    let viewed = submitters.iter().filter(|s| s.viewed_at.is_some() || is_completed(&s.status)).count() as i64;
+
+    let times_to_view = submitters.iter()
+        .filter_map(|s| s.viewed_at.map(|viewed_at| (viewed_at - s.created_at).num_seconds().max(0)))
+        .collect();
+    let times_to_sign = submitters.iter()
+        .filter(|s| is_completed(&s.status))
+        .filter_map(|s| s.signed_at.map(|signed_at| (signed_at - s.created_at).num_seconds().max(0)))
+        .collect();
+
+    let max_reminders = submitters.iter().map(|s| s.reminder_count.max(0)).max();
+    let reminders = match max_reminders {
+        Some(max_reminders) => (0..=max_reminders)
+            .map(|reminder| ReminderEffectiveness {
+                reminder,
+                reminded: submitters.iter().filter(|s| s.reminder_count >= reminder).count() as i64,
+                signed_after: submitters.iter().filter(|s| is_completed(&s.status) && s.reminder_count.max(0) == reminder).count() as i64,
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+
+    let mut reasons: HashMap<String, i64> = HashMap::new();
+    for submitter in submitters.iter().filter(|s| s.status == "declined") {
+        let reason = submitter.decline_reason.as_deref().map(str::trim).filter(|r| !r.is_empty()).unwrap_or("No reason given");
+        *reasons.entry(reason.to_string()).or_default() += 1;
+    }
+    let mut decline_reasons: Vec<DeclineReasonCount> = reasons.into_iter()
+        .map(|(reason, count)| DeclineReasonCount { reason, count })
+        .collect();
+    decline_reasons.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.reason.cmp(&b.reason)));
+
+    let abandoned: Vec<&DbSubmitterActivity> = submitters.iter().filter(|s| is_abandoned(s, now)).collect();
+    let mut abandonment: HashMap<i64, AbandonmentField> = HashMap::new();
+    for submitter in &abandoned {
+        if let Some(field) = abandoned_at(submitter, fields) {
+            abandonment.entry(field.template_field_id)
+                .or_insert_with(|| AbandonmentField { template_field_id: field.template_field_id, name: field.name.clone(), count: 0 })
+                .count += 1;
+        }
+    }
+    let mut abandonment_fields: Vec<AbandonmentField> = abandonment.into_values().collect();
+    abandonment_fields.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    TemplateAnalytics {
+        template_id,
+        from,
+        to,
+        sends,
+        viewed,
+        completed,
+        declined,
+        pending: sends - completed - declined,
+        completion_rate: rate(completed, sends),
+        decline_rate: rate(declined, sends),
+        median_time_to_first_view_seconds: median(times_to_view),
+        median_time_to_sign_seconds: median(times_to_sign),
+        reminders,
+        decline_reasons,
+        abandoned_drafts: abandoned.len() as i64,
+        abandonment_fields,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn submitter(id: i64, status: &str, hours_to_view: Option<i64>, hours_to_sign: Option<i64>, reminder_count: i32) -> DbSubmitterActivity {
+        let created_at = DateTime::parse_from_rfc3339("2026-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        DbSubmitterActivity {
+            id,
+            name: format!("Signer {}", id),
+            email: format!("signer{}@example.com", id),
+            status: status.to_string(),
+            created_at,
+            viewed_at: hours_to_view.map(|h| created_at + Duration::hours(h)),
+            signed_at: hours_to_sign.map(|h| created_at + Duration::hours(h)),
+            reminder_count,
+            decline_reason: None,
+            draft_values: None,
+            draft_saved_at: None,
+        }
+    }
+
+    fn field(id: i64, submitter_id: i64, name: &str, required: bool, display_order: i32) -> DbSubmissionField {
+        DbSubmissionField {
+            id,
+            submitter_id,
+            template_field_id: id % 100,
+            name: name.to_string(),
+            field_type: "text".to_string(),
+            required,
+            display_order,
+            position: None,
+            options: None,
+            metadata: None,
+            partner: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn rates_medians_and_reminders() {
+        let mut declined = submitter(4, "declined", Some(1), None, 1);
+        declined.decline_reason = Some(" Wrong salary ".to_string());
+        let submitters = vec![
+            submitter(1, "completed", Some(1), Some(2), 0),
+            submitter(2, "signed", Some(3), Some(30), 2),
+            submitter(3, "pending", None, None, 2),
+            declined,
+        ];
+        let analytics = template_analytics(1, None, None, &submitters, &[], Utc::now());
+
+        assert_eq!((analytics.sends, analytics.viewed, analytics.completed, analytics.declined, analytics.pending), (4, 3, 2, 1, 1));
+        assert_eq!((analytics.completion_rate, analytics.decline_rate), (0.5, 0.25));
+        assert_eq!(analytics.median_time_to_first_view_seconds, Some(3600));
+        assert_eq!(analytics.median_time_to_sign_seconds, Some(16 * 3600));
+        assert_eq!(analytics.reminders, vec![
+            ReminderEffectiveness { reminder: 0, reminded: 4, signed_after: 1 },
+            ReminderEffectiveness { reminder: 1, reminded: 3, signed_after: 0 },
+            ReminderEffectiveness { reminder: 2, reminded: 2, signed_after: 1 },
+        ]);
+        assert_eq!(analytics.decline_reasons, vec![DeclineReasonCount { reason: "Wrong salary".to_string(), count: 1 }]);
+
+        let empty = template_analytics(1, None, None, &[], &[], Utc::now());
+        assert_eq!((empty.completion_rate, empty.median_time_to_sign_seconds, empty.reminders.len()), (0.0, None, 0));
+    }
+
+    #[test]
+    fn drafts_are_abandoned_at_the_first_empty_required_field() {
+        let now = Utc::now();
+        let mut stale = submitter(1, "pending", Some(1), None, 0);
+        stale.draft_values = Some(serde_json::json!({"101": "Jane", "103": ""}));
+        stale.draft_saved_at = Some(now - Duration::hours(30));
+        let mut recent = submitter(2, "pending", Some(1), None, 0);
+        recent.draft_values = Some(serde_json::json!({}));
+        recent.draft_saved_at = Some(now - Duration::hours(1));
+        let fields = vec![
+            field(101, 1, "Name", true, 1),
+            field(102, 1, "Nickname", false, 2),
+            field(103, 1, "Bank account", true, 3),
+            field(201, 2, "Name", true, 1),
+        ];
+
+        assert!(is_abandoned(&stale, now) && !is_abandoned(&recent, now));
+        assert_eq!(abandoned_at(&stale, &fields).map(|f| f.id), Some(103));
+        assert_eq!(abandoned_at(&recent, &fields).map(|f| f.id), Some(201));
+
+        let analytics = template_analytics(1, None, None, &[stale, recent], &fields, now);
+        assert_eq!(analytics.abandoned_drafts, 1);
+        assert_eq!(analytics.abandonment_fields, vec![AbandonmentField { template_field_id: 3, name: "Bank account".to_string(), count: 1 }]);
+    }
+}
@@ -0,0 +1,581 @@
+// Portable template bundles, to move templates between instances (e.g. staging to production).
+//
+// A bundle is a ZIP archive holding
+//   manifest.json          the templates - fields, recipient roles, settings, folder path - and
+//                          the email templates they are sent with
+//   files/<n>-<filename>   their document files, and the files those were converted from
+// Importing recreates the templates, their folders and missing email templates for the
+// importing user, with new slugs and storage keys. Clashes with what the user already has are
+// reported, not overwritten (except email templates, when asked to).
+
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::database::models::{CreateEmailTemplate, CreateTemplate, CreateTemplateField, CreateTemplateFolder, DbTemplate, DbUser, UpdateEmailTemplate};
+use crate::database::queries::{EmailTemplateQueries, TemplateFieldQueries, TemplateFolderQueries, TemplateQueries, UserQueries};
+use crate::models::template::{Document, ImportConflict, ImportedTemplate, SourceDocument, TemplateImportResult};
+use crate::services::storage::StorageService;
+use crate::services::{page_previews, template_documents, template_search};
+
+const FORMAT: &str = "letmesign-template-bundle";
+const FORMAT_VERSION: u32 = 1;
+const MANIFEST: &str = "manifest.json";
+// Email templates a template is sent with: the default of each type
+const EMAIL_TEMPLATE_TYPES: [&str; 4] = ["invitation", "reminder", "completion", "copy"];
+
+// What a bundle may unpack to, so an archive can't expand into more memory than an upload of
+// the largest allowed size holds many times over
+const MAX_ENTRIES: usize = 1000;
+const MAX_ENTRY_BYTES: u64 = 100 * 1024 * 1024;
+const MAX_TOTAL_BYTES: u64 = 250 * 1024 * 1024;
+
+type BundleResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync + 'static>>;
+
+/// The uploaded file is not a bundle this version can import
+#[derive(Debug)]
+pub struct InvalidBundle(pub String);
+
+impl std::fmt::Display for InvalidBundle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid template bundle: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidBundle {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub templates: Vec<BundleTemplate>,
+    pub email_templates: Vec<BundleEmailTemplate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleTemplate {
+    pub name: String,
+    pub slug: String,
+    // Folder names from the top, empty when the template is not in a folder
+    pub folder_path: Vec<String>,
+    #[serde(default)] // bundles exported before templates had tags
+    pub tags: Vec<String>,
+    pub settings: Option<serde_json::Value>,
+    // Recipient roles (field partners), in field order
+    pub recipients: Vec<String>,
+    pub documents: Vec<BundleDocument>,
+    pub fields: Vec<BundleField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleDocument {
+    pub id: String, // fields reference documents by id
+    pub filename: String,
+    pub content_type: String,
+    pub file: String, // entry in the archive
+    pub source: Option<BundleFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleFile {
+    pub filename: String,
+    pub content_type: String,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleField {
+    pub name: String,
+    pub field_type: String,
+    pub required: bool,
+    pub display_order: i32,
+    pub position: Option<serde_json::Value>,
+    pub options: Option<serde_json::Value>,
+    pub metadata: Option<serde_json::Value>,
+    pub partner: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleEmailTemplate {
+    pub template_type: String,
+    pub subject: String,
+    pub body: String,
+    pub body_format: String,
+    pub attach_documents: bool,
+    pub attach_audit_log: bool,
+}
+
+// Archive entry for a file: numbered, so equal file names don't collide
+fn entry_name(index: usize, filename: &str) -> String {
+    let filename: String = filename.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_') { c } else { '_' })
+        .collect();
+    format!("files/{}-{}", index, filename)
+}
+
+/// Write a bundle archive
+pub fn write_bundle(manifest: &BundleManifest, files: &[(String, Vec<u8>)]) -> BundleResult<Vec<u8>> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    writer.start_file(MANIFEST, options)?;
+    writer.write_all(&serde_json::to_vec_pretty(manifest)?)?;
+    for (name, data) in files {
+        writer.start_file(name.as_str(), options)?;
+        writer.write_all(data)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Read a bundle archive: its manifest and files by entry name
+pub fn read_bundle(data: &[u8]) -> BundleResult<(BundleManifest, HashMap<String, Vec<u8>>)> {
+    read_bundle_within(data, MAX_ENTRIES, MAX_ENTRY_BYTES, MAX_TOTAL_BYTES)
+}
+
+fn read_bundle_within(
+    data: &[u8],
+    max_entries: usize,
+    max_entry_bytes: u64,
+    max_total_bytes: u64,
+) -> BundleResult<(BundleManifest, HashMap<String, Vec<u8>>)> {
+    let mut archive = ZipArchive::new(Cursor::new(data)).map_err(|_| InvalidBundle("not a ZIP archive".to_string()))?;
+    if archive.len() > max_entries {
+        return Err(Box::new(InvalidBundle(format!("more than {} files", max_entries))));
+    }
+    let mut manifest = None;
+    let mut files = HashMap::new();
+    let mut total_bytes = 0;
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.name().to_string();
+        // The declared size can't be trusted, so reading stops past the limit too
+        let too_large = || Box::new(InvalidBundle(format!("'{}' is larger than {} bytes", name, max_entry_bytes)));
+        if file.size() > max_entry_bytes {
+            return Err(too_large());
+        }
+        let mut data = Vec::new();
+        file.by_ref().take(max_entry_bytes + 1).read_to_end(&mut data)?;
+        if data.len() as u64 > max_entry_bytes {
+            return Err(too_large());
+        }
+        total_bytes += data.len() as u64;
+        if total_bytes > max_total_bytes {
+            return Err(Box::new(InvalidBundle(format!("unpacks to more than {} bytes", max_total_bytes))));
+        }
+        if name == MANIFEST {
+            manifest = Some(serde_json::from_slice::<BundleManifest>(&data)
+                .map_err(|e| InvalidBundle(format!("unreadable manifest: {}", e)))?);
+        } else {
+            files.insert(name, data);
+        }
+    }
+
+    let manifest = manifest.ok_or_else(|| InvalidBundle("manifest.json is missing".to_string()))?;
+    if manifest.format != FORMAT {
+        return Err(Box::new(InvalidBundle(format!("unknown format '{}'", manifest.format))));
+    }
+    if manifest.version > FORMAT_VERSION {
+        return Err(Box::new(InvalidBundle(format!("version {} is newer than this server supports", manifest.version))));
+    }
+    for template in &manifest.templates {
+        for document in &template.documents {
+            for file in std::iter::once(&document.file).chain(document.source.as_ref().map(|source| &source.file)) {
+                if !files.contains_key(file) {
+                    return Err(Box::new(InvalidBundle(format!("file '{}' of template '{}' is missing", file, template.name))));
+                }
+            }
+        }
+    }
+    Ok((manifest, files))
+}
+
+/// Names of the folders from the top down to the given one
+pub async fn folder_path(pool: &PgPool, folder_id: Option<i64>) -> Result<Vec<String>, sqlx::Error> {
+    let mut path = Vec::new();
+    let mut next = folder_id;
+    while let Some(id) = next {
+        // Guard against cycles in parent links
+        if path.len() > 64 {
+            break;
+        }
+        match TemplateFolderQueries::get_folder_by_id(pool, id).await? {
+            Some(folder) => {
+                path.push(folder.name);
+                next = folder.parent_folder_id;
+            }
+            None => break,
+        }
+    }
+    path.reverse();
+    Ok(path)
+}
+
+/// Bundle templates with the folder path each is to be imported under. Email templates are the
+/// defaults of the exporting user.
+pub async fn export_templates(
+    pool: &PgPool,
+    storage: &StorageService,
+    user_id: i64,
+    templates: Vec<(DbTemplate, Vec<String>)>,
+) -> BundleResult<Vec<u8>> {
+    let mut files: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut bundle_templates = Vec::new();
+    for (db_template, folder_path) in &templates {
+        let mut documents = template_documents::template_documents(db_template)?;
+        template_documents::assign_document_ids(&mut documents);
+        let mut bundle_documents = Vec::new();
+        for (index, document) in documents.into_iter().enumerate() {
+            let file = entry_name(files.len(), &document.filename);
+            files.push((file.clone(), storage.download_file(&document.url).await?));
+            let source = match document.source {
+                Some(source) => {
+                    let file = entry_name(files.len(), &source.filename);
+                    files.push((file.clone(), storage.download_file(&source.url).await?));
+                    Some(BundleFile { filename: source.filename, content_type: source.content_type, file })
+                }
+                None => None,
+            };
+            bundle_documents.push(BundleDocument {
+                id: document.id.unwrap_or_else(|| index.to_string()),
+                filename: document.filename,
+                content_type: document.content_type,
+                file,
+                source,
+            });
+        }
+
+        let fields: Vec<BundleField> = TemplateFieldQueries::get_template_fields(pool, db_template.id).await?
+            .into_iter()
+            .map(|field| BundleField {
+                name: field.name,
+                field_type: field.field_type,
+                required: field.required,
+                display_order: field.display_order,
+                position: field.position,
+                options: field.options,
+                metadata: field.metadata,
+                partner: field.partner,
+            })
+            .collect();
+        let mut recipients: Vec<String> = Vec::new();
+        for partner in fields.iter().filter_map(|field| field.partner.as_ref()) {
+            if !recipients.contains(partner) {
+                recipients.push(partner.clone());
+            }
+        }
+
+        bundle_templates.push(BundleTemplate {
+            name: db_template.name.clone(),
+            slug: db_template.slug.clone(),
+            folder_path: folder_path.clone(),
+            tags: TemplateQueries::get_template_tags(pool, db_template.id).await?,
+            settings: db_template.settings.clone(),
+            recipients,
+            documents: bundle_documents,
+            fields,
+        });
+    }
+
+    let mut email_templates = Vec::new();
+    for template_type in EMAIL_TEMPLATE_TYPES {
+        if let Some(email_template) = EmailTemplateQueries::get_default_template_by_type(pool, user_id, template_type).await? {
+            email_templates.push(BundleEmailTemplate {
+                template_type: email_template.template_type,
+                subject: email_template.subject,
+                body: email_template.body,
+                body_format: email_template.body_format,
+                attach_documents: email_template.attach_documents,
+                attach_audit_log: email_template.attach_audit_log,
+            });
+        }
+    }
+
+    let manifest = BundleManifest {
+        format: FORMAT.to_string(),
+        version: FORMAT_VERSION,
+        exported_at: Utc::now(),
+        templates: bundle_templates,
+        email_templates,
+    };
+    write_bundle(&manifest, &files)
+}
+
+/// Import a bundle for the user, under the given folder (or at the top). Nothing is kept when
+/// a template fails to import.
+pub async fn import_bundle(
+    pool: &PgPool,
+    storage: &StorageService,
+    user_id: i64,
+    data: &[u8],
+    folder_id: Option<i64>,
+    replace_email_templates: bool,
+) -> BundleResult<TemplateImportResult> {
+    let (manifest, files) = read_bundle(data)?;
+    let user = UserQueries::get_user_by_id(pool, user_id).await?.ok_or("User not found")?;
+
+    let mut progress = ImportProgress {
+        result: TemplateImportResult { templates: Vec::new(), email_templates: Vec::new(), conflicts: Vec::new() },
+        uploaded: Vec::new(),
+        created: Vec::new(),
+    };
+    if let Err(e) = import_templates(pool, storage, &user, &manifest, &files, folder_id, &mut progress).await {
+        for id in progress.created {
+            let _ = TemplateQueries::delete_template(pool, id).await;
+        }
+        for key in progress.uploaded {
+            let _ = storage.delete_file(&key).await;
+        }
+        return Err(e);
+    }
+    let mut result = progress.result;
+
+    for email_template in &manifest.email_templates {
+        match EmailTemplateQueries::get_default_template_by_type(pool, user_id, &email_template.template_type).await? {
+            None => {
+                EmailTemplateQueries::create_template(pool, CreateEmailTemplate {
+                    user_id,
+                    template_type: email_template.template_type.clone(),
+                    subject: email_template.subject.clone(),
+                    body: email_template.body.clone(),
+                    body_format: email_template.body_format.clone(),
+                    is_default: true,
+                    attach_documents: email_template.attach_documents,
+                    attach_audit_log: email_template.attach_audit_log,
+                }).await?;
+                result.email_templates.push(email_template.template_type.clone());
+            }
+            Some(existing) if existing.subject == email_template.subject
+                && existing.body == email_template.body
+                && existing.body_format == email_template.body_format
+                && existing.attach_documents == email_template.attach_documents
+                && existing.attach_audit_log == email_template.attach_audit_log => {}
+            Some(existing) if replace_email_templates => {
+                EmailTemplateQueries::update_template(pool, existing.id, user_id, UpdateEmailTemplate {
+                    template_type: None,
+                    subject: Some(email_template.subject.clone()),
+                    body: Some(email_template.body.clone()),
+                    body_format: Some(email_template.body_format.clone()),
+                    is_default: None,
+                    attach_documents: Some(email_template.attach_documents),
+                    attach_audit_log: Some(email_template.attach_audit_log),
+                }).await?;
+                result.email_templates.push(email_template.template_type.clone());
+            }
+            Some(_) => result.conflicts.push(ImportConflict {
+                kind: "email_template".to_string(),
+                name: email_template.template_type.clone(),
+                message: "Your default email template differs from the bundle's; yours was kept".to_string(),
+            }),
+        }
+    }
+    Ok(result)
+}
+
+// What an import did so far, to report it or undo it
+struct ImportProgress {
+    result: TemplateImportResult,
+    uploaded: Vec<String>, // storage keys
+    created: Vec<i64>, // template ids
+}
+
+async fn import_templates(
+    pool: &PgPool,
+    storage: &StorageService,
+    user: &DbUser,
+    manifest: &BundleManifest,
+    files: &HashMap<String, Vec<u8>>,
+    folder_id: Option<i64>,
+    progress: &mut ImportProgress,
+) -> BundleResult<()> {
+    let mut folders: HashMap<Vec<String>, Option<i64>> = HashMap::new();
+    for (index, template) in manifest.templates.iter().enumerate() {
+        let template_folder = match folders.get(&template.folder_path) {
+            Some(folder) => *folder,
+            None => {
+                let folder = ensure_folder_path(pool, user, folder_id, &template.folder_path).await?;
+                folders.insert(template.folder_path.clone(), folder);
+                folder
+            }
+        };
+
+        let mut documents = Vec::new();
+        for document in &template.documents {
+            let data = files[&document.file].clone();
+            let size = data.len() as i64;
+            let url = storage.upload_file(data, &document.filename, &document.content_type).await?;
+            progress.uploaded.push(url.clone());
+            let source = match &document.source {
+                Some(source) => {
+                    let data = files[&source.file].clone();
+                    let size = data.len() as i64;
+                    let url = storage.upload_file(data, &source.filename, &source.content_type).await?;
+                    progress.uploaded.push(url.clone());
+                    Some(SourceDocument { filename: source.filename.clone(), content_type: source.content_type.clone(), size, url })
+                }
+                None => None,
+            };
+            documents.push(Document {
+                id: Some(document.id.clone()),
+                filename: document.filename.clone(),
+                content_type: document.content_type.clone(),
+                size,
+                url,
+                source,
+            });
+        }
+
+        if TemplateQueries::get_template_by_slug(pool, &template.slug).await?.is_some() {
+            progress.result.conflicts.push(ImportConflict {
+                kind: "slug".to_string(),
+                name: template.name.clone(),
+                message: format!("Slug '{}' is already taken here; the template got a new one", template.slug),
+            });
+        }
+        let siblings = TemplateFolderQueries::get_templates_in_folder(pool, user.id, template_folder).await?;
+        if siblings.iter().any(|sibling| sibling.name == template.name && !progress.created.contains(&sibling.id)) {
+            progress.result.conflicts.push(ImportConflict {
+                kind: "name".to_string(),
+                name: template.name.clone(),
+                message: "A template with this name already exists in the folder; both are kept".to_string(),
+            });
+        }
+
+        let slug = format!("import-{}-{}-{}", template.name.to_lowercase().replace(" ", "-"), Utc::now().timestamp(), index);
+        let db_template = TemplateQueries::create_template(pool, CreateTemplate {
+            name: template.name.clone(),
+            slug: slug.clone(),
+            user_id: user.id,
+            account_id: user.account_id,
+            folder_id: template_folder,
+            documents: Some(serde_json::to_value(&documents)?),
+        }).await?;
+        progress.created.push(db_template.id);
+        if let Some(settings) = &template.settings {
+            TemplateQueries::update_template_settings(pool, db_template.id, settings).await?;
+        }
+        if let Ok(tags) = template_search::normalize_tags(&template.tags) {
+            TemplateQueries::set_template_tags(pool, db_template.id, &tags).await?;
+        }
+        for field in &template.fields {
+            TemplateFieldQueries::create_template_field(pool, CreateTemplateField {
+                template_id: db_template.id,
+                name: field.name.clone(),
+                field_type: field.field_type.clone(),
+                required: field.required,
+                display_order: field.display_order,
+                position: field.position.clone(),
+                options: field.options.clone(),
+                metadata: field.metadata.clone(),
+                partner: field.partner.clone(),
+            }).await?;
+        }
+        page_previews::pregenerate(&db_template);
+        template_search::index_in_background(pool, db_template.id);
+
+        progress.result.templates.push(ImportedTemplate {
+            id: db_template.id,
+            name: db_template.name,
+            slug,
+            original_slug: template.slug.clone(),
+            folder_id: template_folder,
+        });
+    }
+    Ok(())
+}
+
+// Folder at the path below the given one, created where missing
+async fn ensure_folder_path(
+    pool: &PgPool,
+    user: &DbUser,
+    mut parent: Option<i64>,
+    path: &[String],
+) -> Result<Option<i64>, sqlx::Error> {
+    for name in path {
+        let existing = TemplateFolderQueries::get_folders_by_parent(pool, user.id, parent).await?
+            .into_iter()
+            .find(|folder| &folder.name == name);
+        parent = Some(match existing {
+            Some(folder) => folder.id,
+            None => TemplateFolderQueries::create_folder(pool, CreateTemplateFolder {
+                name: name.clone(),
+                user_id: user.id,
+                account_id: user.account_id,
+                parent_folder_id: parent,
+            }).await?.id,
+        });
+    }
+    Ok(parent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(templates: Vec<BundleTemplate>) -> BundleManifest {
+        BundleManifest {
+            format: FORMAT.to_string(),
+            version: FORMAT_VERSION,
+            exported_at: Utc::now(),
+            templates,
+            email_templates: Vec::new(),
+        }
+    }
+
+    fn template(file: &str) -> BundleTemplate {
+        BundleTemplate {
+            name: "NDA".to_string(),
+            slug: "pdf-nda-1".to_string(),
+            folder_path: vec!["Legal".to_string()],
+            tags: vec!["legal".to_string()],
+            settings: None,
+            recipients: vec!["Client".to_string()],
+            documents: vec![BundleDocument {
+                id: "0".to_string(),
+                filename: "nda.pdf".to_string(),
+                content_type: "application/pdf".to_string(),
+                file: file.to_string(),
+                source: None,
+            }],
+            fields: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn bundle_round_trip() {
+        let file = entry_name(0, "nda final/v2.pdf");
+        assert_eq!(file, "files/0-nda_final_v2.pdf");
+        let data = write_bundle(&manifest(vec![template(&file)]), &[(file.clone(), b"%PDF-1.7".to_vec())]).unwrap();
+
+        let (read, files) = read_bundle(&data).unwrap();
+        assert_eq!(read.templates[0].folder_path, vec!["Legal"]);
+        assert_eq!(files[&file], b"%PDF-1.7");
+    }
+
+    #[test]
+    fn rejects_incomplete_bundles() {
+        let data = write_bundle(&manifest(vec![template("files/0-nda.pdf")]), &[]).unwrap();
+        assert!(read_bundle(&data).unwrap_err().downcast::<InvalidBundle>().is_ok());
+        assert!(read_bundle(b"not a zip").unwrap_err().downcast::<InvalidBundle>().is_ok());
+    }
+
+    #[test]
+    fn rejects_bundles_unpacking_past_the_limits() {
+        let file = entry_name(0, "nda.pdf");
+        let data = write_bundle(&manifest(vec![template(&file)]), &[(file.clone(), vec![b'0'; 4096])]).unwrap();
+        assert!(read_bundle_within(&data, 10, 8192, 16384).is_ok());
+
+        let limits = [(1, 8192, 16384), (10, 1024, 16384), (10, 8192, 2048)];
+        for (max_entries, max_entry_bytes, max_total_bytes) in limits {
+            let error = read_bundle_within(&data, max_entries, max_entry_bytes, max_total_bytes).unwrap_err();
+            assert!(error.downcast::<InvalidBundle>().is_ok());
+        }
+    }
+}
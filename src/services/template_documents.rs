@@ -0,0 +1,272 @@
+// Documents of multi-document templates: lookup by the id fields reference, downloading,
+// per-submission personalization of DOCX documents, and temporary files for email attachments.
+
+use sqlx::PgPool;
+
+use crate::database::models::DbTemplate;
+use crate::database::queries::{SubmitterQueries, TemplateQueries, TemplateVersionQueries};
+use crate::models::template::{Document, SourceDocument};
+use crate::services::storage::StorageService;
+use crate::services::{document_conversion, docx_merge, page_previews, template_versions};
+
+// A PDF produced for (or stored with) a template
+pub struct DocumentFile {
+    pub filename: String,
+    pub bytes: Vec<u8>,
+}
+
+/// Documents of a template, in order
+pub fn template_documents(template: &DbTemplate) -> Result<Vec<Document>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let documents = template.documents.clone().ok_or("Template has no documents")?;
+    let documents: Vec<Document> = serde_json::from_value(documents).map_err(|_| "Invalid documents format")?;
+    if documents.is_empty() {
+        return Err("No documents found in template".into());
+    }
+    Ok(documents)
+}
+
+/// Index of the document a field references; fields without a document are on the first one
+pub fn document_index(documents: &[Document], document_id: Option<&str>) -> Option<usize> {
+    match document_id {
+        None if documents.is_empty() => None,
+        None => Some(0),
+        Some(id) => documents.iter().enumerate().position(|(index, document)| document.key(index) == id),
+    }
+}
+
+/// Give every document an explicit id, so index-based references survive the list changing
+pub fn assign_document_ids(documents: &mut [Document]) {
+    for (index, document) in documents.iter_mut().enumerate() {
+        if document.id.is_none() {
+            document.id = Some(index.to_string());
+        }
+    }
+}
+
+/// Documents the given submitters sign: the first set personalized for one of their submissions,
+/// otherwise those of the template version they were sent, otherwise the template's
+pub async fn submitter_documents(
+    pool: &PgPool,
+    template: &DbTemplate,
+    submitter_ids: &[i64],
+) -> Result<Vec<Document>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    for submitter_id in submitter_ids {
+        if let Some(documents) = SubmitterQueries::get_submitter_documents(pool, *submitter_id).await? {
+            let documents: Vec<Document> = serde_json::from_value(documents).map_err(|_| "Invalid documents format")?;
+            if documents.is_empty() {
+                return Err("No documents found for submitter".into());
+            }
+            return Ok(documents);
+        }
+    }
+    for submitter_id in submitter_ids {
+        if let Some(version) = TemplateVersionQueries::get_submitter_template_version(pool, *submitter_id).await? {
+            let documents = template_versions::version_documents(&version);
+            if !documents.is_empty() {
+                return Ok(documents);
+            }
+        }
+    }
+    template_documents(template)
+}
+
+/// Keep the template's current documents for the submissions already sent, before they change:
+/// submitters signing the template's documents get their own copy of the list, unless their
+/// template version holds it. Returns the storage keys the template's submitters refer to,
+/// which must be kept.
+pub async fn pin_submitter_documents(
+    pool: &PgPool,
+    template: &DbTemplate,
+) -> Result<std::collections::HashSet<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut documents = template_documents(template)?;
+    assign_document_ids(&mut documents);
+    let documents_json = serde_json::to_value(&documents)?;
+
+    let mut referenced = std::collections::HashSet::new();
+    for submitter in SubmitterQueries::get_submitters_by_template_id(pool, template.id).await? {
+        let submitter_documents = match SubmitterQueries::get_submitter_documents(pool, submitter.id).await? {
+            Some(submitter_documents) => serde_json::from_value(submitter_documents).unwrap_or_default(),
+            None => match TemplateVersionQueries::get_submitter_template_version(pool, submitter.id).await? {
+                Some(version) => template_versions::version_documents(&version),
+                None => {
+                    SubmitterQueries::set_submitter_documents(pool, submitter.id, &documents_json).await?;
+                    documents.clone()
+                }
+            },
+        };
+        for document in submitter_documents {
+            let document: Document = document;
+            referenced.extend(document.source.map(|source| source.url));
+            referenced.insert(document.url);
+        }
+    }
+    Ok(referenced)
+}
+
+/// Delete stored document files, with their previews, unless a template, template version or
+/// submitter still refers to them. Copies of a template share its files, so a file can outlive
+/// the template it was uploaded for.
+pub async fn delete_unreferenced_files(
+    pool: &PgPool,
+    storage: &StorageService,
+    keys: std::collections::HashSet<String>,
+) -> Result<(), sqlx::Error> {
+    let keys: Vec<String> = keys.into_iter().collect();
+    let referenced = TemplateQueries::get_referenced_storage_keys(pool, &keys).await?;
+    for key in keys.iter().filter(|key| !referenced.contains(*key)) {
+        page_previews::remove_previews(storage, key).await;
+        if let Err(e) = storage.delete_file(key).await {
+            eprintln!("Warning: Failed to delete file '{}': {}", key, e);
+        }
+    }
+    Ok(())
+}
+
+/// Download every document of a template, in order
+pub async fn download_documents(
+    storage: &StorageService,
+    template: &DbTemplate,
+) -> Result<Vec<(Document, Vec<u8>)>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    download_document_list(storage, template_documents(template)?).await
+}
+
+pub async fn download_document_list(
+    storage: &StorageService,
+    documents: Vec<Document>,
+) -> Result<Vec<(Document, Vec<u8>)>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut downloaded = Vec::new();
+    for document in documents {
+        let bytes = storage.download_file(&document.url).await?;
+        downloaded.push((document, bytes));
+    }
+    Ok(downloaded)
+}
+
+// DOCX a document was converted from, if it has one
+fn docx_source(document: &Document) -> Option<&SourceDocument> {
+    document.source.as_ref().filter(|source| source.content_type == document_conversion::DOCX_CONTENT_TYPE)
+}
+
+/// {{placeholders}} of the template's DOCX documents, sorted
+pub async fn template_placeholders(
+    storage: &StorageService,
+    template: &DbTemplate,
+) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut names = std::collections::BTreeSet::new();
+    for document in template_documents(template)? {
+        if let Some(source) = docx_source(&document) {
+            names.extend(docx_merge::placeholders(&storage.download_file(&source.url).await?)?);
+        }
+    }
+    Ok(names.into_iter().collect())
+}
+
+/// Fill the placeholders of the template's DOCX documents with a submission's values and convert
+/// them to PDF. Returns the submission's documents, or None when the template has nothing to
+/// fill. Fails with docx_merge::UnresolvedPlaceholders (covering all documents) when values are
+/// missing.
+pub async fn personalize_documents(
+    storage: &StorageService,
+    template: &DbTemplate,
+    values: &serde_json::Map<String, serde_json::Value>,
+) -> Result<Option<Vec<Document>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut documents = template_documents(template)?;
+
+    // Merge everything first, so missing values are reported for all documents at once
+    let mut merged = Vec::new();
+    let mut unresolved = std::collections::BTreeSet::new();
+    for (index, document) in documents.iter().enumerate() {
+        let Some(source) = docx_source(document) else { continue };
+        let docx = storage.download_file(&source.url).await?;
+        if docx_merge::placeholders(&docx)?.is_empty() {
+            continue;
+        }
+        match docx_merge::merge_docx(&docx, values) {
+            Ok(docx) => merged.push((index, docx)),
+            Err(e) => match e.downcast::<docx_merge::UnresolvedPlaceholders>() {
+                Ok(missing) => unresolved.extend(missing.0),
+                Err(e) => return Err(e),
+            },
+        }
+    }
+    if !unresolved.is_empty() {
+        return Err(Box::new(docx_merge::UnresolvedPlaceholders(unresolved.into_iter().collect())));
+    }
+    if merged.is_empty() {
+        return Ok(None);
+    }
+
+    // Personalized documents keep the template document's id, so fields stay on them
+    assign_document_ids(&mut documents);
+    let mut uploaded: Vec<String> = Vec::new();
+    for (index, docx) in merged {
+        let document = &mut documents[index];
+        let result = match document_conversion::convert_docx_to_pdf(&docx, &docx_source(document).map_or("document.docx".to_string(), |s| s.filename.clone())).await {
+            Ok(pdf) => {
+                let size = pdf.len() as i64;
+                storage.upload_file(pdf, &document.filename, "application/pdf").await.map(|key| (key, size))
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok((key, size)) => {
+                uploaded.push(key.clone());
+                document.url = key;
+                document.size = size;
+                // The DOCX belongs to the template
+                document.source = None;
+            }
+            Err(e) => {
+                for key in &uploaded {
+                    let _ = storage.delete_file(key).await;
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(Some(documents))
+}
+
+/// Write files to the temp directory for attaching to an email: (file name, path) pairs
+pub async fn write_attachments(prefix: &str, files: &[DocumentFile]) -> Vec<(String, String)> {
+    let mut attachments = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        let path = std::env::temp_dir().join(format!("{}_{}.pdf", prefix, index));
+        match tokio::fs::write(&path, &file.bytes).await {
+            Ok(_) => attachments.push((file.filename.clone(), path.to_string_lossy().to_string())),
+            Err(e) => eprintln!("Failed to write attachment {}: {}", file.filename, e),
+        }
+    }
+    attachments
+}
+
+/// The original documents of a template, or of a submitter's submission, as email attachments
+pub async fn original_document_attachments(pool: &PgPool, template: &DbTemplate, submitter_ids: &[i64]) -> Vec<(String, String)> {
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => {
+            eprintln!("Failed to initialize storage: {}", e);
+            return Vec::new();
+        }
+    };
+    let downloaded = match submitter_documents(pool, template, submitter_ids).await {
+        Ok(documents) => download_document_list(&storage, documents).await,
+        Err(e) => Err(e),
+    };
+    let files = match downloaded {
+        Ok(documents) => documents.into_iter()
+            .map(|(document, bytes)| DocumentFile { filename: document.filename, bytes })
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            eprintln!("Failed to download documents of template {}: {}", template.id, e);
+            return Vec::new();
+        }
+    };
+    write_attachments(&format!("original_document_{}", template.id), &files).await
+}
+
+pub async fn remove_attachments(attachments: &[(String, String)]) {
+    for (_, path) in attachments {
+        let _ = tokio::fs::remove_file(path).await;
+    }
+}
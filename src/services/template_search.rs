@@ -0,0 +1,164 @@
+// Full-text search over templates and submitters. Postgres keeps the search vectors up to date
+// (see the add_search migration); this module extracts the text of template documents when they
+// are uploaded, normalizes tags and turns what users type into tsqueries.
+
+use std::sync::Arc;
+
+use sqlx::PgPool;
+use tokio::sync::{Mutex, Semaphore};
+
+use crate::database::connection::DbPool;
+use crate::database::queries::TemplateQueries;
+use crate::services::storage::StorageService;
+use crate::services::template_documents;
+
+const MAX_TAGS: usize = 20;
+const MAX_TAG_LENGTH: usize = 50;
+// A tsvector holds at most 1MB; longer document text is cut
+const MAX_DOCUMENT_TEXT: usize = 512 * 1024;
+// Templates indexed per round when catching up on templates uploaded before search existed
+const INDEX_BATCH: i64 = 50;
+// Background extractions at once; each holds a whole document in memory
+static EXTRACTION_SLOTS: Semaphore = Semaphore::const_new(2);
+
+/// Trimmed, lowercased, unique tags without empty ones, in the order given
+pub fn normalize_tags(tags: &[String]) -> Result<Vec<String>, String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags {
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        if tag.is_empty() || normalized.contains(&tag) {
+            continue;
+        }
+        if tag.chars().count() > MAX_TAG_LENGTH {
+            return Err(format!("Tags can be at most {} characters long", MAX_TAG_LENGTH));
+        }
+        normalized.push(tag);
+    }
+    if normalized.len() > MAX_TAGS {
+        return Err(format!("A template can have at most {} tags", MAX_TAGS));
+    }
+    Ok(normalized)
+}
+
+/// A tsquery matching every word of the search text, the last one also as a prefix so results
+/// show up while typing. None when the text has no words.
+pub fn prefix_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect();
+    let (last, rest) = words.split_last()?;
+    let mut terms: Vec<String> = rest.to_vec();
+    terms.push(format!("{}:*", last));
+    Some(terms.join(" & "))
+}
+
+async fn extract_document_text(
+    storage: &StorageService,
+    db_template: &crate::database::models::DbTemplate,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let mut text = String::new();
+    for document in template_documents::template_documents(db_template)? {
+        if document.content_type != "application/pdf" && !document.url.to_lowercase().ends_with(".pdf") {
+            continue;
+        }
+        let data = storage.download_file(&document.url).await?;
+        // pdf-extract panics on some malformed files; a failed document is left out of the index
+        match tokio::task::spawn_blocking(move || pdf_extract::extract_text_from_mem(&data)).await {
+            Ok(Ok(document_text)) => {
+                text.push_str(&document_text.split_whitespace().collect::<Vec<_>>().join(" "));
+                text.push('\n');
+            }
+            Ok(Err(e)) => eprintln!("Failed to extract text of {}: {}", document.url, e),
+            Err(e) => eprintln!("Failed to extract text of {}: {}", document.url, e),
+        }
+    }
+    if text.len() > MAX_DOCUMENT_TEXT {
+        let mut end = MAX_DOCUMENT_TEXT;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    Ok(text)
+}
+
+/// Extract the text of the template's documents and store it for search
+pub async fn index_documents(
+    pool: &PgPool,
+    storage: &StorageService,
+    template_id: i64,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let Some(db_template) = TemplateQueries::get_template_by_id(pool, template_id).await? else { return Ok(()) };
+    let text = extract_document_text(storage, &db_template).await?;
+    TemplateQueries::set_template_document_text(pool, template_id, &text).await?;
+    Ok(())
+}
+
+/// Index the template's documents in the background, after they were uploaded or changed
+pub fn index_in_background(pool: &PgPool, template_id: i64) {
+    let pool = pool.clone();
+    tokio::spawn(async move {
+        let Ok(_slot) = EXTRACTION_SLOTS.acquire().await else { return };
+        let storage = match StorageService::new().await {
+            Ok(storage) => storage,
+            Err(e) => return eprintln!("Failed to initialize storage for search indexing: {}", e),
+        };
+        if let Err(e) = index_documents(&pool, &storage, template_id).await {
+            eprintln!("Failed to index documents of template {}: {}", template_id, e);
+        }
+    });
+}
+
+/// Background task indexing the documents of templates uploaded before search existed
+pub async fn index_existing(db_pool: Arc<Mutex<DbPool>>) {
+    let storage = match StorageService::new().await {
+        Ok(storage) => storage,
+        Err(e) => return eprintln!("Failed to initialize storage for search indexing: {}", e),
+    };
+    let mut indexed = 0;
+    loop {
+        let pool = db_pool.lock().await.clone();
+        let templates = match TemplateQueries::get_templates_without_document_text(&pool, INDEX_BATCH).await {
+            Ok(templates) if !templates.is_empty() => templates,
+            Ok(_) => break,
+            Err(e) => return eprintln!("❌ Error finding templates to index: {}", e),
+        };
+        for db_template in templates {
+            // Stored even when empty, so a template that can't be read isn't retried forever
+            let text = extract_document_text(&storage, &db_template).await.unwrap_or_else(|e| {
+                eprintln!("Failed to index documents of template {}: {}", db_template.id, e);
+                String::new()
+            });
+            if let Err(e) = TemplateQueries::set_template_document_text(&pool, db_template.id, &text).await {
+                return eprintln!("❌ Error storing document text of template {}: {}", db_template.id, e);
+            }
+            indexed += 1;
+        }
+    }
+    if indexed > 0 {
+        println!("🔎 Indexed the documents of {} template(s) for search", indexed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags_are_trimmed_lowercased_and_unique() {
+        let tags = vec![" HR ".to_string(), "hr".to_string(), "".to_string(), "Offer  Letter".to_string()];
+        assert_eq!(normalize_tags(&tags).unwrap(), vec!["hr", "offer letter"]);
+        assert!(normalize_tags(&["x".repeat(51)]).is_err());
+        assert!(normalize_tags(&(0..21).map(|i| i.to_string()).collect::<Vec<_>>()).is_err());
+    }
+
+    #[test]
+    fn prefix_query_matches_all_words() {
+        assert_eq!(prefix_query("Jane Do").as_deref(), Some("jane & do:*"));
+        assert_eq!(prefix_query("jane.doe@example"), Some("jane & doe & example:*".to_string()));
+        assert_eq!(prefix_query("hợp đồng"), Some("hợp & đồng:*".to_string()));
+        assert_eq!(prefix_query(" & !"), None);
+    }
+}
@@ -0,0 +1,157 @@
+// Trash for deleted templates. Deleting a template only marks it (templates.deleted_at): it
+// disappears from listings and lookups but keeps its fields, submissions and files, and can be
+// restored. Templates are purged for good - database rows and storage files - on request or once
+// they have been in the trash for the retention period.
+//
+// Environment:
+//   TEMPLATE_TRASH_RETENTION_DAYS  days a deleted template can be restored (default 30)
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+use tokio::sync::Mutex;
+
+use crate::database::connection::DbPool;
+use crate::database::models::DbTemplate;
+use crate::database::queries::{SubmitterQueries, TemplateQueries};
+use crate::models::template::Document;
+use crate::services::storage::StorageService;
+use crate::services::{template_documents, template_versions};
+
+const DEFAULT_RETENTION_DAYS: i64 = 30;
+// How often expired templates are looked for
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+/// How long deleted templates are kept
+pub fn retention() -> Duration {
+    parse_retention(std::env::var("TEMPLATE_TRASH_RETENTION_DAYS").ok().as_deref())
+}
+
+fn parse_retention(value: Option<&str>) -> Duration {
+    let days = value
+        .and_then(|value| value.trim().parse::<i64>().ok())
+        .filter(|days| *days >= 0)
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+    Duration::days(days)
+}
+
+/// When a template deleted at the given time is purged
+pub fn purge_at(deleted_at: DateTime<Utc>) -> DateTime<Utc> {
+    deleted_at + retention()
+}
+
+/// Delete the template with its submissions, and every file stored for it: its documents and
+/// their previews, documents personalized for submissions and those of earlier versions. Files
+/// a copy of the template still uses are kept.
+pub async fn purge_template(
+    pool: &PgPool,
+    storage: &StorageService,
+    db_template: &DbTemplate,
+) -> Result<bool, sqlx::Error> {
+    let mut files = template_versions::version_files(pool, db_template.id).await?;
+    for document in template_documents::template_documents(db_template).unwrap_or_default() {
+        files.extend(document.source.map(|source| source.url));
+        files.insert(document.url);
+    }
+    // Documents personalized for the template's submissions
+    for submitter in SubmitterQueries::get_submitters_by_template_id(pool, db_template.id).await? {
+        if let Some(documents) = SubmitterQueries::get_submitter_documents(pool, submitter.id).await? {
+            let documents: Vec<Document> = serde_json::from_value(documents).unwrap_or_default();
+            files.extend(documents.into_iter().map(|document| document.url));
+        }
+    }
+
+    // Versions and submitters go with the template, so only other templates' references remain
+    if !TemplateQueries::delete_template(pool, db_template.id).await? {
+        return Ok(false);
+    }
+    template_documents::delete_unreferenced_files(pool, storage, files).await?;
+    Ok(true)
+}
+
+/// Purge the templates whose retention period is over
+pub async fn purge_expired(pool: &PgPool) -> Result<usize, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let expired = TemplateQueries::get_templates_trashed_before(pool, Utc::now() - retention()).await?;
+    if expired.is_empty() {
+        return Ok(0);
+    }
+    let storage = StorageService::new().await?;
+    let mut purged = 0;
+    for db_template in expired {
+        if purge_template(pool, &storage, &db_template).await? {
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+/// Background task purging expired templates
+pub async fn start_purging(db_pool: Arc<Mutex<DbPool>>) {
+    loop {
+        let pool = db_pool.lock().await.clone();
+        match purge_expired(&pool).await {
+            Ok(0) => {}
+            Ok(purged) => println!("🗑️ Purged {} template(s) from the trash", purged),
+            Err(e) => eprintln!("❌ Error purging deleted templates: {}", e),
+        }
+        tokio::time::sleep(PURGE_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retention_defaults_to_thirty_days() {
+        assert_eq!(parse_retention(None), Duration::days(30));
+        assert_eq!(parse_retention(Some("7")), Duration::days(7));
+        assert_eq!(parse_retention(Some("0")), Duration::days(0));
+        assert_eq!(parse_retention(Some("-1")), Duration::days(30));
+        assert_eq!(parse_retention(Some("week")), Duration::days(30));
+    }
+
+    // Needs DATABASE_URL pointing at a migrated database, skipped without one
+    #[tokio::test]
+    async fn copies_keep_the_files_of_a_purged_template() {
+        let Ok(database_url) = std::env::var("DATABASE_URL") else {
+            eprintln!("DATABASE_URL not set, skipping");
+            return;
+        };
+        let pool = PgPool::connect(&database_url).await.unwrap();
+        let storage_path = std::env::temp_dir().join(format!("template_trash_{}", uuid::Uuid::new_v4()));
+        std::env::set_var("STORAGE_TYPE", "local");
+        std::env::set_var("STORAGE_PATH", &storage_path);
+        let storage = StorageService::new().await.unwrap();
+
+        let key = format!("templates/{}.pdf", uuid::Uuid::new_v4());
+        storage.upload_file_with_key(b"%PDF-1.7".to_vec(), &key, "application/pdf").await.unwrap();
+        let user_id: i64 = sqlx::query_scalar("INSERT INTO users (name, email, password_hash) VALUES ('Trash', $1, '') RETURNING id")
+            .bind(format!("trash-{}@example.com", uuid::Uuid::new_v4()))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        let documents = serde_json::json!([{ "id": "0", "filename": "contract.pdf", "content_type": "application/pdf", "size": 8, "url": key }]);
+        let original = TemplateQueries::create_template(&pool, crate::database::models::CreateTemplate {
+            name: "Contract".to_string(),
+            slug: format!("contract-{}", uuid::Uuid::new_v4()),
+            user_id,
+            account_id: None,
+            folder_id: None,
+            documents: Some(documents),
+        }).await.unwrap();
+        let copy = TemplateQueries::clone_template(&pool, original.id, user_id, "Contract (copy)", &format!("copy-{}", uuid::Uuid::new_v4()))
+            .await.unwrap().unwrap();
+
+        assert!(purge_template(&pool, &storage, &original).await.unwrap());
+        assert!(TemplateQueries::get_template_by_id(&pool, original.id).await.unwrap().is_none());
+        assert!(storage.file_exists(&key).await.unwrap(), "the copy's document was deleted");
+
+        assert!(purge_template(&pool, &storage, &copy).await.unwrap());
+        assert!(!storage.file_exists(&key).await.unwrap());
+
+        sqlx::query("DELETE FROM users WHERE id = $1").bind(user_id).execute(&pool).await.unwrap();
+        let _ = std::fs::remove_dir_all(&storage_path);
+    }
+}
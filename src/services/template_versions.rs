@@ -0,0 +1,260 @@
+// Immutable template versions: snapshots of a template's documents and fields, recorded when the
+// template is edited or sent. Submitters keep the version they were sent with, so later edits
+// change neither what pending signers see nor how their completed documents render. Document
+// files of versions submissions were sent with are kept until the template is deleted.
+
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+use crate::database::models::{DbTemplateField, DbTemplateVersion};
+use crate::database::queries::{TemplateFieldQueries, TemplateQueries, TemplateVersionQueries};
+use crate::models::template::{
+    Document, TemplateVersion, TemplateVersionDiff, TemplateVersionDocumentChange, TemplateVersionField,
+    TemplateVersionFieldChange,
+};
+use crate::services::template_documents;
+
+impl From<&DbTemplateField> for TemplateVersionField {
+    fn from(field: &DbTemplateField) -> Self {
+        TemplateVersionField {
+            id: field.id,
+            name: field.name.clone(),
+            field_type: field.field_type.clone(),
+            required: field.required,
+            display_order: field.display_order,
+            position: field.position.clone(),
+            options: field.options.clone(),
+            partner: field.partner.clone(),
+        }
+    }
+}
+
+/// Snapshot the template's current documents and fields, unless they match its latest version.
+/// Returns the version describing the template as it is now.
+pub async fn record_version(
+    pool: &PgPool,
+    template_id: i64,
+    created_by: Option<i64>,
+) -> Result<DbTemplateVersion, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    let template = TemplateQueries::get_template_by_id(pool, template_id).await?
+        .ok_or("Template not found")?;
+    let mut documents: Vec<Document> = template.documents.clone()
+        .and_then(|documents| serde_json::from_value(documents).ok())
+        .unwrap_or_default();
+    template_documents::assign_document_ids(&mut documents);
+    let fields: Vec<TemplateVersionField> = TemplateFieldQueries::get_template_fields(pool, template_id).await?
+        .iter()
+        .map(TemplateVersionField::from)
+        .collect();
+
+    let documents = serde_json::to_value(&documents)?;
+    let fields = serde_json::to_value(&fields)?;
+    let checksum = snapshot_checksum(&documents, &fields);
+    if let Some(latest) = TemplateVersionQueries::get_latest_template_version(pool, template_id).await? {
+        if latest.checksum == checksum {
+            return Ok(latest);
+        }
+    }
+    Ok(TemplateVersionQueries::create_template_version(pool, template_id, &documents, &fields, &checksum, created_by).await?)
+}
+
+/// Record a version after an edit; failures are logged, the edit itself already succeeded
+pub async fn record_edit(pool: &PgPool, template_id: i64, user_id: i64) {
+    if let Err(e) = record_version(pool, template_id, Some(user_id)).await {
+        eprintln!("Failed to record version of template {}: {}", template_id, e);
+    }
+}
+
+fn snapshot_checksum(documents: &serde_json::Value, fields: &serde_json::Value) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(documents.to_string());
+    hasher.update(b"\n");
+    hasher.update(fields.to_string());
+    hex::encode(hasher.finalize())
+}
+
+pub fn version_documents(version: &DbTemplateVersion) -> Vec<Document> {
+    serde_json::from_value(version.documents.clone()).unwrap_or_default()
+}
+
+pub fn version_fields(version: &DbTemplateVersion) -> Vec<TemplateVersionField> {
+    serde_json::from_value(version.fields.clone()).unwrap_or_default()
+}
+
+pub fn to_template_version(version: DbTemplateVersion, submitter_count: i64) -> TemplateVersion {
+    TemplateVersion {
+        documents: version_documents(&version),
+        fields: version_fields(&version),
+        id: version.id,
+        template_id: version.template_id,
+        version: version.version,
+        created_by: version.created_by,
+        created_at: version.created_at,
+        submitter_count,
+    }
+}
+
+/// The template's versions, newest first
+pub async fn list_versions(pool: &PgPool, template_id: i64) -> Result<Vec<TemplateVersion>, sqlx::Error> {
+    let counts = TemplateVersionQueries::count_submitters_by_version(pool, template_id).await?;
+    Ok(TemplateVersionQueries::get_template_versions(pool, template_id).await?
+        .into_iter()
+        .map(|version| {
+            let submitter_count = counts.iter().find(|(id, _)| *id == version.id).map_or(0, |(_, count)| *count);
+            to_template_version(version, submitter_count)
+        })
+        .collect())
+}
+
+/// Storage keys of the documents any version of the template refers to
+pub async fn version_files(
+    pool: &PgPool,
+    template_id: i64,
+) -> Result<std::collections::HashSet<String>, sqlx::Error> {
+    let mut referenced = std::collections::HashSet::new();
+    for version in TemplateVersionQueries::get_template_versions(pool, template_id).await? {
+        for document in version_documents(&version) {
+            referenced.extend(document.source.map(|source| source.url));
+            referenced.insert(document.url);
+        }
+    }
+    Ok(referenced)
+}
+
+/// Fields are matched by template field id, documents by their id
+pub fn diff_versions(from: &TemplateVersion, to: &TemplateVersion) -> TemplateVersionDiff {
+    let mut diff = TemplateVersionDiff {
+        from: from.version,
+        to: to.version,
+        fields_added: Vec::new(),
+        fields_removed: Vec::new(),
+        fields_changed: Vec::new(),
+        documents_added: Vec::new(),
+        documents_removed: Vec::new(),
+        documents_changed: Vec::new(),
+    };
+
+    for before in &from.fields {
+        match to.fields.iter().find(|after| after.id == before.id) {
+            None => diff.fields_removed.push(before.clone()),
+            Some(after) if after != before => diff.fields_changed.push(TemplateVersionFieldChange {
+                id: before.id,
+                changes: field_changes(before, after),
+                before: before.clone(),
+                after: after.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    diff.fields_added = to.fields.iter()
+        .filter(|after| !from.fields.iter().any(|before| before.id == after.id))
+        .cloned()
+        .collect();
+
+    let keyed = |documents: &[Document]| -> Vec<(String, Document)> {
+        documents.iter().enumerate().map(|(index, document)| (document.key(index), document.clone())).collect()
+    };
+    let (before_documents, after_documents) = (keyed(&from.documents), keyed(&to.documents));
+    for (id, before) in &before_documents {
+        match after_documents.iter().find(|(after_id, _)| after_id == id) {
+            None => diff.documents_removed.push(before.clone()),
+            Some((_, after)) if after.url != before.url || after.filename != before.filename => {
+                diff.documents_changed.push(TemplateVersionDocumentChange {
+                    id: id.clone(),
+                    before: before.clone(),
+                    after: after.clone(),
+                })
+            }
+            Some(_) => {}
+        }
+    }
+    diff.documents_added = after_documents.into_iter()
+        .filter(|(id, _)| !before_documents.iter().any(|(before_id, _)| before_id == id))
+        .map(|(_, document)| document)
+        .collect();
+    diff
+}
+
+fn field_changes(before: &TemplateVersionField, after: &TemplateVersionField) -> Vec<String> {
+    let mut changes = Vec::new();
+    let mut check = |name: &str, changed: bool| {
+        if changed {
+            changes.push(name.to_string());
+        }
+    };
+    check("name", before.name != after.name);
+    check("field_type", before.field_type != after.field_type);
+    check("required", before.required != after.required);
+    check("display_order", before.display_order != after.display_order);
+    check("position", before.position != after.position);
+    check("options", before.options != after.options);
+    check("partner", before.partner != after.partner);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(id: i64, name: &str, x: f64) -> TemplateVersionField {
+        TemplateVersionField {
+            id,
+            name: name.to_string(),
+            field_type: "signature".to_string(),
+            required: true,
+            display_order: 0,
+            position: Some(serde_json::json!({"x": x, "y": 0.5, "width": 0.2, "height": 0.05, "page": 1})),
+            options: None,
+            partner: None,
+        }
+    }
+
+    fn document(id: &str, url: &str) -> Document {
+        Document {
+            id: Some(id.to_string()),
+            filename: "contract.pdf".to_string(),
+            content_type: "application/pdf".to_string(),
+            size: 100,
+            url: url.to_string(),
+            source: None,
+        }
+    }
+
+    fn version(version: i32, documents: Vec<Document>, fields: Vec<TemplateVersionField>) -> TemplateVersion {
+        TemplateVersion {
+            id: version as i64,
+            template_id: 1,
+            version,
+            documents,
+            fields,
+            created_by: None,
+            created_at: chrono::Utc::now(),
+            submitter_count: 0,
+        }
+    }
+
+    #[test]
+    fn diff_matches_fields_and_documents_by_id() {
+        let from = version(1, vec![document("0", "a.pdf"), document("1", "b.pdf")], vec![field(1, "sign", 0.1), field(2, "date", 0.5)]);
+        let to = version(2, vec![document("0", "a2.pdf"), document("2", "c.pdf")], vec![field(1, "signature", 0.3), field(3, "name", 0.5)]);
+        let diff = diff_versions(&from, &to);
+
+        assert_eq!(diff.fields_added.iter().map(|f| f.id).collect::<Vec<_>>(), vec![3]);
+        assert_eq!(diff.fields_removed.iter().map(|f| f.id).collect::<Vec<_>>(), vec![2]);
+        assert_eq!(diff.fields_changed.len(), 1);
+        assert_eq!(diff.fields_changed[0].changes, vec!["name", "position"]);
+        assert_eq!(diff.documents_added.iter().map(|d| d.url.as_str()).collect::<Vec<_>>(), vec!["c.pdf"]);
+        assert_eq!(diff.documents_removed.iter().map(|d| d.url.as_str()).collect::<Vec<_>>(), vec!["b.pdf"]);
+        assert_eq!(diff.documents_changed[0].id, "0");
+
+        assert!(diff_versions(&to, &to).fields_changed.is_empty());
+    }
+
+    #[test]
+    fn checksum_depends_on_documents_and_fields() {
+        let documents = serde_json::json!([{"url": "a.pdf"}]);
+        let fields = serde_json::json!([]);
+        assert_eq!(snapshot_checksum(&documents, &fields), snapshot_checksum(&documents.clone(), &fields.clone()));
+        assert_ne!(snapshot_checksum(&documents, &fields), snapshot_checksum(&serde_json::json!([]), &documents));
+    }
+}
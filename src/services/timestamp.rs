@@ -0,0 +1,345 @@
+// RFC 3161 trusted timestamps.
+//
+// Configuration (environment):
+//   TSA_URL                     external timestamp authority (HTTP, application/timestamp-query)
+//   TSA_USERNAME / TSA_PASSWORD optional basic auth for TSA_URL
+//   TSA_LOCAL_P12_PATH          PKCS#12 with the key/certificate of the built-in local TSA,
+//                               used when TSA_URL isn't set (self-hosted / offline deployments)
+//   TSA_LOCAL_P12_PASSWORD      password for TSA_LOCAL_P12_PATH
+//   TSA_POLICY_OID              policy reported by the local TSA (default 1.2.3.4.1)
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use openssl::cms::{CMSOptions, CmsContentInfo};
+use openssl::hash::{hash, MessageDigest};
+use openssl::x509::X509;
+use rand::RngCore;
+
+use crate::services::cms::{self, CmsSigner, SignedContent};
+use crate::services::pdf_signing::SigningCredentials;
+
+const DEFAULT_POLICY_OID: &str = "1.2.3.4.1";
+
+pub struct TimestampToken {
+    // DER encoded TimeStampToken (CMS ContentInfo)
+    pub token: Vec<u8>,
+    pub tsa_name: String,
+    pub gen_time: DateTime<Utc>,
+    pub serial_number: String,
+    // SHA-256 of the timestamped data
+    pub message_imprint: Vec<u8>,
+    // Digest algorithm of the message imprint
+    pub imprint_algorithm: String,
+    // Nonce echoed from the request, without leading zero bytes
+    pub nonce: Option<Vec<u8>>,
+}
+
+// INTEGER content as an unsigned magnitude
+fn integer_magnitude(content: &[u8]) -> Vec<u8> {
+    let start = content.iter().position(|b| *b != 0).unwrap_or(content.len());
+    content[start..].to_vec()
+}
+
+impl TimestampToken {
+    /// Parse the TSTInfo of a TimeStampToken
+    pub fn from_der(token: &[u8], tsa_name: &str) -> Result<Self, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        // ContentInfo ::= SEQUENCE { contentType, [0] EXPLICIT SignedData }
+        let (content_info, _) = cms::der_parse(token)?;
+        let content_info = cms::der_children(content_info.content)?;
+        let signed_data = content_info.get(1).ok_or("Timestamp token has no content")?;
+        let (signed_data, _) = cms::der_parse(signed_data.content)?;
+        let signed_data = cms::der_children(signed_data.content)?;
+
+        // EncapsulatedContentInfo ::= SEQUENCE { eContentType, [0] EXPLICIT OCTET STRING }
+        let encap = cms::der_children(signed_data.get(2).ok_or("Timestamp token has no encapsulated content")?.content)?;
+        if encap.first().map(|oid| cms::decode_oid(oid.content)) != Some(cms::OID_CT_TST_INFO.to_string()) {
+            return Err("Timestamp token doesn't contain a TSTInfo".into());
+        }
+        let (octets, _) = cms::der_parse(encap.get(1).ok_or("Timestamp token has no TSTInfo")?.content)?;
+        let (tst_info, _) = cms::der_parse(octets.content)?;
+        let tst_info = cms::der_children(tst_info.content)?;
+
+        // TSTInfo ::= SEQUENCE { version, policy, messageImprint, serialNumber, genTime, ... }
+        if tst_info.len() < 5 {
+            return Err("Malformed TSTInfo".into());
+        }
+        let imprint = cms::der_children(tst_info[2].content)?;
+        let imprint_algorithm = imprint.first()
+            .and_then(|algorithm| cms::der_children(algorithm.content).ok())
+            .and_then(|algorithm| algorithm.first().map(|oid| cms::decode_oid(oid.content)))
+            .ok_or("TSTInfo has no message imprint algorithm")?;
+        let message_imprint = imprint.get(1).ok_or("TSTInfo has no message imprint")?.content.to_vec();
+        let serial_number = hex::encode(tst_info[3].content);
+        if tst_info[4].tag != cms::TAG_GENERALIZED_TIME {
+            return Err("Malformed TSTInfo genTime".into());
+        }
+        let gen_time = cms::decode_generalized_time(tst_info[4].content)?;
+        // Optional fields follow: accuracy, ordering, nonce (the only INTEGER), tsa, extensions
+        let nonce = tst_info[5..].iter().find(|field| field.tag == 0x02).map(|field| integer_magnitude(field.content));
+
+        Ok(Self {
+            token: token.to_vec(),
+            tsa_name: tsa_name.to_string(),
+            gen_time,
+            serial_number,
+            message_imprint,
+            imprint_algorithm,
+            nonce,
+        })
+    }
+
+    // Check that the token answers our request: a SHA-256 imprint of our digest, with our nonce
+    pub fn check_request(&self, digest: &[u8], nonce: &[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        if self.imprint_algorithm != cms::OID_SHA256 {
+            return Err(format!("Timestamp token uses digest algorithm {}, SHA-256 was requested", self.imprint_algorithm).into());
+        }
+        if self.message_imprint != digest {
+            return Err("Timestamp token message imprint doesn't match the request".into());
+        }
+        if self.nonce.as_deref() != Some(integer_magnitude(nonce).as_slice()) {
+            return Err("Timestamp token nonce doesn't match the request".into());
+        }
+        Ok(())
+    }
+
+    // Check the token's CMS signature and that it covers the expected digest
+    pub fn verify(&self, expected_digest: &[u8]) -> Result<(), Box<dyn std::error::Error + Send + Sync + 'static>> {
+        if self.message_imprint != expected_digest {
+            return Err("Timestamp token message imprint doesn't match".into());
+        }
+        let mut cms = CmsContentInfo::from_der(&self.token)?;
+        cms.verify(None, None, None, None, CMSOptions::NO_SIGNER_CERT_VERIFY)?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+pub trait TimestampAuthority: Send + Sync {
+    // Name recorded alongside issued tokens (URL or certificate subject)
+    fn name(&self) -> String;
+
+    /// Obtain a timestamp token for data with the given SHA-256 digest
+    async fn timestamp(&self, digest: &[u8]) -> Result<TimestampToken, Box<dyn std::error::Error + Send + Sync + 'static>>;
+}
+
+/// Configured timestamp authority: TSA_URL first, then the local TSA, otherwise None
+pub fn timestamp_authority_from_env() -> Result<Option<Box<dyn TimestampAuthority>>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+    if let Ok(url) = std::env::var("TSA_URL") {
+        if !url.is_empty() {
+            let credentials = match std::env::var("TSA_USERNAME") {
+                Ok(username) if !username.is_empty() => Some((username, std::env::var("TSA_PASSWORD").unwrap_or_default())),
+                _ => None,
+            };
+            return Ok(Some(Box::new(HttpTimestampAuthority::new(url, credentials))));
+        }
+    }
+
+    if let Ok(path) = std::env::var("TSA_LOCAL_P12_PATH") {
+        if !path.is_empty() {
+            let der = std::fs::read(&path)
+                .map_err(|e| format!("Failed to read local TSA certificate {}: {}", path, e))?;
+            let password = std::env::var("TSA_LOCAL_P12_PASSWORD").unwrap_or_default();
+            let policy = std::env::var("TSA_POLICY_OID").unwrap_or_else(|_| DEFAULT_POLICY_OID.to_string());
+            let credentials = SigningCredentials::from_pkcs12(&der, &password)?;
+            return Ok(Some(Box::new(LocalTimestampAuthority::new(credentials, policy))));
+        }
+    }
+
+    Ok(None)
+}
+
+// ===== External TSA over HTTP (RFC 3161, section 3.4) =====
+
+pub struct HttpTimestampAuthority {
+    url: String,
+    credentials: Option<(String, String)>,
+    client: reqwest::Client,
+}
+
+impl HttpTimestampAuthority {
+    pub fn new(url: String, credentials: Option<(String, String)>) -> Self {
+        Self { url, credentials, client: reqwest::Client::new() }
+    }
+
+    fn build_request(digest: &[u8], nonce: &[u8]) -> Vec<u8> {
+        // TimeStampReq ::= SEQUENCE { version, messageImprint, reqPolicy?, nonce?, certReq, extensions? }
+        let message_imprint = cms::der_sequence(&[
+            cms::algorithm_identifier(cms::OID_SHA256, true),
+            cms::der_octet_string(digest),
+        ]);
+        cms::der_sequence(&[
+            cms::der_integer(1),
+            message_imprint,
+            cms::der_integer_bytes(nonce),
+            cms::der_boolean(true),
+        ])
+    }
+}
+
+#[async_trait]
+impl TimestampAuthority for HttpTimestampAuthority {
+    fn name(&self) -> String {
+        self.url.clone()
+    }
+
+    async fn timestamp(&self, digest: &[u8]) -> Result<TimestampToken, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let mut nonce = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let request = Self::build_request(digest, &nonce);
+
+        let mut http_request = self.client
+            .post(&self.url)
+            .header("Content-Type", "application/timestamp-query")
+            .body(request);
+        if let Some((username, password)) = &self.credentials {
+            http_request = http_request.basic_auth(username, Some(password));
+        }
+        let response = http_request.send().await?;
+        if !response.status().is_success() {
+            return Err(format!("TSA returned HTTP {}", response.status()).into());
+        }
+        let body = response.bytes().await?;
+
+        // TimeStampResp ::= SEQUENCE { status PKIStatusInfo, timeStampToken TimeStampToken OPTIONAL }
+        let (resp, _) = cms::der_parse(&body)?;
+        let resp = cms::der_children(resp.content)?;
+        let status_info = cms::der_children(resp.first().ok_or("Empty TSA response")?.content)?;
+        let status = status_info.first().and_then(|s| s.content.last().copied()).unwrap_or(2);
+        // 0 = granted, 1 = grantedWithMods
+        if status > 1 {
+            return Err(format!("TSA rejected the request (status {})", status).into());
+        }
+        let token_der = resp.get(1).ok_or("TSA response has no timestamp token")?.raw;
+
+        let token = TimestampToken::from_der(token_der, &self.name())?;
+        token.check_request(digest, &nonce)?;
+        token.verify(digest)?;
+        Ok(token)
+    }
+}
+
+// ===== Built-in local TSA =====
+
+pub struct LocalTimestampAuthority {
+    credentials: SigningCredentials,
+    policy: String,
+}
+
+impl LocalTimestampAuthority {
+    pub fn new(credentials: SigningCredentials, policy: String) -> Self {
+        Self { credentials, policy }
+    }
+
+    fn certificate(&self) -> &X509 {
+        self.credentials.certificate()
+    }
+
+    fn issue(&self, digest: &[u8], nonce: Option<&[u8]>) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let mut serial = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut serial);
+        serial[0] &= 0x7F;
+
+        let subject = self.certificate().subject_name().to_der()?;
+        let mut fields = vec![
+            cms::der_integer(1),
+            cms::der_oid(&self.policy),
+            cms::der_sequence(&[
+                cms::algorithm_identifier(cms::OID_SHA256, true),
+                cms::der_octet_string(digest),
+            ]),
+            cms::der_integer_bytes(&serial),
+            cms::der_generalized_time(&Utc::now()),
+            // accuracy: 1 second
+            cms::der_sequence(&[cms::der_integer(1)]),
+        ];
+        if let Some(nonce) = nonce {
+            fields.push(cms::der_integer_bytes(nonce));
+        }
+        // tsa [0] GeneralName (directoryName)
+        fields.push(cms::der_explicit(0, &cms::der_explicit(4, &subject)));
+        let tst_info = cms::der_sequence(&fields);
+
+        let tst_digest = hash(MessageDigest::sha256(), &tst_info)?;
+        let content = SignedContent {
+            content_type: cms::OID_CT_TST_INFO,
+            digest: &tst_digest,
+            encapsulated: Some(&tst_info),
+        };
+        let signer: CmsSigner = self.credentials.cms_signer();
+        let parts = cms::compute_signature(&signer, &content)?;
+        cms::build_signed_data(&signer, &content, &parts, &[])
+    }
+}
+
+#[async_trait]
+impl TimestampAuthority for LocalTimestampAuthority {
+    fn name(&self) -> String {
+        format!("Local TSA ({})", self.credentials.signer_name())
+    }
+
+    async fn timestamp(&self, digest: &[u8]) -> Result<TimestampToken, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        let token_der = self.issue(digest, None)?;
+        TimestampToken::from_der(&token_der, &self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+
+    fn local_tsa() -> LocalTimestampAuthority {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "Test TSA").unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let credentials = SigningCredentials::new(key, builder.build(), Vec::new());
+        LocalTimestampAuthority::new(credentials, DEFAULT_POLICY_OID.to_string())
+    }
+
+    #[tokio::test]
+    async fn local_tsa_issues_verifiable_tokens() {
+        let tsa = local_tsa();
+        let digest = hash(MessageDigest::sha256(), b"completed document").unwrap();
+
+        let token = tsa.timestamp(&digest).await.unwrap();
+
+        assert_eq!(token.message_imprint, digest.to_vec());
+        assert!((Utc::now() - token.gen_time).num_seconds().abs() < 5);
+        token.verify(&digest).unwrap();
+        assert!(token.verify(&[0u8; 32]).is_err());
+    }
+
+    #[test]
+    fn tokens_must_answer_the_request() {
+        let tsa = local_tsa();
+        let digest = hash(MessageDigest::sha256(), b"completed document").unwrap();
+        let nonce = [0x80, 1, 2, 3, 4, 5, 6, 7];
+
+        let token = TimestampToken::from_der(&tsa.issue(&digest, Some(&nonce)).unwrap(), "test").unwrap();
+        assert_eq!(token.imprint_algorithm, cms::OID_SHA256);
+        token.check_request(&digest, &nonce).unwrap();
+        assert!(token.check_request(&digest, &[1, 2, 3, 4, 5, 6, 7, 8]).is_err());
+        assert!(token.check_request(&[0u8; 32], &nonce).is_err());
+
+        // A replayed token without our nonce
+        let token = TimestampToken::from_der(&tsa.issue(&digest, None).unwrap(), "test").unwrap();
+        assert!(token.nonce.is_none());
+        assert!(token.check_request(&digest, &nonce).is_err());
+    }
+}